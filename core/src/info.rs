@@ -1,3 +1,11 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::protocol::INPUT_REPORT_ID;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "strum")]
 use strum::{Display, EnumIter, EnumString};
 
@@ -44,9 +52,58 @@ pub fn is_vendor_familiar(vendor: &u16) -> bool {
     RECOGNIZED_VENDORS.contains(vendor)
 }
 
+/// Vendor of Stream Deck hardware, for showing the right branding.
+///
+/// Only Elgato devices are supported for now, more vendors will be added along with their devices
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "strum", derive(Display, EnumIter, EnumString))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Vendor {
+    /// Elgato, maker of the original Stream Decks
+    Elgato,
+}
+
+impl Vendor {
+    /// Creates [Vendor] variant from Vendor ID
+    pub fn from_id(vid: u16) -> Option<Vendor> {
+        match vid {
+            ELGATO_VENDOR_ID => Some(Vendor::Elgato),
+            _ => None,
+        }
+    }
+
+    /// Vendor ID the vendor's hardware uses
+    pub fn id(&self) -> u16 {
+        match self {
+            Vendor::Elgato => ELGATO_VENDOR_ID,
+        }
+    }
+
+    /// Name of the vendor, like "Elgato"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Vendor::Elgato => "Elgato",
+        }
+    }
+
+    /// Tells if the vendor makes devices compatible with Stream Decks rather than Stream Decks themselves
+    pub fn is_third_party(&self) -> bool {
+        !matches!(self, Vendor::Elgato)
+    }
+
+    /// Name of the vendor's application that updates firmware of their devices, this library can't do that
+    pub fn firmware_update_tool(&self) -> &'static str {
+        match self {
+            Vendor::Elgato => "Elgato Stream Deck",
+        }
+    }
+}
+
 /// Enum describing kinds of Stream Decks out there
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 #[cfg_attr(feature = "strum", derive(Display, EnumIter, EnumString))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Kind {
     /// First revision of original Stream Deck
     Original,
@@ -82,7 +139,66 @@ pub enum Kind {
     XlV2Module,
 }
 
+// Every kind has to be in ALL at the position it's given, in declaration order
+const _: () = {
+    let mut index = 0;
+
+    while index < Kind::ALL.len() {
+        let kind = Kind::ALL[index];
+        assert!(kind.position_in_all() == index && kind as usize == index, "Kind::ALL doesn't match kinds");
+        index += 1;
+    }
+};
+
 impl Kind {
+    /// Every kind known to the library
+    pub const ALL: [Kind; 16] = [
+        Kind::Original,
+        Kind::OriginalV2,
+        Kind::Mini,
+        Kind::Xl,
+        Kind::XlV2,
+        Kind::Mk2,
+        Kind::Mk2Scissor,
+        Kind::MiniMk2,
+        Kind::MiniDiscord,
+        Kind::Neo,
+        Kind::Pedal,
+        Kind::Plus,
+        Kind::PlusXl,
+        Kind::MiniMk2Module,
+        Kind::Mk2Module,
+        Kind::XlV2Module,
+    ];
+
+    /// Position of the kind in [ALL](Kind::ALL). The match is exhaustive, so a new kind doesn't build until it gets a position here and an entry there
+    const fn position_in_all(&self) -> usize {
+        match self {
+            Kind::Original => 0,
+            Kind::OriginalV2 => 1,
+            Kind::Mini => 2,
+            Kind::Xl => 3,
+            Kind::XlV2 => 4,
+            Kind::Mk2 => 5,
+            Kind::Mk2Scissor => 6,
+            Kind::MiniMk2 => 7,
+            Kind::MiniDiscord => 8,
+            Kind::Neo => 9,
+            Kind::Pedal => 10,
+            Kind::Plus => 11,
+            Kind::PlusXl => 12,
+            Kind::MiniMk2Module => 13,
+            Kind::Mk2Module => 14,
+            Kind::XlV2Module => 15,
+        }
+    }
+
+    /// Every supported kind.
+    /// Along with [vendor_id](Kind::vendor_id) and [product_id](Kind::product_id) of each, it's enough for listing supported hardware or generating udev rules
+    pub fn all() -> impl Iterator<Item = Kind> {
+        Kind::ALL.into_iter()
+    }
+
     /// Creates [Kind] variant from Vendor ID and Product ID
     pub fn from_vid_pid(vid: u16, pid: u16) -> Option<Kind> {
         match vid {
@@ -153,6 +269,36 @@ impl Kind {
         }
     }
 
+    /// Vendor of the Stream Deck kind
+    pub fn vendor(&self) -> Vendor {
+        Vendor::Elgato
+    }
+
+    /// Tells if the Stream Deck kind is made by a vendor other than Elgato
+    pub fn is_third_party(&self) -> bool {
+        self.vendor().is_third_party()
+    }
+
+    /// Product name of the Stream Deck kind, like "Stream Deck +"
+    pub fn product_name(&self) -> &'static str {
+        match self {
+            Kind::Original | Kind::OriginalV2 => "Stream Deck",
+            Kind::Mini => "Stream Deck Mini",
+            Kind::Xl | Kind::XlV2 => "Stream Deck XL",
+            Kind::Mk2 => "Stream Deck MK.2",
+            Kind::Mk2Scissor => "Stream Deck MK.2 (Scissor Keys)",
+            Kind::MiniMk2 => "Stream Deck Mini MK.2",
+            Kind::MiniDiscord => "Stream Deck Mini Discord Edition",
+            Kind::Neo => "Stream Deck Neo",
+            Kind::Pedal => "Stream Deck Pedal",
+            Kind::Plus => "Stream Deck +",
+            Kind::PlusXl => "Stream Deck + XL",
+            Kind::MiniMk2Module => "Stream Deck Mini MK.2 Module",
+            Kind::Mk2Module => "Stream Deck MK.2 Module",
+            Kind::XlV2Module => "Stream Deck XL Module",
+        }
+    }
+
     /// Amount of keys the Stream Deck kind has
     pub fn key_count(&self) -> u8 {
         match self {
@@ -205,14 +351,186 @@ impl Kind {
         }
     }
 
+    /// Named touch keys the Stream Deck kind has, in order of their touch point indices
+    pub fn touch_keys(&self) -> &'static [TouchKey] {
+        match self {
+            Kind::Neo => &[TouchKey::PageLeft, TouchKey::PageRight],
+            _ => &[],
+        }
+    }
+
+    /// Returns named touch key at the touch point index, if the touch point has one
+    pub fn touch_key(&self, point: u8) -> Option<TouchKey> {
+        self.touch_keys().get(point as usize).copied()
+    }
+
+    /// Tells if touch keys of the Stream Deck kind have LEDs that can be colored
+    pub fn has_touch_key_leds(&self) -> bool {
+        matches!(self, Kind::Neo)
+    }
+
+    /// How often input should be polled to not miss or delay input, devices send input reports only when something changes
+    pub fn recommended_poll_interval(&self) -> Duration {
+        match self {
+            Kind::Pedal => Duration::from_millis(10),
+            _ => Duration::from_millis(5),
+        }
+    }
+
+    /// Image data in bytes per second that the device accepts without stalling other communication with it.
+    /// Conservative, devices on fast USB ports usually do better, see the `bench` feature for measuring it
+    pub fn max_image_throughput(&self) -> usize {
+        match self {
+            Kind::Pedal => 0,
+            Kind::Xl | Kind::XlV2 | Kind::XlV2Module | Kind::PlusXl => 4_000_000,
+            _ => 1_000_000,
+        }
+    }
+
+    /// Layout of input reports the Stream Deck kind sends
+    pub fn report_layout(&self) -> ReportLayout {
+        let button_count = self.key_count() as usize + self.touchpoint_count() as usize;
+
+        match self {
+            Kind::Plus | Kind::PlusXl => ReportLayout {
+                length: (6 + self.key_count()).max(5 + self.encoder_count()) as usize,
+                type_offset: Some(1),
+                buttons: ButtonReportLayout {
+                    report_type: 0x0,
+                    offset: 4,
+                    count: button_count,
+                    flipped_columns: false,
+                },
+                touch_screen: Some(TouchScreenReportLayout {
+                    report_type: 0x2,
+                    event_offset: 4,
+                    start_offset: 6,
+                    end_offset: 10,
+                }),
+                encoders: Some(EncoderReportLayout {
+                    report_type: 0x3,
+                    event_offset: 4,
+                    offset: 5,
+                    count: self.encoder_count() as usize,
+                }),
+            },
+
+            Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => ReportLayout {
+                length: 1 + button_count,
+                type_offset: None,
+                buttons: ButtonReportLayout {
+                    report_type: 0x0,
+                    offset: 1,
+                    count: button_count,
+                    flipped_columns: matches!(self, Kind::Original),
+                },
+                touch_screen: None,
+                encoders: None,
+            },
+
+            _ => ReportLayout {
+                length: 4 + button_count,
+                type_offset: None,
+                buttons: ButtonReportLayout {
+                    report_type: 0x0,
+                    offset: 4,
+                    count: button_count,
+                    flipped_columns: false,
+                },
+                touch_screen: None,
+                encoders: None,
+            },
+        }
+    }
+
+    /// Screens of the device besides key displays
+    pub fn screens(&self) -> Vec<Screen> {
+        match self {
+            Kind::Neo => vec![Screen {
+                id: Screen::LCD_STRIP,
+                resolution: (248, 58),
+                segments: vec![ScreenSegment { x: 0, y: 0, w: 248, h: 58 }],
+                format: ImageFormat {
+                    mode: ImageMode::JPEG,
+                    size: (248, 58),
+                    rotation: ImageRotation::Rot180,
+                    mirror: ImageMirroring::None,
+                },
+                writes_regions: false,
+            }],
+
+            // Segments are the parts of the strip above each encoder
+            Kind::Plus => vec![Screen {
+                id: Screen::LCD_STRIP,
+                resolution: (800, 100),
+                segments: (0..4).map(|i| ScreenSegment { x: i * 200, y: 0, w: 200, h: 100 }).collect(),
+                format: ImageFormat {
+                    mode: ImageMode::JPEG,
+                    size: (800, 100),
+                    rotation: ImageRotation::Rot0,
+                    mirror: ImageMirroring::None,
+                },
+                writes_regions: true,
+            }],
+
+            Kind::PlusXl => vec![Screen {
+                id: Screen::LCD_STRIP,
+                resolution: (100, 1200),
+                segments: (0..6).map(|i| ScreenSegment { x: 0, y: i * 200, w: 100, h: 200 }).collect(),
+                format: ImageFormat {
+                    mode: ImageMode::JPEG,
+                    size: (100, 1200),
+                    rotation: ImageRotation::Rot270,
+                    mirror: ImageMirroring::None,
+                },
+                writes_regions: true,
+            }],
+
+            _ => vec![],
+        }
+    }
+
+    /// Returns screen of the device with the id
+    pub fn screen(&self, id: u8) -> Option<Screen> {
+        self.screens().into_iter().find(|screen| screen.id == id)
+    }
+
     /// Size of the LCD strip on the device
     pub fn lcd_strip_size(&self) -> Option<(usize, usize)> {
-        match self {
-            Kind::Plus => Some((800, 100)),
-            Kind::PlusXl => Some((100, 1200)),
-            Kind::Neo => Some((248, 58)),
-            _ => None,
+        self.screen(Screen::LCD_STRIP).map(|screen| screen.resolution)
+    }
+
+    /// Tells if the Stream Deck kind has a touch screen
+    pub fn has_touch_screen(&self) -> bool {
+        matches!(self, Kind::Plus | Kind::PlusXl)
+    }
+
+    /// Normalizes touch screen coordinates into 0.0 - 1.0 range of the LCD strip, in the orientation images for the strip are drawn in.
+    /// Device reports points as its panel is mounted, so they're mapped back through rotation and mirroring of the strip's image format.
+    /// Returns None if the Stream Deck kind has no touch screen
+    pub fn normalize_touch_point(&self, x: u16, y: u16) -> Option<(f32, f32)> {
+        if !self.has_touch_screen() {
+            return None;
         }
+
+        let (w, h) = self.lcd_strip_size()?;
+        let format = self.lcd_image_format()?;
+        let (x, y) = ((x as f32 / w as f32).clamp(0.0, 1.0), (y as f32 / h as f32).clamp(0.0, 1.0));
+
+        // Images are rotated first and mirrored after, so undoing it goes the other way around
+        let (x, y) = match format.mirror {
+            ImageMirroring::None => (x, y),
+            ImageMirroring::X => (1.0 - x, y),
+            ImageMirroring::Y => (x, 1.0 - y),
+            ImageMirroring::Both => (1.0 - x, 1.0 - y),
+        };
+
+        Some(match format.rotation {
+            ImageRotation::Rot0 => (x, y),
+            ImageRotation::Rot90 => (y, 1.0 - x),
+            ImageRotation::Rot180 => (1.0 - x, 1.0 - y),
+            ImageRotation::Rot270 => (1.0 - y, x),
+        })
     }
 
     /// Tells if the Stream Deck kind has a screen
@@ -225,7 +543,30 @@ impl Kind {
         (self.row_count(), self.column_count())
     }
 
-    /// Image format used by the Stream Deck kind
+    /// Position of the key as (row, column), counted from top left. None if the kind doesn't have the key
+    pub fn key_position(&self, key: u8) -> Option<(u8, u8)> {
+        if key >= self.key_count() {
+            return None;
+        }
+
+        Some((key / self.column_count(), key % self.column_count()))
+    }
+
+    /// Index of the key at the row and column, counted from top left. None if the kind doesn't have a key there
+    pub fn key_at(&self, row: u8, column: u8) -> Option<u8> {
+        if row >= self.row_count() || column >= self.column_count() {
+            return None;
+        }
+
+        Some(row * self.column_count() + column).filter(|key| *key < self.key_count())
+    }
+
+    /// Resolution of key images as (width, height) in pixels
+    pub fn key_image_resolution(&self) -> (usize, usize) {
+        self.key_image_format().size
+    }
+
+    /// Image format used by keys of the Stream Deck kind, describes codec, resolution, rotation and mirroring
     pub fn key_image_format(&self) -> ImageFormat {
         match self {
             Kind::Original => ImageFormat {
@@ -276,39 +617,33 @@ impl Kind {
 
     /// Image format used by LCD screen, used for filling LCD
     pub fn lcd_image_format(&self) -> Option<ImageFormat> {
-        match self {
-            Kind::Neo => Some(ImageFormat {
-                mode: ImageMode::JPEG,
-                size: (248, 58),
-                rotation: ImageRotation::Rot180,
-                mirror: ImageMirroring::None,
-            }),
-            Kind::Plus => Some(ImageFormat {
-                mode: ImageMode::JPEG,
-                size: (800, 100),
-                rotation: ImageRotation::Rot0,
-                mirror: ImageMirroring::None,
-            }),
-            Kind::PlusXl => Some(ImageFormat {
-                mode: ImageMode::JPEG,
-                size: (100, 1200),
-                rotation: ImageRotation::Rot270,
-                mirror: ImageMirroring::None,
-            }),
-            _ => None,
-        }
+        self.screen(Screen::LCD_STRIP).map(|screen| screen.format)
     }
 
     /// Returns blank image data appropriate for the Stream Deck kind
     pub fn blank_image(&self) -> Vec<u8> {
         match self {
             Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => {
-                let mut data = vec![
-                    0x42, 0x4d, 0xf6, 0x3c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x36, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x48, 0x00, 0x00, 0x00, 0x48, 0x00, 0x00, 0x00, 0x01, 0x00, 0x18, 0x00,
-                    0x00, 0x00, 0x00, 0x00, 0xc0, 0x3c, 0x00, 0x00, 0xc4, 0x0e, 0x00, 0x00, 0xc4, 0x0e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                ];
-
-                let (ws, hs) = self.key_image_format().size;
+                let (ws, hs) = self.key_image_resolution();
+                let pixel_data_size = (ws * hs * 3) as u32;
+
+                // Bitmap file header
+                let mut data = vec![0x42, 0x4d];
+                data.extend((54 + pixel_data_size).to_le_bytes());
+                data.extend([0u8; 4]);
+                data.extend(54u32.to_le_bytes());
+
+                // Bitmap info header, 24 bits per pixel without compression
+                data.extend(40u32.to_le_bytes());
+                data.extend((ws as u32).to_le_bytes());
+                data.extend((hs as u32).to_le_bytes());
+                data.extend(1u16.to_le_bytes());
+                data.extend(24u16.to_le_bytes());
+                data.extend(0u32.to_le_bytes());
+                data.extend(pixel_data_size.to_le_bytes());
+                data.extend(3780u32.to_le_bytes());
+                data.extend(3780u32.to_le_bytes());
+                data.extend([0u8; 8]);
 
                 data.extend(vec![0u8; ws * hs * 3]);
 
@@ -415,12 +750,194 @@ impl Kind {
     }
 }
 
+/// Screen of a Stream Deck besides key displays, like the LCD strip of Stream Deck Plus
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Screen {
+    /// Identifier of the screen, unique within the Stream Deck kind
+    pub id: u8,
+    /// Size of the screen as (width, height) in pixels, in coordinates the device uses for regions
+    pub resolution: (usize, usize),
+    /// Parts of the screen that belong to specific controls, like encoders, covering the whole screen
+    pub segments: Vec<ScreenSegment>,
+    /// Image format used for filling the whole screen
+    pub format: ImageFormat,
+    /// Tells if regions of the screen can be written, otherwise only the whole screen can be filled
+    pub writes_regions: bool,
+}
+
+impl Screen {
+    /// Id of the LCD strip, the only screen of current devices
+    pub const LCD_STRIP: u8 = 0;
+}
+
+/// Rectangle of a screen in pixels
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ScreenSegment {
+    /// Left edge
+    pub x: usize,
+    /// Top edge
+    pub y: usize,
+    /// Width
+    pub w: usize,
+    /// Height
+    pub h: usize,
+}
+
+impl ScreenSegment {
+    /// Tells if the point is within the rectangle
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.w && y < self.y + self.h
+    }
+}
+
+/// Touch sensitive key of the Stream Deck, reported as a touch point
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TouchKey {
+    /// Key for switching to the previous page, left of the screen on Stream Deck Neo
+    PageLeft,
+    /// Key for switching to the next page, right of the screen on Stream Deck Neo
+    PageRight,
+}
+
+impl TouchKey {
+    /// Returns touch point index of the key on the Stream Deck kind, None if the kind doesn't have it
+    pub fn point(&self, kind: Kind) -> Option<u8> {
+        kind.touch_keys().iter().position(|key| key == self).map(|point| point as u8)
+    }
+}
+
+/// Layout of input reports sent by a Stream Deck kind, offsets are counted from the start of the report including the report id
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReportLayout {
+    /// Length of the input report buffer
+    pub length: usize,
+    /// Offset of the byte telling type of the report, None if the kind only sends button reports
+    pub type_offset: Option<usize>,
+    /// Layout of button state reports
+    pub buttons: ButtonReportLayout,
+    /// Layout of touch screen reports, if the kind has a touch screen
+    pub touch_screen: Option<TouchScreenReportLayout>,
+    /// Layout of encoder reports, if the kind has encoders
+    pub encoders: Option<EncoderReportLayout>,
+}
+
+impl ReportLayout {
+    /// Returns type of the report. Kinds without typed reports only send button reports with the input report id,
+    /// so reports of theirs with any other report id are of unknown type
+    pub fn report_type(&self, report: &[u8]) -> Option<u8> {
+        match self.type_offset {
+            Some(offset) => report.get(offset).copied(),
+            None if report.first() == Some(&INPUT_REPORT_ID) => Some(self.buttons.report_type),
+            None => None,
+        }
+    }
+
+    /// Smallest length of a report of the type that contains all of its data, None for unknown types
+    pub fn required_length(&self, report_type: u8) -> Option<usize> {
+        if report_type == self.buttons.report_type {
+            return Some(self.buttons.required_length());
+        }
+
+        if let Some(touch_screen) = self.touch_screen
+            && report_type == touch_screen.report_type
+        {
+            return Some(touch_screen.required_length());
+        }
+
+        if let Some(encoders) = self.encoders
+            && report_type == encoders.report_type
+        {
+            return Some(encoders.required_length());
+        }
+
+        None
+    }
+
+    /// Tells if the report is of a known type, but is shorter than it needs to be, meaning reading went out of sync with the device.
+    /// Read is the number of bytes of the report that were actually read
+    pub fn is_malformed(&self, report: &[u8], read: usize) -> bool {
+        self.report_type(report)
+            .and_then(|report_type| self.required_length(report_type))
+            .is_some_and(|required| read < required)
+    }
+}
+
+/// Layout of button state reports, key states are followed by touch point states
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ButtonReportLayout {
+    /// Type of the report, only meaningful if the layout has a type offset
+    pub report_type: u8,
+    /// Offset of the first button state
+    pub offset: usize,
+    /// Number of button states, including touch points
+    pub count: usize,
+    /// Tells if key states are reported right to left in each row, like Original v1 does
+    pub flipped_columns: bool,
+}
+
+impl ButtonReportLayout {
+    /// Smallest length of a report that contains all button states
+    pub fn required_length(&self) -> usize {
+        self.offset + self.count
+    }
+}
+
+/// Layout of touch screen reports, coordinates are little endian u16 pairs
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TouchScreenReportLayout {
+    /// Type of the report
+    pub report_type: u8,
+    /// Offset of the byte telling if it's a press, long press or a swipe
+    pub event_offset: usize,
+    /// Offset of the touch coordinates
+    pub start_offset: usize,
+    /// Offset of the swipe end coordinates
+    pub end_offset: usize,
+}
+
+impl TouchScreenReportLayout {
+    /// Smallest length of a report that contains all touch data
+    pub fn required_length(&self) -> usize {
+        self.start_offset.max(self.end_offset) + 4
+    }
+}
+
+/// Layout of encoder reports
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EncoderReportLayout {
+    /// Type of the report
+    pub report_type: u8,
+    /// Offset of the byte telling if it's a press or a twist
+    pub event_offset: usize,
+    /// Offset of the first encoder value
+    pub offset: usize,
+    /// Number of encoders
+    pub count: usize,
+}
+
+impl EncoderReportLayout {
+    /// Smallest length of a report that contains all encoder values
+    pub fn required_length(&self) -> usize {
+        self.offset + self.count
+    }
+}
+
 /// Image format used by the Stream Deck
-#[derive(Copy, Clone, Debug, Hash)]
+///
+/// Images are first rotated, then resized to `size`, then mirrored and finally encoded with `mode`
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ImageFormat {
     /// Image format/mode
     pub mode: ImageMode,
-    /// Image size
+    /// Image size as (width, height) in pixels that the encoded image must have
     pub size: (usize, usize),
     /// Image rotation
     pub rotation: ImageRotation,
@@ -440,7 +957,8 @@ impl Default for ImageFormat {
 }
 
 /// Image rotation
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ImageRotation {
     /// No rotation
     Rot0,
@@ -453,7 +971,8 @@ pub enum ImageRotation {
 }
 
 /// Image mirroring
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ImageMirroring {
     /// No image mirroring
     None,
@@ -466,7 +985,8 @@ pub enum ImageMirroring {
 }
 
 /// Image format
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ImageMode {
     /// No image
     None,
@@ -0,0 +1,74 @@
+//! Elgato Streamdeck protocol core
+//!
+//! Device descriptions, report building and parsing of [elgato-streamdeck](https://crates.io/crates/elgato-streamdeck),
+//! without any dependency on std or hidapi. Useful for USB hosts that can't run hidapi, like firmware or RTOS based ones,
+//! which only need the encoding logic and bring their own transport.
+
+#![no_std]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![warn(missing_docs)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+
+/// Various information about Stream Deck devices
+pub mod info;
+/// Byte-level encoding of commands sent to devices and parsing of their input reports
+pub mod protocol;
+
+/// Type of input that the device produced
+#[derive(Clone, Debug)]
+pub enum StreamDeckInput {
+    /// No data was passed from the device
+    NoData,
+
+    /// Button was pressed
+    ButtonStateChange(Vec<bool>),
+
+    /// Encoder/Knob was pressed
+    EncoderStateChange(Vec<bool>),
+
+    /// Encoder/Knob was twisted/turned
+    EncoderTwist(Vec<i8>),
+
+    /// Touch screen received short press
+    TouchScreenPress(u16, u16),
+
+    /// Touch screen received long press
+    TouchScreenLongPress(u16, u16),
+
+    /// Touch screen received a swipe
+    TouchScreenSwipe((u16, u16), (u16, u16)),
+
+    /// Report the library doesn't understand as (report type, data of the report), only produced in passthrough parse mode.
+    /// Report type is the byte at [type offset](crate::info::ReportLayout::type_offset) of the report, or the HID report id for kinds without one
+    Unknown(u8, Vec<u8>),
+}
+
+impl StreamDeckInput {
+    /// Checks if there's data received or not
+    pub fn is_empty(&self) -> bool {
+        matches!(self, StreamDeckInput::NoData)
+    }
+}
+
+/// Errors that can occur while encoding or parsing reports
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum ProtocolError {
+    /// Report couldn't be parsed
+    BadData,
+
+    /// Image data can't be split into reports the device accepts
+    InvalidImageLength,
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for ProtocolError {}
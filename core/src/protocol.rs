@@ -0,0 +1,665 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::info::Kind;
+use crate::{ProtocolError, StreamDeckInput};
+
+/// Report id of input reports devices send
+pub const INPUT_REPORT_ID: u8 = 0x01;
+/// Report id of output reports carrying image data
+pub const IMAGE_REPORT_ID: u8 = 0x02;
+
+/// Report id of feature reports carrying commands to Gen2 devices, see [feature_report_length]
+pub const GEN2_COMMAND_REPORT_ID: u8 = 0x03;
+/// Opcode of the Gen2 command that resets the device
+pub const GEN2_RESET_OPCODE: u8 = 0x02;
+/// Opcode of the Gen2 command that sets LED color of a touch point
+pub const GEN2_TOUCHPOINT_COLOR_OPCODE: u8 = 0x06;
+/// Opcode of the Gen2 command that sets brightness
+pub const GEN2_BRIGHTNESS_OPCODE: u8 = 0x08;
+
+/// Report id of the feature report that resets Gen1 devices
+pub const GEN1_RESET_REPORT_ID: u8 = 0x0b;
+/// Opcode of the Gen1 reset command
+pub const GEN1_RESET_OPCODE: u8 = 0x63;
+/// Report id of the feature report that sets brightness of Gen1 devices
+pub const GEN1_BRIGHTNESS_REPORT_ID: u8 = 0x05;
+/// Bytes preceding the brightness percentage in the Gen1 brightness command
+pub const GEN1_BRIGHTNESS_PREFIX: [u8; 4] = [0x55, 0xaa, 0xd1, 0x01];
+
+/// Opcode of image reports writing a key on Gen1 devices
+pub const GEN1_KEY_IMAGE_OPCODE: u8 = 0x01;
+/// Opcode of image reports writing a key on Gen2 devices
+pub const GEN2_KEY_IMAGE_OPCODE: u8 = 0x07;
+/// Opcode of image reports filling the whole LCD
+pub const LCD_FILL_OPCODE: u8 = 0x0b;
+/// Opcode of image reports writing a region of the LCD
+pub const LCD_REGION_OPCODE: u8 = 0x0c;
+
+/// Report id of the feature report holding serial number of Original, Mini and Mini Mk2 family
+pub const GEN1_SERIAL_NUMBER_REPORT_ID: u8 = 0x03;
+/// Report id of the feature report holding firmware version of Gen1 devices
+pub const GEN1_FIRMWARE_VERSION_REPORT_ID: u8 = 0x04;
+/// Report id of the feature report holding firmware version of Mini Mk2 Module
+pub const MODULE_FIRMWARE_VERSION_REPORT_ID: u8 = 0xa1;
+/// Report id of the feature report holding serial number of Gen2 devices
+pub const GEN2_SERIAL_NUMBER_REPORT_ID: u8 = 0x06;
+/// Report id of the feature report holding firmware version of Gen2 devices
+pub const GEN2_FIRMWARE_VERSION_REPORT_ID: u8 = 0x05;
+
+/// Length of feature reports of Gen1 devices, including report id
+pub const GEN1_FEATURE_REPORT_LENGTH: usize = 17;
+/// Length of feature reports of Gen2 devices, including report id
+pub const GEN2_FEATURE_REPORT_LENGTH: usize = 32;
+/// Length of image reports, except for Original
+pub const IMAGE_REPORT_LENGTH: usize = 1024;
+/// Length of image reports of Original
+pub const ORIGINAL_IMAGE_REPORT_LENGTH: usize = 8191;
+/// Length of the header of Gen1 key image reports
+pub const GEN1_IMAGE_HEADER_LENGTH: usize = 16;
+/// Length of the header of Gen2 key image and LCD fill reports
+pub const GEN2_IMAGE_HEADER_LENGTH: usize = 8;
+/// Length of the header of LCD region reports
+pub const LCD_REGION_HEADER_LENGTH: usize = 16;
+
+/// Event byte of encoder input reports carrying press states
+pub const ENCODER_PRESS_EVENT: u8 = 0x00;
+/// Event byte of encoder input reports carrying twist values
+pub const ENCODER_TWIST_EVENT: u8 = 0x01;
+/// Event byte of touch screen input reports for a short press
+pub const TOUCH_PRESS_EVENT: u8 = 0x01;
+/// Event byte of touch screen input reports for a long press
+pub const TOUCH_LONG_PRESS_EVENT: u8 = 0x02;
+/// Event byte of touch screen input reports for a swipe
+pub const TOUCH_SWIPE_EVENT: u8 = 0x03;
+
+/// Feature report that a string is read from
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct StringReport {
+    /// Report id to request
+    pub report_id: u8,
+    /// Length of the report, including report id
+    pub length: usize,
+    /// Offset of the string within the report
+    pub offset: usize,
+}
+
+impl StringReport {
+    /// Feature report holding serial number of the kind
+    pub fn serial_number(kind: Kind) -> Self {
+        match kind {
+            Kind::Original | Kind::Mini => StringReport {
+                report_id: GEN1_SERIAL_NUMBER_REPORT_ID,
+                length: GEN1_FEATURE_REPORT_LENGTH,
+                offset: 5,
+            },
+
+            Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => StringReport {
+                report_id: GEN1_SERIAL_NUMBER_REPORT_ID,
+                length: GEN2_FEATURE_REPORT_LENGTH,
+                offset: 5,
+            },
+
+            _ => StringReport {
+                report_id: GEN2_SERIAL_NUMBER_REPORT_ID,
+                length: GEN2_FEATURE_REPORT_LENGTH,
+                offset: 2,
+            },
+        }
+    }
+
+    /// Feature report holding firmware version of the kind
+    pub fn firmware_version(kind: Kind) -> Self {
+        match kind {
+            Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord => StringReport {
+                report_id: GEN1_FIRMWARE_VERSION_REPORT_ID,
+                length: GEN1_FEATURE_REPORT_LENGTH,
+                offset: 5,
+            },
+
+            Kind::MiniMk2Module => StringReport {
+                report_id: MODULE_FIRMWARE_VERSION_REPORT_ID,
+                length: GEN1_FEATURE_REPORT_LENGTH,
+                offset: 5,
+            },
+
+            _ => StringReport {
+                report_id: GEN2_FIRMWARE_VERSION_REPORT_ID,
+                length: GEN2_FEATURE_REPORT_LENGTH,
+                offset: 6,
+            },
+        }
+    }
+}
+
+/// How image data of a key is split into reports
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum PagingStrategy {
+    /// Every page carries up to this many bytes, using as many pages as the image needs
+    FixedPayload(usize),
+    /// Image is split into this many pages of equal length, so the image must have exactly `image_len` bytes.
+    /// Used by Original, which expects its bitmap in two halves
+    EqualPages {
+        /// Number of pages
+        pages: usize,
+        /// Length of the image data the device expects
+        image_len: usize,
+    },
+}
+
+impl PagingStrategy {
+    /// Paging of key images used by the kind
+    pub fn for_key(kind: Kind) -> Self {
+        match kind {
+            Kind::Original => {
+                let (w, h) = kind.key_image_resolution();
+
+                // Bitmap headers followed by 24 bit pixels
+                PagingStrategy::EqualPages { pages: 2, image_len: 54 + w * h * 3 }
+            }
+
+            Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => PagingStrategy::FixedPayload(IMAGE_REPORT_LENGTH - GEN1_IMAGE_HEADER_LENGTH),
+
+            _ => PagingStrategy::FixedPayload(IMAGE_REPORT_LENGTH - GEN2_IMAGE_HEADER_LENGTH),
+        }
+    }
+
+    /// Most image bytes carried by a single page of the image
+    pub fn payload_length(&self, image_data_len: usize) -> usize {
+        match *self {
+            PagingStrategy::FixedPayload(length) => length,
+            PagingStrategy::EqualPages { pages, .. } => image_data_len.div_ceil(pages.max(1)),
+        }
+    }
+
+    /// Checks that image data can be paged with the strategy, fails with [ProtocolError::InvalidImageLength] otherwise
+    pub fn validate(&self, image_data_len: usize) -> Result<(), ProtocolError> {
+        let valid = match *self {
+            PagingStrategy::FixedPayload(_) => image_data_len > 0,
+            PagingStrategy::EqualPages { image_len, .. } => image_data_len == image_len,
+        };
+
+        if valid { Ok(()) } else { Err(ProtocolError::InvalidImageLength) }
+    }
+}
+
+/// Lengths of reports image data is split into
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct ImageReportParameters {
+    /// Length of every report, including header and padding
+    pub report_length: usize,
+    /// Most image bytes carried by a single report
+    pub payload_length: usize,
+}
+
+impl ImageReportParameters {
+    /// Parameters of key image reports for the kind, payload length depends on image length with [PagingStrategy::EqualPages]
+    pub fn for_key(kind: Kind, image_data_len: usize) -> Self {
+        let report_length = match kind {
+            Kind::Original => ORIGINAL_IMAGE_REPORT_LENGTH,
+            _ => IMAGE_REPORT_LENGTH,
+        };
+
+        Self {
+            report_length,
+            payload_length: PagingStrategy::for_key(kind).payload_length(image_data_len),
+        }
+    }
+
+    /// Parameters of LCD reports for the kind, None if the kind has no LCD that can be written
+    pub fn for_lcd(kind: Kind) -> Option<Self> {
+        match kind {
+            Kind::Neo => Some(Self {
+                report_length: IMAGE_REPORT_LENGTH,
+                payload_length: IMAGE_REPORT_LENGTH - GEN2_IMAGE_HEADER_LENGTH,
+            }),
+
+            Kind::Plus | Kind::PlusXl => Some(Self {
+                report_length: IMAGE_REPORT_LENGTH,
+                payload_length: IMAGE_REPORT_LENGTH - LCD_REGION_HEADER_LENGTH,
+            }),
+
+            _ => None,
+        }
+    }
+}
+
+/// Feature report that resets the device
+pub fn reset_command(kind: Kind) -> Vec<u8> {
+    let mut buf = match kind {
+        Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => vec![GEN1_RESET_REPORT_ID, GEN1_RESET_OPCODE],
+        _ => vec![GEN2_COMMAND_REPORT_ID, GEN2_RESET_OPCODE],
+    };
+
+    buf.resize(feature_report_length(kind), 0);
+    buf
+}
+
+/// Feature report that sets brightness of the device, value is clamped to 0 - 100 range
+pub fn brightness_command(kind: Kind, percent: u8) -> Vec<u8> {
+    let percent = percent.clamp(0, 100);
+
+    let mut buf = match kind {
+        Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => {
+            let mut buf = vec![GEN1_BRIGHTNESS_REPORT_ID];
+            buf.extend(GEN1_BRIGHTNESS_PREFIX);
+            buf.push(percent);
+            buf
+        }
+
+        _ => vec![GEN2_COMMAND_REPORT_ID, GEN2_BRIGHTNESS_OPCODE, percent],
+    };
+
+    buf.resize(feature_report_length(kind), 0);
+    buf
+}
+
+/// Feature report that sets LED color of the touch point, None if the kind doesn't have the touch point
+pub fn touchpoint_color_command(kind: Kind, point: u8, red: u8, green: u8, blue: u8) -> Option<Vec<u8>> {
+    if point >= kind.touchpoint_count() {
+        return None;
+    }
+
+    Some(vec![GEN2_COMMAND_REPORT_ID, GEN2_TOUCHPOINT_COLOR_OPCODE, point + kind.key_count(), red, green, blue])
+}
+
+/// Flips key index horizontally, for use with Original v1 Stream Deck
+pub fn flip_key_index(kind: &Kind, key: u8) -> u8 {
+    let col = key % kind.column_count();
+    (key - col) + ((kind.column_count() - 1) - col)
+}
+
+/// Reads button states, empty vector if no data
+pub fn read_button_states(kind: &Kind, states: &[u8]) -> Vec<bool> {
+    if states[0] == 0 {
+        return vec![];
+    }
+
+    let layout = kind.report_layout().buttons;
+    let states = &states[layout.offset..layout.required_length().min(states.len())];
+
+    if layout.flipped_columns {
+        return (0..states.len() as u8).filter_map(|i| states.get(flip_key_index(kind, i) as usize)).map(|s| *s != 0).collect();
+    }
+
+    states.iter().map(|s| *s != 0).collect()
+}
+
+/// Reads lcd screen input, fails if the kind has no touch screen
+pub fn read_lcd_input(kind: &Kind, data: &[u8]) -> Result<StreamDeckInput, ProtocolError> {
+    let layout = kind.report_layout().touch_screen.ok_or(ProtocolError::BadData)?;
+    let point = |offset: usize| (u16::from_le_bytes([data[offset], data[offset + 1]]), u16::from_le_bytes([data[offset + 2], data[offset + 3]]));
+
+    let (start_x, start_y) = point(layout.start_offset);
+
+    match data[layout.event_offset] {
+        TOUCH_PRESS_EVENT => Ok(StreamDeckInput::TouchScreenPress(start_x, start_y)),
+        TOUCH_LONG_PRESS_EVENT => Ok(StreamDeckInput::TouchScreenLongPress(start_x, start_y)),
+        TOUCH_SWIPE_EVENT => Ok(StreamDeckInput::TouchScreenSwipe((start_x, start_y), point(layout.end_offset))),
+
+        _ => Err(ProtocolError::BadData),
+    }
+}
+
+/// Reads encoder input, fails if the kind has no encoders
+pub fn read_encoder_input(kind: &Kind, data: &[u8]) -> Result<StreamDeckInput, ProtocolError> {
+    let layout = kind.report_layout().encoders.ok_or(ProtocolError::BadData)?;
+    let values = &data[layout.offset..layout.required_length()];
+
+    match data[layout.event_offset] {
+        ENCODER_PRESS_EVENT => Ok(StreamDeckInput::EncoderStateChange(values.iter().map(|s| *s != 0).collect())),
+
+        ENCODER_TWIST_EVENT => Ok(StreamDeckInput::EncoderTwist(values.iter().map(|s| i8::from_le_bytes([*s])).collect())),
+
+        _ => Err(ProtocolError::BadData),
+    }
+}
+
+/// Header of a key image report. Key is the index used everywhere else in the library, Original's flipped order is handled here
+pub fn key_image_header(kind: Kind, key: u8, page_number: usize, this_length: usize, last_package: bool) -> Vec<u8> {
+    match kind {
+        Kind::Original => gen1_image_header((page_number + 1) as u8, last_package, flip_key_index(&kind, key) + 1),
+
+        Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => gen1_image_header(page_number as u8, last_package, key + 1),
+
+        _ => vec![
+            IMAGE_REPORT_ID,
+            GEN2_KEY_IMAGE_OPCODE,
+            key,
+            last_package as u8,
+            (this_length & 0xff) as u8,
+            (this_length >> 8) as u8,
+            (page_number & 0xff) as u8,
+            (page_number >> 8) as u8,
+        ],
+    }
+}
+
+/// Header of a report writing a region of Stream Deck Plus' LCD
+pub fn lcd_region_header(x: u16, y: u16, w: u16, h: u16, page_number: usize, this_length: usize, last_package: bool) -> Vec<u8> {
+    vec![
+        IMAGE_REPORT_ID,
+        LCD_REGION_OPCODE,
+        (x & 0xff) as u8,
+        (x >> 8) as u8,
+        (y & 0xff) as u8,
+        (y >> 8) as u8,
+        (w & 0xff) as u8,
+        (w >> 8) as u8,
+        (h & 0xff) as u8,
+        (h >> 8) as u8,
+        last_package as u8,
+        (page_number & 0xff) as u8,
+        (page_number >> 8) as u8,
+        (this_length & 0xff) as u8,
+        (this_length >> 8) as u8,
+        0,
+    ]
+}
+
+/// Header of a report filling the whole LCD, None if the kind has no LCD that can be written
+pub fn lcd_fill_header(kind: Kind, page_number: usize, this_length: usize, last_package: bool) -> Option<Vec<u8>> {
+    match kind {
+        Kind::Neo => Some(vec![
+            IMAGE_REPORT_ID,
+            LCD_FILL_OPCODE,
+            0,
+            last_package as u8,
+            (this_length & 0xff) as u8,
+            (this_length >> 8) as u8,
+            (page_number & 0xff) as u8,
+            (page_number >> 8) as u8,
+        ]),
+
+        Kind::Plus | Kind::PlusXl => {
+            let (w, h) = kind.lcd_strip_size()?;
+            Some(lcd_region_header(0, 0, w as u16, h as u16, page_number, this_length, last_package))
+        }
+
+        _ => None,
+    }
+}
+
+/// Splits image data into padded reports, `header_fn` gets page number, length of the page's payload and whether it's the last page
+pub fn image_reports<'a, T>(image_data: &'a [u8], parameters: ImageReportParameters, header_fn: T) -> impl Iterator<Item = Vec<u8>> + 'a
+where
+    T: Fn(usize, usize, bool) -> Vec<u8> + 'a,
+{
+    image_data.chunks(parameters.payload_length.max(1)).enumerate().map(move |(page_number, payload)| {
+        let last_package = (page_number * parameters.payload_length.max(1) + payload.len()) == image_data.len();
+
+        let mut buf = header_fn(page_number, payload.len(), last_package);
+        buf.extend(payload);
+        buf.resize(parameters.report_length.max(buf.len()), 0);
+
+        buf
+    })
+}
+
+/// All reports that write the image data to the key
+pub fn key_image_reports(kind: Kind, key: u8, image_data: &[u8]) -> Vec<Vec<u8>> {
+    image_reports(image_data, ImageReportParameters::for_key(kind, image_data.len()), |page_number, this_length, last_package| {
+        key_image_header(kind, key, page_number, this_length, last_package)
+    })
+    .collect()
+}
+
+/// What an image report writes to
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum ImageTarget {
+    /// Display of the key, using the index used everywhere else in the library
+    Key(u8),
+    /// Region of the LCD
+    LcdRegion {
+        /// X position of the region
+        x: u16,
+        /// Y position of the region
+        y: u16,
+        /// Width of the region
+        w: u16,
+        /// Height of the region
+        h: u16,
+    },
+    /// Whole LCD
+    LcdFill,
+}
+
+/// Image report that was sent to a device, see [decode_image_report]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DecodedImageReport<'a> {
+    /// What the image is written to
+    pub target: ImageTarget,
+    /// Number of the page within the image, starting from 0
+    pub page_number: usize,
+    /// Tells if this is the last page of the image
+    pub last_package: bool,
+    /// Image data carried by the report, pages of kinds that don't report payload length include the padding
+    pub payload: &'a [u8],
+}
+
+/// Decodes output report built by [image_reports] for the kind, None if the report isn't an image report
+pub fn decode_image_report(kind: Kind, report: &[u8]) -> Option<DecodedImageReport<'_>> {
+    let u16_at = |offset: usize| Some(u16::from_le_bytes([*report.get(offset)?, *report.get(offset + 1)?]));
+
+    match (kind, report.get(..2)?) {
+        (Kind::Original, [IMAGE_REPORT_ID, GEN1_KEY_IMAGE_OPCODE]) => {
+            let payload_length = PagingStrategy::for_key(kind).payload_length(kind.blank_image().len());
+
+            Some(DecodedImageReport {
+                target: ImageTarget::Key(flip_key_index(&kind, report.get(5)?.checked_sub(1)?)),
+                page_number: (*report.get(2)? as usize).checked_sub(1)?,
+                last_package: *report.get(4)? != 0,
+                payload: report.get(16..(16 + payload_length).min(report.len()))?,
+            })
+        }
+
+        (Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module, [IMAGE_REPORT_ID, GEN1_KEY_IMAGE_OPCODE]) => Some(DecodedImageReport {
+            target: ImageTarget::Key(report.get(5)?.checked_sub(1)?),
+            page_number: *report.get(2)? as usize,
+            last_package: *report.get(4)? != 0,
+            payload: report.get(16..)?,
+        }),
+
+        (Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module, _) => None,
+
+        (_, [IMAGE_REPORT_ID, GEN2_KEY_IMAGE_OPCODE | LCD_FILL_OPCODE]) => Some(DecodedImageReport {
+            target: if report[1] == GEN2_KEY_IMAGE_OPCODE {
+                ImageTarget::Key(*report.get(2)?)
+            } else {
+                ImageTarget::LcdFill
+            },
+            page_number: u16_at(6)? as usize,
+            last_package: *report.get(3)? != 0,
+            payload: report.get(8..8 + u16_at(4)? as usize)?,
+        }),
+
+        (_, [IMAGE_REPORT_ID, LCD_REGION_OPCODE]) => Some(DecodedImageReport {
+            target: ImageTarget::LcdRegion {
+                x: u16_at(2)?,
+                y: u16_at(4)?,
+                w: u16_at(6)?,
+                h: u16_at(8)?,
+            },
+            page_number: u16_at(11)? as usize,
+            last_package: *report.get(10)? != 0,
+            payload: report.get(16..16 + u16_at(13)? as usize)?,
+        }),
+
+        _ => None,
+    }
+}
+
+/// Input report the kind sends for the input, None if the kind can't send it.
+/// Button states and encoder values have to be given for every button or encoder of the kind, like they are read
+pub fn input_report(kind: Kind, input: &StreamDeckInput) -> Option<Vec<u8>> {
+    let layout = kind.report_layout();
+
+    let mut report = vec![0u8; layout.length];
+    report[0] = INPUT_REPORT_ID;
+
+    let set_type = |report: &mut Vec<u8>, report_type: u8| {
+        if let Some(offset) = layout.type_offset {
+            report[offset] = report_type;
+        }
+    };
+
+    let put_point = |report: &mut Vec<u8>, offset: usize, (x, y): (u16, u16)| {
+        report[offset..offset + 2].copy_from_slice(&x.to_le_bytes());
+        report[offset + 2..offset + 4].copy_from_slice(&y.to_le_bytes());
+    };
+
+    match input {
+        StreamDeckInput::ButtonStateChange(states) => {
+            let buttons = layout.buttons;
+
+            if states.len() != buttons.count {
+                return None;
+            }
+
+            set_type(&mut report, buttons.report_type);
+
+            for (index, state) in states.iter().enumerate() {
+                let index = if buttons.flipped_columns && index < kind.key_count() as usize {
+                    flip_key_index(&kind, index as u8) as usize
+                } else {
+                    index
+                };
+
+                report[buttons.offset + index] = *state as u8;
+            }
+        }
+
+        StreamDeckInput::EncoderStateChange(_) | StreamDeckInput::EncoderTwist(_) => {
+            let encoders = layout.encoders?;
+
+            let (event, values): (u8, Vec<u8>) = match input {
+                StreamDeckInput::EncoderStateChange(states) => (ENCODER_PRESS_EVENT, states.iter().map(|state| *state as u8).collect()),
+                StreamDeckInput::EncoderTwist(values) => (ENCODER_TWIST_EVENT, values.iter().map(|value| *value as u8).collect()),
+                _ => return None,
+            };
+
+            if values.len() != encoders.count {
+                return None;
+            }
+
+            set_type(&mut report, encoders.report_type);
+            report.resize(report.len().max(encoders.required_length()), 0);
+            report[encoders.event_offset] = event;
+            report[encoders.offset..encoders.offset + encoders.count].copy_from_slice(&values);
+        }
+
+        StreamDeckInput::TouchScreenPress(..) | StreamDeckInput::TouchScreenLongPress(..) | StreamDeckInput::TouchScreenSwipe(..) => {
+            let touch_screen = layout.touch_screen?;
+
+            set_type(&mut report, touch_screen.report_type);
+            report.resize(report.len().max(touch_screen.required_length()), 0);
+
+            match *input {
+                StreamDeckInput::TouchScreenPress(x, y) => {
+                    report[touch_screen.event_offset] = TOUCH_PRESS_EVENT;
+                    put_point(&mut report, touch_screen.start_offset, (x, y));
+                }
+
+                StreamDeckInput::TouchScreenLongPress(x, y) => {
+                    report[touch_screen.event_offset] = TOUCH_LONG_PRESS_EVENT;
+                    put_point(&mut report, touch_screen.start_offset, (x, y));
+                }
+
+                StreamDeckInput::TouchScreenSwipe(start, end) => {
+                    report[touch_screen.event_offset] = TOUCH_SWIPE_EVENT;
+                    put_point(&mut report, touch_screen.start_offset, start);
+                    put_point(&mut report, touch_screen.end_offset, end);
+                }
+
+                _ => return None,
+            }
+        }
+
+        _ => return None,
+    }
+
+    Some(report)
+}
+
+/// Renders every command of the kind as text, one report per line, so changes to the encoding show up in a diff.
+///
+/// Image reports are rendered as their header along with payload and report lengths, using blank key image and fixed LCD data.
+/// Snapshots of all kinds are checked by `tests/command_encoding.rs`, run it with `UPDATE_SNAPSHOTS=1` to regenerate them after adding a device
+pub fn command_snapshot(kind: Kind) -> String {
+    let mut snapshot = format!("# {:?}\n", kind);
+
+    let _ = writeln!(snapshot, "reset: {}", hex(&reset_command(kind)));
+
+    for percent in [0, 50, 100] {
+        let _ = writeln!(snapshot, "brightness {}: {}", percent, hex(&brightness_command(kind, percent)));
+    }
+
+    for point in 0..kind.touchpoint_count() {
+        if let Some(report) = touchpoint_color_command(kind, point, 0x11, 0x22, 0x33) {
+            let _ = writeln!(snapshot, "touchpoint {} color: {}", point, hex(&report));
+        }
+    }
+
+    if kind.is_visual() {
+        let blank = kind.blank_image();
+        let parameters = ImageReportParameters::for_key(kind, blank.len());
+
+        for key in [0, kind.key_count() - 1] {
+            write_image_pages(&mut snapshot, &format!("key {} image", key), &blank, parameters, |page_number, this_length, last_package| {
+                key_image_header(kind, key, page_number, this_length, last_package)
+            });
+        }
+    }
+
+    if let Some(parameters) = ImageReportParameters::for_lcd(kind) {
+        let data = vec![0u8; parameters.payload_length + 1];
+
+        if matches!(kind, Kind::Plus | Kind::PlusXl) {
+            write_image_pages(&mut snapshot, "lcd region", &data, parameters, |page_number, this_length, last_package| {
+                lcd_region_header(10, 20, 100, 50, page_number, this_length, last_package)
+            });
+        }
+
+        write_image_pages(&mut snapshot, "lcd fill", &data, parameters, |page_number, this_length, last_package| {
+            lcd_fill_header(kind, page_number, this_length, last_package).unwrap_or_default()
+        });
+    }
+
+    snapshot
+}
+
+/// Writes header, payload length and report length of every page of the image
+fn write_image_pages<T>(snapshot: &mut String, name: &str, image_data: &[u8], parameters: ImageReportParameters, header_fn: T)
+where
+    T: Fn(usize, usize, bool) -> Vec<u8>,
+{
+    let payloads = image_data.chunks(parameters.payload_length.max(1));
+    let page_count = payloads.len();
+
+    for ((page_number, payload), report) in payloads.enumerate().zip(image_reports(image_data, parameters, &header_fn)) {
+        let header = header_fn(page_number, payload.len(), page_number + 1 == page_count);
+        let _ = writeln!(snapshot, "{} page {}: {} (payload {}, report {})", name, page_number, hex(&header), payload.len(), report.len());
+    }
+}
+
+fn hex(report: &[u8]) -> String {
+    report.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+/// Length of feature reports sent to the kind, including report id
+pub fn feature_report_length(kind: Kind) -> usize {
+    match kind {
+        Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => GEN1_FEATURE_REPORT_LENGTH,
+        _ => GEN2_FEATURE_REPORT_LENGTH,
+    }
+}
+
+/// Header of a Gen1 key image report, page number and key are already adjusted for the kind
+fn gen1_image_header(page_number: u8, last_package: bool, key: u8) -> Vec<u8> {
+    let mut header = vec![0; GEN1_IMAGE_HEADER_LENGTH];
+    header[..6].copy_from_slice(&[IMAGE_REPORT_ID, GEN1_KEY_IMAGE_OPCODE, page_number, 0, last_package as u8, key]);
+    header
+}
@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use elgato_streamdeck::bench::{mock_input_latency, run};
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{list_devices, new_hidapi, ConnectOptions, StreamDeck};
+
+/// Runs benchmarks against every connected device, or against a mock Stream Deck XL if started with `--mock`
+fn main() {
+    let iterations = 50;
+
+    if std::env::args().any(|arg| arg == "--mock") {
+        // Roughly the time a full report takes to transfer over USB 2.0
+        let transport = MockTransport::new();
+        transport.set_write_delay(Duration::from_micros(125));
+
+        let device = StreamDeck::from_transport(Kind::Xl, transport, ConnectOptions::default());
+        println!("{}", run(&device, iterations).expect("Benchmark failed"));
+
+        let latency = mock_input_latency(Kind::Xl, iterations, |update| println!("{:?}", update)).expect("Benchmark failed");
+        println!("End-to-end input latency: {}", latency);
+
+        return;
+    }
+
+    let hid = new_hidapi().expect("Failed to create HidApi");
+
+    for (kind, serial) in list_devices(&hid) {
+        let device = StreamDeck::connect(&hid, kind, &serial).expect("Failed to connect");
+        println!("{}\n", run(&device, iterations).expect("Benchmark failed"));
+    }
+}
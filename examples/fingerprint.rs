@@ -0,0 +1,15 @@
+use elgato_streamdeck::new_hidapi;
+use elgato_streamdeck::probe::{probe_unknown_device, probe_unknown_devices};
+
+/// Writes fingerprints of connected devices that aren't supported yet, attach them to an issue asking for support
+fn main() {
+    let hid = new_hidapi().expect("Failed to create HidApi");
+
+    for device in probe_unknown_devices(&hid) {
+        let fingerprint = probe_unknown_device(&hid, &device.path).expect("Failed to probe device");
+        let file = format!("fingerprint-{:04x}-{:04x}.json", device.vendor_id, device.product_id);
+
+        fingerprint.save(&file).expect("Failed to save fingerprint");
+        println!("Saved fingerprint of {:04x}:{:04x} to {}", device.vendor_id, device.product_id, file);
+    }
+}
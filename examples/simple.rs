@@ -3,7 +3,7 @@ use std::time::Duration;
 
 use image::open;
 
-use elgato_streamdeck::info::{ImageFormat};
+use elgato_streamdeck::info::{ImageFormat, Screen, ScreenSegment};
 use elgato_streamdeck::{DeviceStateUpdate, list_devices, new_hidapi, StreamDeck};
 use elgato_streamdeck::images::{convert_image_with_format, ImageRect};
 
@@ -79,7 +79,7 @@ fn main() {
                                 }
                                 DeviceStateUpdate::ButtonUp(key) => {
                                     println!("Button {} up", key);
-                                    if key == device.kind().key_count() - 1 {
+                                    if key.0 == device.kind().key_count() - 1 {
                                         break 'infinite;
                                     }
                                 }
@@ -100,10 +100,24 @@ fn main() {
                                     println!("Touch point {} up", point);
                                 }
 
+                                DeviceStateUpdate::TouchKeyDown(key) => {
+                                    println!("Touch key {:?} down", key);
+                                }
+                                DeviceStateUpdate::TouchKeyUp(key) => {
+                                    println!("Touch key {:?} up", key);
+                                }
+
                                 DeviceStateUpdate::TouchScreenPress(x, y) => {
                                     println!("Touch Screen press at {x}, {y}");
                                     if let Some(small) = &small {
-                                        device.write_lcd(x, y, small).unwrap();
+                                        let region = ScreenSegment {
+                                            x: x as usize,
+                                            y: y as usize,
+                                            w: small.w as usize,
+                                            h: small.h as usize,
+                                        };
+                                        // Presses near the edge would put the image partly outside of the screen
+                                        let _ = device.write_screen(Screen::LCD_STRIP, Some(region), &small.data);
                                     }
                                 }
 
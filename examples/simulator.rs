@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::thread::spawn;
+use std::time::Duration;
+
+use image::{DynamicImage, Rgb, RgbImage};
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::simulator::Simulator;
+use elgato_streamdeck::{ConnectOptions, DeviceStateUpdate};
+
+fn main() {
+    let simulator = Simulator::new(Kind::Plus);
+    let device = simulator.connect(ConnectOptions::default());
+
+    // Device is used from another thread, since the window has to run on the main one
+    spawn(move || {
+        let kind = device.kind();
+
+        for key in 0..kind.key_count() {
+            let shade = (key as u32 * 255 / kind.key_count() as u32) as u8;
+            let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(72, 72, Rgb([shade, 64, 255 - shade])));
+            device.set_button_image(key, image).unwrap();
+        }
+
+        device.flush().unwrap();
+
+        #[allow(clippy::arc_with_non_send_sync)]
+        let device = Arc::new(device);
+        let reader = device.get_reader();
+
+        loop {
+            let Ok(updates) = reader.read(Some(Duration::from_secs(1))) else {
+                break;
+            };
+
+            for update in updates {
+                match update {
+                    DeviceStateUpdate::ButtonDown(key) => {
+                        println!("Button {} down", key);
+                        device.set_button_image(key, DynamicImage::ImageRgb8(RgbImage::from_pixel(72, 72, Rgb([255, 255, 255])))).unwrap();
+                        device.flush().unwrap();
+                    }
+                    other => println!("{:?}", other),
+                }
+            }
+        }
+    });
+
+    simulator.run().unwrap();
+}
@@ -0,0 +1,191 @@
+use std::fmt::{Display, Formatter};
+
+use hidapi::HidApi;
+
+use crate::info::Kind;
+use crate::util::normalize_serial;
+
+/// Outcome of [check_access], telling what stands between the application and the device
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum AccessVerdict {
+    /// Device can be opened
+    Ok,
+    /// Device is there, but the user isn't allowed to open it without udev rules
+    NeedsUdevRule,
+    /// Device is there, but macOS didn't allow opening it, missing permission or sandbox entitlement
+    NeedsEntitlement,
+    /// Device is held exclusively by another application
+    DeviceBusy,
+    /// Device is connected over USB, but no HID interface is available for it
+    DriverMissing,
+    /// No such device seems to be connected
+    NotFound,
+    /// Device is there, but it couldn't be figured out why it can't be opened, the detail of the check tells what failed
+    Unknown,
+}
+
+impl AccessVerdict {
+    /// Tells if the device can be opened
+    pub fn is_ok(&self) -> bool {
+        *self == AccessVerdict::Ok
+    }
+
+    /// What the user can do about the verdict on the platform the crate was compiled for, None if there's nothing to do
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            AccessVerdict::Ok => None,
+            AccessVerdict::NeedsUdevRule => Some(
+                "Copy 40-streamdeck.rules from the elgato-streamdeck repository into /etc/udev/rules.d/, \
+                 run `sudo udevadm control --reload-rules && sudo udevadm trigger` and replug the device",
+            ),
+            AccessVerdict::NeedsEntitlement => Some(
+                "Allow the application in System Settings > Privacy & Security > Input Monitoring, \
+                 and give sandboxed applications the com.apple.security.device.usb entitlement",
+            ),
+            AccessVerdict::DeviceBusy => Some(if cfg!(windows) {
+                "Quit other software using the device, such as the Elgato Stream Deck application"
+            } else if cfg!(target_os = "macos") {
+                "Quit other software using the device, such as the Elgato Stream Deck application, or another instance of this application"
+            } else {
+                "Close other applications that have the device open, `fuser -v /dev/hidraw*` lists them"
+            }),
+            AccessVerdict::DriverMissing => Some(if cfg!(windows) {
+                "Reinstall the device in Device Manager, it should show up as a HID-compliant device"
+            } else if cfg!(target_os = "macos") {
+                "Replug the device, and make sure no other driver claimed it"
+            } else {
+                "Make sure the hidraw and usbhid kernel modules are loaded, `sudo modprobe hid usbhid hidraw`"
+            }),
+            AccessVerdict::NotFound => Some("Check that the device is plugged in, and try another cable or port"),
+            AccessVerdict::Unknown => Some("Replug the device and try again"),
+        }
+    }
+}
+
+impl Display for AccessVerdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AccessVerdict::Ok => "ok",
+            AccessVerdict::NeedsUdevRule => "needs udev rule",
+            AccessVerdict::NeedsEntitlement => "needs entitlement",
+            AccessVerdict::DeviceBusy => "device busy",
+            AccessVerdict::DriverMissing => "driver missing",
+            AccessVerdict::NotFound => "not found",
+            AccessVerdict::Unknown => "unknown",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Result of [check_access]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccessCheck {
+    /// What stands between the application and the device
+    pub verdict: AccessVerdict,
+    /// Error that led to the verdict, if there was one
+    pub detail: Option<String>,
+}
+
+impl AccessCheck {
+    fn new(verdict: AccessVerdict, detail: Option<String>) -> AccessCheck {
+        AccessCheck { verdict, detail }
+    }
+}
+
+/// Checks if the device could be opened, and if not, tries to figure out why,
+/// so applications can show the user what to do instead of a bare error.
+/// With `fake` feature, only checks if the device is among fake devices if [FAKE_ENV](crate::fake::FAKE_ENV) is set.
+///
+/// **WARNING:** Device is opened and closed right away, run it before connecting to the device
+pub fn check_access(hidapi: &HidApi, kind: Kind, serial: &str) -> AccessCheck {
+    let serial = normalize_serial(serial);
+
+    #[cfg(feature = "fake")]
+    if let Some(devices) = crate::fake::fake_devices() {
+        let verdict = if devices.iter().any(|device| device.kind == kind && device.serial == serial) {
+            AccessVerdict::Ok
+        } else {
+            AccessVerdict::NotFound
+        };
+
+        return AccessCheck::new(verdict, None);
+    }
+
+    let Some(info) = hidapi
+        .device_list()
+        .find(|info| info.vendor_id() == kind.vendor_id() && info.product_id() == kind.product_id() && info.serial_number().is_some_and(|other| normalize_serial(other) == serial))
+    else {
+        return if is_on_usb(kind, &serial) {
+            AccessCheck::new(AccessVerdict::DriverMissing, None)
+        } else {
+            AccessCheck::new(AccessVerdict::NotFound, None)
+        };
+    };
+
+    match info.open_device(hidapi) {
+        Ok(_) => AccessCheck::new(AccessVerdict::Ok, None),
+        Err(e) => AccessCheck::new(open_failure_verdict(&info.path().to_string_lossy(), &e.to_string()), Some(e.to_string())),
+    }
+}
+
+/// Figures out why the device at the path couldn't be opened
+#[cfg(target_os = "linux")]
+fn open_failure_verdict(path: &str, _message: &str) -> AccessVerdict {
+    use std::fs::OpenOptions;
+    use std::io::ErrorKind;
+
+    // hidapi doesn't keep the error code, so opening is retried to get it
+    if !path.starts_with("/dev/") {
+        return AccessVerdict::NeedsUdevRule;
+    }
+
+    match OpenOptions::new().read(true).write(true).open(path) {
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => AccessVerdict::NeedsUdevRule,
+        Err(e) if e.kind() == ErrorKind::ResourceBusy => AccessVerdict::DeviceBusy,
+        Err(e) if e.kind() == ErrorKind::NotFound => AccessVerdict::NotFound,
+        _ => AccessVerdict::Unknown,
+    }
+}
+
+/// Figures out why the device couldn't be opened
+#[cfg(target_os = "macos")]
+fn open_failure_verdict(_path: &str, message: &str) -> AccessVerdict {
+    let message = message.to_lowercase();
+
+    if message.contains("exclusive") || message.contains("busy") {
+        AccessVerdict::DeviceBusy
+    } else {
+        AccessVerdict::NeedsEntitlement
+    }
+}
+
+/// Figures out why the device couldn't be opened
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn open_failure_verdict(_path: &str, _message: &str) -> AccessVerdict {
+    // Windows only refuses HID devices that another application opened without sharing
+    AccessVerdict::DeviceBusy
+}
+
+/// Tells if a device of the kind with the [normalized](normalize_serial) serial number is connected to USB, regardless of it having a HID interface
+#[cfg(target_os = "linux")]
+fn is_on_usb(kind: Kind, serial: &str) -> bool {
+    let Ok(entries) = std::fs::read_dir("/sys/bus/usb/devices") else {
+        return false;
+    };
+
+    let read_id = |path: std::path::PathBuf| std::fs::read_to_string(path).map(|id| id.trim().to_lowercase()).unwrap_or_default();
+
+    entries.flatten().any(|entry| {
+        read_id(entry.path().join("idVendor")) == format!("{:04x}", kind.vendor_id())
+            && read_id(entry.path().join("idProduct")) == format!("{:04x}", kind.product_id())
+            && std::fs::read_to_string(entry.path().join("serial")).is_ok_and(|other| normalize_serial(&other) == serial)
+    })
+}
+
+/// Tells if a device of the kind with the [normalized](normalize_serial) serial number is connected to USB, regardless of it having a HID interface
+#[cfg(not(target_os = "linux"))]
+fn is_on_usb(_kind: Kind, _serial: &str) -> bool {
+    // USB devices can't be listed without HID here, so missing devices are reported as not found
+    false
+}
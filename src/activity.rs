@@ -0,0 +1,162 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hidapi::HidError;
+
+use crate::transport::Transport;
+
+/// Most bytes of a report kept in the log
+const RECORDED_LENGTH: usize = 64;
+
+/// What was exchanged with the device
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum ActivityKind {
+    /// Output report was written, like a page of an image
+    Write,
+    /// Input report was read
+    Read,
+    /// Feature report was sent, like a brightness command
+    SendFeatureReport,
+    /// Feature report was requested, like firmware version
+    GetFeatureReport,
+}
+
+/// Report exchanged with the device, see [StreamDeck::recent_activity](crate::StreamDeck::recent_activity).
+///
+/// Displays as a single line with timestamp and report in hex, suitable for crash reports and bug filings
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Activity {
+    /// When the report was exchanged
+    pub time: SystemTime,
+    /// What was exchanged
+    pub kind: ActivityKind,
+    /// Full length of the report
+    pub length: usize,
+    /// Start of the report, up to 64 bytes
+    pub data: Vec<u8>,
+    /// Error the exchange failed with
+    pub error: Option<String>,
+}
+
+impl Display for Activity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let since_epoch = self.time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+
+        write!(f, "{}.{:03} {:?} ({} bytes)", since_epoch.as_secs(), since_epoch.subsec_millis(), self.kind, self.length)?;
+
+        if let Some(error) = &self.error {
+            write!(f, " failed: {}", error)?;
+        }
+
+        if !self.data.is_empty() {
+            write!(f, ":")?;
+
+            for byte in &self.data {
+                write!(f, " {:02x}", byte)?;
+            }
+
+            if self.data.len() < self.length {
+                write!(f, " ...")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Ring buffer of recent activity of a device
+#[derive(Debug)]
+pub(crate) struct ActivityLog {
+    capacity: usize,
+    entries: Mutex<VecDeque<Activity>>,
+}
+
+impl ActivityLog {
+    pub(crate) fn new(capacity: usize) -> ActivityLog {
+        ActivityLog {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Recorded activity, oldest first
+    pub(crate) fn entries(&self) -> Vec<Activity> {
+        self.entries.lock().map(|entries| entries.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    fn record(&self, kind: ActivityKind, report: &[u8], error: Option<&HidError>) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+
+        entries.push_back(Activity {
+            time: SystemTime::now(),
+            kind,
+            length: report.len(),
+            data: report[..report.len().min(RECORDED_LENGTH)].to_vec(),
+            error: error.map(|error| error.to_string()),
+        });
+    }
+}
+
+/// Transport that records everything exchanged through it into the log
+pub(crate) struct RecordingTransport {
+    pub(crate) inner: Box<dyn Transport>,
+    pub(crate) log: Arc<ActivityLog>,
+}
+
+impl Transport for RecordingTransport {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        let result = self.inner.write(data);
+        self.log.record(ActivityKind::Write, data, result.as_ref().err());
+        result
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, HidError> {
+        let result = self.inner.read(buf, timeout);
+
+        // Reads that timed out without data are not worth recording
+        match &result {
+            Ok(0) => {}
+            Ok(length) => self.log.record(ActivityKind::Read, &buf[..*length], None),
+            Err(error) => self.log.record(ActivityKind::Read, &[], Some(error)),
+        }
+
+        result
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        let result = self.inner.get_feature_report(buf);
+
+        match &result {
+            Ok(length) => self.log.record(ActivityKind::GetFeatureReport, &buf[..(*length).min(buf.len())], None),
+            Err(error) => self.log.record(ActivityKind::GetFeatureReport, &buf[..1.min(buf.len())], Some(error)),
+        }
+
+        result
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError> {
+        let result = self.inner.send_feature_report(data);
+        self.log.record(ActivityKind::SendFeatureReport, data, result.as_ref().err());
+        result
+    }
+
+    fn manufacturer(&self) -> Result<Option<String>, HidError> {
+        self.inner.manufacturer()
+    }
+
+    fn product(&self) -> Result<Option<String>, HidError> {
+        self.inner.product()
+    }
+
+    fn report_descriptor(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        self.inner.report_descriptor(buf)
+    }
+}
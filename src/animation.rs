@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use image::DynamicImage;
+
+use crate::{KeyIndex, StreamDeck, StreamDeckError};
+
+/// Something that produces images of a key over time
+pub trait KeyAnimation {
+    /// Returns image of the key for the moment in time since the animation started,
+    /// or None if the image didn't change since the previous frame
+    fn frame(&mut self, elapsed: Duration) -> Option<DynamicImage>;
+}
+
+struct RunningAnimation {
+    animation: Box<dyn KeyAnimation + Send>,
+    elapsed: Duration,
+    speed: f32,
+    paused: bool,
+}
+
+/// Drives animations of individual keys at a fixed frame rate
+pub struct AnimationScheduler {
+    animations: BTreeMap<u8, RunningAnimation>,
+    frame_interval: Duration,
+    last_tick: Option<Instant>,
+}
+
+impl AnimationScheduler {
+    /// Creates scheduler that renders animations at provided frames per second
+    pub fn new(fps: f32) -> AnimationScheduler {
+        AnimationScheduler {
+            animations: BTreeMap::new(),
+            frame_interval: Duration::from_secs_f32(1.0 / fps.max(0.001)),
+            last_tick: None,
+        }
+    }
+
+    /// Starts animation on the key, replacing animation that was running on it before
+    pub fn start(&mut self, key: impl Into<KeyIndex>, animation: impl KeyAnimation + Send + 'static) {
+        self.animations.insert(
+            key.into().0,
+            RunningAnimation {
+                animation: Box::new(animation),
+                elapsed: Duration::ZERO,
+                speed: 1.0,
+                paused: false,
+            },
+        );
+    }
+
+    /// Stops animation on the key, the last frame stays on the key. Returns false if nothing was running on it
+    pub fn stop(&mut self, key: impl Into<KeyIndex>) -> bool {
+        self.animations.remove(&key.into().0).is_some()
+    }
+
+    /// Stops all animations
+    pub fn stop_all(&mut self) {
+        self.animations.clear();
+    }
+
+    /// Tells if there's an animation running on the key
+    pub fn is_running(&self, key: impl Into<KeyIndex>) -> bool {
+        self.animations.contains_key(&key.into().0)
+    }
+
+    /// Pauses or resumes animation on the key
+    pub fn set_paused(&mut self, key: impl Into<KeyIndex>, paused: bool) {
+        if let Some(running) = self.animations.get_mut(&key.into().0) {
+            running.paused = paused;
+        }
+    }
+
+    /// Sets playback speed of animation on the key, 1.0 is normal speed
+    pub fn set_speed(&mut self, key: impl Into<KeyIndex>, speed: f32) {
+        if let Some(running) = self.animations.get_mut(&key.into().0) {
+            running.speed = speed.max(0.0);
+        }
+    }
+
+    /// Time left until the next frame is due
+    pub fn next_frame_in(&self) -> Duration {
+        match self.last_tick {
+            Some(last) => self.frame_interval.saturating_sub(last.elapsed()),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Renders frames of all animations if the next frame is due, stages them and flushes the device.
+    /// Returns true if anything was written to the device
+    pub fn tick(&mut self, device: &StreamDeck) -> Result<bool, StreamDeckError> {
+        let now = Instant::now();
+
+        let delta = match self.last_tick {
+            Some(last) if now.duration_since(last) < self.frame_interval => return Ok(false),
+            Some(last) => now.duration_since(last),
+            None => Duration::ZERO,
+        };
+
+        self.last_tick = Some(now);
+
+        let mut updated = false;
+
+        for (key, running) in &mut self.animations {
+            if running.paused {
+                continue;
+            }
+
+            running.elapsed += delta.mul_f32(running.speed);
+
+            if let Some(frame) = running.animation.frame(running.elapsed) {
+                device.set_button_image(*key, frame)?;
+                updated = true;
+            }
+        }
+
+        if updated {
+            device.flush()?;
+        }
+
+        Ok(updated)
+    }
+}
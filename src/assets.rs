@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, RwLock};
+
+use image::DynamicImage;
+
+use crate::images::{convert_image_with_filter, ResizeFilter};
+use crate::info::Kind;
+use crate::StreamDeckError;
+
+/// Converted image data is kept per asset name, device kind and filter it was scaled with
+type ConvertedKey = (String, Kind, ResizeFilter);
+
+/// Images registered by name once and reused across keys, pages and profiles.
+///
+/// Images are converted for a kind the first time a device of that kind uses them, and the converted data is kept,
+/// so setting the same image again or on other devices of the kind costs nothing but the transfer.
+/// Registry is shared between devices through [ConnectOptions::assets](crate::ConnectOptions::assets),
+/// images are then set with [set_button_asset](crate::StreamDeck::set_button_asset)
+#[derive(Default)]
+pub struct AssetRegistry {
+    images: RwLock<HashMap<String, DynamicImage>>,
+    converted: RwLock<HashMap<ConvertedKey, Arc<[u8]>>>,
+}
+
+impl AssetRegistry {
+    /// Creates empty registry
+    pub fn new() -> AssetRegistry {
+        AssetRegistry::default()
+    }
+
+    /// Registers the image under the name, replacing image that was registered under it before.
+    /// Keys already showing the previous image keep it until the asset is set on them again
+    pub fn register(&self, name: &str, image: DynamicImage) -> Result<(), StreamDeckError> {
+        self.images.write()?.insert(name.to_string(), image);
+        self.converted.write()?.retain(|(asset, _, _), _| asset != name);
+
+        Ok(())
+    }
+
+    /// Removes the image registered under the name, returns it if there was one
+    pub fn unregister(&self, name: &str) -> Result<Option<DynamicImage>, StreamDeckError> {
+        self.converted.write()?.retain(|(asset, _, _), _| asset != name);
+        Ok(self.images.write()?.remove(name))
+    }
+
+    /// Image registered under the name
+    pub fn get(&self, name: &str) -> Result<Option<DynamicImage>, StreamDeckError> {
+        Ok(self.images.read()?.get(name).cloned())
+    }
+
+    /// Tells if an image is registered under the name
+    pub fn contains(&self, name: &str) -> Result<bool, StreamDeckError> {
+        Ok(self.images.read()?.contains_key(name))
+    }
+
+    /// Names of all registered images, in no particular order
+    pub fn names(&self) -> Result<Vec<String>, StreamDeckError> {
+        Ok(self.images.read()?.keys().cloned().collect())
+    }
+
+    /// Image data of the asset for keys of the kind, converted on first use.
+    /// Fails with [StreamDeckError::UnknownAsset] if nothing is registered under the name
+    pub fn converted(&self, name: &str, kind: Kind, filter: ResizeFilter) -> Result<Arc<[u8]>, StreamDeckError> {
+        let key = (name.to_string(), kind, filter);
+
+        if let Some(data) = self.converted.read()?.get(&key) {
+            return Ok(data.clone());
+        }
+
+        let image = self.get(name)?.ok_or(StreamDeckError::UnknownAsset)?;
+        let data: Arc<[u8]> = convert_image_with_filter(kind.key_image_format(), image, filter)?.into();
+
+        self.converted.write()?.insert(key, data.clone());
+
+        Ok(data)
+    }
+
+    /// Drops all converted image data, keeping the images. Converted data is created again as assets get used
+    pub fn clear_converted(&self) -> Result<(), StreamDeckError> {
+        self.converted.write()?.clear();
+        Ok(())
+    }
+}
+
+impl Debug for AssetRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Printing images would flood the output, names are what matters
+        let mut names = self.images.read().map(|images| images.keys().cloned().collect::<Vec<_>>()).unwrap_or_default();
+        names.sort();
+
+        f.debug_struct("AssetRegistry").field("names", &names).finish()
+    }
+}
@@ -1,18 +1,42 @@
 //! Code from this module is using [block_in_place](tokio::task::block_in_place),
 //! and so they cannot be used in [current_thread](tokio::runtime::Builder::new_current_thread) runtimes
 
-use std::iter::zip;
-use std::sync::Arc;
+#[cfg(feature = "manager")]
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
 use std::time::Duration;
+#[cfg(feature = "manager")]
+use std::time::Instant;
 
 use hidapi::{HidApi, HidResult};
 use image::DynamicImage;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 use tokio::task::block_in_place;
-use tokio::time::sleep;
-
-use crate::{DeviceState, DeviceStateUpdate, Kind, list_devices, StreamDeck, StreamDeckError, StreamDeckInput};
-use crate::images::{convert_image_async, ImageRect};
+use tokio::time::{interval, sleep, MissedTickBehavior};
+
+use crate::{
+    ConnectOptions, FlushStrategy, UploadCanceller, InputDiffer, DeviceStateUpdate, HeldInput, KeyIndex, Kind, list_devices, ReaderStats, TouchPointIndex, StreamDeck, StreamDeckError,
+    StreamDeckInput, NormalizedTouch,
+};
+#[cfg(feature = "manager")]
+use crate::DeviceId;
+#[cfg(feature = "manager")]
+use crate::hotplug::DeviceWatcher;
+#[cfg(feature = "manager")]
+use crate::manager::DeviceEvent;
+use crate::info::{ScreenSegment, TouchKey};
+use crate::activity::Activity;
+#[cfg(feature = "tools")]
+use crate::calibration::KeyRemap;
+#[cfg(feature = "widgets")]
+use crate::compositor::Badge;
+#[cfg(feature = "input-tools")]
+use crate::layers::{LayerUpdate, Layers};
+#[cfg(feature = "widgets")]
+use crate::palette::StatusColor;
+use crate::images::{convert_image_with_filter, fit_image, load_image, ImageRect, RawImage, ResizeFilter};
 
 /// Actually refreshes the device list, can be safely ran inside [multi_thread](tokio::runtime::Builder::new_multi_thread) runtime
 pub fn refresh_device_list_async(hidapi: &mut HidApi) -> HidResult<()> {
@@ -32,28 +56,90 @@ pub fn list_devices_async(hidapi: &HidApi) -> Vec<(Kind, String)> {
 #[derive(Clone)]
 pub struct AsyncStreamDeck {
     kind: Kind,
+    held_input: HeldInput,
+    #[cfg(feature = "tools")]
+    key_remap: Option<KeyRemap>,
+    resize_filter: ResizeFilter,
+    flush_strategy: FlushStrategy,
+    ticker_started: Arc<AtomicBool>,
     device: Arc<Mutex<StreamDeck>>,
+    upload_canceller: UploadCanceller,
+    flush_queue: Arc<std::sync::Mutex<FlushQueue>>,
+    flushed: Arc<watch::Sender<FlushOutcome>>,
+}
+
+/// Number of the last flush request that was covered by a flush, and the error the flush ended with
+type FlushOutcome = (u64, Option<Arc<StreamDeckError>>);
+
+#[derive(Default)]
+struct FlushQueue {
+    requested: u64,
+    flushed: u64,
+    running: bool,
 }
 
 /// Static functions of the struct
 impl AsyncStreamDeck {
     /// Attempts to connect to the device, can be safely ran inside [multi_thread](tokio::runtime::Builder::new_multi_thread) runtime
     pub fn connect(hidapi: &HidApi, kind: Kind, serial: &str) -> Result<AsyncStreamDeck, StreamDeckError> {
-        let device = block_in_place(move || StreamDeck::connect(hidapi, kind, serial))?;
+        Self::connect_with_options(hidapi, kind, serial, ConnectOptions::default())
+    }
 
-        Ok(AsyncStreamDeck {
-            kind,
-            device: Arc::new(Mutex::new(device)),
-        })
+    /// Attempts to connect to the device with provided connection options,
+    /// can be safely ran inside [multi_thread](tokio::runtime::Builder::new_multi_thread) runtime
+    pub fn connect_with_options(hidapi: &HidApi, kind: Kind, serial: &str, options: ConnectOptions) -> Result<AsyncStreamDeck, StreamDeckError> {
+        let device = block_in_place(move || StreamDeck::connect_with_options(hidapi, kind, serial, options))?;
+
+        Ok(AsyncStreamDeck::from(device))
     }
 }
 
 impl From<StreamDeck> for AsyncStreamDeck {
-    /// Wraps an already-opened [StreamDeck], allowing the caller to control which thread performs the HID open
-    fn from(device: StreamDeck) -> AsyncStreamDeck {
-        AsyncStreamDeck {
+    /// Wraps an already-opened [StreamDeck], allowing the caller to control which thread performs the HID open.
+    /// Starts the flush ticker if the device uses [FlushStrategy::Ticker], or with the first write if this isn't called inside a tokio runtime
+    fn from(mut device: StreamDeck) -> AsyncStreamDeck {
+        device.defer_flush();
+
+        let device = AsyncStreamDeck {
             kind: device.kind(),
+            held_input: device.options().held_input,
+            #[cfg(feature = "tools")]
+            key_remap: device.options().key_remap.clone(),
+            resize_filter: device.options().resize_filter,
+            flush_strategy: device.options().flush_strategy,
+            ticker_started: Default::default(),
+            upload_canceller: device.upload_canceller(),
             device: Arc::new(Mutex::new(device)),
+            flush_queue: Default::default(),
+            flushed: Arc::new(watch::channel((0, None)).0),
+        };
+
+        device.start_ticker();
+
+        device
+    }
+}
+
+/// Flushes staged images every period until the device is dropped, a failed flush is retried on the next tick
+async fn flush_ticker(device: Weak<Mutex<StreamDeck>>, period: Duration) {
+    let mut ticker = interval(period.max(Duration::from_millis(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+
+        let Some(device) = device.upgrade() else {
+            return;
+        };
+
+        // Unlocking the device after every image, same as flush does
+        loop {
+            let device = device.lock().await;
+
+            match block_in_place(|| device.flush_next()) {
+                Ok(true) => {}
+                Ok(false) | Err(_) => break,
+            }
         }
     }
 }
@@ -65,6 +151,34 @@ impl AsyncStreamDeck {
         self.kind
     }
 
+    /// Starts the flush ticker if the device uses [FlushStrategy::Ticker] and it isn't running yet, needs a tokio runtime
+    fn start_ticker(&self) {
+        let FlushStrategy::Ticker(period) = self.flush_strategy else {
+            return;
+        };
+
+        if self.ticker_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(flush_ticker(Arc::downgrade(&self.device), period));
+            }
+
+            Err(_) => self.ticker_started.store(false, Ordering::Release),
+        }
+    }
+
+    /// Sends staged images if the flush strategy says it's time to, unlocking the device after every image like [flush](AsyncStreamDeck::flush)
+    async fn apply_flush_strategy(&self) -> Result<(), StreamDeckError> {
+        self.start_ticker();
+
+        let due = self.device.lock().await.take_deferred_flush()?;
+
+        if due { self.flush().await } else { Ok(()) }
+    }
+
     /// Returns manufacturer string of the device
     pub async fn manufacturer(&self) -> Result<String, StreamDeckError> {
         let device = self.device.lock().await;
@@ -83,6 +197,11 @@ impl AsyncStreamDeck {
         block_in_place(move || device.serial_number())
     }
 
+    /// Returns recent reports exchanged with the device, see [StreamDeck::recent_activity]
+    pub async fn recent_activity(&self) -> Vec<Activity> {
+        self.device.lock().await.recent_activity()
+    }
+
     /// Returns firmware version of the StreamDeck
     pub async fn firmware_version(&self) -> Result<String, StreamDeckError> {
         let device = self.device.lock().await;
@@ -104,29 +223,126 @@ impl AsyncStreamDeck {
         }
     }
 
+    /// Returns how many times reading got out of sync with reports of the device and was recovered
+    pub async fn recovered_desyncs(&self) -> u64 {
+        self.device.lock().await.recovered_desyncs()
+    }
+
+    /// Returns how many input reports couldn't be parsed, including ones that were skipped because of [ParseMode](crate::ParseMode)
+    pub async fn parse_failures(&self) -> u64 {
+        self.device.lock().await.parse_failures()
+    }
+
     /// Resets the device
     pub async fn reset(&self) -> Result<(), StreamDeckError> {
         let device = self.device.lock().await;
         block_in_place(move || device.reset())
     }
 
-    /// Sets brightness of the device, value range is 0 - 100
+    /// Resets the device, then restores brightness, touch point colors and key images
+    /// that were previously applied to it
+    pub async fn reset_preserving_state(&self) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.reset_preserving_state())
+    }
+
+    /// Sets brightness of the device, value range is 0 - 100.
+    /// Only waits for the image that is being sent by a flush in progress, not the whole flush
     pub async fn set_brightness(&self, percent: u8) -> Result<(), StreamDeckError> {
         let device = self.device.lock().await;
-        block_in_place(move || device.set_brightness(percent))
+        block_in_place(move || device.set_brightness(percent))?;
+
+        self.apply_flush_strategy().await
+    }
+
+    /// Turns screens and touch point LEDs off for presentation blackout, while input keeps being read.
+    /// [AsyncDeviceStateReader] wakes the device on the first press and swallows it, so the tap doesn't trigger an action
+    pub async fn blackout(&self) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.blackout())
+    }
+
+    /// Ends blackout, restoring brightness and touch point colors that were last set
+    pub async fn wake(&self) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.wake())?;
+
+        self.apply_flush_strategy().await
+    }
+
+    /// Returns true if the device is in blackout
+    pub async fn is_blacked_out(&self) -> bool {
+        self.device.lock().await.is_blacked_out()
+    }
+
+    /// Holds back writes to the device, brightness and touch point colors are only remembered and images stay staged until resume
+    pub async fn suspend(&self) {
+        self.device.lock().await.suspend()
+    }
+
+    /// Ends suspension, applying state that was last set and sending images staged in the meantime
+    pub async fn resume(&self) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.resume())
+    }
+
+    /// Ends suspension like resume, but resets the device first, for when it lost its state like during system sleep
+    pub async fn resume_with_reset(&self) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.resume_with_reset())
+    }
+
+    /// Returns true if writes are held back
+    pub async fn is_suspended(&self) -> bool {
+        self.device.lock().await.is_suspended()
+    }
+
+    /// Adjusts key images to brightness, lifting dark tones at low brightness so dark icons stay legible.
+    /// Keys are staged again with the new setting, changes must be flushed with `.flush()` before they will appear on the device!
+    pub async fn set_brightness_compensation(&self, enabled: bool) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.set_brightness_compensation(enabled))?;
+
+        self.apply_flush_strategy().await
+    }
+
+    /// Returns true if key images are adjusted to brightness
+    pub async fn brightness_compensation(&self) -> bool {
+        self.device.lock().await.brightness_compensation()
     }
 
     /// Writes image data to Stream Deck device, changes must be flushed with `.flush()` before
     /// they will appear on the device!
-    pub async fn write_image(&self, key: u8, image_data: &[u8]) -> Result<(), StreamDeckError> {
+    ///
+    /// Image data passed as `Arc<[u8]>` is staged without copying, so the same encoded image can be put on many keys cheaply.
+    /// Upload of the key's previous image that is still in progress in another task is canceled
+    pub async fn write_image(&self, key: impl Into<KeyIndex>, image_data: impl Into<Arc<[u8]>>) -> Result<(), StreamDeckError> {
+        let key = key.into();
+
+        let image_data = image_data.into();
+        self.upload_canceller.cancel(key);
+
+        let device = self.device.lock().await;
+        block_in_place(move || device.write_image(key, image_data))?;
+
+        self.apply_flush_strategy().await
+    }
+
+    /// Writes image data to the screen of the device, see [Kind::screens] for screens the device has.
+    ///
+    /// Region is filled if it's specified, otherwise the whole screen is, image data must be encoded with the screen's format and match the size.
+    /// Fails with [StreamDeckError::UnsupportedOperation] if the screen only supports full fills and region doesn't cover all of it
+    pub async fn write_screen(&self, screen_id: u8, region: Option<ScreenSegment>, image_data: &[u8]) -> Result<(), StreamDeckError> {
         let device = self.device.lock().await;
-        block_in_place(move || device.write_image(key, image_data))
+        block_in_place(move || device.write_screen(screen_id, region, image_data))
     }
 
     /// Writes image data to Stream Deck device's lcd strip/screen as region.
     /// Only Stream Deck Plus supports writing LCD regions, for Stream Deck Neo use write_lcd_fill
+    #[deprecated(note = "use write_screen")]
     pub async fn write_lcd(&self, x: u16, y: u16, rect: &ImageRect) -> Result<(), StreamDeckError> {
         let device = self.device.lock().await;
+        #[allow(deprecated)]
         block_in_place(move || device.write_lcd(x, y, rect))
     }
 
@@ -134,136 +350,429 @@ impl AsyncStreamDeck {
     ///
     /// You can convert your images into proper image_data like this:
     /// ```
+    /// # async fn example(device: elgato_streamdeck::AsyncStreamDeck, image: image::DynamicImage) {
     /// use elgato_streamdeck::images::{convert_image_with_format_async};
-    /// let image_data = convert_image_with_format_async(device.kind().lcd_image_format(), image).await.unwrap();
-    /// device.write_lcd_fill(&image_data).await;
+    /// let image_data = convert_image_with_format_async(device.kind().lcd_image_format().unwrap(), image).await.unwrap();
+    /// device.write_lcd_fill(&image_data).await.unwrap();
+    /// # }
     /// ```
     pub async fn write_lcd_fill(&self, image_data: &[u8]) -> Result<(), StreamDeckError> {
         let device = self.device.lock().await;
         block_in_place(move || device.write_lcd_fill(image_data))
     }
 
-    /// Sets button's image to blank, changes must be flushed with `.flush()` before
-    /// they will appear on the device!
-    pub async fn clear_button_image(&self, key: u8) -> Result<(), StreamDeckError> {
+    /// Sets button's image to blank right away, see [StreamDeck::clear_button_image]
+    pub async fn clear_button_image(&self, key: impl Into<KeyIndex>) -> Result<(), StreamDeckError> {
+        let key = key.into();
+
         let device = self.device.lock().await;
-        block_in_place(move || device.clear_button_image(key))
+        block_in_place(move || device.clear_button_image(key))?;
+
+        self.apply_flush_strategy().await
     }
 
-    /// Sets blank images to every button, changes must be flushed with `.flush()` before
-    /// they will appear on the device!
+    /// Sets blank images to every button right away, see [StreamDeck::clear_button_image]
     pub async fn clear_all_button_images(&self) -> Result<(), StreamDeckError> {
         let device = self.device.lock().await;
-        block_in_place(move || device.clear_all_button_images())
+        block_in_place(move || device.clear_all_button_images())?;
+
+        self.apply_flush_strategy().await
+    }
+
+    #[cfg(feature = "widgets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+    /// Shows test pattern on every key and screen, key images must be flushed with `.flush()` before
+    /// they will appear on the device!
+    pub async fn show_test_pattern(&self) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.show_test_pattern())?;
+
+        self.apply_flush_strategy().await
     }
 
     /// Sets specified button's image, changes must be flushed with `.flush()` before
     /// they will appear on the device!
-    pub async fn set_button_image(&self, key: u8, image: DynamicImage) -> Result<(), StreamDeckError> {
-        let image = convert_image_async(self.kind, image)?;
+    ///
+    /// Upload of the key's previous image that is still in progress in another task is canceled
+    pub async fn set_button_image(&self, key: impl Into<KeyIndex>, image: DynamicImage) -> Result<(), StreamDeckError> {
+        let key = key.into();
+
+        self.upload_canceller.cancel(key);
+
+        // Converted before locking, so other tasks can use the device in the meantime
+        let (format, filter) = (self.kind.key_image_format(), self.resize_filter);
+        let (image, image_data) = tokio::task::spawn_blocking(move || -> Result<_, StreamDeckError> {
+            let image = fit_image(format, image, filter);
+            let image_data = convert_image_with_filter(format, image.clone(), filter)?;
+            Ok((image, image_data))
+        })
+        .await??;
 
         let device = self.device.lock().await;
-        block_in_place(move || device.write_image(key, &image))
+        block_in_place(move || device.set_button_image_converted(key.0, image, image_data.into()))?;
+
+        self.apply_flush_strategy().await
+    }
+
+    #[cfg(feature = "assets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "assets")))]
+    /// Sets specified button's image to the asset registered under the name in [ConnectOptions::assets](crate::ConnectOptions::assets).
+    /// Changes must be flushed with `.flush()` before they will appear on the device!
+    pub async fn set_button_asset(&self, key: impl Into<KeyIndex>, name: &str) -> Result<(), StreamDeckError> {
+        let key = key.into();
+
+        self.upload_canceller.cancel(key);
+
+        let device = self.device.lock().await;
+        block_in_place(move || device.set_button_asset(key, name))?;
+
+        self.apply_flush_strategy().await
     }
 
-    /// Sets specified touch point's led strip color
-    pub async fn set_touchpoint_color(&self, point: u8, red: u8, green: u8, blue: u8) -> Result<(), StreamDeckError> {
+    /// Sets specified button's image from raw pixels, changes must be flushed with `.flush()` before
+    /// they will appear on the device!
+    pub async fn set_button_image_raw(&self, key: impl Into<KeyIndex>, image: RawImage) -> Result<(), StreamDeckError> {
+        let key = key.into();
+
+        self.set_button_image(key, image.into()).await
+    }
+
+    /// Sets specified button's image from the file, loaded on the blocking thread pool, see [load_image](crate::images::load_image) for supported formats.
+    /// Changes must be flushed with `.flush()` before they will appear on the device!
+    pub async fn set_button_image_from_path(&self, key: impl Into<KeyIndex>, path: impl AsRef<Path>) -> Result<(), StreamDeckError> {
+        let key = key.into();
+
+        let path = path.as_ref().to_path_buf();
+        let image = tokio::task::spawn_blocking(move || load_image(path)).await??;
+
+        self.set_button_image(key, image).await
+    }
+
+    /// Dims button's image by multiplying its colors with the factor, value range is 0.0 - 1.0.
+    /// Image set with [set_button_image](AsyncStreamDeck::set_button_image) is reused, so it doesn't have to be supplied again.
+    /// Changes must be flushed with `.flush()` before they will appear on the device!
+    pub async fn set_key_dim(&self, key: impl Into<KeyIndex>, factor: f32) -> Result<(), StreamDeckError> {
+        let key = key.into();
+
+        let device = self.device.lock().await;
+        block_in_place(move || device.set_key_dim(key, factor))?;
+
+        self.apply_flush_strategy().await
+    }
+
+    #[cfg(feature = "widgets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+    /// Draws a badge in a corner of button's image, replacing previous badge.
+    /// Image set with [set_button_image](AsyncStreamDeck::set_button_image) is reused, so it doesn't have to be supplied again.
+    /// Changes must be flushed with `.flush()` before they will appear on the device!
+    pub async fn set_key_badge(&self, key: impl Into<KeyIndex>, badge: Badge) -> Result<(), StreamDeckError> {
+        let key = key.into();
+
+        let device = self.device.lock().await;
+        block_in_place(move || device.set_key_badge(key, badge))?;
+
+        self.apply_flush_strategy().await
+    }
+
+    #[cfg(feature = "widgets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+    /// Removes badge from button's image, changes must be flushed with `.flush()` before
+    /// they will appear on the device!
+    pub async fn clear_key_badge(&self, key: impl Into<KeyIndex>) -> Result<(), StreamDeckError> {
+        let key = key.into();
+
+        let device = self.device.lock().await;
+        block_in_place(move || device.clear_key_badge(key))?;
+
+        self.apply_flush_strategy().await
+    }
+
+    /// Sets specified touch point's led strip color.
+    /// Only waits for the image that is being sent by a flush in progress, not the whole flush
+    pub async fn set_touchpoint_color(&self, point: impl Into<TouchPointIndex>, red: u8, green: u8, blue: u8) -> Result<(), StreamDeckError> {
+        let point = point.into();
+
         let device = self.device.lock().await;
         block_in_place(move || device.set_touchpoint_color(point, red, green, blue))
     }
 
+    /// Sets colors of multiple touch points' led strips as (point, (red, green, blue)), checking all indices before anything is sent
+    pub async fn set_touchpoint_colors(&self, colors: &[(u8, (u8, u8, u8))]) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.set_touchpoint_colors(colors))
+    }
+
+    /// Turns off led strips of all touch points
+    pub async fn clear_touchpoints(&self) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.clear_touchpoints())
+    }
+
+    #[cfg(feature = "widgets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+    /// Sets specified touch point's led strip to a [status color](StatusColor), which stays distinguishable with color vision deficiencies
+    pub async fn set_touchpoint_status(&self, point: impl Into<TouchPointIndex>, status: StatusColor) -> Result<(), StreamDeckError> {
+        let point = point.into();
+
+        let device = self.device.lock().await;
+        block_in_place(move || device.set_touchpoint_status(point, status))
+    }
+
+    /// Sets LED color of the touch key, fails with [StreamDeckError::UnsupportedOperation] if the device's touch keys have no LEDs.
+    /// Only waits for the image that is being sent by a flush in progress, not the whole flush
+    pub async fn set_touch_key_color(&self, key: TouchKey, red: u8, green: u8, blue: u8) -> Result<(), StreamDeckError> {
+        let device = self.device.lock().await;
+        block_in_place(move || device.set_touch_key_color(key, red, green, blue))
+    }
+
     /// Flushes the button's image to the device
+    ///
+    /// Device is unlocked after every image, so brightness and touch point color changes from other tasks
+    /// don't have to wait until all the images are sent
     pub async fn flush(&self) -> Result<(), StreamDeckError> {
-        let device = self.device.lock().await;
-        block_in_place(move || device.flush())
+        loop {
+            let device = self.device.lock().await;
+
+            if !block_in_place(|| device.flush_next())? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Flushes the button's images together with images staged by other tasks that call this at the same time.
+    /// Only one flush runs at a time, requests that come while it's running are merged into the next one.
+    /// Returns once a flush that started after this call finished
+    pub async fn flush_coalesced(&self) -> Result<(), StreamDeckError> {
+        let mut flushed = self.flushed.subscribe();
+
+        let ticket = {
+            let mut queue = self.flush_queue.lock()?;
+            queue.requested += 1;
+
+            if !queue.running {
+                queue.running = true;
+                tokio::spawn(self.clone().flush_worker());
+            }
+
+            queue.requested
+        };
+
+        let outcome = flushed.wait_for(|(done, _)| *done >= ticket).await.map_err(|_| StreamDeckError::PoisonError)?;
+
+        match &outcome.1 {
+            Some(error) => Err(StreamDeckError::SharedFlushError(error.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Keeps flushing until there are no unserved flush requests left
+    async fn flush_worker(self) {
+        let mut guard = FlushWorkerGuard { device: &self, finished: false };
+
+        loop {
+            let target = {
+                let Ok(mut queue) = self.flush_queue.lock() else {
+                    return;
+                };
+
+                if queue.flushed == queue.requested {
+                    queue.running = false;
+                    guard.finished = true;
+                    return;
+                }
+
+                queue.requested
+            };
+
+            let result = self.flush().await;
+
+            if let Ok(mut queue) = self.flush_queue.lock() {
+                queue.flushed = target;
+            }
+
+            self.flushed.send_replace((target, result.err().map(Arc::new)));
+        }
     }
 
     /// Returns button state reader for this device
     pub fn get_reader(&self) -> Arc<AsyncDeviceStateReader> {
+        let mut differ = InputDiffer::new(self.kind);
+        differ.set_held_input(self.held_input);
+        #[cfg(feature = "tools")]
+        differ.set_key_remap(self.key_remap.clone());
+
         Arc::new(AsyncDeviceStateReader {
             device: self.clone(),
-            states: Mutex::new(DeviceState {
-                buttons: vec![false; self.kind.key_count() as usize + self.kind.touchpoint_count() as usize],
-                encoders: vec![false; self.kind.encoder_count() as usize],
-            }),
+            states: Mutex::new(differ),
+            reports_read: AtomicU64::new(0),
+            updates_emitted: AtomicU64::new(0),
         })
     }
 }
 
+/// Lets the next request start a new flush worker if the worker stopped without serving every request, like when it panicked.
+/// Waiting requests are failed, since it's unknown what was sent
+struct FlushWorkerGuard<'a> {
+    device: &'a AsyncStreamDeck,
+    finished: bool,
+}
+
+impl Drop for FlushWorkerGuard<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        let requested = {
+            let mut queue = self.device.flush_queue.lock().unwrap_or_else(|e| e.into_inner());
+            queue.running = false;
+            queue.flushed = queue.requested;
+            queue.requested
+        };
+
+        self.device.flushed.send_replace((requested, Some(Arc::new(StreamDeckError::PoisonError))));
+    }
+}
+
 /// Button reader that keeps state of the Stream Deck and returns events instead of full states
 pub struct AsyncDeviceStateReader {
     device: AsyncStreamDeck,
-    states: Mutex<DeviceState>,
+    states: Mutex<InputDiffer>,
+    reports_read: AtomicU64,
+    updates_emitted: AtomicU64,
 }
 
 impl AsyncDeviceStateReader {
     /// Reads states and returns updates
     pub async fn read(&self, poll_rate: f32) -> Result<Vec<DeviceStateUpdate>, StreamDeckError> {
-        let input = self.device.read_input(poll_rate).await?;
-        let mut my_states = self.states.lock().await;
-
-        let mut updates = vec![];
-
-        match input {
-            StreamDeckInput::ButtonStateChange(buttons) => {
-                for (index, (their, mine)) in zip(buttons.iter(), my_states.buttons.iter()).enumerate() {
-                    if *their != *mine {
-                        if index < self.device.kind.key_count() as usize {
-                            if *their {
-                                updates.push(DeviceStateUpdate::ButtonDown(index as u8));
-                            } else {
-                                updates.push(DeviceStateUpdate::ButtonUp(index as u8));
-                            }
-                        } else if *their {
-                            updates.push(DeviceStateUpdate::TouchPointDown(index as u8 - self.device.kind.key_count()));
-                        } else {
-                            updates.push(DeviceStateUpdate::TouchPointUp(index as u8 - self.device.kind.key_count()));
-                        }
-                    }
-                }
-
-                my_states.buttons = buttons;
+        // Polls instead of awaiting input, so transitions held back by debounce get delivered without waiting for more input
+        let (mut my_states, mut updates) = loop {
+            let device = self.device.device.lock().await;
+            let input = block_in_place(move || device.read_input(None))?;
+            let got_input = !input.is_empty();
+
+            if got_input {
+                self.reports_read.fetch_add(1, Ordering::Relaxed);
             }
 
-            StreamDeckInput::EncoderStateChange(encoders) => {
-                for (index, (their, mine)) in zip(encoders.iter(), my_states.encoders.iter()).enumerate() {
-                    if *their != *mine {
-                        if *their {
-                            updates.push(DeviceStateUpdate::EncoderDown(index as u8));
-                        } else {
-                            updates.push(DeviceStateUpdate::EncoderUp(index as u8));
-                        }
-                    }
-                }
+            let mut my_states = self.states.lock().await;
+            let updates = my_states.diff(input);
 
-                my_states.encoders = encoders;
+            if got_input || !updates.is_empty() {
+                break (my_states, updates);
             }
 
-            StreamDeckInput::EncoderTwist(twist) => {
-                for (index, change) in twist.iter().enumerate() {
-                    if *change != 0 {
-                        updates.push(DeviceStateUpdate::EncoderTwist(index as u8, *change));
-                    }
-                }
-            }
+            drop(my_states);
+            sleep(Duration::from_secs_f32(1.0 / poll_rate)).await;
+        };
 
-            StreamDeckInput::TouchScreenPress(x, y) => {
-                updates.push(DeviceStateUpdate::TouchScreenPress(x, y));
-            }
+        let wakes = my_states.swallow_wake_input(&mut updates, self.device.is_blacked_out().await);
 
-            StreamDeckInput::TouchScreenLongPress(x, y) => {
-                updates.push(DeviceStateUpdate::TouchScreenLongPress(x, y));
-            }
+        drop(my_states);
 
-            StreamDeckInput::TouchScreenSwipe(s, e) => {
-                updates.push(DeviceStateUpdate::TouchScreenSwipe(s, e));
-            }
+        if wakes {
+            self.device.wake().await?;
+        }
+
+        self.updates_emitted.fetch_add(updates.len() as u64, Ordering::Relaxed);
 
-            _ => {}
+        Ok(updates)
+    }
+
+    /// Reads states like [read](AsyncDeviceStateReader::read), pairing touch screen updates with their coordinates
+    /// [normalized](DeviceStateUpdate::normalized_touch) to 0.0 - 1.0 range of the LCD strip
+    pub async fn read_normalized(&self, poll_rate: f32) -> Result<Vec<(DeviceStateUpdate, Option<NormalizedTouch>)>, StreamDeckError> {
+        let kind = self.device.kind;
+        Ok(self.read(poll_rate).await?.into_iter().map(|update| (update, update.normalized_touch(kind))).collect())
+    }
+
+    /// Returns counters of the reader and its device. Device counters include reads done without the reader.
+    /// Updates are returned straight to the caller, so none are dropped however slowly they're read
+    pub async fn stats(&self) -> ReaderStats {
+        let device = self.device.device.lock().await;
+
+        ReaderStats {
+            reports_read: self.reports_read.load(Ordering::Relaxed),
+            updates_emitted: self.updates_emitted.load(Ordering::Relaxed),
+            parse_failures: device.parse_failures(),
+            recovered_desyncs: device.recovered_desyncs(),
         }
+    }
 
-        drop(my_states);
+    #[cfg(feature = "input-tools")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "input-tools")))]
+    /// Reads states and returns updates tagged by the layers, staging images of the new layer if the active layer changed
+    /// and [swap_images](Layers::swap_images) is set. Staged images must be flushed with `.flush()` before they will appear on the device!
+    pub async fn read_layered(&self, layers: &mut Layers, poll_rate: f32) -> Result<Vec<LayerUpdate>, StreamDeckError> {
+        let updates = layers.handle_all(self.read(poll_rate).await?);
+
+        if layers.swap_images && updates.iter().any(|update| matches!(update, LayerUpdate::LayerChanged { .. })) {
+            let device = self.device.device.lock().await;
+            block_in_place(|| layers.stage_images(&device))?;
+        }
 
         Ok(updates)
     }
+
+    /// Sets how long transitions of a button are held back after its previous transition, to suppress contact bounce.
+    /// Transitions that are held back get delivered by a later read if the button stays in the new state. Zero by default
+    pub async fn set_debounce(&self, debounce: Duration) {
+        self.states.lock().await.set_debounce(debounce);
+    }
+}
+
+#[cfg(feature = "manager")]
+#[cfg_attr(docsrs, doc(cfg(feature = "manager")))]
+/// Async [DeviceWatcher], refreshing the device list with [block_in_place](block_in_place)
+pub struct AsyncDeviceWatcher {
+    watcher: DeviceWatcher,
+    interval: Duration,
+    pending: VecDeque<DeviceEvent>,
+    last_scan: Option<Instant>,
+}
+
+#[cfg(feature = "manager")]
+impl AsyncDeviceWatcher {
+    /// Creates a watcher with a new HidApi instance, scanning every interval
+    pub fn new(interval: Duration) -> Result<AsyncDeviceWatcher, StreamDeckError> {
+        Ok(Self::from_watcher(block_in_place(DeviceWatcher::new)?, interval))
+    }
+
+    /// Wraps existing watcher, scanning every interval
+    pub fn from_watcher(watcher: DeviceWatcher, interval: Duration) -> AsyncDeviceWatcher {
+        AsyncDeviceWatcher {
+            watcher,
+            interval,
+            pending: VecDeque::new(),
+            last_scan: None,
+        }
+    }
+
+    /// HidApi instance used for scanning, devices that were reported as connected can be connected with it
+    pub fn hidapi(&self) -> &HidApi {
+        self.watcher.hidapi()
+    }
+
+    /// Devices that were present at the last scan
+    pub fn devices(&self) -> Vec<DeviceId> {
+        self.watcher.devices()
+    }
+
+    /// Waits for the next device to get plugged in or unplugged, the first scan happens right away
+    pub async fn next(&mut self) -> DeviceEvent {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return event;
+            }
+
+            if let Some(last_scan) = self.last_scan {
+                sleep(self.interval.saturating_sub(last_scan.elapsed())).await;
+            }
+
+            self.last_scan = Some(Instant::now());
+
+            let watcher = &mut self.watcher;
+            self.pending.extend(block_in_place(move || watcher.scan()));
+        }
+    }
 }
@@ -0,0 +1,268 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use image::{DynamicImage, Rgb, RgbImage};
+
+use crate::images::convert_image_with_format;
+use crate::info::{ImageFormat, ImageMode, Kind};
+use crate::transport::MockTransport;
+use crate::{ConnectOptions, DeviceStateReader, DeviceStateUpdate, StreamDeck, StreamDeckError};
+
+/// Number of distinct frames cycled through by [frames_per_second], so consecutive frames always differ
+const FRAME_VARIANTS: u32 = 4;
+
+/// Spread of measured durations
+#[derive(Clone, Debug, Default)]
+pub struct Distribution {
+    /// Number of measurements
+    pub samples: usize,
+    /// Shortest measurement
+    pub min: Duration,
+    /// Median measurement
+    pub median: Duration,
+    /// 95th percentile of measurements
+    pub p95: Duration,
+    /// Longest measurement
+    pub max: Duration,
+}
+
+impl Distribution {
+    /// Computes distribution of provided measurements
+    pub fn from_samples(mut samples: Vec<Duration>) -> Distribution {
+        if samples.is_empty() {
+            return Distribution::default();
+        }
+
+        samples.sort();
+
+        let percentile = |p: usize| samples[((samples.len() - 1) * p / 100).min(samples.len() - 1)];
+
+        Distribution {
+            samples: samples.len(),
+            min: samples[0],
+            median: percentile(50),
+            p95: percentile(95),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+impl Display for Distribution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "min {:?}, median {:?}, p95 {:?}, max {:?} ({} samples)", self.min, self.median, self.p95, self.max, self.samples)
+    }
+}
+
+/// Results of [run]
+#[derive(Clone, Debug)]
+pub struct BenchReport {
+    /// Kind of the benchmarked device
+    pub kind: Kind,
+    /// Full-deck frames per second the device accepted
+    pub frames_per_second: f64,
+    /// Time to convert a single key image, per image codec
+    pub conversion: Vec<(ImageMode, Distribution)>,
+    /// Time to flush a single key image
+    pub flush_latency: Distribution,
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Device: {:?}", self.kind)?;
+        writeln!(f, "Full-deck frames per second: {:.2}", self.frames_per_second)?;
+
+        for (mode, distribution) in &self.conversion {
+            writeln!(f, "{:?} conversion: {}", mode, distribution)?;
+        }
+
+        write!(f, "Key flush latency: {}", self.flush_latency)
+    }
+}
+
+/// Deterministic test pattern, different for every seed
+pub fn test_image(size: (usize, usize), seed: u32) -> DynamicImage {
+    let (w, h) = (size.0 as u32, size.1 as u32);
+
+    DynamicImage::ImageRgb8(RgbImage::from_fn(w, h, |x, y| {
+        let shift = seed.wrapping_mul(37);
+        Rgb([
+            (x * 255 / w.max(1)).wrapping_add(shift) as u8,
+            (y * 255 / h.max(1)).wrapping_add(shift) as u8,
+            ((x ^ y) + seed * 64) as u8,
+        ])
+    }))
+}
+
+/// Measures how many frames per second can be written to all keys of the device.
+/// Images are converted before the measurement starts, so only the transfer is measured
+pub fn frames_per_second(device: &StreamDeck, frames: usize) -> Result<f64, StreamDeckError> {
+    let kind = device.kind();
+
+    let variants = (0..FRAME_VARIANTS)
+        .map(|seed| convert_image_with_format(kind.key_image_format(), test_image(kind.key_image_resolution(), seed)).map(Arc::<[u8]>::from))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let start = Instant::now();
+
+    for frame in 0..frames {
+        for key in 0..kind.key_count() {
+            device.write_image(key, variants[(frame + key as usize) % variants.len()].clone())?;
+        }
+
+        device.flush()?;
+    }
+
+    Ok(frames as f64 / start.elapsed().as_secs_f64())
+}
+
+/// Measures how long it takes to convert a key image of the device with every image codec
+pub fn conversion_time(kind: Kind, iterations: usize) -> Result<Vec<(ImageMode, Distribution)>, StreamDeckError> {
+    let mut results = vec![];
+
+    for mode in [ImageMode::BMP, ImageMode::JPEG] {
+        let format = ImageFormat { mode, ..kind.key_image_format() };
+        let mut samples = Vec::with_capacity(iterations);
+
+        for iteration in 0..iterations {
+            let image = test_image(format.size, iteration as u32);
+
+            let start = Instant::now();
+            convert_image_with_format(format, image)?;
+            samples.push(start.elapsed());
+        }
+
+        results.push((mode, Distribution::from_samples(samples)));
+    }
+
+    Ok(results)
+}
+
+/// Measures how long it takes to flush a single key image to the device
+pub fn flush_latency(device: &StreamDeck, samples: usize) -> Result<Distribution, StreamDeckError> {
+    let kind = device.kind();
+    let image_data: Arc<[u8]> = convert_image_with_format(kind.key_image_format(), test_image(kind.key_image_resolution(), 0))?.into();
+
+    let mut measurements = Vec::with_capacity(samples);
+
+    for sample in 0..samples {
+        device.write_image((sample % kind.key_count() as usize) as u8, image_data.clone())?;
+
+        let start = Instant::now();
+        device.flush()?;
+        measurements.push(start.elapsed());
+    }
+
+    Ok(Distribution::from_samples(measurements))
+}
+
+/// Runs all benchmarks against the device, `iterations` controls number of frames and samples of every benchmark
+pub fn run(device: &StreamDeck, iterations: usize) -> Result<BenchReport, StreamDeckError> {
+    Ok(BenchReport {
+        kind: device.kind(),
+        frames_per_second: frames_per_second(device, iterations)?,
+        conversion: conversion_time(device.kind(), iterations)?,
+        flush_latency: flush_latency(device, iterations)?,
+    })
+}
+
+/// Latencies of input handling, measured by [LatencyMonitor]
+#[derive(Clone, Debug)]
+pub struct LatencyReport {
+    /// Time from input being read from the device until its updates were returned
+    pub processing: Distribution,
+    /// Time from updates being returned until the application reported them handled
+    pub handling: Distribution,
+}
+
+impl Display for LatencyReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Input processing: {}", self.processing)?;
+        write!(f, "Application handling: {}", self.handling)
+    }
+}
+
+/// Measures input latency of an application that reads input through it instead of the reader
+pub struct LatencyMonitor {
+    reader: Arc<DeviceStateReader>,
+    processing: Vec<Duration>,
+    handling: Vec<Duration>,
+    delivered_at: Option<Instant>,
+}
+
+impl LatencyMonitor {
+    /// Creates monitor reading from the reader
+    pub fn new(reader: Arc<DeviceStateReader>) -> LatencyMonitor {
+        LatencyMonitor {
+            reader,
+            processing: vec![],
+            handling: vec![],
+            delivered_at: None,
+        }
+    }
+
+    /// Reads updates from the reader, recording how long it took to turn the input into updates
+    pub fn read(&mut self, timeout: Option<Duration>) -> Result<Vec<DeviceStateUpdate>, StreamDeckError> {
+        let (updates, read_at) = self.reader.read_timed(timeout)?;
+
+        if !updates.is_empty() {
+            let delivered_at = Instant::now();
+            self.processing.push(delivered_at - read_at);
+            self.delivered_at = Some(delivered_at);
+        }
+
+        Ok(updates)
+    }
+
+    /// Tells the monitor that updates of the last read were handled, recording how long the application took
+    pub fn handled(&mut self) {
+        if let Some(delivered_at) = self.delivered_at.take() {
+            self.handling.push(delivered_at.elapsed());
+        }
+    }
+
+    /// Returns latencies measured so far
+    pub fn report(&self) -> LatencyReport {
+        LatencyReport {
+            processing: Distribution::from_samples(self.processing.clone()),
+            handling: Distribution::from_samples(self.handling.clone()),
+        }
+    }
+}
+
+/// Measures end-to-end latency from a button report arriving to a mock device of the kind until the handler returns,
+/// by alternately pressing and releasing the first key
+pub fn mock_input_latency(kind: Kind, samples: usize, mut handler: impl FnMut(DeviceStateUpdate)) -> Result<Distribution, StreamDeckError> {
+    let transport = MockTransport::new();
+
+    #[allow(clippy::arc_with_non_send_sync)]
+    let device = Arc::new(StreamDeck::from_transport(kind, transport.clone(), ConnectOptions::default()));
+    let reader = device.get_reader();
+
+    let layout = kind.report_layout();
+    let mut measurements = Vec::with_capacity(samples);
+
+    for sample in 0..samples {
+        let mut report = vec![0u8; layout.length];
+        report[0] = 1;
+
+        if let Some(offset) = layout.type_offset {
+            report[offset] = layout.buttons.report_type;
+        }
+
+        // Original reports keys right to left
+        let first_key = if layout.buttons.flipped_columns { kind.column_count() as usize - 1 } else { 0 };
+        report[layout.buttons.offset + first_key] = (sample % 2 == 0) as u8;
+
+        let start = Instant::now();
+        transport.push_input(report);
+
+        for update in reader.read(None)? {
+            handler(update);
+        }
+
+        measurements.push(start.elapsed());
+    }
+
+    Ok(Distribution::from_samples(measurements))
+}
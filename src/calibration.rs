@@ -0,0 +1,185 @@
+use crate::info::Kind;
+use crate::{DeviceStateUpdate, KeyIndex};
+
+/// Discovers which index a device reports for each physical key by asking for the keys to be pressed one by one,
+/// for devices whose key order isn't known, like ones found with [probe](crate::probe).
+///
+/// It's only a state machine, prompting the user is up to the application: [prompt](Calibration::prompt) tells which physical key to ask for,
+/// counted row by row from the top left corner, and updates read from the device are given to [handle](Calibration::handle)
+/// ```
+/// # use elgato_streamdeck::calibration::{Calibration, CalibrationEvent};
+/// # use elgato_streamdeck::{DeviceStateUpdate, KeyIndex};
+/// let mut calibration = Calibration::new(2, 2);
+///
+/// // Device reports keys in reverse order
+/// for report in [1, 0] {
+///     let (row, column) = calibration.prompt().unwrap();
+///     println!("Press key in row {} column {}", row, column);
+///
+///     if let Some(CalibrationEvent::Finished(remap)) = calibration.handle(&DeviceStateUpdate::ButtonDown(KeyIndex(report))) {
+///         assert_eq!(remap.physical(1), Some(0));
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Calibration {
+    key_count: u8,
+    columns: u8,
+    /// Reported index of every physical key that was pressed so far, in order of physical keys
+    reported: Vec<u8>,
+}
+
+/// What happened with a press given to [Calibration::handle]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CalibrationEvent {
+    /// Reported index was assigned to the physical key that was asked for
+    Assigned {
+        /// Physical key, counted row by row from the top left corner
+        physical: u8,
+        /// Index the device reported for the key
+        reported: u8,
+    },
+
+    /// Pressed key was already assigned to an earlier physical key, so the same physical key is still asked for
+    AlreadyAssigned {
+        /// Physical key the reported index belongs to
+        physical: u8,
+        /// Index the device reported
+        reported: u8,
+    },
+
+    /// Reported index is outside of the key count, so the key count is likely wrong
+    OutOfRange(u8),
+
+    /// Last key was assigned, contains the resulting table
+    Finished(KeyRemap),
+}
+
+impl Calibration {
+    /// Starts calibration of a device with the key count and keys laid out in rows of the column count
+    pub fn new(key_count: u8, columns: u8) -> Calibration {
+        Calibration {
+            key_count,
+            columns: columns.max(1),
+            reported: Vec::with_capacity(key_count as usize),
+        }
+    }
+
+    /// Starts calibration of a device laid out like the kind, for example [provisional kind](crate::probe::ProbeReport::provisional_kind) of a probed device
+    pub fn for_kind(kind: Kind) -> Calibration {
+        Calibration::new(kind.key_count(), kind.column_count())
+    }
+
+    /// Physical key that should be pressed next, None if calibration is finished
+    pub fn next_key(&self) -> Option<u8> {
+        let next = self.reported.len() as u8;
+        (next < self.key_count).then_some(next)
+    }
+
+    /// Position of the key that should be pressed next as (row, column), None if calibration is finished
+    pub fn prompt(&self) -> Option<(u8, u8)> {
+        self.next_key().map(|key| (key / self.columns, key % self.columns))
+    }
+
+    /// Number of keys assigned so far
+    pub fn progress(&self) -> u8 {
+        self.reported.len() as u8
+    }
+
+    /// Takes back the last assignment, so the previous physical key is asked for again
+    pub fn undo(&mut self) -> Option<u8> {
+        self.reported.pop()
+    }
+
+    /// Handles update read from the device, only key presses are used and other updates return None
+    pub fn handle(&mut self, update: &DeviceStateUpdate) -> Option<CalibrationEvent> {
+        let DeviceStateUpdate::ButtonDown(KeyIndex(reported)) = *update else {
+            return None;
+        };
+
+        let physical = self.next_key()?;
+
+        if reported >= self.key_count {
+            return Some(CalibrationEvent::OutOfRange(reported));
+        }
+
+        if let Some(earlier) = self.reported.iter().position(|other| *other == reported) {
+            return Some(CalibrationEvent::AlreadyAssigned { physical: earlier as u8, reported });
+        }
+
+        self.reported.push(reported);
+
+        match self.finish() {
+            Some(remap) => Some(CalibrationEvent::Finished(remap)),
+            None => Some(CalibrationEvent::Assigned { physical, reported }),
+        }
+    }
+
+    /// Resulting table, None until every key was assigned
+    pub fn finish(&self) -> Option<KeyRemap> {
+        if self.next_key().is_some() {
+            return None;
+        }
+
+        let mut physical = vec![0; self.key_count as usize];
+
+        for (key, reported) in self.reported.iter().enumerate() {
+            physical[*reported as usize] = key as u8;
+        }
+
+        Some(KeyRemap { physical })
+    }
+}
+
+/// Table translating key indices reported by a device to physical keys, counted row by row from the top left corner.
+/// Set it as [ConnectOptions::key_remap](crate::ConnectOptions::key_remap) to have readers and key writes of the device use physical keys
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct KeyRemap {
+    physical: Vec<u8>,
+}
+
+impl KeyRemap {
+    /// Creates table from physical key of every reported index, None if it's not a permutation
+    pub fn from_table(physical: Vec<u8>) -> Option<KeyRemap> {
+        let mut seen = vec![false; physical.len()];
+
+        for key in &physical {
+            if std::mem::replace(seen.get_mut(*key as usize)?, true) {
+                return None;
+            }
+        }
+
+        Some(KeyRemap { physical })
+    }
+
+    /// Physical key of every reported index
+    pub fn table(&self) -> &[u8] {
+        &self.physical
+    }
+
+    /// Tells if the device already reports keys in physical order
+    pub fn is_identity(&self) -> bool {
+        self.physical.iter().enumerate().all(|(index, key)| index == *key as usize)
+    }
+
+    /// Physical key of the reported index
+    pub fn physical(&self, reported: u8) -> Option<u8> {
+        self.physical.get(reported as usize).copied()
+    }
+
+    /// Reported index of the physical key
+    pub fn reported(&self, physical: u8) -> Option<u8> {
+        self.physical.iter().position(|key| *key == physical).map(|index| index as u8)
+    }
+
+    /// Translates key indices of the update into physical keys, indices outside of the table are left as they are
+    pub fn apply(&self, update: DeviceStateUpdate) -> DeviceStateUpdate {
+        let map = |key: KeyIndex| KeyIndex(self.physical(key.0).unwrap_or(key.0));
+
+        match update {
+            DeviceStateUpdate::ButtonDown(key) => DeviceStateUpdate::ButtonDown(map(key)),
+            DeviceStateUpdate::ButtonUp(key) => DeviceStateUpdate::ButtonUp(map(key)),
+            update => update,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use image::imageops::{overlay, FilterType};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+#[cfg(feature = "async")]
+use crate::StreamDeckError;
+
+/// Digits and plus sign as 3x5 bitmaps, each row is 3 bits from left to right
+const GLYPHS: [(char, [u8; 5]); 11] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+];
+
+/// Badge drawn in a corner of a key on top of its image
+#[derive(Clone, Debug)]
+pub struct Badge {
+    /// What the badge shows
+    pub content: BadgeContent,
+    /// Corner the badge is placed in
+    pub corner: BadgeCorner,
+    /// Background color of the badge as (red, green, blue)
+    pub color: (u8, u8, u8),
+}
+
+impl Badge {
+    /// Red dot in the top right corner
+    pub fn dot() -> Badge {
+        Badge {
+            content: BadgeContent::Dot,
+            corner: BadgeCorner::TopRight,
+            color: (230, 40, 40),
+        }
+    }
+
+    /// Red counter in the top right corner, numbers above 99 are shown as "99+"
+    pub fn number(number: u32) -> Badge {
+        Badge {
+            content: BadgeContent::Number(number),
+            ..Self::dot()
+        }
+    }
+}
+
+/// Content of a [Badge]
+#[derive(Clone, Debug)]
+pub enum BadgeContent {
+    /// Plain dot
+    Dot,
+    /// Number drawn in white
+    Number(u32),
+    /// Icon scaled to fit the badge
+    Icon(DynamicImage),
+}
+
+/// Corner of a key
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum BadgeCorner {
+    /// Top left corner
+    TopLeft,
+    /// Top right corner
+    TopRight,
+    /// Bottom left corner
+    BottomLeft,
+    /// Bottom right corner
+    BottomRight,
+}
+
+/// Draws the badge on top of the image
+pub fn draw_badge(image: &DynamicImage, badge: &Badge) -> DynamicImage {
+    let mut canvas = image.to_rgba8();
+    let (w, h) = canvas.dimensions();
+
+    let size = (w.min(h) * 2 / 5).max(1);
+    let margin = w.min(h) / 20;
+
+    let x = match badge.corner {
+        BadgeCorner::TopLeft | BadgeCorner::BottomLeft => margin,
+        BadgeCorner::TopRight | BadgeCorner::BottomRight => w.saturating_sub(size + margin),
+    };
+
+    let y = match badge.corner {
+        BadgeCorner::TopLeft | BadgeCorner::TopRight => margin,
+        BadgeCorner::BottomLeft | BadgeCorner::BottomRight => h.saturating_sub(size + margin),
+    };
+
+    let (red, green, blue) = badge.color;
+    fill_circle(&mut canvas, x, y, size, Rgba([red, green, blue, 255]));
+
+    match &badge.content {
+        BadgeContent::Dot => {}
+
+        BadgeContent::Number(number) => {
+            let text = if *number > 99 { "99+".to_string() } else { number.to_string() };
+            draw_glyphs(&mut canvas, x, y, size, &text, Rgba([255, 255, 255, 255]));
+        }
+
+        BadgeContent::Icon(icon) => {
+            let inner = (size * 7 / 10).max(1);
+            let icon = icon.resize(inner, inner, FilterType::Triangle);
+            let (icon_w, icon_h) = icon.dimensions();
+
+            overlay(&mut canvas, &icon.to_rgba8(), (x + (size - icon_w) / 2) as i64, (y + (size - icon_h) / 2) as i64);
+        }
+    }
+
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// Draws the badge on top of the image on the blocking thread pool, so async applications don't block the runtime
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub async fn draw_badge_async(image: DynamicImage, badge: Badge) -> Result<DynamicImage, StreamDeckError> {
+    Ok(tokio::task::spawn_blocking(move || draw_badge(&image, &badge)).await?)
+}
+
+/// Creates image of provided size filled with the color, with area outside of rounded corners left black
+pub fn rounded_background(size: (usize, usize), color: (u8, u8, u8), corner_radius: u32) -> RgbaImage {
+    let (w, h) = (size.0 as u32, size.1 as u32);
+    let radius = corner_radius.min(w / 2).min(h / 2) as f32;
+    let (red, green, blue) = color;
+
+    RgbaImage::from_fn(w, h, |px, py| {
+        let dx = px as f32 + 0.5 - (px as f32 + 0.5).clamp(radius, w as f32 - radius);
+        let dy = py as f32 + 0.5 - (py as f32 + 0.5).clamp(radius, h as f32 - radius);
+
+        if dx * dx + dy * dy <= radius * radius {
+            Rgba([red, green, blue, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    })
+}
+
+fn fill_circle(canvas: &mut RgbaImage, x: u32, y: u32, size: u32, color: Rgba<u8>) {
+    let radius = size as f32 / 2.0;
+
+    for py in y..(y + size).min(canvas.height()) {
+        for px in x..(x + size).min(canvas.width()) {
+            let dx = px as f32 + 0.5 - (x as f32 + radius);
+            let dy = py as f32 + 0.5 - (y as f32 + radius);
+
+            if dx * dx + dy * dy <= radius * radius {
+                canvas.put_pixel(px, py, color);
+            }
+        }
+    }
+}
+
+fn draw_glyphs(canvas: &mut RgbaImage, x: u32, y: u32, size: u32, text: &str, color: Rgba<u8>) {
+    let columns = (text.chars().count() * 4).saturating_sub(1) as u32;
+    let scale = ((size * 3 / 5) / columns.max(1)).min((size / 2) / 5).max(1);
+
+    let start_x = x + size.saturating_sub(columns * scale) / 2;
+    let start_y = y + size.saturating_sub(5 * scale) / 2;
+
+    for (index, c) in text.chars().enumerate() {
+        let Some((_, rows)) = GLYPHS.iter().find(|(glyph, _)| *glyph == c) else {
+            continue;
+        };
+
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+
+                let cell_x = start_x + (index as u32 * 4 + column) * scale;
+                let cell_y = start_y + row as u32 * scale;
+
+                for py in cell_y..(cell_y + scale).min(canvas.height()) {
+                    for px in cell_x..(cell_x + scale).min(canvas.width()) {
+                        canvas.put_pixel(px, py, color);
+                    }
+                }
+            }
+        }
+    }
+}
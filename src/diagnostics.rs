@@ -0,0 +1,91 @@
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::{StreamDeck, StreamDeckError};
+
+/// Most diagnostics emitted per window, the rest are only counted
+const BURST: u32 = 5;
+
+/// Length of the rate limiting window
+const WINDOW: Duration = Duration::from_secs(10);
+
+/// Most bytes of a report included in a diagnostic
+const DUMP_LENGTH: usize = 64;
+
+/// Rate limited diagnostics of protocol errors of a single device
+#[derive(Default)]
+pub(crate) struct Diagnostics {
+    limiter: Mutex<Limiter>,
+    firmware: OnceLock<Option<String>>,
+}
+
+#[derive(Default)]
+struct Limiter {
+    window_start: Option<Instant>,
+    emitted: u32,
+    suppressed: u32,
+}
+
+impl Diagnostics {
+    /// Emits diagnostic of the error through tracing, unless too many were emitted recently
+    pub(crate) fn record(&self, device: &StreamDeck, operation: &'static str, error: &StreamDeckError, report: &[u8]) {
+        let Some(suppressed) = self.admit() else {
+            return;
+        };
+
+        // Queried only once, so a misbehaving device isn't asked again on every error.
+        // A device that timed out may still be stuck in the write, so it isn't asked at all
+        let firmware = match error {
+            StreamDeckError::Timeout => self.firmware.get().and_then(Option::as_ref),
+            _ => self.firmware.get_or_init(|| device.firmware_version().ok()).as_ref(),
+        };
+
+        tracing::warn!(
+            kind = ?device.kind(),
+            firmware = firmware.map(String::as_str).unwrap_or("unknown"),
+            operation,
+            error = %error,
+            report = %hex_dump(report),
+            suppressed,
+            "Stream Deck protocol error"
+        );
+    }
+
+    /// Returns number of diagnostics suppressed since the last emitted one, or None if this one should be suppressed
+    fn admit(&self) -> Option<u32> {
+        let mut limiter = self.limiter.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+
+        if limiter.window_start.is_none_or(|start| now.duration_since(start) >= WINDOW) {
+            limiter.window_start = Some(now);
+            limiter.emitted = 0;
+        }
+
+        if limiter.emitted >= BURST {
+            limiter.suppressed += 1;
+            return None;
+        }
+
+        limiter.emitted += 1;
+        Some(std::mem::take(&mut limiter.suppressed))
+    }
+}
+
+fn hex_dump(report: &[u8]) -> String {
+    let mut dump = String::with_capacity(DUMP_LENGTH * 3);
+
+    for (index, byte) in report.iter().take(DUMP_LENGTH).enumerate() {
+        if index > 0 {
+            dump.push(' ');
+        }
+
+        let _ = write!(dump, "{:02x}", byte);
+    }
+
+    if report.len() > DUMP_LENGTH {
+        let _ = write!(dump, " ... ({} bytes)", report.len());
+    }
+
+    dump
+}
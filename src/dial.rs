@@ -0,0 +1,178 @@
+use std::time::{Duration, Instant};
+
+use image::DynamicImage;
+
+use crate::gauge::{Gauge, GaugeShape};
+use crate::images::convert_image_with_format;
+use crate::info::{ImageRotation, Screen};
+use crate::palette::StatusColor;
+use crate::{DeviceStateUpdate, StreamDeck, StreamDeckError};
+
+/// What happens when a [DialValue] is turned past its bounds
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub enum DialBounds {
+    /// Value stops at min and max
+    #[default]
+    Clamp,
+    /// Value continues from the other end, like an angle
+    Wrap,
+}
+
+/// Speeds up a [DialValue] when its encoder is turned quickly
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DialAcceleration {
+    /// Twists that come sooner than this after the previous one are quick
+    pub window: Duration,
+    /// How much every quick twist in a row multiplies the step
+    pub factor: f32,
+    /// Largest multiplier of the step
+    pub max_multiplier: f32,
+}
+
+impl Default for DialAcceleration {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_millis(80),
+            factor: 1.5,
+            max_multiplier: 8.0,
+        }
+    }
+}
+
+/// Change of a [DialValue]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DialChange {
+    /// Encoder that changed the value
+    pub encoder: u8,
+    /// Value before the change
+    pub previous: f32,
+    /// Value after the change
+    pub value: f32,
+}
+
+/// Value bound to an encoder, like volume controlled by a knob.
+///
+/// Feed it updates from [DeviceStateReader](crate::DeviceStateReader) with [handle](DialValue::handle),
+/// and show it on the LCD segment above the encoder with [write_gauge](DialValue::write_gauge)
+#[derive(Clone, Debug)]
+pub struct DialValue {
+    /// Encoder that changes the value
+    pub encoder: u8,
+    /// Lowest value
+    pub min: f32,
+    /// Highest value
+    pub max: f32,
+    /// How much one tick of the encoder changes the value
+    pub step: f32,
+    /// What happens when the value is turned past min or max
+    pub bounds: DialBounds,
+    /// Speeds up quick turns, None keeps the step constant
+    pub acceleration: Option<DialAcceleration>,
+    /// Color of the filled part of the gauge as (red, green, blue)
+    pub color: (u8, u8, u8),
+    /// Shape of the gauge
+    pub gauge_shape: GaugeShape,
+    value: f32,
+    multiplier: f32,
+    last_twist: Option<Instant>,
+}
+
+impl DialValue {
+    /// Creates value of the encoder that starts at min, clamped and without acceleration
+    pub fn new(encoder: u8, min: f32, max: f32, step: f32) -> DialValue {
+        DialValue {
+            encoder,
+            min,
+            max: max.max(min),
+            step,
+            bounds: DialBounds::Clamp,
+            acceleration: None,
+            color: StatusColor::Info.rgb(),
+            gauge_shape: GaugeShape::Bar,
+            value: min,
+            multiplier: 1.0,
+            last_twist: None,
+        }
+    }
+
+    /// Current value
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Sets the value, keeping it within bounds
+    pub fn set_value(&mut self, value: f32) {
+        self.value = self.bound(value);
+    }
+
+    /// Current value within min and max as 0.0 - 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.max > self.min { (self.value - self.min) / (self.max - self.min) } else { 0.0 }
+    }
+
+    /// Changes the value if the update is a twist of the encoder, returns the change if the value changed
+    pub fn handle(&mut self, update: &DeviceStateUpdate) -> Option<DialChange> {
+        match update {
+            DeviceStateUpdate::EncoderTwist(encoder, ticks) if encoder.0 == self.encoder => self.twist(*ticks),
+            _ => None,
+        }
+    }
+
+    /// Changes the value by ticks of the encoder, returns the change if the value changed
+    pub fn twist(&mut self, ticks: i8) -> Option<DialChange> {
+        let now = Instant::now();
+
+        self.multiplier = match (self.acceleration, self.last_twist) {
+            (Some(acceleration), Some(last_twist)) if now.duration_since(last_twist) < acceleration.window => (self.multiplier * acceleration.factor).min(acceleration.max_multiplier),
+            _ => 1.0,
+        };
+
+        self.last_twist = Some(now);
+
+        let steps = (ticks as f32 * self.multiplier).round();
+        let previous = self.value;
+        self.value = self.bound(previous + steps * self.step);
+
+        (self.value != previous).then_some(DialChange {
+            encoder: self.encoder,
+            previous,
+            value: self.value,
+        })
+    }
+
+    /// Renders the value as a [gauge](Gauge) of the size
+    pub fn render_gauge(&self, width: u32, height: u32) -> DynamicImage {
+        let gauge = Gauge {
+            color: self.color,
+            ..Gauge::new(self.gauge_shape)
+        };
+
+        DynamicImage::ImageRgba8(gauge.render(self.fraction(), width, height))
+    }
+
+    /// Writes the gauge to the LCD segment of the encoder, fails with [StreamDeckError::NoScreen] if the device has no LCD strip
+    pub fn write_gauge(&self, device: &StreamDeck) -> Result<(), StreamDeckError> {
+        let screen = device.kind().screen(Screen::LCD_STRIP).ok_or(StreamDeckError::NoScreen)?;
+        let segment = *screen.segments.get(self.encoder as usize).ok_or(StreamDeckError::InvalidScreenRegion)?;
+
+        // Segments are in coordinates of the device, the gauge is drawn the way the user sees it
+        let (width, height) = match screen.format.rotation {
+            ImageRotation::Rot90 | ImageRotation::Rot270 => (segment.h, segment.w),
+            ImageRotation::Rot0 | ImageRotation::Rot180 => (segment.w, segment.h),
+        };
+
+        let mut format = screen.format;
+        format.size = (segment.w, segment.h);
+
+        let image_data = convert_image_with_format(format, self.render_gauge(width as u32, height as u32))?;
+        device.write_screen(screen.id, Some(segment), &image_data)
+    }
+
+    fn bound(&self, value: f32) -> f32 {
+        match self.bounds {
+            DialBounds::Clamp => value.clamp(self.min, self.max),
+            DialBounds::Wrap if self.max > self.min => self.min + (value - self.min).rem_euclid(self.max - self.min),
+            DialBounds::Wrap => self.min,
+        }
+    }
+}
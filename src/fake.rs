@@ -0,0 +1,208 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use hidapi::HidError;
+
+use crate::activity::{Activity, ActivityKind};
+use crate::info::Kind;
+use crate::transport::MockTransport;
+use crate::util::normalize_serial;
+use crate::{ConnectOptions, StreamDeck, StreamDeckError};
+
+/// Environment variable that replaces connected devices with fake ones.
+///
+/// Value is a comma separated list of devices as `Kind[:serial][=transcript]`, for example `Plus,Mk2:CI0001=tests/transcripts/mk2.txt`.
+/// Kinds are named like [Kind] variants, serial defaults to `FAKE` followed by the position in the list,
+/// and input reports of the [transcript](Transcript) are replayed to the application as if the device sent them
+pub const FAKE_ENV: &str = "ELGATO_STREAMDECK_FAKE";
+
+/// Device that is pretended to be connected in fake mode, see [FAKE_ENV]
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct FakeDevice {
+    /// Kind of the device
+    pub kind: Kind,
+    /// Serial number of the device
+    pub serial: String,
+    /// Transcript whose input reports are replayed
+    pub transcript: Option<PathBuf>,
+}
+
+impl FakeDevice {
+    /// Connects to the fake device, backed by a [MockTransport] that has the transcript's input reports queued
+    pub fn connect(&self, options: ConnectOptions) -> Result<StreamDeck, StreamDeckError> {
+        let transport = match &self.transcript {
+            Some(path) => Transcript::load(path)?.mock(),
+            None => MockTransport::new(),
+        };
+
+        Ok(StreamDeck::from_transport(self.kind, transport, options))
+    }
+}
+
+/// Fake devices configured with [FAKE_ENV], None if it isn't set and real devices should be used
+pub fn fake_devices() -> Option<Vec<FakeDevice>> {
+    let value = std::env::var(FAKE_ENV).ok()?;
+
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let (device, transcript) = match entry.split_once('=') {
+                    Some((device, transcript)) => (device, Some(PathBuf::from(transcript.trim()))),
+                    None => (entry, None),
+                };
+
+                let (name, serial) = match device.split_once(':') {
+                    Some((name, serial)) => (name.trim(), normalize_serial(serial)),
+                    None => (device.trim(), format!("FAKE{:04}", index + 1)),
+                };
+
+                let kind = Kind::all().find(|kind| format!("{:?}", kind).eq_ignore_ascii_case(name))?;
+
+                Some(FakeDevice { kind, serial, transcript })
+            })
+            .collect(),
+    )
+}
+
+/// Connects to the fake device if fake mode is on, fails like a missing device if there's no such fake device
+pub(crate) fn connect_fake(kind: Kind, serial: &str, options: ConnectOptions) -> Option<Result<StreamDeck, StreamDeckError>> {
+    let devices = fake_devices()?;
+
+    Some(match devices.iter().find(|device| device.kind == kind && device.serial == normalize_serial(serial)) {
+        Some(device) => device.connect(options),
+        None => Err(StreamDeckError::HidError(HidError::HidApiError {
+            message: format!("No fake {:?} with serial {}", kind, serial),
+        })),
+    })
+}
+
+/// Report in a [Transcript]
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub enum TranscriptEntry {
+    /// Input report the device sent, written as `in` line
+    Input(Vec<u8>),
+    /// Output report the device was sent, written as `out` line
+    Output(Vec<u8>),
+    /// Feature report the device was sent, written as `feature` line
+    FeatureReport(Vec<u8>),
+}
+
+/// Reports recorded from a device, so application logic can be tested end to end without hardware.
+///
+/// In text form every line is a report in hex, prefixed with `in`, `out` or `feature`, for example `in 01 00 01 00`.
+/// Empty lines and lines starting with `#` are ignored. Recorded outputs can be shortened to their start,
+/// which is how [recent activity](StreamDeck::recent_activity) records them
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub struct Transcript {
+    /// Reports in order they were exchanged
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    /// Parses transcript from its text form, fails with [StreamDeckError::BadData] on lines that can't be parsed
+    pub fn parse(text: &str) -> Result<Transcript, StreamDeckError> {
+        let entries = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (direction, data) = line.split_once(' ').ok_or(StreamDeckError::BadData)?;
+
+                let data = data
+                    .split_whitespace()
+                    .map(|byte| u8::from_str_radix(byte, 16).map_err(|_| StreamDeckError::BadData))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                match direction {
+                    "in" => Ok(TranscriptEntry::Input(data)),
+                    "out" => Ok(TranscriptEntry::Output(data)),
+                    "feature" => Ok(TranscriptEntry::FeatureReport(data)),
+                    _ => Err(StreamDeckError::BadData),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Transcript { entries })
+    }
+
+    /// Loads transcript from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Transcript, StreamDeckError> {
+        Transcript::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Makes transcript from activity of a real device, failed exchanges and requested feature reports are left out
+    pub fn from_activity(activity: &[Activity]) -> Transcript {
+        let entries = activity
+            .iter()
+            .filter(|activity| activity.error.is_none())
+            .filter_map(|activity| match activity.kind {
+                ActivityKind::Read => Some(TranscriptEntry::Input(activity.data.clone())),
+                ActivityKind::Write => Some(TranscriptEntry::Output(activity.data.clone())),
+                ActivityKind::SendFeatureReport => Some(TranscriptEntry::FeatureReport(activity.data.clone())),
+                ActivityKind::GetFeatureReport => None,
+            })
+            .collect();
+
+        Transcript { entries }
+    }
+
+    /// Text form of the transcript
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for entry in &self.entries {
+            let (direction, data) = match entry {
+                TranscriptEntry::Input(data) => ("in", data),
+                TranscriptEntry::Output(data) => ("out", data),
+                TranscriptEntry::FeatureReport(data) => ("feature", data),
+            };
+
+            let _ = write!(text, "{}", direction);
+
+            for byte in data {
+                let _ = write!(text, " {:02x}", byte);
+            }
+
+            text.push('\n');
+        }
+
+        text
+    }
+
+    /// Returns mock transport that has input reports of the transcript queued
+    pub fn mock(&self) -> MockTransport {
+        let transport = MockTransport::new();
+
+        for entry in &self.entries {
+            if let TranscriptEntry::Input(report) = entry {
+                transport.push_input(report.clone());
+            }
+        }
+
+        transport
+    }
+
+    /// Checks reports the transport was sent against recorded outputs and feature reports, taking them from the transport.
+    /// Returns the first recorded entry that wasn't matched, None if everything was sent as recorded
+    pub fn verify(&self, transport: &MockTransport) -> Option<TranscriptEntry> {
+        let mut written = transport.take_written().into_iter();
+        let mut feature_reports = transport.take_feature_reports().into_iter();
+
+        self.entries
+            .iter()
+            .find(|entry| {
+                let (sent, recorded) = match entry {
+                    TranscriptEntry::Input(_) => return false,
+                    TranscriptEntry::Output(recorded) => (written.next(), recorded),
+                    TranscriptEntry::FeatureReport(recorded) => (feature_reports.next(), recorded),
+                };
+
+                !sent.is_some_and(|sent| sent.starts_with(recorded))
+            })
+            .cloned()
+    }
+}
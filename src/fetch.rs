@@ -0,0 +1,123 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use image::DynamicImage;
+use ureq::Agent;
+
+use crate::images::{content_hash, decode_image};
+use crate::StreamDeckError;
+
+/// How long fetching a file may take by default, including connecting
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest file fetched by default, images for keys and screens are much smaller
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Fetches images from URLs over HTTP, like weather icons of a dashboard, keeping downloaded files in a directory.
+///
+/// Files are named after a hash of the URL, so the directory can be shared by multiple caches and safely deleted at any time.
+/// Cached files are used until they're older than [max_age](ImageCache::max_age), and also when fetching fails, so keys keep their icons while offline.
+/// Fetching gives up after [timeout](ImageCache::timeout) and on files larger than [max_size](ImageCache::max_size), so a slow or misbehaving server can't stall the caller
+#[derive(Clone, Debug)]
+pub struct ImageCache {
+    dir: PathBuf,
+    max_age: Duration,
+    max_size: u64,
+    agent: Agent,
+}
+
+impl ImageCache {
+    /// Creates cache keeping files in the directory, which is created on first fetch. Files are kept for a day,
+    /// fetching times out after 30 seconds and files up to 10 MiB are accepted
+    pub fn new(dir: impl AsRef<Path>) -> ImageCache {
+        ImageCache {
+            dir: dir.as_ref().to_path_buf(),
+            max_age: Duration::from_secs(24 * 60 * 60),
+            max_size: DEFAULT_MAX_SIZE,
+            agent: agent(DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Sets how long fetched files are used before they're fetched again
+    pub fn max_age(mut self, max_age: Duration) -> ImageCache {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Sets how long fetching a file may take, including connecting, before it fails
+    pub fn timeout(mut self, timeout: Duration) -> ImageCache {
+        self.agent = agent(timeout);
+        self
+    }
+
+    /// Sets size of the largest file that is fetched in bytes, larger files fail to fetch
+    pub fn max_size(mut self, max_size: u64) -> ImageCache {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Returns image of the URL from the cache, or fetches it if it isn't cached or is too old.
+    /// Format is detected from contents, see [load_image](crate::images::load_image) for supported formats
+    pub fn from_url(&self, url: &str) -> Result<DynamicImage, StreamDeckError> {
+        let path = self.path_of(url);
+
+        let age = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(|modified| SystemTime::now().duration_since(modified).unwrap_or(Duration::ZERO));
+
+        if let Some(age) = age
+            && age < self.max_age
+            && let Ok(image) = fs::read(&path).map_err(StreamDeckError::from).and_then(|data| decode_image(&data))
+        {
+            return Ok(image);
+        }
+
+        match self.fetch(url, &path) {
+            Ok(image) => Ok(image),
+            // Stale image is better than none
+            Err(error) => fs::read(&path).ok().and_then(|data| decode_image(&data).ok()).ok_or(error),
+        }
+    }
+
+    /// Same as [from_url](ImageCache::from_url), but fetches on the blocking thread pool so the runtime isn't blocked
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn from_url_async(&self, url: &str) -> Result<DynamicImage, StreamDeckError> {
+        let cache = self.clone();
+        let url = url.to_string();
+
+        tokio::task::spawn_blocking(move || cache.from_url(&url)).await?
+    }
+
+    /// Removes the URL's file from the cache, so the next request fetches it again
+    pub fn invalidate(&self, url: &str) -> Result<(), StreamDeckError> {
+        match fs::remove_file(self.path_of(url)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn path_of(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", content_hash(url.as_bytes())))
+    }
+
+    fn fetch(&self, url: &str, path: &Path) -> Result<DynamicImage, StreamDeckError> {
+        let data = self.agent.get(url).call()?.body_mut().with_config().limit(self.max_size).read_to_vec()?;
+        let image = decode_image(&data)?;
+
+        // Written next to the final file and renamed, so readers never see a partial file
+        fs::create_dir_all(&self.dir)?;
+        let temp = path.with_extension("part");
+        fs::write(&temp, &data)?;
+        fs::rename(&temp, path)?;
+
+        Ok(image)
+    }
+}
+
+fn agent(timeout: Duration) -> Agent {
+    Agent::config_builder().timeout_global(Some(timeout)).build().into()
+}
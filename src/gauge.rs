@@ -0,0 +1,123 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::info::Kind;
+use crate::palette::StatusColor;
+
+/// Angle the radial gauge sweeps, in degrees
+const RADIAL_SWEEP: f32 = 270.0;
+
+/// Angle the radial gauge starts at, in degrees clockwise from the right, which puts the gap at the bottom
+const RADIAL_START: f32 = 135.0;
+
+/// Shape of a [Gauge]
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub enum GaugeShape {
+    /// Straight bar, horizontal filling from the left when the image is wider than tall, otherwise vertical filling from the bottom
+    #[default]
+    Bar,
+    /// Ring with a gap at the bottom filling clockwise, like a knob
+    Radial,
+}
+
+/// Gauge showing a fraction, drawn to fit any size, so the same gauge works on keys and on segments of the LCD strip
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct Gauge {
+    /// Shape of the gauge
+    pub shape: GaugeShape,
+    /// Color of the filled part as (red, green, blue)
+    pub color: (u8, u8, u8),
+    /// Color of the part that isn't filled as (red, green, blue)
+    pub track_color: (u8, u8, u8),
+    /// Color around the gauge as (red, green, blue)
+    pub background: (u8, u8, u8),
+}
+
+impl Gauge {
+    /// Creates gauge of the shape, in the info color on black background
+    pub fn new(shape: GaugeShape) -> Gauge {
+        Gauge {
+            shape,
+            color: StatusColor::Info.rgb(),
+            track_color: (64, 64, 64),
+            background: (0, 0, 0),
+        }
+    }
+
+    /// Renders the gauge filled to the fraction, which is clamped to 0.0 - 1.0
+    pub fn render(&self, fraction: f32, width: u32, height: u32) -> RgbaImage {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let [color, track, background] = [self.color, self.track_color, self.background].map(|(red, green, blue)| Rgba([red, green, blue, 255]));
+
+        match self.shape {
+            GaugeShape::Bar if width >= height => {
+                let margin_x = width / 10;
+                let track_height = (height / 4).max(1);
+                let track_top = (height - track_height) / 2;
+                let track_width = width.saturating_sub(margin_x * 2);
+                let filled = (track_width as f32 * fraction).round() as u32;
+
+                RgbaImage::from_fn(width, height, |x, y| {
+                    if y < track_top || y >= track_top + track_height || x < margin_x || x >= margin_x + track_width {
+                        background
+                    } else if x < margin_x + filled {
+                        color
+                    } else {
+                        track
+                    }
+                })
+            }
+
+            GaugeShape::Bar => {
+                let margin_y = height / 10;
+                let track_width = (width / 4).max(1);
+                let track_left = (width - track_width) / 2;
+                let track_height = height.saturating_sub(margin_y * 2);
+                let filled = (track_height as f32 * fraction).round() as u32;
+
+                RgbaImage::from_fn(width, height, |x, y| {
+                    if x < track_left || x >= track_left + track_width || y < margin_y || y >= margin_y + track_height {
+                        background
+                    } else if y >= margin_y + track_height - filled {
+                        color
+                    } else {
+                        track
+                    }
+                })
+            }
+
+            GaugeShape::Radial => {
+                let (center_x, center_y) = (width as f32 / 2.0, height as f32 / 2.0);
+                let outer = width.min(height) as f32 / 2.0 * 0.9;
+                let inner = outer - (outer / 4.0).max(1.0);
+
+                RgbaImage::from_fn(width, height, |x, y| {
+                    let (dx, dy) = (x as f32 + 0.5 - center_x, y as f32 + 0.5 - center_y);
+                    let distance = (dx * dx + dy * dy).sqrt();
+
+                    // Y grows downwards, so angles grow clockwise
+                    let angle = (dy.atan2(dx).to_degrees() - RADIAL_START).rem_euclid(360.0);
+
+                    if distance < inner || distance > outer || angle > RADIAL_SWEEP {
+                        background
+                    } else if angle <= RADIAL_SWEEP * fraction {
+                        color
+                    } else {
+                        track
+                    }
+                })
+            }
+        }
+    }
+
+    /// Renders the gauge in the key size of the kind, ready for [set_button_image](crate::StreamDeck::set_button_image)
+    pub fn render_key(&self, kind: Kind, fraction: f32) -> DynamicImage {
+        let (width, height) = kind.key_image_resolution();
+        DynamicImage::ImageRgba8(self.render(fraction, width as u32, height as u32))
+    }
+}
+
+impl Default for Gauge {
+    fn default() -> Self {
+        Gauge::new(GaugeShape::Bar)
+    }
+}
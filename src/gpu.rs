@@ -0,0 +1,359 @@
+use std::sync::mpsc::channel;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageError, RgbaImage};
+use wgpu::util::DeviceExt;
+
+use crate::images::{convert_image, tile_image};
+use crate::info::Kind;
+
+const SHADER: &str = r#"
+struct Params {
+    origin: vec2<f32>,
+    extent: vec2<f32>,
+    footprint: vec2<f32>,
+    taps: vec2<f32>,
+}
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var linear: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let center = params.origin + in.uv * params.extent;
+    let taps = vec2<i32>(params.taps);
+
+    // Averaging samples across the area of the source a pixel covers, so downscaling doesn't alias
+    var color = vec4<f32>(0.0);
+    for (var y = 0; y < taps.y; y++) {
+        for (var x = 0; x < taps.x; x++) {
+            let offset = (vec2<f32>(f32(x), f32(y)) + 0.5) / params.taps - 0.5;
+            color += textureSampleLevel(source, linear, center + offset * params.footprint, 0.0);
+        }
+    }
+
+    return color / f32(taps.x * taps.y);
+}
+"#;
+
+/// Most samples taken along each axis for a single pixel
+const MAX_TAPS: f32 = 8.0;
+
+/// Scales images on the GPU, for applications streaming video frames to many keys.
+///
+/// Whole frame is scaled to the key grid in a single pass and only cut into tiles and encoded on the CPU.
+/// If the GPU fails to scale an image, it's scaled on the CPU the same way [images](crate::images) does it
+pub struct GpuScaler {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl GpuScaler {
+    /// Sets up the scaler on the default GPU, None if there's no GPU that can be used
+    pub fn new() -> Option<GpuScaler> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).ok()?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()?;
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("streamdeck scale shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("streamdeck scale bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("streamdeck scale pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("streamdeck scale pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::TextureFormat::Rgba8Unorm.into())],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("streamdeck scale sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Some(GpuScaler {
+            device,
+            queue,
+            pipeline,
+            bind_group_layout,
+            sampler,
+        })
+    }
+
+    /// Scales image to exactly the size, ignoring aspect ratio
+    pub fn resize_exact(&self, image: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        match self.scale(image, width, height, (0.0, 0.0), (1.0, 1.0)) {
+            Some(image) => DynamicImage::ImageRgba8(image),
+            None => image.resize_exact(width, height, FilterType::Triangle),
+        }
+    }
+
+    /// Scales image to cover the size, cropping what doesn't fit, same as [DynamicImage::resize_to_fill]
+    pub fn resize_to_fill(&self, image: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        let (image_width, image_height) = (image.width().max(1) as f32, image.height().max(1) as f32);
+        let scale = (width as f32 / image_width).max(height as f32 / image_height);
+
+        let extent = ((width as f32 / (image_width * scale)).min(1.0), (height as f32 / (image_height * scale)).min(1.0));
+        let origin = ((1.0 - extent.0) / 2.0, (1.0 - extent.1) / 2.0);
+
+        match self.scale(image, width, height, origin, extent) {
+            Some(image) => DynamicImage::ImageRgba8(image),
+            None => image.resize_to_fill(width, height, FilterType::Triangle),
+        }
+    }
+
+    /// Splits image into images of every key of the kind, same as [tile_image] but scaling the frame on the GPU
+    pub fn tile_image(&self, kind: Kind, image: &DynamicImage) -> Vec<DynamicImage> {
+        let (rows, columns) = (kind.row_count() as u32, kind.column_count() as u32);
+        let (key_width, key_height) = (kind.key_image_resolution().0 as u32, kind.key_image_resolution().1 as u32);
+
+        if key_width == 0 || key_height == 0 {
+            return tile_image(kind, image);
+        }
+
+        let grid = self.resize_to_fill(image, key_width * columns, key_height * rows);
+
+        (0..rows * columns)
+            .map(|key| grid.crop_imm((key % columns) * key_width, (key / columns) * key_height, key_width, key_height))
+            .take(kind.key_count() as usize)
+            .collect()
+    }
+
+    /// Tiles image across keys of the kind and encodes every tile, ready to be written with [write_image](crate::StreamDeck::write_image)
+    pub fn convert_tiles(&self, kind: Kind, image: &DynamicImage) -> Result<Vec<Vec<u8>>, ImageError> {
+        self.tile_image(kind, image).into_iter().map(|tile| convert_image(kind, tile)).collect()
+    }
+
+    /// Renders the region of the image, given in 0.0 - 1.0 range, at the size. None if the GPU couldn't do it
+    fn scale(&self, image: &DynamicImage, width: u32, height: u32, origin: (f32, f32), extent: (f32, f32)) -> Option<RgbaImage> {
+        let limit = self.device.limits().max_texture_dimension_2d;
+        let source = image.to_rgba8();
+
+        if [source.width(), source.height(), width, height].iter().any(|size| *size == 0 || *size > limit) {
+            return None;
+        }
+
+        let source_size = wgpu::Extent3d {
+            width: source.width(),
+            height: source.height(),
+            depth_or_array_layers: 1,
+        };
+
+        let source_texture = self.device.create_texture_with_data(
+            &self.queue,
+            &wgpu::TextureDescriptor {
+                label: Some("streamdeck scale source"),
+                size: source_size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            source.as_raw(),
+        );
+
+        let target_size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let target_texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("streamdeck scale target"),
+            size: target_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        // Size of a target pixel in source coordinates, and how many texels it spans
+        let footprint = (extent.0 / width as f32, extent.1 / height as f32);
+        let taps = (
+            (footprint.0 * source.width() as f32).ceil().clamp(1.0, MAX_TAPS),
+            (footprint.1 * source.height() as f32).ceil().clamp(1.0, MAX_TAPS),
+        );
+
+        let params: Vec<u8> = [origin.0, origin.1, extent.0, extent.1, footprint.0, footprint.1, taps.0, taps.1]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+
+        let params = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("streamdeck scale params"),
+            contents: &params,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let source_view = source_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let target_view = target_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("streamdeck scale bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+        });
+
+        // Rows of a buffer the texture is copied into have to be aligned
+        let unpadded_row = width * 4;
+        let padded_row = unpadded_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("streamdeck scale output"),
+            size: padded_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("streamdeck scale") });
+
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("streamdeck scale pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &target_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &output,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            target_size,
+        );
+
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = channel();
+        let slice = output.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        self.device.poll(wgpu::PollType::Wait).ok()?;
+        receiver.recv().ok()?.ok()?;
+
+        let data = slice.get_mapped_range().chunks(padded_row as usize).flat_map(|row| row[..unpadded_row as usize].to_vec()).collect();
+
+        output.unmap();
+
+        RgbaImage::from_raw(width, height, data)
+    }
+}
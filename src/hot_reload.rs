@@ -0,0 +1,138 @@
+use std::collections::BTreeSet;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+
+use notify::{recommended_watcher, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::assets::AssetRegistry;
+use crate::images::load_image;
+use crate::{StreamDeck, StreamDeckError};
+
+/// Keeps an [AssetRegistry] in sync with image files in a directory, for seeing changes to icons on the device while designing layouts.
+///
+/// Every image file in the directory is registered under its name without extension, `mute_on.png` as `mute_on`.
+/// Files that fail to load, like ones that are still being written, are skipped until they change again.
+/// Changes are picked up by calling [update](AssetWatcher::update) from the application's loop
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use elgato_streamdeck::assets::AssetRegistry;
+/// # use elgato_streamdeck::hot_reload::AssetWatcher;
+/// # use elgato_streamdeck::{new_hidapi, ConnectOptions, StreamDeck};
+/// # use elgato_streamdeck::info::Kind;
+/// let assets = Arc::new(AssetRegistry::new());
+/// let watcher = AssetWatcher::new(assets.clone(), "icons").unwrap();
+///
+/// let options = ConnectOptions { assets: Some(assets), ..Default::default() };
+/// let device = StreamDeck::connect_with_options(&new_hidapi().unwrap(), Kind::Mk2, "AL12H1A00000", options).unwrap();
+/// device.set_button_asset(0, "mute_on").unwrap();
+///
+/// loop {
+///     watcher.update(&[&device]).unwrap();
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+/// }
+/// ```
+pub struct AssetWatcher {
+    assets: Arc<AssetRegistry>,
+    directory: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    // Watching stops when the watcher is dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl AssetWatcher {
+    /// Registers all images in the directory and starts watching it. Subdirectories aren't watched
+    pub fn new(assets: Arc<AssetRegistry>, directory: impl AsRef<Path>) -> Result<AssetWatcher, StreamDeckError> {
+        let directory = directory.as_ref().to_path_buf();
+        let (sender, events) = channel();
+
+        // Watching before the initial load, so files changed in between aren't missed
+        let mut watcher = recommended_watcher(move |event| {
+            sender.send(event).ok();
+        })?;
+        watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+        let watcher = AssetWatcher {
+            assets,
+            directory,
+            events,
+            _watcher: watcher,
+        };
+
+        for entry in read_dir(&watcher.directory)? {
+            watcher.reload(&entry?.path())?;
+        }
+
+        Ok(watcher)
+    }
+
+    /// Directory that is watched
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Applies changes of files to the registry without waiting, returns names of assets that were registered again or removed
+    pub fn poll(&self) -> Result<Vec<String>, StreamDeckError> {
+        let mut changed = BTreeSet::new();
+
+        for event in self.events.try_iter() {
+            let event = event?;
+
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+
+            for path in &event.paths {
+                if let Some(name) = self.reload(path)? {
+                    changed.insert(name);
+                }
+            }
+        }
+
+        Ok(changed.into_iter().collect())
+    }
+
+    /// Applies changes of files to the registry, then sets keys of the devices showing changed assets again and flushes them.
+    /// Returns names of assets that changed
+    pub fn update(&self, devices: &[&StreamDeck]) -> Result<Vec<String>, StreamDeckError> {
+        let changed = self.poll()?;
+
+        if !changed.is_empty() {
+            for device in devices {
+                if device.refresh_assets(&changed)? > 0 {
+                    device.flush()?;
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Registers image of the file again, or removes it if the file is gone. Returns name of the asset if the registry changed
+    fn reload(&self, path: &Path) -> Result<Option<String>, StreamDeckError> {
+        let Some(name) = asset_name(path) else {
+            return Ok(None);
+        };
+
+        if !path.exists() {
+            return Ok(self.assets.unregister(&name)?.map(|_| name));
+        }
+
+        match load_image(path) {
+            Ok(image) => {
+                self.assets.register(&name, image)?;
+                Ok(Some(name))
+            }
+
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Name of the asset the file is registered under, None for hidden files and directories
+fn asset_name(path: &Path) -> Option<String> {
+    let name = path.file_stem()?.to_string_lossy().into_owned();
+
+    if name.starts_with('.') || path.is_dir() { None } else { Some(name) }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use hidapi::HidApi;
+
+use crate::manager::{DeviceEvent, DEFAULT_RESCAN_INTERVAL};
+use crate::{list_devices, DeviceId, StreamDeckError};
+
+/// Watches for devices getting plugged in and unplugged, by refreshing the device list and comparing it with the previous one.
+/// Devices that are already present are reported as connected by the first scan.
+/// With `fake` feature, fake devices are watched instead if [FAKE_ENV](crate::fake::FAKE_ENV) is set
+/// ```no_run
+/// # use elgato_streamdeck::hotplug::DeviceWatcher;
+/// # use elgato_streamdeck::manager::DeviceEvent;
+/// let mut watcher = DeviceWatcher::new().unwrap();
+///
+/// loop {
+///     for event in watcher.poll(None) {
+///         match event {
+///             DeviceEvent::Connected(id) => println!("{:?} {} connected", id.kind, id.serial),
+///             DeviceEvent::Disconnected(id) => println!("{:?} {} disconnected", id.kind, id.serial),
+///             DeviceEvent::Error(e) => println!("{}", e),
+///         }
+///     }
+/// }
+/// ```
+pub struct DeviceWatcher {
+    hidapi: HidApi,
+    known: HashSet<DeviceId>,
+    interval: Duration,
+    last_scan: Option<Instant>,
+}
+
+impl DeviceWatcher {
+    /// Creates a watcher with a new HidApi instance
+    pub fn new() -> Result<DeviceWatcher, StreamDeckError> {
+        Ok(Self::with_hidapi(HidApi::new()?))
+    }
+
+    /// Creates a watcher using existing HidApi instance, scanning every [DEFAULT_RESCAN_INTERVAL]
+    pub fn with_hidapi(hidapi: HidApi) -> DeviceWatcher {
+        DeviceWatcher {
+            hidapi,
+            known: HashSet::new(),
+            interval: DEFAULT_RESCAN_INTERVAL,
+            last_scan: None,
+        }
+    }
+
+    /// HidApi instance used for scanning, devices that were reported as connected can be connected with it
+    pub fn hidapi(&self) -> &HidApi {
+        &self.hidapi
+    }
+
+    /// Sets how often [poll](DeviceWatcher::poll) scans for devices
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Devices that were present at the last scan
+    pub fn devices(&self) -> Vec<DeviceId> {
+        self.known.iter().cloned().collect()
+    }
+
+    /// Refreshes the device list right away and returns what changed since the last scan
+    pub fn scan(&mut self) -> Vec<DeviceEvent> {
+        self.last_scan = Some(Instant::now());
+
+        if let Err(e) = self.hidapi.refresh_devices() {
+            return vec![DeviceEvent::Error(e.into())];
+        }
+
+        let present = list_devices(&self.hidapi).into_iter().map(|(kind, serial)| DeviceId { kind, serial }).collect();
+        diff_devices(&mut self.known, present)
+    }
+
+    /// Scans for devices every [interval](DeviceWatcher::set_interval) until something changes or the timeout runs out,
+    /// None waits indefinitely
+    pub fn poll(&mut self, timeout: Option<Duration>) -> Vec<DeviceEvent> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            let due = self.last_scan.map_or_else(Instant::now, |last| last + self.interval);
+            let now = Instant::now();
+
+            if let Some(deadline) = deadline
+                && deadline < due
+            {
+                sleep(deadline.saturating_duration_since(now));
+                return vec![];
+            }
+
+            sleep(due.saturating_duration_since(now));
+
+            let events = self.scan();
+
+            if !events.is_empty() {
+                return events;
+            }
+        }
+    }
+}
+
+/// Events of devices that disappeared from or appeared in the device list, updating the known devices
+pub(crate) fn diff_devices(known: &mut HashSet<DeviceId>, present: HashSet<DeviceId>) -> Vec<DeviceEvent> {
+    let mut events = known.difference(&present).cloned().map(DeviceEvent::Disconnected).collect::<Vec<_>>();
+    events.extend(present.difference(known).cloned().map(DeviceEvent::Connected));
+
+    *known = present;
+    events
+}
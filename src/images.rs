@@ -1,20 +1,81 @@
+use std::io::{BufRead, Cursor, Seek};
+use std::path::Path;
 #[allow(unused_imports)]
 use std::sync::Arc;
-use image::{ColorType, DynamicImage, GenericImageView, ImageError};
+use image::{ColorType, DynamicImage, GenericImageView, ImageDecoder, ImageError, ImageReader, RgbaImage};
 use image::codecs::bmp::BmpEncoder;
 use image::codecs::jpeg::JpegEncoder;
 use image::imageops::FilterType;
 
-use crate::{Kind, StreamDeckError};
+use crate::{Kind, StreamDeck, StreamDeckError};
 use crate::info::{ImageFormat, ImageMirroring, ImageMode, ImageRotation};
 
-/// Converts image into image data depending on provided kind of device
+/// Drawing captions onto button images, defined in [text](crate::text) along with the rest of text rendering
+#[cfg(feature = "text")]
+#[cfg_attr(docsrs, doc(cfg(feature = "text")))]
+pub use crate::text::{key_text_image, render_text, TextAlignment, TextStyle, VerticalAlignment};
+
+/// Quality of JPEG images sent to devices, pinned so encoded images stay the same across releases
+const JPEG_QUALITY: u8 = 90;
+
+/// Converts image into image data depending on provided kind of device, see [convert_image_with_format]
 pub fn convert_image(kind: Kind, image: DynamicImage) -> Result<Vec<u8>, ImageError> {
     convert_image_with_format(kind.key_image_format(), image)
 }
 
-/// Converts image into image data depending on provided image format
+/// Filter used for scaling images to the size keys and screens expect, from fastest to best looking.
+///
+/// Cost grows with size of the source image, so downscaling large images with the slower filters can take
+/// tens of milliseconds per key. Scaling images to the right size beforehand, like with an asset cache, avoids the cost entirely
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub enum ResizeFilter {
+    /// Picks the nearest pixel. Fastest, but aliased when downscaling, fine for pixel art and images that are already the right size
+    #[default]
+    Nearest,
+    /// Linear interpolation. Several times slower than nearest, smooth but slightly blurry
+    Triangle,
+    /// Cubic interpolation. Slower than triangle, sharp with little ringing, a good default for photos and icons
+    CatmullRom,
+    /// Lanczos with window 3. Slowest, sharpest result, worth it for large photos scaled down a lot
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    /// Matching filter of the image crate
+    pub fn filter_type(&self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Converts image into image data depending on provided image format, scaling it with [ResizeFilter::Nearest].
+///
+/// Output is byte-identical for identical inputs on every platform, so it can be hashed with [content_hash] for deduplication and golden tests
 pub fn convert_image_with_format(image_format: ImageFormat, image: DynamicImage) -> Result<Vec<u8>, ImageError> {
+    convert_image_with_filter(image_format, image, ResizeFilter::Nearest)
+}
+
+/// Scales image down to the resolution of the format, keeping the orientation it was supplied in.
+/// Images that are no larger than the resolution are returned as they are
+pub fn fit_image(image_format: ImageFormat, image: DynamicImage, filter: ResizeFilter) -> DynamicImage {
+    let (w, h) = match image_format.rotation {
+        ImageRotation::Rot90 | ImageRotation::Rot270 => (image_format.size.1 as u32, image_format.size.0 as u32),
+        ImageRotation::Rot0 | ImageRotation::Rot180 => (image_format.size.0 as u32, image_format.size.1 as u32),
+    };
+
+    if image.width() <= w && image.height() <= h {
+        image
+    } else {
+        image.resize_exact(w, h, filter.filter_type())
+    }
+}
+
+/// Converts image into image data depending on provided image format, scaling it with the filter if it isn't the right size already
+pub fn convert_image_with_filter(image_format: ImageFormat, image: DynamicImage, filter: ResizeFilter) -> Result<Vec<u8>, ImageError> {
     // Ensuring size of the image
     let (ws, hs) = image_format.size;
 
@@ -26,7 +87,11 @@ pub fn convert_image_with_format(image_format: ImageFormat, image: DynamicImage)
         ImageRotation::Rot270 => image.rotate270(),
     };
 
-    let image = image.resize_exact(ws as u32, hs as u32, FilterType::Nearest);
+    let image = if image.dimensions() == (ws as u32, hs as u32) {
+        image
+    } else {
+        image.resize_exact(ws as u32, hs as u32, filter.filter_type())
+    };
 
     // Applying mirroring
     let image = match image_format.mirror {
@@ -49,25 +114,216 @@ pub fn convert_image_with_format(image_format: ImageFormat, image: DynamicImage)
         }
         ImageMode::JPEG => {
             let mut buf = Vec::new();
-            let mut encoder = JpegEncoder::new_with_quality(&mut buf, 90);
+            let mut encoder = JpegEncoder::new_with_quality(&mut buf, JPEG_QUALITY);
             encoder.encode(&image_data, ws as u32, hs as u32, ColorType::Rgb8.into())?;
             Ok(buf)
         }
     }
 }
 
-/// Converts image into image data depending on provided kind of device, can be safely ran inside [multi_thread](tokio::runtime::Builder::new_multi_thread) runtime
+/// Hashes the data with 64-bit FNV-1a, which unlike [DefaultHasher](std::collections::hash_map::DefaultHasher)
+/// gives the same hash on every platform and Rust version, so hashes can be stored and compared between runs
+pub fn content_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Loads image from the file, detecting its format from contents rather than extension.
+///
+/// Only formats the image crate is built with can be loaded, which are BMP and JPEG by default, and PNG with `text` or `url` features.
+/// Others can be enabled on the [image] crate re-exported by this crate.
+///
+/// With `icc` feature, colors of images with an embedded ICC profile, like icons authored in Display P3, are converted to sRGB that devices display.
+/// Otherwise, the profile is ignored and colors are taken as sRGB, which makes wide gamut images look washed out
+pub fn load_image(path: impl AsRef<Path>) -> Result<DynamicImage, StreamDeckError> {
+    decode(ImageReader::open(path)?)
+}
+
+/// Decodes image from the bytes, detecting its format from contents, see [load_image] for supported formats and color handling
+pub fn decode_image(data: &[u8]) -> Result<DynamicImage, StreamDeckError> {
+    decode(ImageReader::new(Cursor::new(data)))
+}
+
+fn decode<R: BufRead + Seek>(reader: ImageReader<R>) -> Result<DynamicImage, StreamDeckError> {
+    let mut decoder = reader.with_guessed_format()?.into_decoder()?;
+    let icc_profile = decoder.icc_profile()?;
+    let image = DynamicImage::from_decoder(decoder)?;
+
+    #[cfg(feature = "icc")]
+    if let Some(converted) = icc_profile.and_then(|profile| convert_to_srgb(&image, &profile)) {
+        return Ok(converted);
+    }
+
+    #[cfg(not(feature = "icc"))]
+    let _ = icc_profile;
+
+    Ok(image)
+}
+
+/// Converts colors of the image from the ICC profile to sRGB, None if the profile is invalid or isn't an RGB profile.
+/// Images with more than 8 bits per channel are reduced to 8 bits, which is what devices display anyway
+#[cfg(feature = "icc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "icc")))]
+pub fn convert_to_srgb(image: &DynamicImage, icc_profile: &[u8]) -> Option<DynamicImage> {
+    use moxcms::{ColorProfile, Layout, TransformOptions};
+
+    let source = ColorProfile::new_from_slice(icc_profile).ok()?;
+    let transform = source.create_transform_8bit(Layout::Rgba, &ColorProfile::new_srgb(), Layout::Rgba, TransformOptions::default()).ok()?;
+
+    let pixels = image.to_rgba8();
+    let mut converted = RgbaImage::new(pixels.width(), pixels.height());
+    transform.transform(pixels.as_raw(), &mut converted).ok()?;
+
+    let converted = DynamicImage::ImageRgba8(converted);
+    Some(if image.color().has_alpha() { converted } else { DynamicImage::ImageRgb8(converted.into_rgb8()) })
+}
+
+/// Dims image by multiplying its colors with the factor, value range is 0.0 - 1.0
+pub fn dim_image(image: &DynamicImage, factor: f32) -> DynamicImage {
+    let factor = factor.clamp(0.0, 1.0);
+    let mut image = image.to_rgba8();
+
+    for pixel in image.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = (*channel as f32 * factor).round() as u8;
+        }
+    }
+
+    DynamicImage::ImageRgba8(image)
+}
+
+/// Lifts dark tones of the image so it stays legible at the brightness, value range is 0 - 100.
+///
+/// Applies a gamma curve that gets stronger as brightness goes down, images are unchanged at full brightness.
+/// Alpha is left alone, and white and black stay white and black
+pub fn compensate_brightness(image: &DynamicImage, brightness: u8) -> DynamicImage {
+    let brightness = brightness.min(100);
+
+    if brightness == 100 {
+        return image.clone();
+    }
+
+    // Gamma goes from 1.0 at full brightness to 0.5 at zero, so dark icons at 10% are lifted to roughly their look at 40%
+    let gamma = 0.5 + 0.5 * brightness as f32 / 100.0;
+    let curve: [u8; 256] = std::array::from_fn(|value| ((value as f32 / 255.0).powf(gamma) * 255.0).round() as u8);
+
+    let mut image = image.to_rgba8();
+
+    for pixel in image.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = curve[*channel as usize];
+        }
+    }
+
+    DynamicImage::ImageRgba8(image)
+}
+
+/// Splits image into images of every key of the kind, so the image spans all keys. Image is scaled to cover the key grid,
+/// cropping what doesn't fit, and tiles are returned in key order
+pub fn tile_image(kind: Kind, image: &DynamicImage) -> Vec<DynamicImage> {
+    let (rows, columns) = (kind.row_count() as u32, kind.column_count() as u32);
+    let (key_width, key_height) = (kind.key_image_resolution().0 as u32, kind.key_image_resolution().1 as u32);
+
+    let grid = image.resize_to_fill(key_width * columns, key_height * rows, FilterType::Triangle);
+
+    (0..rows * columns)
+        .map(|key| grid.crop_imm((key % columns) * key_width, (key / columns) * key_height, key_width, key_height))
+        .take(kind.key_count() as usize)
+        .collect()
+}
+
+/// Span of columns that differ between the images as (left, right), None if the images are the same
+#[cfg(any(feature = "video", feature = "widgets"))]
+pub(crate) fn changed_columns(previous: &RgbaImage, next: &RgbaImage) -> Option<(usize, usize)> {
+    let column_changed = |x: u32| (0..next.height()).any(|y| previous.get_pixel(x, y) != next.get_pixel(x, y));
+
+    let left = (0..next.width()).find(|x| column_changed(*x))?;
+    let right = (left..next.width()).rev().find(|x| column_changed(*x))? + 1;
+
+    Some((left as usize, right as usize))
+}
+
+/// Remembers tiles last written to every key, so a frame streamed across keys only costs encoding and bandwidth for tiles that changed.
+///
+/// The cache assumes nothing else changes the keys, call [invalidate](TileCache::invalidate) after it does or after the device reconnects
+#[derive(Clone, Debug, Default)]
+pub struct TileCache {
+    kind: Option<Kind>,
+    hashes: Vec<Option<u64>>,
+}
+
+impl TileCache {
+    /// Creates empty cache, the first frame is written in full
+    pub fn new() -> TileCache {
+        TileCache::default()
+    }
+
+    /// Tiles the frame like [tile_image] and encodes tiles that differ from the previous frame, returned along with their key index
+    pub fn changed_tiles(&mut self, kind: Kind, image: &DynamicImage) -> Result<Vec<(u8, Vec<u8>)>, ImageError> {
+        if self.kind != Some(kind) {
+            self.kind = Some(kind);
+            self.hashes = vec![None; kind.key_count() as usize];
+        }
+
+        let mut changed = vec![];
+        let mut hashes = self.hashes.clone();
+
+        for (key, tile) in tile_image(kind, image).into_iter().enumerate() {
+            let hash = content_hash(tile.as_bytes());
+
+            if hashes[key] == Some(hash) {
+                continue;
+            }
+
+            hashes[key] = Some(hash);
+            changed.push((key as u8, convert_image(kind, tile)?));
+        }
+
+        // Only remembering tiles once all of them were encoded
+        self.hashes = hashes;
+
+        Ok(changed)
+    }
+
+    /// Writes tiles of the frame that changed since the previous frame and flushes them, returns how many keys were written
+    pub fn write_frame(&mut self, device: &StreamDeck, image: &DynamicImage) -> Result<usize, StreamDeckError> {
+        let result = self.changed_tiles(device.kind(), image).map_err(StreamDeckError::from).and_then(|tiles| {
+            for (key, data) in &tiles {
+                device.write_image(*key, data.as_slice())?;
+            }
+
+            device.flush()?;
+
+            Ok(tiles.len())
+        });
+
+        // Keys are in unknown state if writing failed midway
+        if result.is_err() {
+            self.invalidate();
+        }
+
+        result
+    }
+
+    /// Forgets all tiles, so the next frame is written in full
+    pub fn invalidate(&mut self) {
+        self.hashes.iter_mut().for_each(|hash| *hash = None);
+    }
+}
+
+/// Converts image into image data depending on provided kind of device on the blocking thread pool,
+/// so encoding doesn't block the runtime. Works in any runtime, including [current_thread](tokio::runtime::Builder::new_current_thread)
 #[cfg(feature = "async")]
 #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
-pub fn convert_image_async(kind: Kind, image: DynamicImage) -> Result<Vec<u8>, StreamDeckError> {
-    Ok(tokio::task::block_in_place(move || convert_image(kind, image))?)
+pub async fn convert_image_async(kind: Kind, image: DynamicImage) -> Result<Vec<u8>, StreamDeckError> {
+    Ok(tokio::task::spawn_blocking(move || convert_image(kind, image)).await??)
 }
 
-/// Converts image into image data depending on provided image format, can be safely ran inside [multi_thread](tokio::runtime::Builder::new_multi_thread) runtime
+/// Converts image into image data depending on provided image format on the blocking thread pool,
+/// so encoding doesn't block the runtime. Works in any runtime, including [current_thread](tokio::runtime::Builder::new_current_thread)
 #[cfg(feature = "async")]
 #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
-pub fn convert_image_with_format_async(format: ImageFormat, image: DynamicImage) -> Result<Vec<u8>, StreamDeckError> {
-    Ok(tokio::task::block_in_place(move || convert_image_with_format(format, image))?)
+pub async fn convert_image_with_format_async(format: ImageFormat, image: DynamicImage) -> Result<Vec<u8>, StreamDeckError> {
+    Ok(tokio::task::spawn_blocking(move || convert_image_with_format(format, image)).await??)
 }
 
 /// Rect to be used when trying to send image to lcd screen
@@ -90,7 +346,7 @@ impl ImageRect {
         let image_data = image.into_rgb8().to_vec();
 
         let mut buf = Vec::new();
-        let mut encoder = JpegEncoder::new_with_quality(&mut buf, 90);
+        let mut encoder = JpegEncoder::new_with_quality(&mut buf, JPEG_QUALITY);
         encoder.encode(&image_data, image_w, image_h, ColorType::Rgb8.into())?;
 
         Ok(ImageRect {
@@ -100,10 +356,74 @@ impl ImageRect {
         })
     }
 
-    /// Converts image to image rect, can be safely ran inside [multi_thread](tokio::runtime::Builder::new_multi_thread) runtime
+    /// Converts raw image to image rect
+    pub fn from_raw_image(image: RawImage) -> Result<ImageRect, StreamDeckError> {
+        ImageRect::from_image(image.into())
+    }
+
+    /// Converts image to image rect on the blocking thread pool, so encoding doesn't block the runtime
     #[cfg(feature = "async")]
     #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
-    pub fn from_image_async(image: DynamicImage) -> Result<ImageRect, StreamDeckError> {
-        tokio::task::block_in_place(move || ImageRect::from_image(image))
+    pub async fn from_image_async(image: DynamicImage) -> Result<ImageRect, StreamDeckError> {
+        tokio::task::spawn_blocking(move || ImageRect::from_image(image)).await?
+    }
+}
+
+/// Image as plain RGBA pixels, for applications that use a different version of image crate than this crate does
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RawImage {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl RawImage {
+    /// Creates image from pixel data row by row as RGBA, 4 bytes per pixel.
+    /// Fails with [StreamDeckError::InvalidImageSize] if the data doesn't have that many bytes
+    pub fn new(width: u32, height: u32, data: Vec<u8>) -> Result<RawImage, StreamDeckError> {
+        if data.len() != width as usize * height as usize * 4 {
+            return Err(StreamDeckError::InvalidImageSize);
+        }
+
+        Ok(RawImage { width, height, data })
+    }
+
+    /// Width of the image
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the image
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Pixel data row by row as RGBA
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns pixel data row by row as RGBA
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl From<RawImage> for DynamicImage {
+    fn from(image: RawImage) -> Self {
+        // Length of the data is checked when the raw image is created
+        DynamicImage::ImageRgba8(RgbaImage::from_raw(image.width, image.height, image.data).expect("raw image data has wrong length"))
+    }
+}
+
+impl From<DynamicImage> for RawImage {
+    fn from(image: DynamicImage) -> Self {
+        let image = image.into_rgba8();
+
+        RawImage {
+            width: image.width(),
+            height: image.height(),
+            data: image.into_raw(),
+        }
     }
 }
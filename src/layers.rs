@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use image::DynamicImage;
+
+use crate::{DeviceStateUpdate, KeyIndex, StreamDeck, StreamDeckError};
+
+/// Layer that is active while no modifier is held
+pub const BASE_LAYER: u8 = 0;
+
+/// Update tagged by [Layers]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum LayerUpdate {
+    /// Update of a control that isn't a modifier, with the layer that was active when the control was pressed
+    Input {
+        /// Layer the update belongs to
+        layer: u8,
+        /// The update itself
+        update: DeviceStateUpdate,
+    },
+
+    /// Active layer changed because a modifier was pressed or released
+    LayerChanged {
+        /// Layer that was active before
+        previous: u8,
+        /// Layer that is active now
+        layer: u8,
+    },
+}
+
+/// Keyboard-style modifier layers, where holding a modifier key switches other keys to another layer, like Fn on a laptop keyboard.
+///
+/// Feed it updates with [handle](Layers::handle), or read through [DeviceStateReader::read_layered](crate::DeviceStateReader::read_layered),
+/// which also swaps key images of the layers. Releases always belong to the layer their press did,
+/// so releasing a modifier before the key doesn't leave the key pressed on another layer
+#[derive(Clone, Debug)]
+pub struct Layers {
+    /// Stages images of the new layer whenever the active layer changes while reading with [read_layered](crate::DeviceStateReader::read_layered)
+    pub swap_images: bool,
+    modifiers: HashMap<u8, u8>,
+    images: HashMap<(u8, u8), DynamicImage>,
+    /// Modifier keys being held, in the order they were pressed
+    held: Vec<u8>,
+    /// Releases that are still to come, with the layer of their press
+    pending_releases: HashMap<DeviceStateUpdate, u8>,
+}
+
+impl Layers {
+    /// Creates layers without modifiers, that swap images
+    pub fn new() -> Layers {
+        Layers {
+            swap_images: true,
+            modifiers: HashMap::new(),
+            images: HashMap::new(),
+            held: vec![],
+            pending_releases: HashMap::new(),
+        }
+    }
+
+    /// Makes the key a modifier that activates the layer while held. When multiple modifiers are held, the last pressed one wins
+    pub fn add_modifier(&mut self, key: u8, layer: u8) {
+        self.modifiers.insert(key, layer);
+    }
+
+    /// Makes the key a normal key again
+    pub fn remove_modifier(&mut self, key: u8) {
+        self.modifiers.remove(&key);
+        self.held.retain(|held| *held != key);
+    }
+
+    /// Sets image of the key on the layer, keys without an image on the active layer show their [BASE_LAYER] image
+    pub fn set_image(&mut self, layer: u8, key: u8, image: DynamicImage) {
+        self.images.insert((layer, key), image);
+    }
+
+    /// Removes image of the key on the layer
+    pub fn clear_image(&mut self, layer: u8, key: u8) {
+        self.images.remove(&(layer, key));
+    }
+
+    /// Layer that is active now
+    pub fn active_layer(&self) -> u8 {
+        self.held.last().and_then(|key| self.modifiers.get(key)).copied().unwrap_or(BASE_LAYER)
+    }
+
+    /// Tags the update with its layer, or turns presses and releases of modifiers into layer changes.
+    /// None for modifier presses and releases that don't change the active layer
+    pub fn handle(&mut self, update: DeviceStateUpdate) -> Option<LayerUpdate> {
+        let previous = self.active_layer();
+
+        match update {
+            DeviceStateUpdate::ButtonDown(KeyIndex(key)) if self.modifiers.contains_key(&key) => {
+                self.held.retain(|held| *held != key);
+                self.held.push(key);
+            }
+
+            DeviceStateUpdate::ButtonUp(KeyIndex(key)) if self.modifiers.contains_key(&key) => {
+                self.held.retain(|held| *held != key);
+            }
+
+            _ => {
+                let layer = match release_of(update) {
+                    Some(release) => {
+                        self.pending_releases.insert(release, previous);
+                        previous
+                    }
+                    None => self.pending_releases.remove(&update).unwrap_or(previous),
+                };
+
+                return Some(LayerUpdate::Input { layer, update });
+            }
+        }
+
+        let layer = self.active_layer();
+        (layer != previous).then_some(LayerUpdate::LayerChanged { previous, layer })
+    }
+
+    /// Handles all of the updates, see [handle](Layers::handle)
+    pub fn handle_all(&mut self, updates: impl IntoIterator<Item = DeviceStateUpdate>) -> Vec<LayerUpdate> {
+        updates.into_iter().filter_map(|update| self.handle(update)).collect()
+    }
+
+    /// Stages images of the active layer on the device, changes must be flushed with `.flush()` before they will appear on the device!
+    ///
+    /// Keys that have an image on any layer get the image of the active layer, or of [BASE_LAYER] if the active layer has none, or are cleared.
+    /// Other keys are left alone
+    pub fn stage_images(&self, device: &StreamDeck) -> Result<(), StreamDeckError> {
+        let layer = self.active_layer();
+
+        let mut keys = self.images.keys().map(|(_, key)| *key).collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.dedup();
+
+        device.for_each_key(keys, |key| match self.images.get(&(layer, key)).or_else(|| self.images.get(&(BASE_LAYER, key))) {
+            Some(image) => device.set_button_image(key, image.clone()),
+            None => device.clear_button_image(key),
+        })
+    }
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Layers::new()
+    }
+}
+
+/// Release that ends the press, None if the update isn't a press
+fn release_of(update: DeviceStateUpdate) -> Option<DeviceStateUpdate> {
+    match update {
+        DeviceStateUpdate::ButtonDown(key) => Some(DeviceStateUpdate::ButtonUp(key)),
+        DeviceStateUpdate::EncoderDown(encoder) => Some(DeviceStateUpdate::EncoderUp(encoder)),
+        DeviceStateUpdate::TouchPointDown(point) => Some(DeviceStateUpdate::TouchPointUp(point)),
+        DeviceStateUpdate::TouchKeyDown(key) => Some(DeviceStateUpdate::TouchKeyUp(key)),
+        _ => None,
+    }
+}
@@ -0,0 +1,622 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "text")]
+use crate::images::convert_image_with_format;
+use crate::images::load_image;
+#[cfg(feature = "text")]
+use crate::info::{ImageRotation, Screen};
+#[cfg(feature = "text")]
+use crate::text::Theme;
+use crate::{DeviceStateUpdate, EncoderIndex, KeyIndex, StreamDeck, StreamDeckError};
+
+/// Declarative description of what a device shows and what its controls do, loaded from TOML or JSON.
+///
+/// Controls are bound to action ids, which [LayoutRuntime] reports instead of raw indices
+/// ```toml
+/// [[pages]]
+/// name = "main"
+///
+/// [[pages.keys]]
+/// key = 0
+/// action = "mute"
+/// image = "icons/mute.png"
+///
+/// [[pages.keys]]
+/// key = 1
+/// text = "Lights"
+/// color = [40, 40, 160]
+/// page = "lights"
+///
+/// [[pages.encoders]]
+/// encoder = 0
+/// action = "volume"
+/// label = "Volume"
+///
+/// [[pages]]
+/// name = "lights"
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    /// Pages of the layout, the first one is shown initially
+    pub pages: Vec<PageLayout>,
+    /// Directory relative image paths are resolved against, set to directory of the file by [load](Layout::load)
+    #[serde(skip)]
+    pub base_directory: Option<PathBuf>,
+}
+
+/// Single page of a [Layout]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PageLayout {
+    /// Name of the page, which keys switching pages refer to
+    pub name: String,
+    /// Keys of the page, keys that aren't listed are blank
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<KeyLayout>,
+    /// Encoders of the page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub encoders: Vec<EncoderLayout>,
+}
+
+/// Key of a [PageLayout]. Key shows the first of image, asset, text and color that is set
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyLayout {
+    /// Index of the key
+    pub key: u8,
+    /// Action id reported when the key is pressed and released
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    /// Name of the page pressing the key switches to, takes precedence over the action
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<String>,
+    /// Path of an image file, relative to [base_directory](Layout::base_directory)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<PathBuf>,
+    /// Name of an asset in [ConnectOptions::assets](crate::ConnectOptions::assets)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    /// Text drawn on the key, needs `text` feature and a theme set with [set_theme](LayoutRuntime::set_theme).
+    /// Variables like `{cpu}` are replaced with their values, see [add_provider](LayoutRuntime::add_provider)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Background color as (red, green, blue)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<(u8, u8, u8)>,
+}
+
+/// Encoder of a [PageLayout]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncoderLayout {
+    /// Index of the encoder
+    pub encoder: u8,
+    /// Action id reported when the encoder is pressed, released or twisted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    /// Label drawn on the encoder's segment of the LCD strip, needs `text` feature and a theme set with [set_theme](LayoutRuntime::set_theme).
+    /// Variables are replaced like in [text](KeyLayout::text)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl Layout {
+    /// Loads layout from the file, parsed as TOML if the file has `.toml` extension and as JSON otherwise
+    pub fn load(path: impl AsRef<Path>) -> Result<Layout, StreamDeckError> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)?;
+
+        let mut layout = match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => Layout::from_toml(&data)?,
+            _ => Layout::from_json(&data)?,
+        };
+
+        layout.base_directory = path.parent().map(Path::to_path_buf);
+        Ok(layout)
+    }
+
+    /// Parses layout from TOML
+    pub fn from_toml(data: &str) -> Result<Layout, StreamDeckError> {
+        let layout: Layout = toml::from_str(data).map_err(|e| StreamDeckError::InvalidLayout(e.to_string()))?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    /// Parses layout from JSON
+    pub fn from_json(data: &str) -> Result<Layout, StreamDeckError> {
+        let layout: Layout = serde_json::from_str(data)?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    /// Page with the name
+    pub fn page(&self, name: &str) -> Option<&PageLayout> {
+        self.pages.iter().find(|page| page.name == name)
+    }
+
+    /// Checks that the layout has pages, names of pages are unique and keys only switch to pages that exist.
+    /// Fails with [StreamDeckError::InvalidLayout] describing the first problem
+    pub fn validate(&self) -> Result<(), StreamDeckError> {
+        if self.pages.is_empty() {
+            return Err(StreamDeckError::InvalidLayout("Layout has no pages".to_string()));
+        }
+
+        let mut names = HashSet::new();
+
+        for page in &self.pages {
+            if !names.insert(page.name.as_str()) {
+                return Err(StreamDeckError::InvalidLayout(format!("Page {:?} is defined more than once", page.name)));
+            }
+        }
+
+        for page in &self.pages {
+            for target in page.keys.iter().filter_map(|key| key.page.as_ref()) {
+                if !names.contains(target.as_str()) {
+                    return Err(StreamDeckError::InvalidLayout(format!("Page {:?} switches to unknown page {:?}", page.name, target)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Event of a [LayoutRuntime], with action ids of controls instead of their indices
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub enum LayoutEvent {
+    /// Control bound to the action got pressed
+    Pressed(String),
+    /// Control bound to the action got released
+    Released(String),
+    /// Encoder bound to the action was twisted
+    Turned(String, i8),
+    /// Key switched the page, the new page has to be shown with [apply](LayoutRuntime::apply)
+    PageChanged {
+        /// Name of the page that was shown before
+        previous: String,
+        /// Name of the page shown now
+        page: String,
+    },
+}
+
+impl LayoutEvent {
+    /// Action id of the event, None for page changes
+    pub fn action(&self) -> Option<&str> {
+        match self {
+            LayoutEvent::Pressed(action) | LayoutEvent::Released(action) | LayoutEvent::Turned(action, _) => Some(action),
+            LayoutEvent::PageChanged { .. } => None,
+        }
+    }
+}
+
+type ValueSource = Box<dyn FnMut() -> String + Send>;
+
+type ActionHandler = Box<dyn FnMut(&LayoutEvent) + Send>;
+
+#[cfg(feature = "async")]
+type AsyncActionHandler = Box<dyn FnMut(LayoutEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+/// Application supplied source of a variable's value, asked again once the interval passes
+struct Provider {
+    name: String,
+    interval: Duration,
+    last_refresh: Option<Instant>,
+    source: ValueSource,
+}
+
+/// Runs a [Layout] on a device, showing its pages and turning updates into [LayoutEvent]s.
+///
+/// Releases are reported with the action of their press, even if the page changed in between.
+/// Texts and labels can contain variables like `{cpu}%` or `{time}`, which are replaced with values of [providers](LayoutRuntime::add_provider)
+/// or ones set with [set_variable](LayoutRuntime::set_variable). Variables without a value are left as they are, `{{` and `}}` stand for braces.
+///
+/// Actions can be bound to closures, so a small tool is a layout file and a handful of closures
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use elgato_streamdeck::layout::{Layout, LayoutEvent, LayoutRuntime};
+/// # fn example(device: Arc<elgato_streamdeck::StreamDeck>) -> Result<(), elgato_streamdeck::StreamDeckError> {
+/// let mut runtime = LayoutRuntime::new(Layout::load("layout.toml")?)?;
+///
+/// runtime.bind("mute", |event| {
+///     if let LayoutEvent::Pressed(_) = event {
+///         println!("Toggling mute");
+///     }
+/// });
+///
+/// runtime.run(&device, std::time::Duration::from_secs(1))
+/// # }
+/// ```
+pub struct LayoutRuntime {
+    layout: Layout,
+    page: usize,
+    /// Releases that are still to come, with the action of their press
+    pending_releases: HashMap<DeviceStateUpdate, String>,
+    variables: HashMap<String, String>,
+    providers: Vec<Provider>,
+    /// Texts of keys as they were last drawn, with variables replaced
+    drawn_keys: HashMap<u8, String>,
+    /// Labels of encoders as they were last drawn, with variables replaced
+    drawn_labels: HashMap<u8, String>,
+    handlers: HashMap<String, ActionHandler>,
+    #[cfg(feature = "async")]
+    async_handlers: HashMap<String, AsyncActionHandler>,
+    #[cfg(feature = "text")]
+    theme: Option<Theme>,
+}
+
+impl LayoutRuntime {
+    /// Creates runtime showing the first page of the layout
+    pub fn new(layout: Layout) -> Result<LayoutRuntime, StreamDeckError> {
+        layout.validate()?;
+
+        Ok(LayoutRuntime {
+            layout,
+            page: 0,
+            pending_releases: HashMap::new(),
+            variables: HashMap::new(),
+            providers: vec![],
+            drawn_keys: HashMap::new(),
+            drawn_labels: HashMap::new(),
+            handlers: HashMap::new(),
+            #[cfg(feature = "async")]
+            async_handlers: HashMap::new(),
+            #[cfg(feature = "text")]
+            theme: None,
+        })
+    }
+
+    /// Layout that is running
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Page that is shown
+    pub fn page(&self) -> &PageLayout {
+        &self.layout.pages[self.page]
+    }
+
+    /// Switches to the page with the name, returns false if there's no such page.
+    /// The page has to be shown with [apply](LayoutRuntime::apply)
+    pub fn set_page(&mut self, name: &str) -> bool {
+        match self.layout.pages.iter().position(|page| page.name == name) {
+            Some(page) => {
+                self.page = page;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets theme used for drawing text of keys and labels of encoders
+    #[cfg(feature = "text")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "text")))]
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = Some(theme);
+    }
+
+    /// Sets value of the variable, replacing value of its provider until the provider is asked again.
+    /// Keys showing it are only drawn again by [refresh](LayoutRuntime::refresh) or [apply](LayoutRuntime::apply)
+    pub fn set_variable(&mut self, name: &str, value: impl Into<String>) {
+        self.variables.insert(name.to_string(), value.into());
+    }
+
+    /// Value of the variable
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    /// Adds provider of the variable, which is asked for the value by [refresh](LayoutRuntime::refresh) once the interval passes.
+    /// Replaces previous provider of the variable
+    pub fn add_provider(&mut self, name: &str, interval: Duration, provider: impl FnMut() -> String + Send + 'static) {
+        self.providers.retain(|provider| provider.name != name);
+        self.providers.push(Provider {
+            name: name.to_string(),
+            interval,
+            last_refresh: None,
+            source: Box::new(provider),
+        });
+    }
+
+    /// Removes provider of the variable, the variable keeps its last value
+    pub fn remove_provider(&mut self, name: &str) {
+        self.providers.retain(|provider| provider.name != name);
+    }
+
+    /// Replaces variables in the text with their values
+    pub fn expand(&self, text: &str) -> String {
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(index) = rest.find(['{', '}']) {
+            expanded.push_str(&rest[..index]);
+            rest = &rest[index..];
+
+            if let Some(tail) = rest.strip_prefix("{{") {
+                expanded.push('{');
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix("}}") {
+                expanded.push('}');
+                rest = tail;
+            } else if rest.starts_with('{')
+                && let Some((name, tail)) = rest[1..].split_once('}')
+                && let Some(value) = self.variables.get(name)
+            {
+                expanded.push_str(value);
+                rest = tail;
+            } else {
+                expanded.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+
+        expanded.push_str(rest);
+        expanded
+    }
+
+    /// Stages images of the page on keys and draws encoder labels, changes must be flushed with `.flush()` before they will appear on the device!
+    ///
+    /// Keys the page doesn't list are cleared
+    pub fn apply(&mut self, device: &StreamDeck) -> Result<(), StreamDeckError> {
+        self.drawn_keys.clear();
+        self.drawn_labels.clear();
+
+        let keys = (0..device.kind().key_count())
+            .map(|key| (key, self.page().keys.iter().find(|layout| layout.key == key).cloned()))
+            .collect::<Vec<_>>();
+
+        device.for_each_key(keys.iter().map(|(key, _)| *key), |key| match &keys[key as usize].1 {
+            Some(layout) => self.apply_key(device, layout),
+            None => device.clear_button_image(key),
+        })?;
+
+        #[cfg(feature = "text")]
+        self.apply_labels(device, |_, _| true)?;
+
+        Ok(())
+    }
+
+    /// Asks providers that are due for values, then draws keys and labels of the page whose text changed because of them.
+    /// Returns true if anything was drawn, changes must be flushed with `.flush()` before they will appear on the device!
+    pub fn refresh(&mut self, device: &StreamDeck) -> Result<bool, StreamDeckError> {
+        for provider in &mut self.providers {
+            if provider.last_refresh.is_none_or(|last| last.elapsed() >= provider.interval) {
+                provider.last_refresh = Some(Instant::now());
+                self.variables.insert(provider.name.clone(), (provider.source)());
+            }
+        }
+
+        // Keys showing an image or an asset don't show their text
+        let changed = self
+            .page()
+            .keys
+            .iter()
+            .filter(|layout| layout.image.is_none() && layout.asset.is_none())
+            .filter(|layout| layout.text.as_ref().is_some_and(|text| self.drawn_keys.get(&layout.key) != Some(&self.expand(text))))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        device.for_each_key(changed.iter().map(|layout| layout.key), |key| match changed.iter().find(|layout| layout.key == key) {
+            Some(layout) => self.apply_key(device, layout),
+            None => Ok(()),
+        })?;
+
+        #[cfg(feature = "text")]
+        let labels_drawn = self.apply_labels(device, |drawn, label| drawn != Some(label))?;
+        #[cfg(not(feature = "text"))]
+        let labels_drawn = false;
+
+        Ok(!changed.is_empty() || labels_drawn)
+    }
+
+    /// Turns the update into an event of the action bound to the control. Pressing a key that switches pages switches the page.
+    /// None for controls without an action and for updates that have no event
+    pub fn handle(&mut self, update: DeviceStateUpdate) -> Option<LayoutEvent> {
+        match update {
+            DeviceStateUpdate::ButtonDown(KeyIndex(key)) => {
+                let layout = self.page().keys.iter().find(|layout| layout.key == key)?;
+
+                if let Some(target) = layout.page.clone() {
+                    let previous = self.page().name.clone();
+                    self.set_page(&target);
+                    return Some(LayoutEvent::PageChanged { previous, page: target });
+                }
+
+                let action = layout.action.clone()?;
+                self.pending_releases.insert(DeviceStateUpdate::ButtonUp(KeyIndex(key)), action.clone());
+                Some(LayoutEvent::Pressed(action))
+            }
+
+            DeviceStateUpdate::EncoderDown(EncoderIndex(encoder)) => {
+                let action = self.encoder_action(encoder)?;
+                self.pending_releases.insert(DeviceStateUpdate::EncoderUp(EncoderIndex(encoder)), action.clone());
+                Some(LayoutEvent::Pressed(action))
+            }
+
+            DeviceStateUpdate::EncoderTwist(EncoderIndex(encoder), ticks) => Some(LayoutEvent::Turned(self.encoder_action(encoder)?, ticks)),
+
+            DeviceStateUpdate::ButtonUp(_) | DeviceStateUpdate::EncoderUp(_) => self.pending_releases.remove(&update).map(LayoutEvent::Released),
+
+            _ => None,
+        }
+    }
+
+    /// Handles all of the updates, see [handle](LayoutRuntime::handle)
+    pub fn handle_all(&mut self, updates: impl IntoIterator<Item = DeviceStateUpdate>) -> Vec<LayoutEvent> {
+        updates.into_iter().filter_map(|update| self.handle(update)).collect()
+    }
+
+    /// Binds the action to the closure, which [dispatch](LayoutRuntime::dispatch) calls with every event of the action.
+    /// Replaces previous binding of the action
+    pub fn bind(&mut self, action: &str, handler: impl FnMut(&LayoutEvent) + Send + 'static) {
+        #[cfg(feature = "async")]
+        self.async_handlers.remove(action);
+
+        self.handlers.insert(action.to_string(), Box::new(handler));
+    }
+
+    /// Binds the action to the async closure, which [dispatch_async](LayoutRuntime::dispatch_async) awaits for every event of the action.
+    /// Replaces previous binding of the action
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub fn bind_async<F, Fut>(&mut self, action: &str, mut handler: F)
+    where
+        F: FnMut(LayoutEvent) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.handlers.remove(action);
+        self.async_handlers.insert(action.to_string(), Box::new(move |event| Box::pin(handler(event))));
+    }
+
+    /// Removes binding of the action
+    pub fn unbind(&mut self, action: &str) {
+        self.handlers.remove(action);
+
+        #[cfg(feature = "async")]
+        self.async_handlers.remove(action);
+    }
+
+    /// Handles the update like [handle](LayoutRuntime::handle) and calls the closure bound to the event's action.
+    /// Returns events no closure was called for, like page changes, actions without a binding and actions bound to async closures
+    pub fn dispatch(&mut self, update: DeviceStateUpdate) -> Option<LayoutEvent> {
+        let event = self.handle(update)?;
+
+        match event.action().and_then(|action| self.handlers.get_mut(action)) {
+            Some(handler) => {
+                handler(&event);
+                None
+            }
+            None => Some(event),
+        }
+    }
+
+    /// Handles the update like [handle](LayoutRuntime::handle) and calls the closure bound to the event's action, awaiting async ones.
+    /// Returns events no closure was called for, like page changes and actions without a binding
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn dispatch_async(&mut self, update: DeviceStateUpdate) -> Option<LayoutEvent> {
+        let event = self.dispatch(update)?;
+
+        match event.action().and_then(|action| self.async_handlers.get_mut(action)) {
+            Some(handler) => {
+                handler(event).await;
+                None
+            }
+            None => Some(event),
+        }
+    }
+
+    /// Shows the layout on the device and dispatches its input until reading fails, showing pages keys switch to.
+    /// Providers are asked for values and changed keys are drawn at least once per the interval
+    pub fn run(&mut self, device: &Arc<StreamDeck>, refresh_interval: Duration) -> Result<(), StreamDeckError> {
+        self.apply(device)?;
+        device.flush()?;
+
+        let reader = device.get_reader();
+
+        loop {
+            for update in reader.read(Some(refresh_interval))? {
+                if let Some(LayoutEvent::PageChanged { .. }) = self.dispatch(update) {
+                    self.apply(device)?;
+                }
+            }
+
+            self.refresh(device)?;
+            device.flush()?;
+        }
+    }
+
+    fn encoder_action(&self, encoder: u8) -> Option<String> {
+        self.page().encoders.iter().find(|layout| layout.encoder == encoder)?.action.clone()
+    }
+
+    fn apply_key(&mut self, device: &StreamDeck, layout: &KeyLayout) -> Result<(), StreamDeckError> {
+        if let Some(path) = &layout.image {
+            let path = match &self.layout.base_directory {
+                Some(base) => base.join(path),
+                None => path.clone(),
+            };
+
+            return device.set_button_image(layout.key, load_image(path)?);
+        }
+
+        if let Some(asset) = &layout.asset {
+            return device.set_button_asset(layout.key, asset);
+        }
+
+        let (w, h) = device.kind().key_image_resolution();
+
+        if let Some(text) = &layout.text {
+            let text = self.expand(text);
+            self.drawn_keys.insert(layout.key, text.clone());
+
+            #[cfg(feature = "text")]
+            if let Some(theme) = &self.theme {
+                let mut theme = theme.clone();
+
+                if let Some(color) = layout.color {
+                    theme.background = color;
+                }
+
+                return device.set_button_image(layout.key, theme.label(&text, (w, h)));
+            }
+        }
+
+        match layout.color {
+            Some((red, green, blue)) => device.set_button_image(layout.key, DynamicImage::ImageRgba8(RgbaImage::from_pixel(w as u32, h as u32, Rgba([red, green, blue, 255])))),
+            None => device.clear_button_image(layout.key),
+        }
+    }
+
+    /// Draws labels of the page's encoders on their segments of the LCD strip, segments without a label are drawn blank.
+    /// Only segments the filter accepts with the label that was drawn before and the new one are drawn, returns true if any was
+    #[cfg(feature = "text")]
+    fn apply_labels(&mut self, device: &StreamDeck, filter: impl Fn(Option<&String>, &String) -> bool) -> Result<bool, StreamDeckError> {
+        let (Some(theme), Some(screen)) = (self.theme.clone(), device.kind().screen(Screen::LCD_STRIP)) else {
+            return Ok(false);
+        };
+
+        let mut drawn = false;
+
+        for (encoder, segment) in screen.segments.iter().enumerate() {
+            let size = (segment.w, segment.h);
+
+            let label = match self.page().encoders.iter().find(|layout| layout.encoder as usize == encoder).and_then(|layout| layout.label.as_ref()) {
+                Some(label) => self.expand(label),
+                None => String::new(),
+            };
+
+            if !filter(self.drawn_labels.get(&(encoder as u8)), &label) {
+                continue;
+            }
+
+            let image = if label.is_empty() { theme.background(size) } else { theme.label(&label, size) };
+
+            // Labels are rendered in device coordinates, so rotation the conversion applies has to be undone first
+            let image = match screen.format.rotation {
+                ImageRotation::Rot0 => image,
+                ImageRotation::Rot90 => image.rotate270(),
+                ImageRotation::Rot180 => image.rotate180(),
+                ImageRotation::Rot270 => image.rotate90(),
+            };
+
+            let mut format = screen.format;
+            format.size = size;
+
+            device.write_screen(screen.id, Some(*segment), &convert_image_with_format(format, image)?)?;
+            self.drawn_labels.insert(encoder as u8, label);
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+}
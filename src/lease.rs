@@ -0,0 +1,102 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{DeviceId, StreamDeckError};
+
+/// Advisory lease on a device, so applications using this crate on the same machine don't fight over it.
+///
+/// Lease is a lock on a file in the [lease directory](lease_directory), held until the lease is dropped or the process exits.
+/// It's advisory, applications that don't take leases aren't stopped from opening the device.
+/// Other processes can ask the holder to give the device up with [request_lease], which the holder sees with [take_request](DeviceLease::take_request)
+#[derive(Debug)]
+pub struct DeviceLease {
+    id: DeviceId,
+    request_path: PathBuf,
+    // Lock is released when the file is closed
+    _file: File,
+}
+
+/// Another process asking for a device through [request_lease]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct LeaseRequest {
+    /// Process id of the requester, None if the request couldn't be read
+    pub pid: Option<u32>,
+}
+
+impl DeviceLease {
+    /// Device the lease is for
+    pub fn id(&self) -> &DeviceId {
+        &self.id
+    }
+
+    /// Returns request another process made for the device since the last call, and forgets it.
+    /// It's up to the holder to drop the lease in response
+    pub fn take_request(&self) -> Result<Option<LeaseRequest>, StreamDeckError> {
+        match fs::read_to_string(&self.request_path) {
+            Ok(content) => {
+                fs::remove_file(&self.request_path).ok();
+                Ok(Some(LeaseRequest { pid: content.trim().parse().ok() }))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Directory leases are kept in by default, within the temporary directory of the system
+pub fn lease_directory() -> PathBuf {
+    std::env::temp_dir().join("elgato-streamdeck-leases")
+}
+
+/// Takes lease on the device, None if another process holds it. See [DeviceLease]
+pub fn try_lease(id: &DeviceId) -> Result<Option<DeviceLease>, StreamDeckError> {
+    try_lease_in(lease_directory(), id)
+}
+
+/// Takes lease on the device like [try_lease] with leases kept in the directory, for applications sandboxed away from the default one
+pub fn try_lease_in(directory: impl AsRef<Path>, id: &DeviceId) -> Result<Option<DeviceLease>, StreamDeckError> {
+    let (lock_path, request_path) = lease_paths(directory.as_ref(), id)?;
+    let mut file = OpenOptions::new().create(true).truncate(false).write(true).open(&lock_path)?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => return Ok(None),
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+
+    // Holder is noted for diagnostics, requests made to the previous holder are void
+    file.set_len(0)?;
+    write!(file, "{}", std::process::id())?;
+    fs::remove_file(&request_path).ok();
+
+    Ok(Some(DeviceLease {
+        id: id.clone(),
+        request_path,
+        _file: file,
+    }))
+}
+
+/// Asks the process holding lease on the device to give it up, the holder sees it with [take_request](DeviceLease::take_request)
+pub fn request_lease(id: &DeviceId) -> Result<(), StreamDeckError> {
+    request_lease_in(lease_directory(), id)
+}
+
+/// Asks for the device like [request_lease] with leases kept in the directory
+pub fn request_lease_in(directory: impl AsRef<Path>, id: &DeviceId) -> Result<(), StreamDeckError> {
+    let (_, request_path) = lease_paths(directory.as_ref(), id)?;
+    fs::write(request_path, std::process::id().to_string())?;
+    Ok(())
+}
+
+/// Paths of the lock file and the request file of the device, creating the directory if it doesn't exist
+fn lease_paths(directory: &Path, id: &DeviceId) -> Result<(PathBuf, PathBuf), StreamDeckError> {
+    fs::create_dir_all(directory)?;
+
+    let name = format!("{:?}-{}", id.kind, id.serial)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect::<String>();
+
+    Ok((directory.join(format!("{}.lock", name)), directory.join(format!("{}.request", name))))
+}
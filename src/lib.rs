@@ -3,32 +3,214 @@
 //! Library for interacting with Elgato Stream Decks through [hidapi](https://crates.io/crates/hidapi).
 //! Heavily based on [python-elgato-streamdeck](https://github.com/abcminiuser/python-elgato-streamdeck) and partially on
 //! [streamdeck library for rust](https://github.com/ryankurte/rust-streamdeck).
+//!
+//! Device descriptions and report encoding live in [elgato-streamdeck-core](https://crates.io/crates/elgato-streamdeck-core),
+//! which works without std and hidapi, and are re-exported here as [info] and [protocol].
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![warn(missing_docs)]
 
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::iter::zip;
+use std::path::Path;
 use std::str::Utf8Error;
 use std::sync::RwLock;
-use std::sync::{Arc, Mutex, PoisonError};
-use std::time::Duration;
-
-use crate::images::{convert_image, ImageRect};
-use hidapi::{HidApi, HidDevice, HidError, HidResult};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{spawn, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "assets")]
+use crate::assets::AssetRegistry;
+#[cfg(feature = "tools")]
+use crate::calibration::KeyRemap;
+#[cfg(feature = "widgets")]
+use crate::compositor::{draw_badge, Badge};
+#[cfg(feature = "widgets")]
+use crate::images::convert_image_with_format;
+use crate::images::{compensate_brightness, convert_image_with_filter, dim_image, fit_image, ImageRect, RawImage, ResizeFilter};
+use hidapi::{HidApi, HidError, HidResult};
 use image::{DynamicImage, ImageError};
 
-use crate::info::{is_vendor_familiar, Kind};
-use crate::util::{extract_str, flip_key_index, get_feature_report, read_button_states, read_data, read_encoder_input, read_lcd_input, send_feature_report, write_data};
+use crate::info::{is_vendor_familiar, Kind, Screen, ScreenSegment, TouchKey};
+use crate::transport::{Transport, WatchdogTransport};
+use crate::protocol::ImageReportParameters;
+use crate::activity::{Activity, ActivityLog, RecordingTransport};
+use crate::util::{extract_str, extract_str_lossy, get_feature_report, normalize_serial, read_button_states, read_encoder_input, read_lcd_input, send_feature_report, write_data};
 
 /// Various information about Stream Deck devices
-pub mod info;
+pub use elgato_streamdeck_core::info;
 /// Utility functions for working with Stream Deck devices
 pub mod util;
+/// Connections to devices
+pub mod transport;
+/// Byte-level encoding of commands sent to devices
+pub use elgato_streamdeck_core::protocol;
+/// Log of recent reports exchanged with devices
+pub mod activity;
+/// Fake devices replaying recorded transcripts, for running tests without hardware
+#[cfg(feature = "fake")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fake")))]
+pub mod fake;
+#[cfg(feature = "tracing")]
+mod diagnostics;
+/// Guessing how to talk to devices of unknown models
+#[cfg(feature = "tools")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tools")))]
+pub mod probe;
+/// Discovering key order of unknown devices by asking for the keys to be pressed
+#[cfg(feature = "tools")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tools")))]
+pub mod calibration;
+/// Checking if devices can be opened, and what to do if they can't
+#[cfg(feature = "tools")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tools")))]
+pub mod access;
 /// Image processing functions
 pub mod images;
+/// Compositing overlays on top of key images
+#[cfg(feature = "widgets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+pub mod compositor;
+/// Test patterns for checking key and screen orientation
+#[cfg(feature = "widgets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+pub mod test_pattern;
+/// Color sets that stay distinguishable with color vision deficiencies, and contrast checking
+#[cfg(feature = "widgets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+pub mod palette;
+/// Named images shared between keys and devices
+#[cfg(feature = "assets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "assets")))]
+pub mod assets;
+/// Animating images of keys
+#[cfg(feature = "widgets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+pub mod animation;
+/// Bar and radial gauges for keys and the LCD strip
+#[cfg(feature = "widgets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+pub mod gauge;
+/// Values bound to encoders, like a volume knob
+#[cfg(feature = "widgets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+pub mod dial;
+/// Slider widget on the touch screen
+#[cfg(feature = "widgets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+pub mod slider;
+/// Audio visualizer widget on the touch screen
+#[cfg(feature = "widgets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+pub mod visualizer;
+/// Layout of the deck preview drawn by the simulator, for hit-testing in configurator UIs
+#[cfg(feature = "widgets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+pub mod preview;
+/// Keyboard-style modifier layers
+#[cfg(feature = "input-tools")]
+#[cfg_attr(docsrs, doc(cfg(feature = "input-tools")))]
+pub mod layers;
+/// Recording input as macros with timings
+#[cfg(feature = "input-tools")]
+#[cfg_attr(docsrs, doc(cfg(feature = "input-tools")))]
+pub mod recording;
+/// Encoding images on worker threads and writing them from a single thread
+#[cfg(feature = "pipeline")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pipeline")))]
+pub mod pipeline;
+
+/// Text rendering
+#[cfg(feature = "text")]
+#[cfg_attr(docsrs, doc(cfg(feature = "text")))]
+pub mod text;
+/// Scaling video frames on the GPU
+#[cfg(feature = "gpu")]
+#[cfg_attr(docsrs, doc(cfg(feature = "gpu")))]
+pub mod gpu;
+/// Software Stream Deck in a window, for developing without hardware
+#[cfg(feature = "simulator")]
+#[cfg_attr(docsrs, doc(cfg(feature = "simulator")))]
+pub mod simulator;
+/// Managing multiple Stream Decks at once
+#[cfg(feature = "manager")]
+#[cfg_attr(docsrs, doc(cfg(feature = "manager")))]
+pub mod manager;
+#[cfg(feature = "manager")]
+#[cfg_attr(docsrs, doc(cfg(feature = "manager")))]
+pub use manager::DeckManager;
+/// Detecting devices getting plugged in and unplugged
+#[cfg(feature = "manager")]
+#[cfg_attr(docsrs, doc(cfg(feature = "manager")))]
+pub mod hotplug;
+
+/// Version of image crate used in the API, use it or [RawImage](images::RawImage) to avoid depending on the same version
+pub use image;
+/// Brightness scheduling
+#[cfg(feature = "manager")]
+#[cfg_attr(docsrs, doc(cfg(feature = "manager")))]
+pub mod schedule;
+/// One-shot commands for scripts and command line tools
+#[cfg(feature = "tools")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tools")))]
+pub mod quick;
+/// Advisory leases that keep processes from fighting over a device
+#[cfg(feature = "tools")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tools")))]
+pub mod lease;
+
+/// Suspending devices while the Windows session is locked or switched away from
+#[cfg(all(windows, feature = "windows-session"))]
+#[cfg_attr(docsrs, doc(cfg(all(windows, feature = "windows-session"))))]
+pub mod session;
+
+/// Suspending devices during macOS sleep and reinitializing them on wake
+#[cfg(all(target_os = "macos", feature = "macos-power"))]
+#[cfg_attr(docsrs, doc(cfg(all(target_os = "macos", feature = "macos-power"))))]
+pub mod power;
+
+/// Persistent per-device settings
+#[cfg(feature = "settings")]
+#[cfg_attr(docsrs, doc(cfg(feature = "settings")))]
+pub mod settings;
+
+/// Talking to devices through nusb instead of hidapi
+#[cfg(feature = "nusb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "nusb")))]
+pub mod usb;
+
+/// Playing video on the LCD strip
+#[cfg(feature = "video")]
+#[cfg_attr(docsrs, doc(cfg(feature = "video")))]
+pub mod video;
+
+/// Reloading assets when their files change
+#[cfg(feature = "hot-reload")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hot-reload")))]
+pub mod hot_reload;
+
+/// Declarative layouts of pages, keys and encoders loaded from TOML or JSON
+#[cfg(feature = "layout")]
+#[cfg_attr(docsrs, doc(cfg(feature = "layout")))]
+pub mod layout;
+
+/// Fetching images from URLs with an on-disk cache
+#[cfg(feature = "url")]
+#[cfg_attr(docsrs, doc(cfg(feature = "url")))]
+pub mod fetch;
+
+/// Device descriptors in the OpenAction manifest format
+#[cfg(feature = "openaction")]
+#[cfg_attr(docsrs, doc(cfg(feature = "openaction")))]
+pub mod openaction;
+
+/// Throughput and latency benchmarks
+#[cfg(feature = "bench")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bench")))]
+pub mod bench;
 
 /// Async Stream Deck
 #[cfg(feature = "async")]
@@ -50,10 +232,16 @@ pub fn refresh_device_list(hidapi: &mut HidApi) -> HidResult<()> {
     hidapi.refresh_devices()
 }
 
-/// Returns a list of devices as (Kind, Serial Number) that could be found using HidApi.
+/// Returns a list of devices as (Kind, Serial Number) that could be found using HidApi, serial numbers are [normalized](util::normalize_serial).
+/// With `fake` feature, fake devices are listed instead if [FAKE_ENV](fake::FAKE_ENV) is set.
 ///
 /// **WARNING:** To refresh the list, use [refresh_device_list]
 pub fn list_devices(hidapi: &HidApi) -> Vec<(Kind, String)> {
+    #[cfg(feature = "fake")]
+    if let Some(devices) = fake::fake_devices() {
+        return devices.into_iter().map(|device| (device.kind, device.serial)).collect();
+    }
+
     hidapi
         .device_list()
         .filter_map(|d| {
@@ -62,7 +250,7 @@ pub fn list_devices(hidapi: &HidApi) -> Vec<(Kind, String)> {
             }
 
             if let Some(serial) = d.serial_number() {
-                Some((Kind::from_vid_pid(d.vendor_id(), d.product_id())?, serial.to_string()))
+                Some((Kind::from_vid_pid(d.vendor_id(), d.product_id())?, normalize_serial(serial)))
             } else {
                 None
             }
@@ -72,64 +260,399 @@ pub fn list_devices(hidapi: &HidApi) -> Vec<(Kind, String)> {
         .collect()
 }
 
-/// Type of input that the device produced
+/// Identifies a Stream Deck by its kind and serial number
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceId {
+    /// Kind of the device
+    pub kind: Kind,
+    /// Serial number of the device
+    pub serial: String,
+}
+
+impl Display for DeviceId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} {}", self.kind, self.serial)
+    }
+}
+
+pub use elgato_streamdeck_core::{ProtocolError, StreamDeckInput};
+
+/// Most reports discarded while resynchronizing after an unexpected report
+const MAX_RESYNC_REPORTS: usize = 32;
+
+/// Interface for a Stream Deck device
+pub struct StreamDeck {
+    /// Kind of the device
+    kind: Kind,
+    /// Connection to the device
+    device: Box<dyn Transport>,
+    /// Temporarily cache the image before sending it to the device
+    image_cache: RwLock<Vec<ImageCache>>,
+    /// Options the device was connected with
+    options: ConnectOptions,
+    /// State that was last applied to the device, used to restore it after reset
+    applied_state: RwLock<AppliedState>,
+    /// Source images of keys along with effects composited on top of them
+    key_sources: RwLock<Vec<KeySource>>,
+    /// Flags of keys whose uploads should be abandoned
+    upload_canceller: UploadCanceller,
+    /// Number of times reading recovered from an unexpected report
+    recovered_desyncs: AtomicU64,
+    /// Well-formed reports that were drained while resynchronizing, returned by reads before new reports
+    resynced_reports: Mutex<VecDeque<(Vec<u8>, usize)>>,
+    /// Number of input reports that couldn't be parsed
+    parse_failures: AtomicU64,
+    /// When staged images were last all sent, used by [FlushStrategy::Interval]
+    last_flush: RwLock<Instant>,
+    /// If screens and LEDs are turned off by [blackout](StreamDeck::blackout)
+    blacked_out: AtomicBool,
+    /// If writes are held back by [suspend](StreamDeck::suspend)
+    suspended: AtomicBool,
+    /// Screen writes held back by suspend, written in order on resume
+    suspended_screen_writes: Mutex<Vec<ScreenWrite>>,
+    /// If key images are adjusted to brightness, see [set_brightness_compensation](StreamDeck::set_brightness_compensation)
+    brightness_compensation: AtomicBool,
+    /// If brightness changed during blackout or suspend, so key images have to be compensated again on wake or resume
+    compensation_stale: AtomicBool,
+    /// If reset was requested while suspended, so the device is reset on resume
+    reset_pending: AtomicBool,
+    /// Recent reports exchanged with the device, if enabled by [ConnectOptions::activity_log_size]
+    activity: Option<Arc<ActivityLog>>,
+    /// Rate limiter of protocol error diagnostics
+    #[cfg(feature = "tracing")]
+    diagnostics: diagnostics::Diagnostics,
+    /// Set if the flush strategy is applied by the async wrapper once it unlocks the device instead of by writes,
+    /// flag tells that a write staged images since the wrapper last applied it
+    #[cfg(feature = "async")]
+    deferred_flush: Option<AtomicBool>,
+}
+
+/// Handle that cancels image uploads of a [StreamDeck] from any thread, see [StreamDeck::upload_canceller]
 #[derive(Clone, Debug)]
-pub enum StreamDeckInput {
-    /// No data was passed from the device
-    NoData,
+pub struct UploadCanceller {
+    keys: Arc<Vec<AtomicBool>>,
+}
 
-    /// Button was pressed
-    ButtonStateChange(Vec<bool>),
+impl UploadCanceller {
+    /// Cancels staged image of the key, and the upload of its image that is in progress if there is one.
+    /// Upload in progress is stopped before its next report page is sent, leaving previous image on the key.
+    /// Staging a new image for the key clears the cancellation
+    pub fn cancel(&self, key: impl Into<KeyIndex>) {
+        let key = key.into().0;
 
-    /// Encoder/Knob was pressed
-    EncoderStateChange(Vec<bool>),
+        if let Some(flag) = self.keys.get(key as usize) {
+            flag.store(true, Ordering::Release);
+        }
+    }
 
-    /// Encoder/Knob was twisted/turned
-    EncoderTwist(Vec<i8>),
+    fn is_canceled(&self, key: u8) -> bool {
+        self.keys.get(key as usize).is_some_and(|flag| flag.load(Ordering::Acquire))
+    }
 
-    /// Touch screen received short press
-    TouchScreenPress(u16, u16),
+    fn reset(&self, key: u8) {
+        if let Some(flag) = self.keys.get(key as usize) {
+            flag.store(false, Ordering::Release);
+        }
+    }
+}
 
-    /// Touch screen received long press
-    TouchScreenLongPress(u16, u16),
+/// Background thread that flushes staged images of a shared [StreamDeck] at a fixed cadence, stops when dropped
+pub struct AutoFlush {
+    stopped: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
 
-    /// Touch screen received a swipe
-    TouchScreenSwipe((u16, u16), (u16, u16)),
+impl AutoFlush {
+    /// Starts flushing staged images every period, so images can be set freely without calling flush.
+    /// Images set for the same key within a period supersede each other and only the latest is sent.
+    /// Device is unlocked after every image, and the thread stops once the handle or the device is dropped
+    pub fn start(device: &Arc<Mutex<StreamDeck>>, period: Duration) -> AutoFlush {
+        let device = Arc::downgrade(device);
+        let period = period.max(Duration::from_millis(1));
+
+        let stopped = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_stopped = stopped.clone();
+
+        let thread = spawn(move || {
+            let (lock, condvar) = &*thread_stopped;
+            let mut next_tick = Instant::now() + period;
+
+            loop {
+                let Ok(mut stopped) = lock.lock() else {
+                    return;
+                };
+
+                // Waiting for the tick, returning early if the handle is dropped
+                while !*stopped && Instant::now() < next_tick {
+                    stopped = match condvar.wait_timeout(stopped, next_tick.saturating_duration_since(Instant::now())) {
+                        Ok((stopped, _)) => stopped,
+                        Err(_) => return,
+                    };
+                }
+
+                if *stopped {
+                    return;
+                }
+
+                drop(stopped);
+
+                let Some(device) = device.upgrade() else {
+                    return;
+                };
+
+                // Failed flush leaves the image staged, so it's retried on the next tick
+                while let Ok(device) = device.lock()
+                    && let Ok(true) = device.flush_next()
+                {}
+
+                // Skipping ticks that were missed while flushing took longer than the period
+                next_tick += period;
+                if next_tick < Instant::now() {
+                    next_tick = Instant::now() + period;
+                }
+            }
+        });
+
+        AutoFlush { stopped, thread: Some(thread) }
+    }
 }
 
-impl StreamDeckInput {
-    /// Checks if there's data received or not
-    pub fn is_empty(&self) -> bool {
-        matches!(self, StreamDeckInput::NoData)
+impl Drop for AutoFlush {
+    fn drop(&mut self) {
+        let (lock, condvar) = &*self.stopped;
+
+        if let Ok(mut stopped) = lock.lock() {
+            *stopped = true;
+        }
+
+        condvar.notify_all();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
     }
 }
 
-/// Interface for a Stream Deck device
-pub struct StreamDeck {
-    /// Kind of the device
-    kind: Kind,
-    /// Connected HIDDevice
-    device: HidDevice,
-    /// Temporarily cache the image before sending it to the device
-    image_cache: RwLock<Vec<ImageCache>>,
+/// Screen write as (region or None for a fill, image data)
+type ScreenWrite = (Option<(u16, u16, u16, u16)>, Vec<u8>);
+
+#[derive(Clone)]
+struct KeySource {
+    image: Option<DynamicImage>,
+    /// Name of the asset the image came from, so keys can be refreshed when the asset changes
+    asset: Option<String>,
+    dim: f32,
+    #[cfg(feature = "widgets")]
+    badge: Option<Badge>,
+}
+
+#[cfg(any(feature = "assets", feature = "async"))]
+impl KeySource {
+    /// Tells if the image is shown as it is, without dimming or badge
+    fn is_plain(&self) -> bool {
+        #[cfg(feature = "widgets")]
+        if self.badge.is_some() {
+            return false;
+        }
+
+        self.dim >= 1.0
+    }
+}
+
+impl Default for KeySource {
+    fn default() -> Self {
+        Self {
+            image: None,
+            asset: None,
+            dim: 1.0,
+            #[cfg(feature = "widgets")]
+            badge: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct AppliedState {
+    brightness: Option<u8>,
+    touchpoint_colors: Vec<Option<(u8, u8, u8)>>,
+    key_images: Vec<Option<Arc<[u8]>>>,
+}
+
+impl AppliedState {
+    fn new(kind: Kind) -> Self {
+        Self {
+            brightness: None,
+            touchpoint_colors: vec![None; kind.touchpoint_count() as usize],
+            key_images: vec![None; kind.key_count() as usize],
+        }
+    }
+}
+
+/// Options that are applied to a connection when connecting to the device
+#[derive(Clone, Debug, Default)]
+pub struct ConnectOptions {
+    /// Maximum time a single image transfer is allowed to take before failing with [StreamDeckError::Timeout].
+    ///
+    /// The timeout is checked before every report of the transfer, and a transfer whose last report was written doesn't fail.
+    /// Writes are moved to a separate thread that is given up on if a single report takes longer than the timeout,
+    /// so a stuck write returns within about twice the timeout. hidapi can't interrupt the stuck write itself,
+    /// so until it returns, everything else done with the device fails with [StreamDeckError::Timeout] right away
+    pub write_timeout: Option<Duration>,
+
+    /// How input reports the library doesn't understand are handled
+    pub parse_mode: ParseMode,
+
+    /// When images staged by writes are sent to the device
+    pub flush_strategy: FlushStrategy,
+
+    /// Number of recent reports kept for [recent_activity](StreamDeck::recent_activity), 0 disables the log
+    pub activity_log_size: usize,
+
+    /// Fail with [StreamDeckError::Utf8Error] when serial number or firmware version isn't valid UTF-8.
+    /// By default, invalid bytes are replaced and padding garbage is trimmed
+    pub strict_strings: bool,
+
+    /// Filter used for scaling images set with [set_button_image](StreamDeck::set_button_image) to the key size
+    pub resize_filter: ResizeFilter,
+
+    /// Initial state of [brightness compensation](StreamDeck::set_brightness_compensation)
+    pub brightness_compensation: bool,
+
+    /// What readers of the device do with controls that are already held when reading starts, like after the device was replugged
+    pub held_input: HeldInput,
+
+    #[cfg(feature = "tools")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tools")))]
+    /// Key order found by [Calibration](calibration::Calibration). Readers of the device deliver physical keys,
+    /// and key images are written to the keys by their physical index, assuming the device indexes images the same way it reports presses
+    pub key_remap: Option<KeyRemap>,
+
+    #[cfg(feature = "assets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "assets")))]
+    /// Registry of images used by [set_button_asset](StreamDeck::set_button_asset), can be shared by multiple devices
+    pub assets: Option<Arc<AssetRegistry>>,
+}
+
+/// When images staged by writes like [set_button_image](StreamDeck::set_button_image) are sent to the device
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub enum FlushStrategy {
+    /// Images are only sent by calling flush, so many keys can be updated at once. Suitable for applications that manage their frames
+    #[default]
+    Manual,
+    /// Every write sends staged images right away, as if flush was called after it
+    Immediate,
+    /// Writes send staged images if the interval passed since the last flush,
+    /// images staged within the interval wait for a later write or flush
+    Interval(Duration),
+    /// Staged images are sent at a fixed cadence by a background ticker, similar to vsync.
+    /// AsyncStreamDeck runs the ticker by itself, [StreamDeck] has to be shared to run it with [AutoFlush]
+    Ticker(Duration),
+}
+
+/// Handling of controls that are already held when reading starts.
+///
+/// Devices report held controls again after they're replugged, which looks like new presses to a reader that was just created.
+/// Devices can't be asked what is held, so held controls can only be told apart by when they're reported
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub enum HeldInput {
+    /// Everything is assumed released when reading starts, so controls held at that time produce down updates
+    #[default]
+    Report,
+    /// Button and encoder states reported within the duration after the reader was created are taken as they are without producing updates,
+    /// and releases of controls held in them are swallowed. Presses that are both made and released within the duration are lost
+    Ignore(Duration),
+}
+
+/// Handling of input reports that can't be parsed
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub enum ParseMode {
+    /// Reports of unknown type are skipped, and malformed reports are skipped along with malformed reports queued after them
+    /// to get back in sync, see [recovered_desyncs](StreamDeck::recovered_desyncs). Suitable for applications
+    #[default]
+    Lenient,
+    /// Any report that can't be parsed fails with [StreamDeckError::BadData]. Suitable for protocol development
+    Strict,
+    /// Reports of unknown type are returned as [StreamDeckInput::Unknown], malformed reports are handled like in lenient mode.
+    /// Suitable for observing what new hardware or firmware sends
+    Passthrough,
 }
 
 struct ImageCache {
     key: u8,
-    image_data: Vec<u8>,
+    image_data: Arc<[u8]>,
 }
 
 /// Static functions of the struct
 impl StreamDeck {
     /// Attempts to connect to the device
     pub fn connect(hidapi: &HidApi, kind: Kind, serial: &str) -> Result<StreamDeck, StreamDeckError> {
-        let device = hidapi.open_serial(kind.vendor_id(), kind.product_id(), serial)?;
+        Self::connect_with_options(hidapi, kind, serial, ConnectOptions::default())
+    }
+
+    /// Attempts to connect to the device with provided connection options.
+    /// With `fake` feature, connects to a fake device instead if [FAKE_ENV](fake::FAKE_ENV) is set
+    pub fn connect_with_options(hidapi: &HidApi, kind: Kind, serial: &str, options: ConnectOptions) -> Result<StreamDeck, StreamDeckError> {
+        #[cfg(feature = "fake")]
+        if let Some(device) = fake::connect_fake(kind, serial, options.clone()) {
+            return device;
+        }
+
+        let serial = normalize_serial(serial);
+
+        let device = match hidapi
+            .device_list()
+            .find(|info| info.vendor_id() == kind.vendor_id() && info.product_id() == kind.product_id() && info.serial_number().is_some_and(|other| normalize_serial(other) == serial))
+        {
+            Some(info) => info.open_device(hidapi)?,
+            None => hidapi.open_serial(kind.vendor_id(), kind.product_id(), &serial)?,
+        };
+
+        Ok(Self::from_transport(kind, device, options))
+    }
+
+    /// Creates interface for a device of specified kind that is reachable through the transport,
+    /// for example a [MockTransport](transport::MockTransport)
+    pub fn from_transport(kind: Kind, transport: impl Transport + 'static, options: ConnectOptions) -> StreamDeck {
+        let activity = (options.activity_log_size > 0).then(|| Arc::new(ActivityLog::new(options.activity_log_size)));
+        let brightness_compensation = AtomicBool::new(options.brightness_compensation);
+
+        let transport: Box<dyn Transport> = match options.write_timeout {
+            Some(timeout) => Box::new(WatchdogTransport::new(Box::new(transport), timeout)),
+            None => Box::new(transport),
+        };
+
+        let device: Box<dyn Transport> = match &activity {
+            Some(log) => Box::new(RecordingTransport { inner: transport, log: log.clone() }),
+            None => transport,
+        };
 
-        Ok(StreamDeck {
+        StreamDeck {
             kind,
             device,
             image_cache: RwLock::new(vec![]),
-        })
+            options,
+            applied_state: RwLock::new(AppliedState::new(kind)),
+            key_sources: RwLock::new(vec![KeySource::default(); kind.key_count() as usize]),
+            upload_canceller: UploadCanceller {
+                keys: Arc::new((0..kind.key_count()).map(|_| AtomicBool::new(false)).collect()),
+            },
+            recovered_desyncs: AtomicU64::new(0),
+            resynced_reports: Mutex::new(VecDeque::new()),
+            parse_failures: AtomicU64::new(0),
+            last_flush: RwLock::new(Instant::now()),
+            blacked_out: AtomicBool::new(false),
+            suspended: AtomicBool::new(false),
+            suspended_screen_writes: Mutex::new(vec![]),
+            brightness_compensation,
+            compensation_stale: AtomicBool::new(false),
+            reset_pending: AtomicBool::new(false),
+            activity,
+            #[cfg(feature = "tracing")]
+            diagnostics: Default::default(),
+            #[cfg(feature = "async")]
+            deferred_flush: None,
+        }
     }
 }
 
@@ -140,428 +663,1013 @@ impl StreamDeck {
         self.kind
     }
 
+    /// Returns options the device was connected with
+    pub fn options(&self) -> &ConnectOptions {
+        &self.options
+    }
+
+    /// Returns recent reports exchanged with the device, oldest first, for embedding in crash reports.
+    /// Empty unless the log was enabled with [ConnectOptions::activity_log_size]
+    pub fn recent_activity(&self) -> Vec<Activity> {
+        self.activity.as_ref().map(|log| log.entries()).unwrap_or_default()
+    }
+
+    /// Returns handle that can cancel image uploads while the device is busy flushing, for example from another thread
+    pub fn upload_canceller(&self) -> UploadCanceller {
+        self.upload_canceller.clone()
+    }
+
     /// Returns manufacturer string of the device
     pub fn manufacturer(&self) -> Result<String, StreamDeckError> {
-        Ok(self.device.get_manufacturer_string()?.unwrap_or_else(|| "Unknown".to_string()))
+        Ok(self.device.manufacturer()?.unwrap_or_else(|| "Unknown".to_string()))
     }
 
     /// Returns product string of the device
     pub fn product(&self) -> Result<String, StreamDeckError> {
-        Ok(self.device.get_product_string()?.unwrap_or_else(|| "Unknown".to_string()))
+        Ok(self.device.product()?.unwrap_or_else(|| "Unknown".to_string()))
     }
 
-    /// Returns serial number of the device
+    /// Returns [normalized](util::normalize_serial) serial number of the device
     pub fn serial_number(&self) -> Result<String, StreamDeckError> {
-        match self.kind {
-            Kind::Original | Kind::Mini => {
-                let bytes = get_feature_report(&self.device, 0x03, 17)?;
-                Ok(extract_str(&bytes[5..])?)
-            }
-
-            Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => {
-                let bytes = get_feature_report(&self.device, 0x03, 32)?;
-                Ok(extract_str(&bytes[5..])?)
-            }
-
-            _ => {
-                let bytes = get_feature_report(&self.device, 0x06, 32)?;
-                Ok(extract_str(&bytes[2..])?)
-            }
-        }
-        .map(|s| s.replace('\u{0001}', ""))
+        self.read_string_report(protocol::StringReport::serial_number(self.kind)).map(|s| normalize_serial(&s))
     }
 
     /// Returns firmware version of the StreamDeck
     pub fn firmware_version(&self) -> Result<String, StreamDeckError> {
-        match self.kind {
-            Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord => {
-                let bytes = get_feature_report(&self.device, 0x04, 17)?;
-                Ok(extract_str(&bytes[5..])?)
-            }
+        self.read_string_report(protocol::StringReport::firmware_version(self.kind))
+    }
 
-            Kind::MiniMk2Module => {
-                let bytes = get_feature_report(&self.device, 0xA1, 17)?;
-                Ok(extract_str(&bytes[5..])?)
-            }
+    /// Reads string from a feature report, strictly or lossily depending on [strict_strings](ConnectOptions::strict_strings)
+    fn read_string_report(&self, report: protocol::StringReport) -> Result<String, StreamDeckError> {
+        let bytes = get_feature_report(self.device.as_ref(), report.report_id, report.length)?;
+        let bytes = bytes.get(report.offset..).unwrap_or_default();
 
-            _ => {
-                let bytes = get_feature_report(&self.device, 0x05, 32)?;
-                Ok(extract_str(&bytes[6..])?)
-            }
-        }
+        if self.options.strict_strings { Ok(extract_str(bytes)?) } else { Ok(extract_str_lossy(bytes)) }
     }
 
-    /// Reads all possible input from Stream Deck device
+    /// Reads all possible input from Stream Deck device.
+    /// What happens with unexpected reports depends on [parse mode](ConnectOptions::parse_mode) of the connection
     pub fn read_input(&self, timeout: Option<Duration>) -> Result<StreamDeckInput, StreamDeckError> {
-        match &self.kind {
-            Kind::Plus | Kind::PlusXl => {
-                let data = read_data(&self.device, (6 + self.kind.key_count()).max(5 + self.kind.encoder_count()) as usize, timeout)?;
+        let layout = self.kind.report_layout();
+        let length = layout.length;
 
-                if data[0] == 0 {
-                    return Ok(StreamDeckInput::NoData);
-                }
+        let resynced = self.resynced_reports.lock()?.pop_front();
 
-                match &data[1] {
-                    0x0 => Ok(StreamDeckInput::ButtonStateChange(read_button_states(&self.kind, &data))),
+        let (data, read) = match resynced {
+            Some(report) => report,
+            None => self.diagnose("read input", self.read_report(length, timeout), &[])?,
+        };
 
-                    0x2 => Ok(read_lcd_input(&data)?),
+        if data[0] == 0 {
+            return Ok(StreamDeckInput::NoData);
+        }
 
-                    0x3 => Ok(read_encoder_input(&self.kind, &data)?),
+        // Malformed reports mean reading is out of sync, while well-formed reports of unknown type can be skipped
+        let mut malformed = false;
 
-                    _ => Err(StreamDeckError::BadData),
+        let input = match layout.report_type(&data) {
+            Some(report_type) => match layout.required_length(report_type) {
+                Some(required) if read < required => {
+                    malformed = true;
+                    Err(StreamDeckError::BadData)
                 }
-            }
 
-            _ => {
-                let data = match self.kind {
-                    Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => read_data(&self.device, 1 + self.kind.key_count() as usize, timeout),
-                    _ => read_data(&self.device, 4 + self.kind.key_count() as usize + self.kind.touchpoint_count() as usize, timeout),
-                }?;
+                Some(_) if report_type == layout.buttons.report_type => Ok(StreamDeckInput::ButtonStateChange(read_button_states(&self.kind, &data))),
 
-                if data[0] == 0 {
-                    return Ok(StreamDeckInput::NoData);
-                }
+                Some(_) if layout.touch_screen.is_some_and(|touch_screen| touch_screen.report_type == report_type) => read_lcd_input(&self.kind, &data),
 
-                Ok(StreamDeckInput::ButtonStateChange(read_button_states(&self.kind, &data)))
-            }
-        }
-    }
+                Some(_) if layout.encoders.is_some_and(|encoders| encoders.report_type == report_type) => read_encoder_input(&self.kind, &data),
 
-    /// Resets the device
-    pub fn reset(&self) -> Result<(), StreamDeckError> {
-        match self.kind {
-            Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => {
-                let mut buf = vec![0x0B, 0x63];
+                _ => Err(StreamDeckError::BadData),
+            },
 
-                buf.extend(vec![0u8; 15]);
+            None => Err(StreamDeckError::BadData),
+        };
+
+        let report = &data[..read.min(data.len())];
+
+        if matches!(input, Err(StreamDeckError::BadData)) {
+            self.parse_failures.fetch_add(1, Ordering::Relaxed);
+        }
 
-                Ok(send_feature_report(&self.device, buf.as_slice())?)
+        match (self.diagnose("parse input", input, report), self.options.parse_mode) {
+            (Err(StreamDeckError::BadData), ParseMode::Lenient | ParseMode::Passthrough) if malformed => {
+                self.resync(length)?;
+                Ok(StreamDeckInput::NoData)
             }
 
-            _ => {
-                let mut buf = vec![0x03, 0x02];
+            (Err(StreamDeckError::BadData), ParseMode::Lenient) => Ok(StreamDeckInput::NoData),
 
-                buf.extend(vec![0u8; 30]);
+            // Kinds without a report type byte only have the HID report id to tell reports apart
+            (Err(StreamDeckError::BadData), ParseMode::Passthrough) => Ok(StreamDeckInput::Unknown(data[layout.type_offset.unwrap_or(0)], report.to_vec())),
 
-                Ok(send_feature_report(&self.device, buf.as_slice())?)
-            }
+            (input, _) => input,
         }
     }
 
-    /// Sets brightness of the device, value range is 0 - 100
-    pub fn set_brightness(&self, percent: u8) -> Result<(), StreamDeckError> {
-        let percent = percent.clamp(0, 100);
+    /// Returns how many times reading got out of sync with reports of the device and was recovered
+    pub fn recovered_desyncs(&self) -> u64 {
+        self.recovered_desyncs.load(Ordering::Relaxed)
+    }
 
-        match self.kind {
-            Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => {
-                let mut buf = vec![0x05, 0x55, 0xaa, 0xd1, 0x01, percent];
+    /// Returns how many input reports couldn't be parsed, including ones that were skipped because of [ParseMode]
+    pub fn parse_failures(&self) -> u64 {
+        self.parse_failures.load(Ordering::Relaxed)
+    }
 
-                buf.extend(vec![0u8; 11]);
+    /// Reads input report, returning it along with number of bytes that were actually read
+    fn read_report(&self, length: usize, timeout: Option<Duration>) -> Result<(Vec<u8>, usize), StreamDeckError> {
+        let mut buf = vec![0u8; length];
+        let read = self.device.read(&mut buf, timeout)?;
 
-                Ok(send_feature_report(&self.device, buf.as_slice())?)
-            }
+        Ok((buf, read))
+    }
 
-            _ => {
-                let mut buf = vec![0x03, 0x08, percent];
+    /// Drains reports that are already waiting after a malformed report, so reading continues from a report boundary.
+    /// Malformed reports among them are discarded, the rest are kept for the following reads
+    fn resync(&self, length: usize) -> Result<(), StreamDeckError> {
+        let layout = self.kind.report_layout();
+        let mut resynced = vec![];
 
-                buf.extend(vec![0u8; 29]);
+        for _ in 0..MAX_RESYNC_REPORTS {
+            let (data, read) = self.read_report(length, None)?;
 
-                Ok(send_feature_report(&self.device, buf.as_slice())?)
+            if read == 0 {
+                break;
             }
-        }
-    }
 
-    fn send_image(&self, key: u8, image_data: &[u8]) -> Result<(), StreamDeckError> {
-        if key >= self.kind.key_count() {
-            return Err(StreamDeckError::InvalidKeyIndex);
+            if !layout.is_malformed(&data, read) {
+                resynced.push((data, read));
+            }
         }
 
-        let key = if let Kind::Original = self.kind { flip_key_index(&self.kind, key) } else { key };
+        self.resynced_reports.lock()?.extend(resynced);
 
-        if !self.kind.is_visual() {
-            return Err(StreamDeckError::NoScreen);
-        }
+        self.recovered_desyncs.fetch_add(1, Ordering::Relaxed);
 
-        self.write_image_data_reports(
-            image_data,
-            WriteImageParameters::for_key(self.kind, image_data.len()),
-            |page_number, this_length, last_package| match self.kind {
-                Kind::Original => vec![0x02, 0x01, (page_number + 1) as u8, 0, if last_package { 1 } else { 0 }, key + 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-
-                Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => vec![0x02, 0x01, page_number as u8, 0, if last_package { 1 } else { 0 }, key + 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-
-                _ => vec![
-                    0x02,
-                    0x07,
-                    key,
-                    if last_package { 1 } else { 0 },
-                    (this_length & 0xff) as u8,
-                    (this_length >> 8) as u8,
-                    (page_number & 0xff) as u8,
-                    (page_number >> 8) as u8,
-                ],
-            },
-        )?;
         Ok(())
     }
 
-    /// Writes image data to Stream Deck device, changes must be flushed with `.flush()` before
-    /// they will appear on the device!
-    pub fn write_image(&self, key: u8, image_data: &[u8]) -> Result<(), StreamDeckError> {
-        let cache_entry = ImageCache { key, image_data: image_data.to_vec() };
-
-        self.image_cache.write()?.push(cache_entry);
+    /// Resets the device. While [suspended](StreamDeck::suspend), the reset is held back until resume
+    pub fn reset(&self) -> Result<(), StreamDeckError> {
+        if self.is_suspended() {
+            self.reset_pending.store(true, Ordering::Release);
+            return Ok(());
+        }
 
-        Ok(())
+        Ok(send_feature_report(self.device.as_ref(), &protocol::reset_command(self.kind))?)
     }
 
-    /// Writes image data to Stream Deck device's lcd strip/screen as region.
-    /// Only Stream Deck Plus supports writing LCD regions, for Stream Deck Neo use write_lcd_fill
-    pub fn write_lcd(&self, x: u16, y: u16, rect: &ImageRect) -> Result<(), StreamDeckError> {
-        match self.kind {
-            Kind::Plus | Kind::PlusXl => (),
-            _ => return Err(StreamDeckError::UnsupportedOperation),
+    /// Resets the device, then restores brightness, touch point colors and key images
+    /// that were previously applied to it, since bare reset leaves the device showing the Elgato logo
+    pub fn reset_preserving_state(&self) -> Result<(), StreamDeckError> {
+        let state = self.applied_state.read()?.clone();
+
+        self.reset()?;
+
+        // Resume restores the state after the held back reset
+        if self.is_suspended() {
+            return Ok(());
         }
 
-        self.write_image_data_reports(
-            rect.data.as_slice(),
-            WriteImageParameters {
-                image_report_length: 1024,
-                image_report_payload_length: 1024 - 16,
-            },
-            |page_number, this_length, last_package| {
-                vec![
-                    0x02,
-                    0x0c,
-                    (x & 0xff) as u8,
-                    (x >> 8) as u8,
-                    (y & 0xff) as u8,
-                    (y >> 8) as u8,
-                    (rect.w & 0xff) as u8,
-                    (rect.w >> 8) as u8,
-                    (rect.h & 0xff) as u8,
-                    (rect.h >> 8) as u8,
-                    if last_package { 1 } else { 0 },
-                    (page_number & 0xff) as u8,
-                    (page_number >> 8) as u8,
-                    (this_length & 0xff) as u8,
-                    (this_length >> 8) as u8,
-                    0,
-                ]
-            },
-        )
+        self.restore_state(&state)
     }
 
-    /// Writes image data to Stream Deck device's lcd strip/screen as full fill
-    ///
-    /// You can convert your images into proper image_data like this:
-    /// ```
-    /// use elgato_streamdeck::images::convert_image_with_format;
-    /// let image_data = convert_image_with_format(device.kind().lcd_image_format(), image).unwrap();
-    /// device.write_lcd_fill(&image_data);
-    /// ```
-    pub fn write_lcd_fill(&self, image_data: &[u8]) -> Result<(), StreamDeckError> {
-        match self.kind {
-            Kind::Neo => self.write_image_data_reports(
-                image_data,
-                WriteImageParameters {
-                    image_report_length: 1024,
-                    image_report_payload_length: 1024 - 8,
-                },
-                |page_number, this_length, last_package| {
-                    vec![
-                        0x02,
-                        0x0b,
-                        0,
-                        if last_package { 1 } else { 0 },
-                        (this_length & 0xff) as u8,
-                        (this_length >> 8) as u8,
-                        (page_number & 0xff) as u8,
-                        (page_number >> 8) as u8,
-                    ]
-                },
-            ),
-
-            Kind::Plus | Kind::PlusXl => {
-                let (w, h) = self.kind.lcd_strip_size().unwrap();
-
-                self.write_image_data_reports(
-                    image_data,
-                    WriteImageParameters {
-                        image_report_length: 1024,
-                        image_report_payload_length: 1024 - 16,
-                    },
-                    |page_number, this_length, last_package| {
-                        vec![
-                            0x02,
-                            0x0c,
-                            0,
-                            0,
-                            0,
-                            0,
-                            (w & 0xff) as u8,
-                            (w >> 8) as u8,
-                            (h & 0xff) as u8,
-                            (h >> 8) as u8,
-                            if last_package { 1 } else { 0 },
-                            (page_number & 0xff) as u8,
-                            (page_number >> 8) as u8,
-                            (this_length & 0xff) as u8,
-                            (this_length >> 8) as u8,
-                            0,
-                        ]
-                    },
-                )
-            }
+    /// Applies brightness, touch point colors and key images of the state to the device
+    fn restore_state(&self, state: &AppliedState) -> Result<(), StreamDeckError> {
+        if let Some(brightness) = state.brightness {
+            self.set_brightness(brightness)?;
+        }
 
-            _ => Err(StreamDeckError::UnsupportedOperation),
+        for (point, color) in state.touchpoint_colors.iter().enumerate() {
+            if let Some((red, green, blue)) = color {
+                self.set_touchpoint_color(point as u8, *red, *green, *blue)?;
+            }
         }
-    }
 
-    /// Sets button's image to blank, changes must be flushed with `.flush()` before
-    /// they will appear on the device!
-    pub fn clear_button_image(&self, key: u8) -> Result<(), StreamDeckError> {
-        self.send_image(key, &self.kind.blank_image())
-    }
+        // Restoring every key even if some fail, so as few keys as possible are left showing the logo
+        let result = self.for_each_key(0..self.kind.key_count(), |key| match &state.key_images[key as usize] {
+            Some(image_data) => self.send_image(key, image_data.clone()).map(|_| ()),
+            None => Ok(()),
+        });
 
-    /// Sets blank images to every button, changes must be flushed with `.flush()` before
-    /// they will appear on the device!
-    pub fn clear_all_button_images(&self) -> Result<(), StreamDeckError> {
-        for i in 0..self.kind.key_count() {
-            self.clear_button_image(i)?
+        // Reset turns screens back on, which blackout has to undo
+        if self.is_blacked_out() {
+            self.blackout()?;
         }
-        Ok(())
+
+        result
     }
 
-    /// Sets specified button's image, changes must be flushed with `.flush()` before
-    /// they will appear on the device!
-    pub fn set_button_image(&self, key: u8, image: DynamicImage) -> Result<(), StreamDeckError> {
-        let image_data = convert_image(self.kind, image)?;
-        self.write_image(key, &image_data)?;
-        Ok(())
+    /// Holds back writes to the device, for when its connection is about to become unusable, like while the user session is locked.
+    /// Brightness and touch point colors are only remembered, images stay staged, screen writes are queued,
+    /// and blackout and wake take effect on [resume](StreamDeck::resume)
+    pub fn suspend(&self) {
+        self.suspended.store(true, Ordering::Release);
     }
 
-    /// Sets specified touch point's led strip color
-    pub fn set_touchpoint_color(&self, point: u8, red: u8, green: u8, blue: u8) -> Result<(), StreamDeckError> {
-        if point >= self.kind.touchpoint_count() {
-            return Err(StreamDeckError::InvalidTouchPointIndex);
+    /// Ends [suspend](StreamDeck::suspend), applying brightness, touch point colors and key images that were last set,
+    /// then sending images staged and screen writes queued in the meantime. Fails if the connection didn't survive, in which case the device has to be connected again
+    pub fn resume(&self) -> Result<(), StreamDeckError> {
+        if !self.suspended.swap(false, Ordering::AcqRel) {
+            return Ok(());
         }
 
-        let mut buf = vec![0x03, 0x06];
+        self.restore_after_suspend(self.reset_pending.swap(false, Ordering::AcqRel))
+    }
 
-        let touchpoint_index: u8 = point + self.kind.key_count();
-        buf.extend(vec![touchpoint_index]);
-        buf.extend(vec![red, green, blue]);
+    /// Ends [suspend](StreamDeck::suspend) like [resume](StreamDeck::resume), but resets the device first,
+    /// for when the device lost its state while suspended, like during system sleep
+    pub fn resume_with_reset(&self) -> Result<(), StreamDeckError> {
+        self.suspended.store(false, Ordering::Release);
+        self.reset_pending.store(false, Ordering::Release);
 
-        Ok(send_feature_report(&self.device, buf.as_slice())?)
+        self.restore_after_suspend(true)
     }
 
-    /// Flushes the button's image to the device
-    pub fn flush(&self) -> Result<(), StreamDeckError> {
-        if self.image_cache.read()?.is_empty() {
-            return Ok(());
+    /// Restores state that was last set, except for key images that were staged while suspended,
+    /// then sends the staged images and queued screen writes
+    fn restore_after_suspend(&self, reset: bool) -> Result<(), StreamDeckError> {
+        let mut state = self.applied_state.read()?.clone();
+
+        if reset {
+            self.reset()?;
         }
 
+        self.render_stale_keys()?;
+
+        // Keys with staged images would be sent twice otherwise
         for image in self.image_cache.read()?.iter() {
-            self.send_image(image.key, &image.image_data)?;
+            if let Some(image_data) = state.key_images.get_mut(image.key as usize) {
+                *image_data = None;
+            }
         }
 
-        self.image_cache.write()?.clear();
+        self.restore_state(&state)?;
+        self.flush()?;
+
+        let screen_writes = std::mem::take(&mut *self.suspended_screen_writes.lock()?);
+
+        for (region, image_data) in screen_writes {
+            self.write_screen_data(region, &image_data)?;
+        }
 
         Ok(())
     }
 
-    /// Returns button state reader for this device
-    pub fn get_reader(self: &Arc<Self>) -> Arc<DeviceStateReader> {
-        #[allow(clippy::arc_with_non_send_sync)]
-        Arc::new(DeviceStateReader {
-            device: self.clone(),
-            states: Mutex::new(DeviceState {
-                buttons: vec![false; self.kind.key_count() as usize + self.kind.touchpoint_count() as usize],
-                encoders: vec![false; self.kind.encoder_count() as usize],
-            }),
-        })
+    /// Returns true if writes are held back by [suspend](StreamDeck::suspend)
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.load(Ordering::Acquire)
     }
 
-    fn write_image_data_reports<T>(&self, image_data: &[u8], parameters: WriteImageParameters, header_fn: T) -> Result<(), StreamDeckError>
-    where
-        T: Fn(usize, usize, bool) -> Vec<u8>,
-    {
-        let image_report_length = parameters.image_report_length;
-        let image_report_payload_length = parameters.image_report_payload_length;
+    /// Sets brightness of the device, value range is 0 - 100.
+    /// During [blackout](StreamDeck::blackout) the brightness is only remembered and gets applied on wake.
+    ///
+    /// With [brightness compensation](StreamDeck::set_brightness_compensation) on, key images are compensated for the new brightness
+    /// and staged, changes must be flushed with `.flush()` before they will appear on the device!
+    /// During blackout or [suspend](StreamDeck::suspend) keys are only compensated once on wake or resume
+    pub fn set_brightness(&self, percent: u8) -> Result<(), StreamDeckError> {
+        let percent = percent.clamp(0, 100);
+        let hidden = self.is_blacked_out() || self.is_suspended();
 
-        let mut page_number = 0;
-        let mut bytes_remaining = image_data.len();
+        if !hidden {
+            send_feature_report(self.device.as_ref(), &protocol::brightness_command(self.kind, percent))?;
+        }
 
-        while bytes_remaining > 0 {
-            let this_length = bytes_remaining.min(image_report_payload_length);
-            let bytes_sent = page_number * image_report_payload_length;
+        let previous = self.applied_state.write()?.brightness.replace(percent);
 
-            // Selecting header based on device
-            let mut buf: Vec<u8> = header_fn(page_number, this_length, this_length == bytes_remaining);
+        if self.brightness_compensation() && previous != Some(percent) {
+            if hidden {
+                self.compensation_stale.store(true, Ordering::Release);
+            } else {
+                self.render_all_keys()?;
+            }
+        }
 
-            buf.extend(&image_data[bytes_sent..bytes_sent + this_length]);
+        Ok(())
+    }
 
-            // Adding padding
-            buf.extend(vec![0u8; image_report_length - buf.len()]);
+    /// Compensates key images again if brightness changed while they couldn't be seen
+    fn render_stale_keys(&self) -> Result<(), StreamDeckError> {
+        if self.compensation_stale.swap(false, Ordering::AcqRel) && self.brightness_compensation() {
+            self.render_all_keys()?;
+        }
 
-            write_data(&self.device, &buf)?;
+        Ok(())
+    }
 
-            bytes_remaining -= this_length;
-            page_number += 1;
+    /// Adjusts key images to brightness, lifting dark tones at low brightness so dark icons stay legible, see [compensate_brightness].
+    /// Keys are staged again with the new setting, and again whenever brightness changes, changes must be flushed with `.flush()`
+    /// before they will appear on the device!
+    pub fn set_brightness_compensation(&self, enabled: bool) -> Result<(), StreamDeckError> {
+        if self.brightness_compensation.swap(enabled, Ordering::AcqRel) != enabled {
+            self.render_all_keys()?;
         }
 
         Ok(())
     }
-}
-
-#[derive(Clone, Copy)]
-struct WriteImageParameters {
-    pub image_report_length: usize,
-    pub image_report_payload_length: usize,
-}
 
-impl WriteImageParameters {
-    pub fn for_key(kind: Kind, image_data_len: usize) -> Self {
-        let image_report_length = match kind {
-            Kind::Original => 8191,
-            _ => 1024,
-        };
+    /// Returns true if key images are adjusted to brightness
+    pub fn brightness_compensation(&self) -> bool {
+        self.brightness_compensation.load(Ordering::Acquire)
+    }
 
-        let image_report_header_length = match kind {
-            Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => 16,
-            _ => 8,
-        };
+    /// Turns screens and touch point LEDs off for presentation blackout, while input keeps being read.
+    /// [DeviceStateReader] wakes the device on the first press and swallows it, so the tap doesn't trigger an action.
+    /// Brightness and touch point colors set during blackout are applied on wake.
+    /// While [suspended](StreamDeck::suspend), the device is blacked out on resume
+    pub fn blackout(&self) -> Result<(), StreamDeckError> {
+        // Resume blacks the device out again after restoring its state
+        if self.is_suspended() {
+            self.blacked_out.store(true, Ordering::Release);
+            return Ok(());
+        }
 
-        let image_report_payload_length = match kind {
-            Kind::Original => image_data_len / 2,
-            _ => image_report_length - image_report_header_length,
-        };
+        // Device that didn't go dark isn't blacked out, so the next press isn't swallowed
+        send_feature_report(self.device.as_ref(), &protocol::brightness_command(self.kind, 0))?;
+        self.blacked_out.store(true, Ordering::Release);
 
-        Self {
-            image_report_length,
-            image_report_payload_length,
+        for point in 0..self.kind.touchpoint_count() {
+            if let Some(buf) = protocol::touchpoint_color_command(self.kind, point, 0, 0, 0) {
+                send_feature_report(self.device.as_ref(), &buf)?;
+            }
         }
+
+        Ok(())
     }
-}
 
-/// Errors that can occur while working with Stream Decks
-#[derive(Debug)]
-pub enum StreamDeckError {
-    /// HidApi error
-    HidError(HidError),
+    /// Ends [blackout](StreamDeck::blackout), restoring brightness and touch point colors that were last set,
+    /// or full brightness if it was never set. Key images compensated for brightness set during blackout are staged,
+    /// and must be flushed with `.flush()` before they will appear on the device!
+    pub fn wake(&self) -> Result<(), StreamDeckError> {
+        if !self.blacked_out.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
 
-    /// Failed to convert bytes into string
-    Utf8Error(Utf8Error),
+        let state = self.applied_state.read()?.clone();
+        let brightness = state.brightness.unwrap_or(100);
+
+        if self.is_suspended() {
+            // Resume applies the brightness along with the rest of the state
+            self.applied_state.write()?.brightness = Some(brightness);
+        } else {
+            send_feature_report(self.device.as_ref(), &protocol::brightness_command(self.kind, brightness))?;
+        }
+
+        for (point, color) in state.touchpoint_colors.iter().enumerate() {
+            if let Some((red, green, blue)) = color {
+                self.set_touchpoint_color(point as u8, *red, *green, *blue)?;
+            }
+        }
+
+        self.render_stale_keys()
+    }
+
+    /// Returns true if the device is in [blackout](StreamDeck::blackout)
+    pub fn is_blacked_out(&self) -> bool {
+        self.blacked_out.load(Ordering::Acquire)
+    }
+
+    /// Sends image to the key, returns false if the upload was canceled
+    fn send_image(&self, key: u8, image_data: Arc<[u8]>) -> Result<bool, StreamDeckError> {
+        if key >= self.kind.key_count() {
+            return Err(StreamDeckError::InvalidKeyIndex);
+        }
+
+        if !self.kind.is_visual() {
+            return Err(StreamDeckError::NoScreen);
+        }
+
+        let completed = self.write_cancelable_image_data_reports(
+            &image_data,
+            ImageReportParameters::for_key(self.kind, image_data.len()),
+            Some(key),
+            |page_number, this_length, last_package| protocol::key_image_header(self.kind, self.device_key(key), page_number, this_length, last_package),
+        )?;
+
+        if !completed {
+            return Ok(false);
+        }
+
+        self.applied_state.write()?.key_images[key as usize] = Some(image_data);
+
+        Ok(true)
+    }
+
+    /// Writes image data to Stream Deck device, changes must be flushed with `.flush()` before
+    /// they will appear on the device!
+    ///
+    /// Image data passed as `Arc<[u8]>` is staged without copying, so the same encoded image can be put on many keys cheaply
+    pub fn write_image(&self, key: impl Into<KeyIndex>, image_data: impl Into<Arc<[u8]>>) -> Result<(), StreamDeckError> {
+        let key = key.into().0;
+
+        if let Some(source) = self.key_sources.write()?.get_mut(key as usize) {
+            source.image = None;
+            source.asset = None;
+        }
+
+        self.stage_image(key, image_data.into())
+    }
+
+    fn stage_image(&self, key: u8, image_data: Arc<[u8]>) -> Result<(), StreamDeckError> {
+        if key >= self.kind.key_count() {
+            return Err(StreamDeckError::InvalidKeyIndex);
+        }
+
+        if !self.kind.is_visual() {
+            return Err(StreamDeckError::NoScreen);
+        }
+
+        protocol::PagingStrategy::for_key(self.kind).validate(image_data.len())?;
+
+        let cache_entry = ImageCache { key, image_data };
+
+        // Newer image supersedes the one that is still waiting to be sent
+        let mut image_cache = self.image_cache.write()?;
+        image_cache.retain(|image| image.key != key);
+        image_cache.push(cache_entry);
+
+        self.upload_canceller.reset(key);
+        drop(image_cache);
+
+        self.apply_flush_strategy()
+    }
+
+    /// Sends staged images if the flush strategy says it's time to
+    fn apply_flush_strategy(&self) -> Result<(), StreamDeckError> {
+        #[cfg(feature = "async")]
+        if let Some(staged) = &self.deferred_flush {
+            staged.store(true, Ordering::Release);
+            return Ok(());
+        }
+
+        if self.flush_due()? { self.flush() } else { Ok(()) }
+    }
+
+    /// Tells if the flush strategy says staged images should be sent now
+    fn flush_due(&self) -> Result<bool, StreamDeckError> {
+        Ok(match self.options.flush_strategy {
+            FlushStrategy::Manual | FlushStrategy::Ticker(_) => false,
+            FlushStrategy::Immediate => true,
+            FlushStrategy::Interval(interval) => self.last_flush.read()?.elapsed() >= interval,
+        })
+    }
+
+    /// Leaves the flush strategy to the async wrapper, so it can flush without holding the device locked
+    #[cfg(feature = "async")]
+    pub(crate) fn defer_flush(&mut self) {
+        self.deferred_flush = Some(AtomicBool::new(false));
+    }
+
+    /// Tells if writes staged images since the last call and the flush strategy says they should be sent now, see [defer_flush](StreamDeck::defer_flush)
+    #[cfg(feature = "async")]
+    pub(crate) fn take_deferred_flush(&self) -> Result<bool, StreamDeckError> {
+        match &self.deferred_flush {
+            Some(staged) if staged.swap(false, Ordering::AcqRel) => self.flush_due(),
+            _ => Ok(false),
+        }
+    }
+
+    /// Renders every key that has a source image again
+    fn render_all_keys(&self) -> Result<(), StreamDeckError> {
+        self.for_each_key(0..self.kind.key_count(), |key| self.render_key(key))
+    }
+
+    /// Composites effects on top of key's source image and stages the result
+    fn render_key(&self, key: u8) -> Result<(), StreamDeckError> {
+        let source = self.key_sources.read()?.get(key as usize).cloned().ok_or(StreamDeckError::InvalidKeyIndex)?;
+
+        let Some(image) = source.image else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "widgets")]
+        let image = match &source.badge {
+            Some(badge) => draw_badge(&image, badge),
+            None => image,
+        };
+
+        let image = if source.dim < 1.0 { dim_image(&image, source.dim) } else { image };
+
+        let image = match self.applied_state.read()?.brightness {
+            Some(brightness) if brightness < 100 && self.brightness_compensation() => compensate_brightness(&image, brightness),
+            _ => image,
+        };
+
+        let image_data = convert_image_with_filter(self.kind.key_image_format(), image, self.options.resize_filter)?;
+        self.stage_image(key, image_data.into())
+    }
+
+    /// Writes image data to the screen of the device, see [Kind::screens] for screens the device has.
+    ///
+    /// Region is filled if it's specified, otherwise the whole screen is, image data must be encoded with the screen's format and match the size.
+    /// Fails with [StreamDeckError::UnsupportedOperation] if the screen only supports full fills and region doesn't cover all of it
+    pub fn write_screen(&self, screen_id: u8, region: Option<ScreenSegment>, image_data: &[u8]) -> Result<(), StreamDeckError> {
+        let screen = self.kind.screen(screen_id).ok_or(StreamDeckError::InvalidScreenId)?;
+
+        let full = ScreenSegment {
+            x: 0,
+            y: 0,
+            w: screen.resolution.0,
+            h: screen.resolution.1,
+        };
+
+        let region = region.unwrap_or(full);
+
+        if region.w == 0 || region.h == 0 || region.x + region.w > full.w || region.y + region.h > full.h {
+            return Err(StreamDeckError::InvalidScreenRegion);
+        }
+
+        if region == full || !screen.writes_regions {
+            if region != full {
+                return Err(StreamDeckError::UnsupportedOperation);
+            }
+
+            return self.write_screen_data(None, image_data);
+        }
+
+        self.write_screen_data(Some((region.x as u16, region.y as u16, region.w as u16, region.h as u16)), image_data)
+    }
+
+    /// Writes image data to Stream Deck device's lcd strip/screen as region.
+    /// Only Stream Deck Plus supports writing LCD regions, for Stream Deck Neo use write_lcd_fill.
+    /// Unlike [write_screen](StreamDeck::write_screen), region isn't checked to be within the screen
+    #[deprecated(note = "use write_screen")]
+    pub fn write_lcd(&self, x: u16, y: u16, rect: &ImageRect) -> Result<(), StreamDeckError> {
+        match self.kind {
+            Kind::Plus | Kind::PlusXl => (),
+            _ => return Err(StreamDeckError::UnsupportedOperation),
+        }
+
+        self.write_screen_data(Some((x, y, rect.w, rect.h)), rect.data.as_slice())
+    }
+
+    /// Writes image data to the LCD as (x, y, w, h) region, or as a full fill if region is None.
+    /// While [suspended](StreamDeck::suspend), the write is queued instead
+    fn write_screen_data(&self, region: Option<(u16, u16, u16, u16)>, image_data: &[u8]) -> Result<(), StreamDeckError> {
+        let parameters = ImageReportParameters::for_lcd(self.kind).ok_or(StreamDeckError::UnsupportedOperation)?;
+
+        if self.is_suspended() {
+            let mut writes = self.suspended_screen_writes.lock()?;
+
+            // Fill covers everything that was written before it
+            if region.is_none() {
+                writes.clear();
+            }
+
+            writes.push((region, image_data.to_vec()));
+            return Ok(());
+        }
+
+        match region {
+            Some((x, y, w, h)) => self.write_image_data_reports(image_data, parameters, |page_number, this_length, last_package| {
+                protocol::lcd_region_header(x, y, w, h, page_number, this_length, last_package)
+            }),
+
+            None => self.write_image_data_reports(image_data, parameters, |page_number, this_length, last_package| {
+                protocol::lcd_fill_header(self.kind, page_number, this_length, last_package).unwrap_or_default()
+            }),
+        }
+    }
+
+    /// Writes image data to Stream Deck device's lcd strip/screen as full fill
+    ///
+    /// You can convert your images into proper image_data like this:
+    /// ```
+    /// # fn example(device: &elgato_streamdeck::StreamDeck, image: image::DynamicImage) {
+    /// use elgato_streamdeck::images::convert_image_with_format;
+    /// let image_data = convert_image_with_format(device.kind().lcd_image_format().unwrap(), image).unwrap();
+    /// device.write_lcd_fill(&image_data).unwrap();
+    /// # }
+    /// ```
+    pub fn write_lcd_fill(&self, image_data: &[u8]) -> Result<(), StreamDeckError> {
+        match self.write_screen(Screen::LCD_STRIP, None, image_data) {
+            Err(StreamDeckError::InvalidScreenId) => Err(StreamDeckError::UnsupportedOperation),
+            result => result,
+        }
+    }
+
+    /// Sets button's image to blank right away, dropping the key's staged image.
+    /// While [suspended](StreamDeck::suspend), the blank image is staged and sent on resume instead
+    pub fn clear_button_image(&self, key: impl Into<KeyIndex>) -> Result<(), StreamDeckError> {
+        let key = key.into().0;
+
+        if let Some(source) = self.key_sources.write()?.get_mut(key as usize) {
+            source.image = None;
+            source.asset = None;
+        }
+
+        if self.is_suspended() {
+            return self.stage_image(key, self.kind.blank_image().into());
+        }
+
+        // Staged image would bring back what was just cleared on the next flush
+        self.image_cache.write()?.retain(|image| image.key != key);
+
+        self.upload_canceller.reset(key);
+        self.send_image(key, self.kind.blank_image().into())?;
+
+        Ok(())
+    }
+
+    /// Sets blank images to every button right away, see [clear_button_image](StreamDeck::clear_button_image).
+    ///
+    /// Keys are cleared even if some fail, failed keys are listed in [StreamDeckError::KeyErrors]
+    pub fn clear_all_button_images(&self) -> Result<(), StreamDeckError> {
+        self.for_each_key(0..self.kind.key_count(), |key| self.clear_button_image(key))
+    }
+
+    #[cfg(feature = "widgets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+    /// Shows [test pattern](test_pattern::key_test_pattern) with index numbers, gradients and alignment crosses on every key and screen,
+    /// for bringing up new devices and checking image orientation. Screens are written right away,
+    /// key images must be flushed with `.flush()` before they will appear on the device!
+    pub fn show_test_pattern(&self) -> Result<(), StreamDeckError> {
+        let size = self.kind.key_image_format().size;
+        self.for_each_key(0..self.kind.key_count(), |key| self.set_button_image(key, test_pattern::key_test_pattern(key, size)))?;
+
+        for screen in self.kind.screens() {
+            let image = test_pattern::screen_test_pattern(&screen);
+            self.write_screen(screen.id, None, &convert_image_with_format(screen.format, image)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the operation on every key even if some fail, collecting errors of the failed ones into [StreamDeckError::KeyErrors]
+    pub(crate) fn for_each_key(&self, keys: impl IntoIterator<Item = u8>, mut operation: impl FnMut(u8) -> Result<(), StreamDeckError>) -> Result<(), StreamDeckError> {
+        let errors = keys.into_iter().filter_map(|key| operation(key).err().map(|error| (KeyIndex(key), error))).collect::<Vec<_>>();
+
+        if errors.is_empty() { Ok(()) } else { Err(StreamDeckError::KeyErrors(errors)) }
+    }
+
+    /// Sets specified button's image, changes must be flushed with `.flush()` before
+    /// they will appear on the device!
+    pub fn set_button_image(&self, key: impl Into<KeyIndex>, image: DynamicImage) -> Result<(), StreamDeckError> {
+        let key = key.into().0;
+
+        // Keys never show more than their resolution, so large source images aren't kept around
+        let image = fit_image(self.kind.key_image_format(), image, self.options.resize_filter);
+
+        {
+            let mut sources = self.key_sources.write()?;
+            let source = sources.get_mut(key as usize).ok_or(StreamDeckError::InvalidKeyIndex)?;
+            source.image = Some(image);
+            source.asset = None;
+        }
+
+        self.render_key(key)
+    }
+
+    #[cfg(feature = "async")]
+    /// Sets button's image that was already fitted with [fit_image] and converted, so the async wrapper can convert before locking the device.
+    /// Converted image data is staged if the key has no dim or badge, otherwise the key is rendered again
+    pub(crate) fn set_button_image_converted(&self, key: u8, image: DynamicImage, image_data: Arc<[u8]>) -> Result<(), StreamDeckError> {
+        let plain = {
+            let mut sources = self.key_sources.write()?;
+            let source = sources.get_mut(key as usize).ok_or(StreamDeckError::InvalidKeyIndex)?;
+            source.image = Some(image);
+            source.asset = None;
+            source.is_plain()
+        };
+
+        if plain && !self.is_compensating()? {
+            self.stage_image(key, image_data)
+        } else {
+            self.render_key(key)
+        }
+    }
+
+    #[cfg(any(feature = "assets", feature = "async"))]
+    /// Tells if key images are currently adjusted to brightness
+    fn is_compensating(&self) -> Result<bool, StreamDeckError> {
+        Ok(matches!(self.applied_state.read()?.brightness, Some(brightness) if brightness < 100 && self.brightness_compensation()))
+    }
+
+    #[cfg(feature = "assets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "assets")))]
+    /// Sets specified button's image to the asset registered under the name in [ConnectOptions::assets].
+    /// Image data converted for the kind is reused when the key has no dim or badge, so repeated use doesn't convert again.
+    /// Fails with [StreamDeckError::UnknownAsset] if there's no such asset. Changes must be flushed with `.flush()` before
+    /// they will appear on the device!
+    pub fn set_button_asset(&self, key: impl Into<KeyIndex>, name: &str) -> Result<(), StreamDeckError> {
+        let key = key.into().0;
+        let assets = self.options.assets.as_ref().ok_or(StreamDeckError::UnknownAsset)?;
+        let image = assets.get(name)?.ok_or(StreamDeckError::UnknownAsset)?;
+
+        // Source is kept, so dimming or badges applied later still have the image to work with
+        let plain = {
+            let mut sources = self.key_sources.write()?;
+            let source = sources.get_mut(key as usize).ok_or(StreamDeckError::InvalidKeyIndex)?;
+            source.image = Some(image);
+            source.asset = Some(name.to_string());
+            source.is_plain()
+        };
+
+        if plain && !self.is_compensating()? {
+            self.stage_image(key, assets.converted(name, self.kind, self.options.resize_filter)?)
+        } else {
+            self.render_key(key)
+        }
+    }
+
+    #[cfg(feature = "assets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "assets")))]
+    /// Sets keys showing any of the assets again, so they pick up images registered under the names since.
+    /// Keys whose asset is no longer registered keep their image. Returns how many keys were set,
+    /// changes must be flushed with `.flush()` before they will appear on the device!
+    pub fn refresh_assets(&self, names: &[String]) -> Result<usize, StreamDeckError> {
+        let Some(assets) = &self.options.assets else {
+            return Ok(0);
+        };
+
+        let bound = self
+            .key_sources
+            .read()?
+            .iter()
+            .enumerate()
+            .filter_map(|(key, source)| Some((key as u8, source.asset.clone()?)))
+            .filter(|(_, asset)| names.contains(asset))
+            .collect::<Vec<_>>();
+
+        let mut refreshed = 0;
+
+        for (key, asset) in bound {
+            if assets.contains(&asset)? {
+                self.set_button_asset(key, &asset)?;
+                refreshed += 1;
+            }
+        }
+
+        Ok(refreshed)
+    }
+
+    /// Sets specified button's image from raw pixels, changes must be flushed with `.flush()` before
+    /// they will appear on the device!
+    pub fn set_button_image_raw(&self, key: impl Into<KeyIndex>, image: RawImage) -> Result<(), StreamDeckError> {
+        let key = key.into().0;
+
+        self.set_button_image(key, image.into())
+    }
+
+    /// Sets specified button's image from the file, see [load_image](images::load_image) for supported formats.
+    /// Changes must be flushed with `.flush()` before they will appear on the device!
+    pub fn set_button_image_from_path(&self, key: impl Into<KeyIndex>, path: impl AsRef<Path>) -> Result<(), StreamDeckError> {
+        let key = key.into().0;
+
+        self.set_button_image(key, images::load_image(path)?)
+    }
+
+    /// Dims button's image by multiplying its colors with the factor, value range is 0.0 - 1.0.
+    /// Image set with [set_button_image](StreamDeck::set_button_image) is reused, so it doesn't have to be supplied again.
+    /// Changes must be flushed with `.flush()` before they will appear on the device!
+    pub fn set_key_dim(&self, key: impl Into<KeyIndex>, factor: f32) -> Result<(), StreamDeckError> {
+        let key = key.into().0;
+
+        self.key_sources.write()?.get_mut(key as usize).ok_or(StreamDeckError::InvalidKeyIndex)?.dim = factor.clamp(0.0, 1.0);
+        self.render_key(key)
+    }
+
+    #[cfg(feature = "widgets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+    /// Draws a badge in a corner of button's image, replacing previous badge.
+    /// Image set with [set_button_image](StreamDeck::set_button_image) is reused, so it doesn't have to be supplied again.
+    /// Changes must be flushed with `.flush()` before they will appear on the device!
+    pub fn set_key_badge(&self, key: impl Into<KeyIndex>, badge: Badge) -> Result<(), StreamDeckError> {
+        let key = key.into().0;
+
+        self.key_sources.write()?.get_mut(key as usize).ok_or(StreamDeckError::InvalidKeyIndex)?.badge = Some(badge);
+        self.render_key(key)
+    }
+
+    #[cfg(feature = "widgets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+    /// Removes badge from button's image, changes must be flushed with `.flush()` before
+    /// they will appear on the device!
+    pub fn clear_key_badge(&self, key: impl Into<KeyIndex>) -> Result<(), StreamDeckError> {
+        let key = key.into().0;
+
+        self.key_sources.write()?.get_mut(key as usize).ok_or(StreamDeckError::InvalidKeyIndex)?.badge = None;
+        self.render_key(key)
+    }
+
+    /// Sets specified touch point's led strip color, during [blackout](StreamDeck::blackout) the color is only remembered and gets applied on wake
+    pub fn set_touchpoint_color(&self, point: impl Into<TouchPointIndex>, red: u8, green: u8, blue: u8) -> Result<(), StreamDeckError> {
+        let point = point.into().0;
+
+        let buf = protocol::touchpoint_color_command(self.kind, point, red, green, blue).ok_or(StreamDeckError::InvalidTouchPointIndex)?;
+
+        if !self.is_blacked_out() && !self.is_suspended() {
+            send_feature_report(self.device.as_ref(), &buf)?;
+        }
+
+        self.applied_state.write()?.touchpoint_colors[point as usize] = Some((red, green, blue));
+
+        Ok(())
+    }
+
+    /// Sets colors of multiple touch points' led strips as (point, (red, green, blue)), so indicators like Neo page dots change together.
+    /// All indices are checked before anything is sent, and only touch points whose color changed are sent, the last color wins for repeated points
+    pub fn set_touchpoint_colors(&self, colors: &[(u8, (u8, u8, u8))]) -> Result<(), StreamDeckError> {
+        let mut pending = vec![None; self.kind.touchpoint_count() as usize];
+
+        for &(point, color) in colors {
+            *pending.get_mut(point as usize).ok_or(StreamDeckError::InvalidTouchPointIndex)? = Some(color);
+        }
+
+        let applied = self.applied_state.read()?.touchpoint_colors.clone();
+
+        for (point, color) in pending.into_iter().enumerate() {
+            if let Some((red, green, blue)) = color
+                && applied[point] != color
+            {
+                self.set_touchpoint_color(point as u8, red, green, blue)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Turns off led strips of all touch points
+    pub fn clear_touchpoints(&self) -> Result<(), StreamDeckError> {
+        let colors = (0..self.kind.touchpoint_count()).map(|point| (point, (0, 0, 0))).collect::<Vec<_>>();
+        self.set_touchpoint_colors(&colors)
+    }
+
+    #[cfg(feature = "widgets")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "widgets")))]
+    /// Sets specified touch point's led strip to a [status color](palette::StatusColor), which stays distinguishable with color vision deficiencies
+    pub fn set_touchpoint_status(&self, point: impl Into<TouchPointIndex>, status: palette::StatusColor) -> Result<(), StreamDeckError> {
+        let point = point.into().0;
+
+        let (red, green, blue) = status.rgb();
+        self.set_touchpoint_color(point, red, green, blue)
+    }
+
+    /// Sets LED color of the touch key, fails with [StreamDeckError::UnsupportedOperation] if the device's touch keys have no LEDs
+    pub fn set_touch_key_color(&self, key: TouchKey, red: u8, green: u8, blue: u8) -> Result<(), StreamDeckError> {
+        if !self.kind.has_touch_key_leds() {
+            return Err(StreamDeckError::UnsupportedOperation);
+        }
+
+        self.set_touchpoint_color(key.point(self.kind).ok_or(StreamDeckError::InvalidTouchPointIndex)?, red, green, blue)
+    }
+
+    /// Flushes the button's image to the device
+    pub fn flush(&self) -> Result<(), StreamDeckError> {
+        while self.flush_next()? {}
+
+        Ok(())
+    }
+
+    /// Sends only the oldest staged image to the device, returns false if there was nothing left to send.
+    /// Allows other commands to be sent in between images of a long flush
+    pub fn flush_next(&self) -> Result<bool, StreamDeckError> {
+        if self.is_suspended() {
+            return Ok(false);
+        }
+
+        let Some((key, image_data)) = self.image_cache.read()?.first().map(|image| (image.key, image.image_data.clone())) else {
+            *self.last_flush.write()? = Instant::now();
+            return Ok(false);
+        };
+
+        if !self.send_image(key, image_data)? {
+            self.upload_canceller.reset(key);
+        }
+
+        // Staging requires the device, so the image that was sent is still first
+        self.image_cache.write()?.remove(0);
+
+        Ok(true)
+    }
+
+    /// Index the device uses for the physical key, see [ConnectOptions::key_remap]
+    fn device_key(&self, key: u8) -> u8 {
+        #[cfg(feature = "tools")]
+        if let Some(remap) = &self.options.key_remap {
+            return remap.reported(key).unwrap_or(key);
+        }
+
+        key
+    }
+
+    /// Returns button state reader for this device
+    pub fn get_reader(self: &Arc<Self>) -> Arc<DeviceStateReader> {
+        let mut differ = InputDiffer::new(self.kind);
+        differ.set_held_input(self.options.held_input);
+        #[cfg(feature = "tools")]
+        differ.set_key_remap(self.options.key_remap.clone());
+
+        #[allow(clippy::arc_with_non_send_sync)]
+        Arc::new(DeviceStateReader {
+            device: self.clone(),
+            states: Mutex::new(differ),
+            reports_read: AtomicU64::new(0),
+            updates_emitted: AtomicU64::new(0),
+        })
+    }
+
+    /// Emits rate limited diagnostic of the failed operation through tracing
+    #[cfg(feature = "tracing")]
+    fn diagnose<T>(&self, operation: &'static str, result: Result<T, StreamDeckError>, report: &[u8]) -> Result<T, StreamDeckError> {
+        if let Err(error) = &result {
+            self.diagnostics.record(self, operation, error, report);
+        }
+
+        result
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    fn diagnose<T>(&self, _operation: &'static str, result: Result<T, StreamDeckError>, _report: &[u8]) -> Result<T, StreamDeckError> {
+        result
+    }
+
+    fn write_image_data_reports<T>(&self, image_data: &[u8], parameters: ImageReportParameters, header_fn: T) -> Result<(), StreamDeckError>
+    where
+        T: Fn(usize, usize, bool) -> Vec<u8>,
+    {
+        self.write_cancelable_image_data_reports(image_data, parameters, None, header_fn)?;
+        Ok(())
+    }
+
+    /// Writes image data reports, stopping before the next page if upload of the key was canceled.
+    /// Returns false if the upload was canceled
+    fn write_cancelable_image_data_reports<T>(&self, image_data: &[u8], parameters: ImageReportParameters, key: Option<u8>, header_fn: T) -> Result<bool, StreamDeckError>
+    where
+        T: Fn(usize, usize, bool) -> Vec<u8>,
+    {
+        let deadline = self.options.write_timeout.map(|timeout| Instant::now() + timeout);
+        let timed_out = || deadline.is_some_and(|deadline| Instant::now() > deadline);
+
+        for buf in protocol::image_reports(image_data, parameters, header_fn) {
+            if key.is_some_and(|key| self.upload_canceller.is_canceled(key)) {
+                return Ok(false);
+            }
+
+            // Checked before writing, so a transfer whose last report was written is never reported as failed
+            if timed_out() {
+                return Err(StreamDeckError::Timeout);
+            }
+
+            let result = write_data(self.device.as_ref(), &buf).map_err(|error| if timed_out() { StreamDeckError::Timeout } else { StreamDeckError::from(error) });
+            self.diagnose("write image", result, &buf)?;
+        }
+
+        Ok(true)
+    }
+}
+
+/// Errors that can occur while working with Stream Decks
+#[derive(Debug)]
+pub enum StreamDeckError {
+    /// HidApi error
+    HidError(HidError),
+
+    /// Failed to convert bytes into string
+    Utf8Error(Utf8Error),
 
     /// Failed to encode image
     ImageError(ImageError),
 
+    /// Failed to read or write a file
+    IoError(std::io::Error),
+
+    #[cfg(any(feature = "serde", feature = "settings", feature = "openaction", feature = "layout"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "serde", feature = "settings", feature = "openaction", feature = "layout"))))]
+    /// Failed to serialize or deserialize settings, manifests, layouts or fingerprints
+    SerdeError(serde_json::Error),
+
+    #[cfg(feature = "simulator")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "simulator")))]
+    /// Simulator window couldn't be opened or drawn
+    SimulatorError(String),
+
+    #[cfg(feature = "url")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url")))]
+    /// Failed to fetch an image from a URL
+    HttpError(Box<ureq::Error>),
+
+    #[cfg(feature = "hot-reload")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reload")))]
+    /// Watching asset files failed
+    WatchError(notify::Error),
+
+    #[cfg(feature = "layout")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "layout")))]
+    /// Layout couldn't be parsed or refers to something that doesn't exist
+    InvalidLayout(String),
+
     #[cfg(feature = "async")]
     #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
     /// Tokio join error
     JoinError(tokio::task::JoinError),
 
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    /// Flush that was shared with other tasks failed
+    SharedFlushError(Arc<StreamDeckError>),
+
     /// Reader mutex was poisoned
     PoisonError,
 
@@ -582,6 +1690,85 @@ pub enum StreamDeckError {
 
     /// Stream Deck sent unexpected data
     BadData,
+
+    /// Device didn't finish the operation in time
+    Timeout,
+
+    /// Font data couldn't be parsed
+    InvalidFont,
+
+    /// Device doesn't have a screen with the id
+    InvalidScreenId,
+
+    /// Region doesn't fit within the screen
+    InvalidScreenRegion,
+
+    /// Image data doesn't have the length the device expects, see [PagingStrategy](protocol::PagingStrategy)
+    InvalidImageLength,
+
+    /// Operation on multiple keys failed for some of them, keys that aren't listed succeeded
+    KeyErrors(Vec<(KeyIndex, StreamDeckError)>),
+
+    /// No image is registered under the name, or the device has no asset registry
+    UnknownAsset,
+
+    /// Pixel data doesn't match the width and height of the image
+    InvalidImageSize,
+
+    /// No connected device matches what was asked for
+    DeviceNotFound,
+}
+
+impl StreamDeckError {
+    /// Numeric code of the error, which stays the same across versions so FFI consumers, logs and scripts can match errors by it.
+    /// Codes of errors that get removed are never reused
+    pub fn code(&self) -> u32 {
+        self.code_and_name().0
+    }
+
+    /// Name of the error's code, like `INVALID_KEY_INDEX`, which stays the same across versions
+    pub fn code_name(&self) -> &'static str {
+        self.code_and_name().1
+    }
+
+    fn code_and_name(&self) -> (u32, &'static str) {
+        match self {
+            StreamDeckError::HidError(_) => (1, "HID_ERROR"),
+            StreamDeckError::Utf8Error(_) => (2, "UTF8_ERROR"),
+            StreamDeckError::ImageError(_) => (3, "IMAGE_ERROR"),
+            StreamDeckError::IoError(_) => (4, "IO_ERROR"),
+            #[cfg(any(feature = "serde", feature = "settings", feature = "openaction", feature = "layout"))]
+            StreamDeckError::SerdeError(_) => (5, "SERDE_ERROR"),
+            #[cfg(feature = "async")]
+            StreamDeckError::JoinError(_) => (6, "JOIN_ERROR"),
+            #[cfg(feature = "async")]
+            StreamDeckError::SharedFlushError(_) => (7, "SHARED_FLUSH_ERROR"),
+            StreamDeckError::PoisonError => (8, "POISON_ERROR"),
+            StreamDeckError::NoScreen => (9, "NO_SCREEN"),
+            StreamDeckError::InvalidKeyIndex => (10, "INVALID_KEY_INDEX"),
+            StreamDeckError::InvalidTouchPointIndex => (11, "INVALID_TOUCH_POINT_INDEX"),
+            StreamDeckError::UnrecognizedPID => (12, "UNRECOGNIZED_PID"),
+            StreamDeckError::UnsupportedOperation => (13, "UNSUPPORTED_OPERATION"),
+            StreamDeckError::BadData => (14, "BAD_DATA"),
+            StreamDeckError::Timeout => (15, "TIMEOUT"),
+            StreamDeckError::InvalidFont => (16, "INVALID_FONT"),
+            StreamDeckError::InvalidScreenId => (17, "INVALID_SCREEN_ID"),
+            StreamDeckError::InvalidScreenRegion => (18, "INVALID_SCREEN_REGION"),
+            StreamDeckError::InvalidImageLength => (19, "INVALID_IMAGE_LENGTH"),
+            StreamDeckError::KeyErrors(_) => (20, "KEY_ERRORS"),
+            #[cfg(feature = "simulator")]
+            StreamDeckError::SimulatorError(_) => (21, "SIMULATOR_ERROR"),
+            #[cfg(feature = "url")]
+            StreamDeckError::HttpError(_) => (22, "HTTP_ERROR"),
+            StreamDeckError::UnknownAsset => (23, "UNKNOWN_ASSET"),
+            #[cfg(feature = "hot-reload")]
+            StreamDeckError::WatchError(_) => (24, "WATCH_ERROR"),
+            #[cfg(feature = "layout")]
+            StreamDeckError::InvalidLayout(_) => (25, "INVALID_LAYOUT"),
+            StreamDeckError::InvalidImageSize => (26, "INVALID_IMAGE_SIZE"),
+            StreamDeckError::DeviceNotFound => (27, "DEVICE_NOT_FOUND"),
+        }
+    }
 }
 
 impl Display for StreamDeckError {
@@ -594,7 +1781,10 @@ impl Error for StreamDeckError {}
 
 impl From<HidError> for StreamDeckError {
     fn from(e: HidError) -> Self {
-        Self::HidError(e)
+        match e {
+            HidError::IoError { error } if error.kind() == std::io::ErrorKind::TimedOut => Self::Timeout,
+            e => Self::HidError(e),
+        }
     }
 }
 
@@ -604,12 +1794,48 @@ impl From<Utf8Error> for StreamDeckError {
     }
 }
 
+impl From<ProtocolError> for StreamDeckError {
+    fn from(e: ProtocolError) -> Self {
+        match e {
+            ProtocolError::BadData => StreamDeckError::BadData,
+            ProtocolError::InvalidImageLength => StreamDeckError::InvalidImageLength,
+        }
+    }
+}
+
 impl From<ImageError> for StreamDeckError {
     fn from(e: ImageError) -> Self {
         Self::ImageError(e)
     }
 }
 
+impl From<std::io::Error> for StreamDeckError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IoError(e)
+    }
+}
+
+#[cfg(any(feature = "serde", feature = "settings", feature = "openaction", feature = "layout"))]
+impl From<serde_json::Error> for StreamDeckError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::SerdeError(e)
+    }
+}
+
+#[cfg(feature = "url")]
+impl From<ureq::Error> for StreamDeckError {
+    fn from(e: ureq::Error) -> Self {
+        Self::HttpError(Box::new(e))
+    }
+}
+
+#[cfg(feature = "hot-reload")]
+impl From<notify::Error> for StreamDeckError {
+    fn from(e: notify::Error) -> Self {
+        Self::WatchError(e)
+    }
+}
+
 #[cfg(feature = "async")]
 impl From<tokio::task::JoinError> for StreamDeckError {
     fn from(e: tokio::task::JoinError) -> Self {
@@ -623,29 +1849,103 @@ impl<T> From<PoisonError<T>> for StreamDeckError {
     }
 }
 
+/// Index of a key, accepted by APIs that work with keys and carried in their updates.
+/// Converts from and into `u8`, so plain numbers can still be passed
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct KeyIndex(pub u8);
+
+impl From<u8> for KeyIndex {
+    fn from(index: u8) -> Self {
+        KeyIndex(index)
+    }
+}
+
+impl From<KeyIndex> for u8 {
+    fn from(index: KeyIndex) -> Self {
+        index.0
+    }
+}
+
+impl Display for KeyIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Index of an encoder, carried in encoder updates. Converts from and into `u8`
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct EncoderIndex(pub u8);
+
+impl From<u8> for EncoderIndex {
+    fn from(index: u8) -> Self {
+        EncoderIndex(index)
+    }
+}
+
+impl From<EncoderIndex> for u8 {
+    fn from(index: EncoderIndex) -> Self {
+        index.0
+    }
+}
+
+impl Display for EncoderIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Index of a touch point, accepted by APIs that work with touch points and carried in their updates.
+/// Converts from and into `u8`
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct TouchPointIndex(pub u8);
+
+impl From<u8> for TouchPointIndex {
+    fn from(index: u8) -> Self {
+        TouchPointIndex(index)
+    }
+}
+
+impl From<TouchPointIndex> for u8 {
+    fn from(index: TouchPointIndex) -> Self {
+        index.0
+    }
+}
+
+impl Display for TouchPointIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 /// Tells what changed in button states
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum DeviceStateUpdate {
     /// Button got pressed down
-    ButtonDown(u8),
+    ButtonDown(KeyIndex),
 
     /// Button got released
-    ButtonUp(u8),
+    ButtonUp(KeyIndex),
 
     /// Encoder got pressed down
-    EncoderDown(u8),
+    EncoderDown(EncoderIndex),
 
     /// Encoder was released from being pressed down
-    EncoderUp(u8),
+    EncoderUp(EncoderIndex),
 
     /// Encoder was twisted
-    EncoderTwist(u8, i8),
+    EncoderTwist(EncoderIndex, i8),
+
+    /// Touch Point without a dedicated name got pressed down
+    TouchPointDown(TouchPointIndex),
 
-    /// Touch Point got pressed down
-    TouchPointDown(u8),
+    /// Touch Point without a dedicated name got released
+    TouchPointUp(TouchPointIndex),
 
-    /// Touch Point got released
-    TouchPointUp(u8),
+    /// Named touch key, like the page keys of Stream Deck Neo, got pressed down
+    TouchKeyDown(TouchKey),
+
+    /// Named touch key, like the page keys of Stream Deck Neo, got released
+    TouchKeyUp(TouchKey),
 
     /// Touch screen received short press
     TouchScreenPress(u16, u16),
@@ -657,67 +1957,219 @@ pub enum DeviceStateUpdate {
     TouchScreenSwipe((u16, u16), (u16, u16)),
 }
 
-#[derive(Default)]
-struct DeviceState {
-    /// Buttons include Touch Points state
-    pub buttons: Vec<bool>,
-    pub encoders: Vec<bool>,
+impl DeviceStateUpdate {
+    /// Returns touch screen update with coordinates normalized to 0.0 - 1.0 range of the LCD strip,
+    /// so code doesn't have to hardcode resolution of the device.
+    /// None if the update is not a touch screen update or the kind has no touch screen
+    pub fn normalized_touch(&self, kind: Kind) -> Option<NormalizedTouch> {
+        match *self {
+            DeviceStateUpdate::TouchScreenPress(x, y) => {
+                let (x, y) = kind.normalize_touch_point(x, y)?;
+                Some(NormalizedTouch::Press(x, y))
+            }
+
+            DeviceStateUpdate::TouchScreenLongPress(x, y) => {
+                let (x, y) = kind.normalize_touch_point(x, y)?;
+                Some(NormalizedTouch::LongPress(x, y))
+            }
+
+            DeviceStateUpdate::TouchScreenSwipe((sx, sy), (ex, ey)) => Some(NormalizedTouch::Swipe(kind.normalize_touch_point(sx, sy)?, kind.normalize_touch_point(ex, ey)?)),
+
+            _ => None,
+        }
+    }
+
+    /// Returns button update with the position of the button as row and column of the kind's key grid,
+    /// so grid-oriented code doesn't have to divide indices by itself.
+    /// None if the update is not a button update or the kind has no such key
+    pub fn grid_button(&self, kind: Kind) -> Option<GridButton> {
+        let (index, pressed) = match *self {
+            DeviceStateUpdate::ButtonDown(index) => (index, true),
+            DeviceStateUpdate::ButtonUp(index) => (index, false),
+            _ => return None,
+        };
+
+        let (row, col) = kind.key_position(index.0)?;
+        let key = GridKey { index, row, col };
+
+        Some(if pressed { GridButton::Down(key) } else { GridButton::Up(key) })
+    }
 }
 
-/// Button reader that keeps state of the Stream Deck and returns events instead of full states
-pub struct DeviceStateReader {
-    device: Arc<StreamDeck>,
-    states: Mutex<DeviceState>,
+/// Button update with position of the button in the key grid, see [DeviceStateUpdate::grid_button]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum GridButton {
+    /// Button got pressed down
+    Down(GridKey),
+
+    /// Button got released
+    Up(GridKey),
 }
 
-impl DeviceStateReader {
-    /// Reads states and returns updates
-    pub fn read(&self, timeout: Option<Duration>) -> Result<Vec<DeviceStateUpdate>, StreamDeckError> {
-        let input = self.device.read_input(timeout)?;
-        let mut my_states = self.states.lock()?;
+/// Key identified by both its index and its position in the key grid
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct GridKey {
+    /// Flat index of the key, as used by [DeviceStateUpdate::ButtonDown] and [StreamDeck::set_button_image]
+    pub index: KeyIndex,
+    /// Row of the key, counted from the top
+    pub row: u8,
+    /// Column of the key, counted from the left
+    pub col: u8,
+}
+
+/// Touch screen update with coordinates normalized to 0.0 - 1.0 range of the LCD strip
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NormalizedTouch {
+    /// Touch screen received short press
+    Press(f32, f32),
+
+    /// Touch screen received long press
+    LongPress(f32, f32),
 
+    /// Touch screen received a swipe
+    Swipe((f32, f32), (f32, f32)),
+}
+
+/// Returns update for the touch point, using the touch key's name if it has one
+fn touch_point_update(kind: Kind, point: u8, pressed: bool) -> DeviceStateUpdate {
+    match (kind.touch_key(point), pressed) {
+        (Some(key), true) => DeviceStateUpdate::TouchKeyDown(key),
+        (Some(key), false) => DeviceStateUpdate::TouchKeyUp(key),
+        (None, true) => DeviceStateUpdate::TouchPointDown(TouchPointIndex(point)),
+        (None, false) => DeviceStateUpdate::TouchPointUp(TouchPointIndex(point)),
+    }
+}
+
+/// Turns inputs read from a device into updates, keeping states of buttons and encoders between inputs.
+///
+/// This is what [DeviceStateReader] and the async reader use, custom readers built on [read_input](StreamDeck::read_input)
+/// can use it too to produce the same updates for the same input
+#[derive(Clone, Debug)]
+pub struct InputDiffer {
+    kind: Kind,
+    /// Buttons include Touch Points state
+    buttons: Vec<bool>,
+    encoders: Vec<bool>,
+    /// Button states last reported by the device, that might not be delivered yet because of debouncing
+    reported_buttons: Vec<bool>,
+    /// Time of the last delivered transition of every button
+    last_transitions: Vec<Option<Instant>>,
+    debounce: Duration,
+    /// Releases of presses that woke the device from blackout, which are swallowed along with the presses
+    swallowed_releases: Vec<DeviceStateUpdate>,
+    held_input: HeldInput,
+    #[cfg(feature = "tools")]
+    key_remap: Option<KeyRemap>,
+    created: Instant,
+    /// Buttons and encoders that were held when reading started, whose releases are swallowed
+    held_buttons: Vec<bool>,
+    held_encoders: Vec<bool>,
+}
+
+impl InputDiffer {
+    /// Creates differ for the kind, with everything released
+    pub fn new(kind: Kind) -> InputDiffer {
+        let button_count = kind.key_count() as usize + kind.touchpoint_count() as usize;
+
+        InputDiffer {
+            kind,
+            buttons: vec![false; button_count],
+            encoders: vec![false; kind.encoder_count() as usize],
+            reported_buttons: vec![false; button_count],
+            last_transitions: vec![None; button_count],
+            debounce: Duration::ZERO,
+            swallowed_releases: vec![],
+            held_input: HeldInput::Report,
+            #[cfg(feature = "tools")]
+            key_remap: None,
+            created: Instant::now(),
+            held_buttons: vec![false; button_count],
+            held_encoders: vec![false; kind.encoder_count() as usize],
+        }
+    }
+
+    /// Kind the differ was created for
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Sets how long transitions of a button are held back after its previous transition, to suppress contact bounce.
+    /// Transitions that are held back get delivered by a later [diff](InputDiffer::diff) if the button stays in the new state. Zero by default
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// Sets what happens with controls that are already held when reading starts, the duration of [HeldInput::Ignore] counts from creation of the differ
+    pub fn set_held_input(&mut self, held_input: HeldInput) {
+        self.held_input = held_input;
+    }
+
+    #[cfg(feature = "tools")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tools")))]
+    /// Sets table translating reported key indices of updates to physical keys, see [ConnectOptions::key_remap]
+    pub fn set_key_remap(&mut self, remap: Option<KeyRemap>) {
+        self.key_remap = remap;
+    }
+
+    /// States of buttons as delivered in updates so far, keys first and then touch points
+    pub fn buttons(&self) -> &[bool] {
+        &self.buttons
+    }
+
+    /// States of encoder presses as delivered in updates so far
+    pub fn encoders(&self) -> &[bool] {
+        &self.encoders
+    }
+
+    /// Turns input into updates. Also delivers button transitions that were held back by debounce and are due now,
+    /// so it should be called with [NoData](StreamDeckInput::NoData) when reads time out while debounce is set
+    pub fn diff(&mut self, input: StreamDeckInput) -> Vec<DeviceStateUpdate> {
         let mut updates = vec![];
+        let seeding = matches!(self.held_input, HeldInput::Ignore(window) if self.created.elapsed() < window);
 
         match input {
+            StreamDeckInput::ButtonStateChange(buttons) if seeding => {
+                for (index, state) in buttons.into_iter().enumerate().take(self.buttons.len()) {
+                    self.buttons[index] = state;
+                    self.reported_buttons[index] = state;
+                    self.held_buttons[index] = state;
+                }
+            }
+
             StreamDeckInput::ButtonStateChange(buttons) => {
-                for (index, (their, mine)) in zip(buttons.iter(), my_states.buttons.iter()).enumerate() {
-                    if their != mine {
-                        let key_count = self.device.kind.key_count();
-                        if index < key_count as usize {
-                            if *their {
-                                updates.push(DeviceStateUpdate::ButtonDown(index as u8));
-                            } else {
-                                updates.push(DeviceStateUpdate::ButtonUp(index as u8));
-                            }
-                        } else if *their {
-                            updates.push(DeviceStateUpdate::TouchPointDown(index as u8 - key_count));
-                        } else {
-                            updates.push(DeviceStateUpdate::TouchPointUp(index as u8 - key_count));
-                        }
-                    }
+                for (reported, state) in zip(self.reported_buttons.iter_mut(), buttons) {
+                    *reported = state;
                 }
+            }
 
-                my_states.buttons = buttons;
+            StreamDeckInput::EncoderStateChange(encoders) if seeding => {
+                self.held_encoders = encoders.clone();
+                self.encoders = encoders;
             }
 
             StreamDeckInput::EncoderStateChange(encoders) => {
-                for (index, (their, mine)) in zip(encoders.iter(), my_states.encoders.iter()).enumerate() {
+                for (index, (their, mine)) in zip(encoders.iter(), self.encoders.iter()).enumerate() {
                     if *their != *mine {
-                        if *their {
-                            updates.push(DeviceStateUpdate::EncoderDown(index as u8));
+                        if let Some(held) = self.held_encoders.get_mut(index)
+                            && *held
+                        {
+                            // Release of an encoder held when reading started
+                            *held = false;
+                        } else if *their {
+                            updates.push(DeviceStateUpdate::EncoderDown(EncoderIndex(index as u8)));
                         } else {
-                            updates.push(DeviceStateUpdate::EncoderUp(index as u8));
+                            updates.push(DeviceStateUpdate::EncoderUp(EncoderIndex(index as u8)));
                         }
                     }
                 }
 
-                my_states.encoders = encoders;
+                self.encoders = encoders;
             }
 
             StreamDeckInput::EncoderTwist(twist) => {
                 for (index, change) in twist.iter().enumerate() {
                     if *change != 0 {
-                        updates.push(DeviceStateUpdate::EncoderTwist(index as u8, *change));
+                        updates.push(DeviceStateUpdate::EncoderTwist(EncoderIndex(index as u8), *change));
                     }
                 }
             }
@@ -737,8 +2189,176 @@ impl DeviceStateReader {
             _ => {}
         }
 
+        updates.splice(0..0, self.button_updates());
+
+        #[cfg(feature = "tools")]
+        if let Some(remap) = &self.key_remap {
+            updates = updates.into_iter().map(|update| remap.apply(update)).collect();
+        }
+
+        updates
+    }
+
+    /// Swallows input that wakes the device from [blackout](StreamDeck::blackout), and later releases of what it pressed,
+    /// so the first tap doesn't trigger an action. Returns true if the device should be woken up with [wake](StreamDeck::wake)
+    pub fn swallow_wake_input(&mut self, updates: &mut Vec<DeviceStateUpdate>, blacked_out: bool) -> bool {
+        let release = |update: &DeviceStateUpdate| match update {
+            DeviceStateUpdate::ButtonDown(key) => Some(DeviceStateUpdate::ButtonUp(*key)),
+            DeviceStateUpdate::EncoderDown(encoder) => Some(DeviceStateUpdate::EncoderUp(*encoder)),
+            DeviceStateUpdate::TouchPointDown(point) => Some(DeviceStateUpdate::TouchPointUp(*point)),
+            DeviceStateUpdate::TouchKeyDown(key) => Some(DeviceStateUpdate::TouchKeyUp(*key)),
+            _ => None,
+        };
+
+        let is_release = |update: &DeviceStateUpdate| {
+            matches!(
+                update,
+                DeviceStateUpdate::ButtonUp(_) | DeviceStateUpdate::EncoderUp(_) | DeviceStateUpdate::TouchPointUp(_) | DeviceStateUpdate::TouchKeyUp(_)
+            )
+        };
+
+        // Releases of buttons held since before the blackout don't wake the device
+        let wakes = blacked_out && updates.iter().any(|update| !is_release(update));
+
+        if wakes {
+            self.swallowed_releases.extend(updates.iter().filter_map(release));
+        }
+
+        updates.retain(|update| {
+            if let Some(index) = self.swallowed_releases.iter().position(|swallowed| swallowed == update) {
+                self.swallowed_releases.remove(index);
+                return false;
+            }
+
+            !wakes || is_release(update)
+        });
+
+        wakes
+    }
+
+    /// Delivers reported button transitions, holding back ones that come too soon after the previous transition of the button.
+    /// Held back transitions are delivered by a later call once debounce time passes, if the button didn't change back
+    fn button_updates(&mut self) -> Vec<DeviceStateUpdate> {
+        let now = Instant::now();
+        let key_count = self.kind.key_count();
+        let mut updates = vec![];
+
+        for index in 0..self.buttons.len() {
+            let reported = self.reported_buttons[index];
+
+            if reported == self.buttons[index] || self.last_transitions[index].is_some_and(|last| now.duration_since(last) < self.debounce) {
+                continue;
+            }
+
+            self.buttons[index] = reported;
+            self.last_transitions[index] = Some(now);
+
+            // Release of a button held when reading started
+            if self.held_buttons[index] {
+                self.held_buttons[index] = false;
+                continue;
+            }
+
+            if index < key_count as usize {
+                if reported {
+                    updates.push(DeviceStateUpdate::ButtonDown(KeyIndex(index as u8)));
+                } else {
+                    updates.push(DeviceStateUpdate::ButtonUp(KeyIndex(index as u8)));
+                }
+            } else {
+                updates.push(touch_point_update(self.kind, index as u8 - key_count, reported));
+            }
+        }
+
+        updates
+    }
+}
+
+/// Counters of a reader, for detecting that an application isn't keeping up with input, see [DeviceStateReader::stats]
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub struct ReaderStats {
+    /// Input reports read by the reader
+    pub reports_read: u64,
+    /// Updates returned by the reader
+    pub updates_emitted: u64,
+    /// Input reports of the device that couldn't be parsed, see [StreamDeck::parse_failures]
+    pub parse_failures: u64,
+    /// Times reading of the device got out of sync and was recovered, see [StreamDeck::recovered_desyncs]
+    pub recovered_desyncs: u64,
+}
+
+/// Button reader that keeps state of the Stream Deck and returns events instead of full states
+pub struct DeviceStateReader {
+    device: Arc<StreamDeck>,
+    states: Mutex<InputDiffer>,
+    reports_read: AtomicU64,
+    updates_emitted: AtomicU64,
+}
+
+impl DeviceStateReader {
+    /// Reads states and returns updates
+    pub fn read(&self, timeout: Option<Duration>) -> Result<Vec<DeviceStateUpdate>, StreamDeckError> {
+        Ok(self.read_timed(timeout)?.0)
+    }
+
+    /// Sets how long transitions of a button are held back after its previous transition, to suppress contact bounce.
+    /// Transitions that are held back get delivered by a later read if the button stays in the new state. Zero by default
+    pub fn set_debounce(&self, debounce: Duration) -> Result<(), StreamDeckError> {
+        self.states.lock()?.set_debounce(debounce);
+        Ok(())
+    }
+
+    /// Reads states and returns updates, along with the time input was read from the device, for measuring latency
+    pub fn read_timed(&self, timeout: Option<Duration>) -> Result<(Vec<DeviceStateUpdate>, Instant), StreamDeckError> {
+        let input = self.device.read_input(timeout)?;
+        let read_at = Instant::now();
+
+        if !input.is_empty() {
+            self.reports_read.fetch_add(1, Ordering::Relaxed);
+        }
+        let mut my_states = self.states.lock()?;
+
+        let mut updates = my_states.diff(input);
+
+        if my_states.swallow_wake_input(&mut updates, self.device.is_blacked_out()) {
+            self.device.wake()?;
+        }
+
         drop(my_states);
 
+        self.updates_emitted.fetch_add(updates.len() as u64, Ordering::Relaxed);
+
+        Ok((updates, read_at))
+    }
+
+    /// Reads states like [read](DeviceStateReader::read), pairing touch screen updates with their coordinates
+    /// [normalized](DeviceStateUpdate::normalized_touch) to 0.0 - 1.0 range of the LCD strip
+    pub fn read_normalized(&self, timeout: Option<Duration>) -> Result<Vec<(DeviceStateUpdate, Option<NormalizedTouch>)>, StreamDeckError> {
+        let kind = self.device.kind;
+        Ok(self.read(timeout)?.into_iter().map(|update| (update, update.normalized_touch(kind))).collect())
+    }
+
+    /// Returns counters of the reader and its device. Device counters include reads done without the reader
+    pub fn stats(&self) -> ReaderStats {
+        ReaderStats {
+            reports_read: self.reports_read.load(Ordering::Relaxed),
+            updates_emitted: self.updates_emitted.load(Ordering::Relaxed),
+            parse_failures: self.device.parse_failures(),
+            recovered_desyncs: self.device.recovered_desyncs(),
+        }
+    }
+
+    #[cfg(feature = "input-tools")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "input-tools")))]
+    /// Reads states and returns updates tagged by the layers, staging images of the new layer if the active layer changed
+    /// and [swap_images](layers::Layers::swap_images) is set. Staged images must be flushed with `.flush()` before they will appear on the device!
+    pub fn read_layered(&self, layers: &mut layers::Layers, timeout: Option<Duration>) -> Result<Vec<layers::LayerUpdate>, StreamDeckError> {
+        let updates = layers.handle_all(self.read(timeout)?);
+
+        if layers.swap_images && updates.iter().any(|update| matches!(update, layers::LayerUpdate::LayerChanged { .. })) {
+            layers.stage_images(&self.device)?;
+        }
+
         Ok(updates)
     }
 }
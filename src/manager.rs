@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use hidapi::{HidApi, HidError};
+use image::DynamicImage;
+
+use crate::images::tile_image;
+use crate::info::{is_vendor_familiar, Kind};
+use crate::schedule::BrightnessSchedule;
+use crate::util::normalize_serial;
+use crate::{ConnectOptions, DeviceId, DeviceStateReader, DeviceStateUpdate, StreamDeck, StreamDeckError};
+
+#[cfg(feature = "settings")]
+use crate::settings::SettingsStore;
+
+#[cfg(feature = "async")]
+use crate::hotplug::diff_devices;
+#[cfg(feature = "async")]
+use crate::list_devices;
+#[cfg(feature = "async")]
+use std::collections::HashSet;
+#[cfg(feature = "async")]
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream};
+
+/// How often the device list gets refreshed by default
+pub const DEFAULT_RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long [DeckManager::poll] sleeps between reads while waiting for events without any devices,
+/// otherwise the shortest [recommended poll interval](Kind::recommended_poll_interval) of connected devices is used
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How often brightness schedules get checked
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Event produced by [DeckManager]
+#[derive(Debug)]
+pub enum ManagerEvent {
+    /// Device was found and connected
+    Connected(DeviceId),
+
+    /// Device was unplugged or its connection was lost
+    Disconnected(DeviceId),
+
+    /// Device produced an input update
+    Input(DeviceId, DeviceStateUpdate),
+
+    /// Error occurred while working with a device, or while refreshing the device list if there's no device
+    Error(Option<DeviceId>, StreamDeckError),
+}
+
+/// Hotplug event produced by [DeckManager::watch]
+#[derive(Debug)]
+pub enum DeviceEvent {
+    /// Device was plugged in
+    Connected(DeviceId),
+
+    /// Device was unplugged
+    Disconnected(DeviceId),
+
+    /// Error occurred while refreshing the device list
+    Error(StreamDeckError),
+}
+
+/// Device found by [DeckManager] that is about to be connected
+#[derive(Clone, Debug)]
+pub struct DeviceCandidate {
+    /// ID of the device
+    pub id: DeviceId,
+    /// Platform specific path of the device
+    pub path: String,
+}
+
+/// Decision of [DeckManager] connection policy about a found device
+#[derive(Clone, Debug)]
+pub enum ConnectDecision {
+    /// Leave the device alone until it gets unplugged
+    Ignore,
+
+    /// Connect to the device
+    Connect {
+        /// Options to connect to the device with
+        options: ConnectOptions,
+        /// Brightness to set right after connecting
+        brightness: Option<u8>,
+    },
+}
+
+impl Default for ConnectDecision {
+    fn default() -> Self {
+        ConnectDecision::Connect {
+            options: ConnectOptions::default(),
+            brightness: None,
+        }
+    }
+}
+
+/// Errors of devices that failed an operation applied to all devices, see [DeckManager::set_brightness_all]
+#[derive(Debug)]
+pub struct GroupError {
+    /// Devices that failed, along with their errors
+    pub errors: Vec<(DeviceId, StreamDeckError)>,
+}
+
+impl Display for GroupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} device(s) failed:", self.errors.len())?;
+
+        for (id, error) in &self.errors {
+            write!(f, " [{}: {}]", id, error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for GroupError {}
+
+struct ManagedDeck {
+    device: Arc<StreamDeck>,
+    reader: Arc<DeviceStateReader>,
+    scheduled_brightness: Option<u8>,
+}
+
+/// Owns HidApi, keeps connections to all present Stream Decks and merges their input into a single event stream
+pub struct DeckManager {
+    hidapi: HidApi,
+    decks: HashMap<DeviceId, ManagedDeck>,
+    ignored: Vec<DeviceId>,
+    policy: Box<dyn FnMut(&DeviceCandidate) -> ConnectDecision>,
+    #[cfg(feature = "settings")]
+    settings: Option<SettingsStore>,
+    schedules: HashMap<DeviceId, BrightnessSchedule>,
+    last_schedule_check: Option<Instant>,
+    rescan_interval: Duration,
+    last_rescan: Option<Instant>,
+}
+
+/// Static functions of the struct
+impl DeckManager {
+    /// Creates a manager with a new instance of HidApi
+    pub fn new() -> Result<DeckManager, StreamDeckError> {
+        Ok(Self::with_hidapi(HidApi::new()?))
+    }
+
+    /// Creates a manager that takes ownership of provided HidApi
+    pub fn with_hidapi(hidapi: HidApi) -> DeckManager {
+        DeckManager {
+            hidapi,
+            decks: HashMap::new(),
+            ignored: vec![],
+            policy: Box::new(|_| ConnectDecision::default()),
+            #[cfg(feature = "settings")]
+            settings: None,
+            schedules: HashMap::new(),
+            last_schedule_check: None,
+            rescan_interval: DEFAULT_RESCAN_INTERVAL,
+            last_rescan: None,
+        }
+    }
+}
+
+/// Instance methods of the struct
+impl DeckManager {
+    /// Sets how often device list gets refreshed while polling
+    pub fn set_rescan_interval(&mut self, interval: Duration) {
+        self.rescan_interval = interval;
+    }
+
+    /// Sets policy that decides if and how found devices get connected, by default all devices are connected.
+    ///
+    /// Devices that the policy ignores aren't asked about again until they're unplugged
+    pub fn set_policy(&mut self, policy: impl FnMut(&DeviceCandidate) -> ConnectDecision + 'static) {
+        self.policy = Box::new(policy);
+    }
+
+    /// Sets settings store, settings stored for a device are applied to it after connecting
+    #[cfg(feature = "settings")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "settings")))]
+    pub fn set_settings_store(&mut self, store: SettingsStore) {
+        self.settings = Some(store);
+    }
+
+    /// Returns settings store of the manager
+    #[cfg(feature = "settings")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "settings")))]
+    pub fn settings_store(&mut self) -> Option<&mut SettingsStore> {
+        self.settings.as_mut()
+    }
+
+    /// Sets brightness schedule of the device, brightness is then changed automatically while polling
+    pub fn set_brightness_schedule(&mut self, id: DeviceId, schedule: BrightnessSchedule) {
+        if let Some(deck) = self.decks.get_mut(&id) {
+            deck.scheduled_brightness = None;
+        }
+
+        self.schedules.insert(id, schedule);
+        self.last_schedule_check = None;
+    }
+
+    /// Removes brightness schedule of the device, leaving brightness as it is
+    pub fn clear_brightness_schedule(&mut self, id: &DeviceId) -> Option<BrightnessSchedule> {
+        self.schedules.remove(id)
+    }
+
+    /// Returns IDs of all currently connected devices
+    pub fn devices(&self) -> Vec<DeviceId> {
+        self.decks.keys().cloned().collect()
+    }
+
+    /// Returns handle of a connected device, that can be used to send commands to it
+    pub fn device(&self, id: &DeviceId) -> Option<Arc<StreamDeck>> {
+        self.decks.get(id).map(|deck| deck.device.clone())
+    }
+
+    /// Refreshes device list, connects to new devices and forgets devices that are gone
+    pub fn rescan(&mut self) -> Vec<ManagerEvent> {
+        self.last_rescan = Some(Instant::now());
+
+        let mut events = vec![];
+
+        if let Err(e) = self.hidapi.refresh_devices() {
+            events.push(ManagerEvent::Error(None, e.into()));
+            return events;
+        }
+
+        let mut present: Vec<DeviceCandidate> = vec![];
+
+        for info in self.hidapi.device_list() {
+            if !is_vendor_familiar(&info.vendor_id()) {
+                continue;
+            }
+
+            let (Some(kind), Some(serial)) = (Kind::from_vid_pid(info.vendor_id(), info.product_id()), info.serial_number()) else {
+                continue;
+            };
+
+            let id = DeviceId {
+                kind,
+                serial: normalize_serial(serial),
+            };
+
+            if !present.iter().any(|candidate| candidate.id == id) {
+                present.push(DeviceCandidate {
+                    id,
+                    path: info.path().to_string_lossy().into_owned(),
+                });
+            }
+        }
+
+        #[cfg(feature = "fake")]
+        if let Some(devices) = crate::fake::fake_devices() {
+            present = devices
+                .into_iter()
+                .map(|device| DeviceCandidate {
+                    id: DeviceId {
+                        kind: device.kind,
+                        serial: device.serial,
+                    },
+                    path: "fake".to_string(),
+                })
+                .collect();
+        }
+
+        let gone = self.decks.keys().filter(|id| !present.iter().any(|candidate| &candidate.id == *id)).cloned().collect::<Vec<_>>();
+
+        for id in gone {
+            self.decks.remove(&id);
+            events.push(ManagerEvent::Disconnected(id));
+        }
+
+        self.ignored.retain(|id| present.iter().any(|candidate| &candidate.id == id));
+
+        for candidate in present {
+            if self.decks.contains_key(&candidate.id) || self.ignored.contains(&candidate.id) {
+                continue;
+            }
+
+            let (options, brightness) = match (self.policy)(&candidate) {
+                ConnectDecision::Ignore => {
+                    self.ignored.push(candidate.id);
+                    continue;
+                }
+
+                ConnectDecision::Connect { options, brightness } => (options, brightness),
+            };
+
+            let id = candidate.id;
+
+            match StreamDeck::connect_with_options(&self.hidapi, id.kind, &id.serial, options) {
+                Ok(device) => {
+                    if let Some(brightness) = brightness
+                        && let Err(e) = device.set_brightness(brightness)
+                    {
+                        events.push(ManagerEvent::Error(Some(id.clone()), e));
+                    }
+
+                    #[cfg(feature = "settings")]
+                    if let Some(settings) = self.settings.as_ref().and_then(|store| store.get(&id))
+                        && let Err(e) = settings.apply(&device)
+                    {
+                        events.push(ManagerEvent::Error(Some(id.clone()), e));
+                    }
+
+                    #[allow(clippy::arc_with_non_send_sync)]
+                    let device = Arc::new(device);
+                    let reader = device.get_reader();
+
+                    self.decks.insert(
+                        id.clone(),
+                        ManagedDeck {
+                            device,
+                            reader,
+                            scheduled_brightness: None,
+                        },
+                    );
+                    events.push(ManagerEvent::Connected(id));
+                }
+
+                Err(e) => events.push(ManagerEvent::Error(Some(id), e)),
+            }
+        }
+
+        events
+    }
+
+    /// Reads input from all connected devices, rescanning the device list when it's due.
+    ///
+    /// Waits up to the timeout for events to appear, returns immediately if timeout is None
+    pub fn poll(&mut self, timeout: Option<Duration>) -> Vec<ManagerEvent> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            let mut events = vec![];
+
+            if self.last_rescan.is_none_or(|last| last.elapsed() >= self.rescan_interval) {
+                events.extend(self.rescan());
+            }
+
+            if self.last_schedule_check.is_none_or(|last| last.elapsed() >= SCHEDULE_INTERVAL) {
+                events.extend(self.apply_schedules());
+            }
+
+            events.extend(self.read_all());
+
+            match deadline {
+                Some(deadline) if events.is_empty() && Instant::now() < deadline => sleep(self.poll_interval().min(deadline - Instant::now())),
+                _ => return events,
+            }
+        }
+    }
+
+    /// Sets brightness of all connected devices, value range is 0 - 100.
+    /// Devices with a brightness schedule get changed again once the schedule is checked
+    pub fn set_brightness_all(&self, percent: u8) -> Result<(), GroupError> {
+        self.for_each_device(|device| device.set_brightness(percent))
+    }
+
+    /// Clears images of all keys of all connected devices
+    pub fn clear_all(&self) -> Result<(), GroupError> {
+        self.for_each_device(|device| {
+            device.clear_all_button_images()?;
+            device.flush()
+        })
+    }
+
+    /// Spreads the image over keys of every connected device, see [tile_image]
+    pub fn set_tiled_image_all(&self, image: &DynamicImage) -> Result<(), GroupError> {
+        self.for_each_device(|device| {
+            if !device.kind().is_visual() {
+                return Ok(());
+            }
+
+            let tiles = tile_image(device.kind(), image);
+
+            // Keys that were set are still flushed if some failed
+            let result = device.for_each_key(0..tiles.len() as u8, |key| device.set_button_image(key, tiles[key as usize].clone()));
+            device.flush()?;
+
+            result
+        })
+    }
+
+    /// Runs the operation on every connected device even if some fail, collecting errors of the failed ones
+    fn for_each_device(&self, mut operation: impl FnMut(&StreamDeck) -> Result<(), StreamDeckError>) -> Result<(), GroupError> {
+        let errors = self
+            .decks
+            .iter()
+            .filter_map(|(id, deck)| operation(&deck.device).err().map(|error| (id.clone(), error)))
+            .collect::<Vec<_>>();
+
+        if errors.is_empty() { Ok(()) } else { Err(GroupError { errors }) }
+    }
+
+    fn poll_interval(&self) -> Duration {
+        self.decks.keys().map(|id| id.kind.recommended_poll_interval()).min().unwrap_or(POLL_INTERVAL)
+    }
+
+    fn apply_schedules(&mut self) -> Vec<ManagerEvent> {
+        self.last_schedule_check = Some(Instant::now());
+
+        let mut events = vec![];
+
+        for (id, schedule) in &self.schedules {
+            let Some(deck) = self.decks.get_mut(id) else {
+                continue;
+            };
+
+            let Some(brightness) = schedule.current_brightness() else {
+                continue;
+            };
+
+            if deck.scheduled_brightness == Some(brightness) {
+                continue;
+            }
+
+            match deck.device.set_brightness(brightness) {
+                Ok(()) => deck.scheduled_brightness = Some(brightness),
+                Err(e) => events.push(ManagerEvent::Error(Some(id.clone()), e)),
+            }
+        }
+
+        events
+    }
+
+    fn read_all(&mut self) -> Vec<ManagerEvent> {
+        let mut events = vec![];
+        let mut lost = vec![];
+
+        for (id, deck) in &self.decks {
+            match deck.reader.read(None) {
+                Ok(updates) => events.extend(updates.into_iter().map(|update| ManagerEvent::Input(id.clone(), update))),
+
+                Err(e) => {
+                    if is_disconnect(&e) {
+                        lost.push(id.clone());
+                    }
+
+                    events.push(ManagerEvent::Error(Some(id.clone()), e));
+                }
+            }
+        }
+
+        for id in lost {
+            self.decks.remove(&id);
+            events.push(ManagerEvent::Disconnected(id));
+        }
+
+        events
+    }
+}
+
+/// Tells if the error means the device is gone. Stalls like timeouts are only reported, and the device is kept
+fn is_disconnect(error: &StreamDeckError) -> bool {
+    match error {
+        StreamDeckError::HidError(HidError::IoError { error }) => !matches!(error.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted),
+        StreamDeckError::HidError(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl DeckManager {
+    /// Returns a stream of devices getting plugged in and unplugged, devices that are already present are reported as connected.
+    ///
+    /// Device list is refreshed every interval on a blocking worker, so the runtime stays responsive.
+    /// Watching stops once the stream is dropped. Must be called inside of tokio runtime
+    pub fn watch(interval: Duration) -> impl Stream<Item = DeviceEvent> {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut hidapi: Option<HidApi> = None;
+            let mut known: HashSet<DeviceId> = HashSet::new();
+
+            while !sender.is_closed() {
+                let previous = hidapi.take();
+
+                let scan = tokio::task::spawn_blocking(move || {
+                    let result = match previous {
+                        Some(mut hidapi) => hidapi.refresh_devices().map(|_| hidapi),
+                        None => HidApi::new(),
+                    };
+
+                    match result {
+                        Ok(hidapi) => {
+                            let present = list_devices(&hidapi).into_iter().map(|(kind, serial)| DeviceId { kind, serial }).collect::<HashSet<_>>();
+                            (Some(hidapi), Ok(present))
+                        }
+
+                        Err(e) => (None, Err(StreamDeckError::from(e))),
+                    }
+                })
+                .await;
+
+                let events = match scan {
+                    Ok((api, Ok(present))) => {
+                        hidapi = api;
+
+                        diff_devices(&mut known, present)
+                    }
+
+                    Ok((api, Err(e))) => {
+                        hidapi = api;
+                        vec![DeviceEvent::Error(e)]
+                    }
+
+                    Err(e) => vec![DeviceEvent::Error(e.into())],
+                };
+
+                for event in events {
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+
+                tokio::time::sleep(interval).await;
+            }
+        });
+
+        UnboundedReceiverStream::new(receiver)
+    }
+}
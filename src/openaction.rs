@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+use crate::info::Kind;
+use crate::{StreamDeck, StreamDeckError};
+
+/// Part of a screen in a [ScreenManifest], see [ScreenSegment](crate::info::ScreenSegment)
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SegmentManifest {
+    /// Horizontal position of the segment
+    pub x: usize,
+    /// Vertical position of the segment
+    pub y: usize,
+    /// Width of the segment
+    pub width: usize,
+    /// Height of the segment
+    pub height: usize,
+}
+
+/// Screen of a device in a [DeviceManifest]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ScreenManifest {
+    /// Identifier of the screen, see [Screen](crate::info::Screen)
+    pub id: u8,
+    /// Width of the screen in pixels
+    pub width: usize,
+    /// Height of the screen in pixels
+    pub height: usize,
+    /// Parts of the screen that belong to encoders, by encoder index
+    #[serde(default)]
+    pub segments: Vec<SegmentManifest>,
+    /// Tells if regions of the screen can be written, otherwise only the whole screen can be filled
+    #[serde(default)]
+    pub writes_regions: bool,
+}
+
+/// Device descriptor in the format OpenAction uses for registering devices, so plugin hosts can pass capabilities of devices to plugins.
+///
+/// Fields OpenAction doesn't know about are skipped when they're empty, and default to empty when importing manifests without them
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceManifest {
+    /// Unique identifier of the device
+    pub id: String,
+    /// Human readable name of the device
+    pub name: String,
+    /// Amount of key rows
+    pub rows: u8,
+    /// Amount of key columns
+    pub columns: u8,
+    /// Amount of encoders
+    pub encoders: u8,
+    /// Device type as numbered by the Stream Deck SDK
+    #[serde(rename = "type")]
+    pub device_type: u8,
+    /// Amount of touch points
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub touchpoints: u8,
+    /// Screens besides key displays
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub screens: Vec<ScreenManifest>,
+}
+
+impl DeviceManifest {
+    /// Creates manifest of the device kind with the serial number, id is serial prefixed with `sd-` like OpenAction does for Stream Decks
+    pub fn new(kind: Kind, serial: &str) -> DeviceManifest {
+        DeviceManifest {
+            id: format!("sd-{}", serial),
+            name: kind.product_name().to_string(),
+            rows: kind.row_count(),
+            columns: kind.column_count(),
+            encoders: kind.encoder_count(),
+            device_type: device_type(kind),
+            touchpoints: kind.touchpoint_count(),
+            screens: kind
+                .screens()
+                .into_iter()
+                .map(|screen| ScreenManifest {
+                    id: screen.id,
+                    width: screen.resolution.0,
+                    height: screen.resolution.1,
+                    segments: screen
+                        .segments
+                        .into_iter()
+                        .map(|segment| SegmentManifest {
+                            x: segment.x,
+                            y: segment.y,
+                            width: segment.w,
+                            height: segment.h,
+                        })
+                        .collect(),
+                    writes_regions: screen.writes_regions,
+                })
+                .collect(),
+        }
+    }
+
+    /// Creates manifest of the connected device
+    pub fn from_device(device: &StreamDeck) -> Result<DeviceManifest, StreamDeckError> {
+        Ok(DeviceManifest::new(device.kind(), &device.serial_number()?))
+    }
+
+    /// Serializes the manifest into JSON
+    pub fn to_json(&self) -> Result<String, StreamDeckError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Reads manifest from JSON
+    pub fn from_json(json: &str) -> Result<DeviceManifest, StreamDeckError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Tells if the manifest describes a device with the same layout as the kind, ignoring id and name
+    pub fn matches_kind(&self, kind: Kind) -> bool {
+        let manifest = DeviceManifest::new(kind, "");
+        (self.rows, self.columns, self.encoders, self.device_type, self.touchpoints, &self.screens)
+            == (manifest.rows, manifest.columns, manifest.encoders, manifest.device_type, manifest.touchpoints, &manifest.screens)
+    }
+}
+
+/// Device type of the kind as numbered by the Stream Deck SDK. Plus XL has no type of its own, so it's reported as Plus
+fn device_type(kind: Kind) -> u8 {
+    match kind {
+        Kind::Original | Kind::OriginalV2 | Kind::Mk2 | Kind::Mk2Scissor | Kind::Mk2Module => 0,
+        Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => 1,
+        Kind::Xl | Kind::XlV2 | Kind::XlV2Module => 2,
+        Kind::Pedal => 5,
+        Kind::Plus | Kind::PlusXl => 7,
+        Kind::Neo => 9,
+    }
+}
+
+fn is_zero(value: &u8) -> bool {
+    *value == 0
+}
@@ -0,0 +1,133 @@
+use image::{DynamicImage, Rgba};
+
+/// Okabe-Ito palette, 8 colors that stay distinguishable with all common color vision deficiencies
+pub const OKABE_ITO: [(u8, u8, u8); 8] = [(0, 0, 0), (230, 159, 0), (86, 180, 233), (0, 158, 115), (240, 228, 66), (0, 114, 178), (213, 94, 0), (204, 121, 167)];
+
+/// Smallest distance between colors simulated with every deficiency for [is_distinguishable] to consider them different
+const MIN_DISTANCE: f32 = 48.0;
+
+/// Status colors taken from [OKABE_ITO], so states of keys and touch points can be told apart regardless of color vision
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum StatusColor {
+    /// Bluish green, for things that are on or fine
+    Ok,
+    /// Yellow, for things that need attention
+    Warning,
+    /// Vermillion, for failures
+    Error,
+    /// Sky blue, for informational states
+    Info,
+    /// Dark grey, for things that are off or inactive
+    Neutral,
+}
+
+impl StatusColor {
+    /// Color as (red, green, blue)
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        match self {
+            StatusColor::Ok => OKABE_ITO[3],
+            StatusColor::Warning => OKABE_ITO[4],
+            StatusColor::Error => OKABE_ITO[6],
+            StatusColor::Info => OKABE_ITO[2],
+            StatusColor::Neutral => (64, 64, 64),
+        }
+    }
+}
+
+/// Color vision deficiency that can be simulated
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum Deficiency {
+    /// No red cones
+    Protanopia,
+    /// No green cones
+    Deuteranopia,
+    /// No blue cones
+    Tritanopia,
+    /// No color vision at all
+    Achromatopsia,
+}
+
+impl Deficiency {
+    /// All deficiencies
+    pub const ALL: [Deficiency; 4] = [Deficiency::Protanopia, Deficiency::Deuteranopia, Deficiency::Tritanopia, Deficiency::Achromatopsia];
+
+    /// Matrix applied to linear RGB, from Machado et al. 2009 at full severity
+    fn matrix(&self) -> [[f32; 3]; 3] {
+        match self {
+            Deficiency::Protanopia => [[0.152286, 1.052583, -0.204868], [0.114503, 0.786281, 0.099216], [-0.003882, -0.048116, 1.051998]],
+            Deficiency::Deuteranopia => [[0.367322, 0.860646, -0.227968], [0.280085, 0.672501, 0.047413], [-0.011820, 0.042940, 0.968881]],
+            Deficiency::Tritanopia => [[1.255528, -0.076749, -0.178779], [-0.078411, 0.930809, 0.147602], [0.004733, 0.691367, 0.303900]],
+            Deficiency::Achromatopsia => [[0.2126, 0.7152, 0.0722]; 3],
+        }
+    }
+}
+
+/// Relative luminance of the color as defined by WCAG, from 0 for black to 1 for white
+pub fn relative_luminance(color: (u8, u8, u8)) -> f32 {
+    let (red, green, blue) = color;
+    0.2126 * to_linear(red) + 0.7152 * to_linear(green) + 0.0722 * to_linear(blue)
+}
+
+/// Contrast ratio of the colors as defined by WCAG, from 1 for same luminance to 21 for black and white.
+/// Text is considered readable on a background at 4.5 and above
+pub fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Black or white, whichever has more contrast with the background
+pub fn readable_text_color(background: (u8, u8, u8)) -> (u8, u8, u8) {
+    if contrast_ratio(background, (0, 0, 0)) >= contrast_ratio(background, (255, 255, 255)) {
+        (0, 0, 0)
+    } else {
+        (255, 255, 255)
+    }
+}
+
+/// Checks that the colors can be told apart with normal vision and with every [Deficiency] except achromatopsia,
+/// which only leaves luminance to tell colors apart, see [contrast_ratio] for that
+pub fn is_distinguishable(a: (u8, u8, u8), b: (u8, u8, u8)) -> bool {
+    let distance = |a: (u8, u8, u8), b: (u8, u8, u8)| {
+        let (dr, dg, db) = (a.0 as f32 - b.0 as f32, a.1 as f32 - b.1 as f32, a.2 as f32 - b.2 as f32);
+        (dr * dr + dg * dg + db * db).sqrt()
+    };
+
+    distance(a, b) >= MIN_DISTANCE
+        && Deficiency::ALL
+            .iter()
+            .filter(|deficiency| **deficiency != Deficiency::Achromatopsia)
+            .all(|deficiency| distance(simulate_deficiency(a, *deficiency), simulate_deficiency(b, *deficiency)) >= MIN_DISTANCE)
+}
+
+/// Color as it's seen with the deficiency
+pub fn simulate_deficiency(color: (u8, u8, u8), deficiency: Deficiency) -> (u8, u8, u8) {
+    let linear = [to_linear(color.0), to_linear(color.1), to_linear(color.2)];
+    let [r, g, b] = deficiency.matrix().map(|row| from_linear(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]));
+
+    (r, g, b)
+}
+
+/// Image as it's seen with the deficiency, for previewing how keys will look
+pub fn simulate_deficiency_image(image: &DynamicImage, deficiency: Deficiency) -> DynamicImage {
+    let mut image = image.to_rgba8();
+
+    for pixel in image.pixels_mut() {
+        let (red, green, blue) = simulate_deficiency((pixel[0], pixel[1], pixel[2]), deficiency);
+        *pixel = Rgba([red, green, blue, pixel[3]]);
+    }
+
+    DynamicImage::ImageRgba8(image)
+}
+
+fn to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+fn from_linear(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let value = if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 };
+
+    (value * 255.0).round() as u8
+}
@@ -0,0 +1,346 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{spawn, JoinHandle};
+
+use image::DynamicImage;
+
+use crate::images::convert_image;
+use crate::info::Kind;
+use crate::{StreamDeck, StreamDeckError};
+
+/// Result of writing a frame, error is shared since every waiter of the frame gets it
+pub type FrameResult = Result<(), Arc<StreamDeckError>>;
+
+type Callback = Box<dyn FnOnce(&FrameResult) + Send>;
+
+/// Encodes key images on a pool of worker threads and writes them from a single thread that owns the device.
+///
+/// Frames are written in the order they were submitted, no matter which finishes encoding first.
+/// Keys that would get the same image they already show are skipped
+pub struct Pipeline {
+    kind: Kind,
+    next_frame: u64,
+    jobs: Option<Sender<Job>>,
+    results: Option<Sender<WriterMessage>>,
+    workers: Vec<JoinHandle<()>>,
+    writer: Option<JoinHandle<StreamDeck>>,
+}
+
+struct Job {
+    frame: u64,
+    index: usize,
+    key: u8,
+    image: DynamicImage,
+}
+
+enum WriterMessage {
+    Frame {
+        frame: u64,
+        images: usize,
+        completion: Arc<Completion>,
+    },
+    Encoded {
+        frame: u64,
+        index: usize,
+        key: u8,
+        data: Result<Vec<u8>, StreamDeckError>,
+    },
+}
+
+#[derive(Default)]
+struct Completion {
+    state: Mutex<CompletionState>,
+    done: Condvar,
+}
+
+#[derive(Default)]
+struct CompletionState {
+    result: Option<FrameResult>,
+    waker: Option<Waker>,
+    callback: Option<Callback>,
+}
+
+impl Completion {
+    fn complete(&self, result: FrameResult) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        state.result = Some(result.clone());
+        let callback = state.callback.take();
+        let waker = state.waker.take();
+
+        drop(state);
+        self.done.notify_all();
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+
+        if let Some(callback) = callback {
+            callback(&result);
+        }
+    }
+}
+
+/// Handle of a submitted frame, can be waited on or awaited until the frame is written
+pub struct FrameHandle {
+    completion: Arc<Completion>,
+}
+
+impl FrameHandle {
+    /// Blocks until the frame is written
+    pub fn wait(&self) -> FrameResult {
+        let mut state = self.completion.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        loop {
+            if let Some(result) = &state.result {
+                return result.clone();
+            }
+
+            state = self.completion.done.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Tells if the frame was already written
+    pub fn is_done(&self) -> bool {
+        self.completion.state.lock().unwrap_or_else(|e| e.into_inner()).result.is_some()
+    }
+
+    /// Calls the callback on the writer thread once the frame is written, or right away if it already was
+    pub fn on_complete(&self, callback: impl FnOnce(&FrameResult) + Send + 'static) {
+        let mut state = self.completion.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        match state.result.clone() {
+            Some(result) => {
+                drop(state);
+                callback(&result);
+            }
+
+            None => state.callback = Some(Box::new(callback)),
+        }
+    }
+}
+
+impl Future for FrameHandle {
+    type Output = FrameResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.completion.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        match &state.result {
+            Some(result) => Poll::Ready(result.clone()),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Pipeline {
+    /// Takes over the device, encoding images with the amount of worker threads
+    pub fn new(device: StreamDeck, workers: usize) -> Pipeline {
+        let kind = device.kind();
+
+        let (jobs, job_receiver) = channel::<Job>();
+        let (results, result_receiver) = channel::<WriterMessage>();
+
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let jobs = job_receiver.clone();
+                let results = results.clone();
+
+                spawn(move || {
+                    loop {
+                        let job = match jobs.lock() {
+                            Ok(jobs) => jobs.recv(),
+                            Err(_) => return,
+                        };
+
+                        let Ok(job) = job else {
+                            return;
+                        };
+
+                        let data = convert_image(kind, job.image).map_err(StreamDeckError::from);
+
+                        if results
+                            .send(WriterMessage::Encoded {
+                                frame: job.frame,
+                                index: job.index,
+                                key: job.key,
+                                data,
+                            })
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let writer = spawn(move || write_frames(device, result_receiver));
+
+        Pipeline {
+            kind,
+            next_frame: 0,
+            jobs: Some(jobs),
+            results: Some(results),
+            workers,
+            writer: Some(writer),
+        }
+    }
+
+    /// Kind of the device the pipeline writes to
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Submits images of keys as a frame, the frame is written after all previously submitted frames.
+    /// If a key appears more than once, the last image wins
+    pub fn submit(&mut self, images: Vec<(u8, DynamicImage)>) -> FrameHandle {
+        let completion = Arc::new(Completion::default());
+        let handle = FrameHandle { completion: completion.clone() };
+
+        let frame = self.next_frame;
+        self.next_frame += 1;
+
+        // Announcing the frame first, so the writer knows how many images to wait for
+        let announced = self.results.as_ref().is_some_and(|results| {
+            results
+                .send(WriterMessage::Frame {
+                    frame,
+                    images: images.len(),
+                    completion: completion.clone(),
+                })
+                .is_ok()
+        });
+
+        // Writer thread is only gone if it panicked
+        let (true, Some(jobs)) = (announced, &self.jobs) else {
+            completion.complete(Err(Arc::new(StreamDeckError::PoisonError)));
+            return handle;
+        };
+
+        for (index, (key, image)) in images.into_iter().enumerate() {
+            let _ = jobs.send(Job { frame, index, key, image });
+        }
+
+        handle
+    }
+
+    /// Writes all submitted frames, stops the threads and gives the device back. None if the writer thread panicked
+    pub fn finish(mut self) -> Option<StreamDeck> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Option<StreamDeck> {
+        self.jobs.take();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+
+        self.results.take();
+
+        self.writer.take()?.join().ok()
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct PendingFrame {
+    remaining: usize,
+    images: BTreeMap<u8, (usize, Vec<u8>)>,
+    error: Option<StreamDeckError>,
+    completion: Arc<Completion>,
+}
+
+fn write_frames(device: StreamDeck, messages: Receiver<WriterMessage>) -> StreamDeck {
+    let mut frames: BTreeMap<u64, PendingFrame> = BTreeMap::new();
+    let mut shown: Vec<Option<Vec<u8>>> = vec![None; device.kind().key_count() as usize];
+    let mut next_frame = 0;
+
+    while let Ok(message) = messages.recv() {
+        match message {
+            WriterMessage::Frame { frame, images, completion } => {
+                frames.insert(
+                    frame,
+                    PendingFrame {
+                        remaining: images,
+                        images: BTreeMap::new(),
+                        error: None,
+                        completion,
+                    },
+                );
+            }
+
+            WriterMessage::Encoded { frame, index, key, data } => {
+                let Some(pending) = frames.get_mut(&frame) else {
+                    continue;
+                };
+
+                pending.remaining -= 1;
+
+                match data {
+                    Ok(data) => {
+                        if pending.images.get(&key).is_none_or(|(existing, _)| *existing < index) {
+                            pending.images.insert(key, (index, data));
+                        }
+                    }
+
+                    Err(e) => pending.error = Some(e),
+                }
+            }
+        }
+
+        while frames.get(&next_frame).is_some_and(|pending| pending.remaining == 0) {
+            let Some(pending) = frames.remove(&next_frame) else {
+                break;
+            };
+
+            next_frame += 1;
+
+            let result = match pending.error {
+                Some(e) => Err(e),
+                None => write_frame(&device, &mut shown, pending.images),
+            };
+
+            pending.completion.complete(result.map_err(Arc::new));
+        }
+    }
+
+    device
+}
+
+fn write_frame(device: &StreamDeck, shown: &mut [Option<Vec<u8>>], images: BTreeMap<u8, (usize, Vec<u8>)>) -> Result<(), StreamDeckError> {
+    let mut written = vec![];
+
+    for (key, (_, data)) in images {
+        let current = shown.get(key as usize).ok_or(StreamDeckError::InvalidKeyIndex)?;
+
+        if current.as_ref() == Some(&data) {
+            continue;
+        }
+
+        device.write_image(key, data.as_slice())?;
+        written.push((key, data));
+    }
+
+    device.flush()?;
+
+    for (key, data) in written {
+        shown[key as usize] = Some(data);
+    }
+
+    Ok(())
+}
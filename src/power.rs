@@ -0,0 +1,217 @@
+use std::ffi::c_void;
+use std::io;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{spawn, JoinHandle};
+use std::time::Duration;
+
+use crate::{StreamDeck, StreamDeckError};
+
+type IoObject = u32;
+type IoConnect = u32;
+type KernReturn = i32;
+type NotificationPort = *mut c_void;
+type RunLoop = *mut c_void;
+type RunLoopSource = *mut c_void;
+type CfString = *const c_void;
+type InterestCallback = extern "C" fn(refcon: *mut c_void, service: IoObject, message_type: u32, message_argument: *mut c_void);
+
+/// System asks if it may sleep, must be answered with IOAllowPowerChange
+const MESSAGE_CAN_SYSTEM_SLEEP: u32 = 0xe0000270;
+/// System is going to sleep, must be answered with IOAllowPowerChange
+const MESSAGE_SYSTEM_WILL_SLEEP: u32 = 0xe0000280;
+/// System finished waking up
+const MESSAGE_SYSTEM_HAS_POWERED_ON: u32 = 0xe0000300;
+/// How long the run loop runs before the stop flag is checked again, in seconds
+const RUN_LOOP_INTERVAL: f64 = 0.1;
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IORegisterForSystemPower(refcon: *mut c_void, port: *mut NotificationPort, callback: InterestCallback, notifier: *mut IoObject) -> IoConnect;
+    fn IODeregisterForSystemPower(notifier: *mut IoObject) -> KernReturn;
+    fn IOAllowPowerChange(kernel_port: IoConnect, notification_id: isize) -> KernReturn;
+    fn IONotificationPortGetRunLoopSource(port: NotificationPort) -> RunLoopSource;
+    fn IONotificationPortDestroy(port: NotificationPort);
+    fn IOServiceClose(connect: IoConnect) -> KernReturn;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    static kCFRunLoopDefaultMode: CfString;
+
+    fn CFRunLoopGetCurrent() -> RunLoop;
+    fn CFRunLoopAddSource(run_loop: RunLoop, source: RunLoopSource, mode: CfString);
+    fn CFRunLoopRunInMode(mode: CfString, seconds: f64, return_after_source_handled: u8) -> i32;
+    fn CFRunLoopStop(run_loop: RunLoop);
+}
+
+/// Power state change of the Mac
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum PowerEvent {
+    /// System is going to sleep
+    Sleeping,
+    /// System woke up from sleep
+    Woke,
+}
+
+impl PowerEvent {
+    /// [Suspends](StreamDeck::suspend) the device before sleep, and [resets and restores it](StreamDeck::resume_with_reset) after wake,
+    /// since devices are often left uninitialized after macOS sleep
+    pub fn apply(&self, device: &StreamDeck) -> Result<(), StreamDeckError> {
+        match self {
+            PowerEvent::Sleeping => {
+                device.suspend();
+                Ok(())
+            }
+            PowerEvent::Woke => device.resume_with_reset(),
+        }
+    }
+}
+
+/// State shared with the IOKit callback
+struct Context {
+    root_port: IoConnect,
+    events: Sender<PowerEvent>,
+}
+
+/// Run loop of the watcher thread, only used for waking it up to stop
+struct RunLoopHandle(RunLoop);
+
+// CFRunLoopStop can be called from any thread
+unsafe impl Send for RunLoopHandle {}
+
+/// Receives system sleep and wake notifications from IOKit on a background thread
+/// ```no_run
+/// # use elgato_streamdeck::power::PowerWatcher;
+/// # fn example(device: &elgato_streamdeck::StreamDeck) -> Result<(), elgato_streamdeck::StreamDeckError> {
+/// let watcher = PowerWatcher::new()?;
+///
+/// while let Some(event) = watcher.recv() {
+///     event.apply(device)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct PowerWatcher {
+    run_loop: RunLoopHandle,
+    stop: Arc<AtomicBool>,
+    events: Receiver<PowerEvent>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PowerWatcher {
+    /// Registers for sleep and wake notifications
+    pub fn new() -> Result<PowerWatcher, StreamDeckError> {
+        let (sender, events) = channel();
+        let (ready_sender, ready) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = spawn({
+            let stop = stop.clone();
+            move || watch(sender, ready_sender, stop)
+        });
+
+        let run_loop = match ready.recv() {
+            Ok(result) => result?,
+            Err(_) => return Err(io::Error::other("Power watcher thread exited").into()),
+        };
+
+        Ok(PowerWatcher {
+            run_loop,
+            stop,
+            events,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns the next event without waiting
+    pub fn try_recv(&self) -> Option<PowerEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Waits for the next event, None if the watcher stopped
+    pub fn recv(&self) -> Option<PowerEvent> {
+        self.events.recv().ok()
+    }
+
+    /// Waits for the next event up to the timeout
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<PowerEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for PowerWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+
+        // Only cuts the wait short, the flag stops the thread even if its run loop isn't running yet
+        unsafe {
+            CFRunLoopStop(self.run_loop.0);
+        }
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// Registers for system power notifications and runs the run loop of the thread until the stop flag is set
+fn watch(events: Sender<PowerEvent>, ready: Sender<io::Result<RunLoopHandle>>, stop: Arc<AtomicBool>) {
+    let context = Box::into_raw(Box::new(Context { root_port: 0, events }));
+    let mut port: NotificationPort = null_mut();
+    let mut notifier: IoObject = 0;
+
+    unsafe {
+        let root_port = IORegisterForSystemPower(context.cast(), &mut port, power_callback, &mut notifier);
+
+        if root_port == 0 {
+            drop(Box::from_raw(context));
+            ready.send(Err(io::Error::other("Couldn't register for system power notifications"))).ok();
+            return;
+        }
+
+        // Callback only runs within the run loop below, so the port can be filled in before
+        (*context).root_port = root_port;
+
+        let run_loop = CFRunLoopGetCurrent();
+        CFRunLoopAddSource(run_loop, IONotificationPortGetRunLoopSource(port), kCFRunLoopDefaultMode);
+
+        ready.send(Ok(RunLoopHandle(run_loop))).ok();
+
+        while !stop.load(Ordering::Acquire) {
+            CFRunLoopRunInMode(kCFRunLoopDefaultMode, RUN_LOOP_INTERVAL, 0);
+        }
+
+        IODeregisterForSystemPower(&mut notifier);
+        IOServiceClose(root_port);
+        IONotificationPortDestroy(port);
+        drop(Box::from_raw(context));
+    }
+}
+
+extern "C" fn power_callback(refcon: *mut c_void, _service: IoObject, message_type: u32, message_argument: *mut c_void) {
+    let context = unsafe { &*(refcon as *const Context) };
+
+    match message_type {
+        // Sleep isn't held up, devices are suspended as soon as the event is handled
+        MESSAGE_CAN_SYSTEM_SLEEP => unsafe {
+            IOAllowPowerChange(context.root_port, message_argument as isize);
+        },
+
+        MESSAGE_SYSTEM_WILL_SLEEP => {
+            context.events.send(PowerEvent::Sleeping).ok();
+
+            unsafe {
+                IOAllowPowerChange(context.root_port, message_argument as isize);
+            }
+        }
+
+        MESSAGE_SYSTEM_HAS_POWERED_ON => {
+            context.events.send(PowerEvent::Woke).ok();
+        }
+
+        _ => {}
+    }
+}
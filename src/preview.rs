@@ -0,0 +1,133 @@
+use crate::info::{Kind, Screen, ScreenSegment};
+
+/// Space between keys and around the deck
+const GAP: usize = 16;
+
+/// Size of keys of kinds that don't have key displays
+const BLANK_KEY_SIZE: usize = 72;
+
+/// Height of touch points drawn below the keys
+const TOUCH_POINT_HEIGHT: usize = 24;
+
+/// Part of the deck at a point of the preview, see [DeckLayout::hit_test]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum PreviewHit {
+    /// Key with the index
+    Key(u8),
+    /// Touch point with the index
+    TouchPoint(u8),
+    /// Point of the screen, in coordinates the device uses for touches and regions
+    Screen {
+        /// Identifier of the screen
+        id: u8,
+        /// Horizontal position within the screen
+        x: usize,
+        /// Vertical position within the screen
+        y: usize,
+        /// Index of the segment the point is in, which is also index of the encoder below it if there is one
+        segment: Option<u8>,
+    },
+}
+
+/// Where parts of the deck are drawn in the preview of the simulator, in pixels.
+///
+/// Configurator UIs built on the preview can use it to find what was clicked, without duplicating the layout math
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct DeckLayout {
+    /// Kind the layout is of
+    pub kind: Kind,
+    /// Size of the whole preview as (width, height)
+    pub size: (usize, usize),
+    /// Rectangles of the keys, by key index
+    pub keys: Vec<ScreenSegment>,
+    /// Rectangles of the touch points, by touch point index
+    pub touch_points: Vec<ScreenSegment>,
+    /// Rectangle of the LCD strip
+    pub lcd: Option<ScreenSegment>,
+}
+
+impl DeckLayout {
+    /// Layout of the kind's preview
+    pub fn of(kind: Kind) -> DeckLayout {
+        let (key_w, key_h) = match kind.key_image_resolution() {
+            (0, _) | (_, 0) => (BLANK_KEY_SIZE, BLANK_KEY_SIZE),
+            size => size,
+        };
+
+        let columns = kind.column_count() as usize;
+
+        let keys: Vec<ScreenSegment> = (0..kind.key_count() as usize)
+            .map(|key| ScreenSegment {
+                x: GAP + (key % columns) * (key_w + GAP),
+                y: GAP + (key / columns) * (key_h + GAP),
+                w: key_w,
+                h: key_h,
+            })
+            .collect();
+
+        let keys_size = (GAP + columns * (key_w + GAP), GAP + (kind.row_count() as usize) * (key_h + GAP));
+
+        // Vertical strips go to the right of the keys, horizontal ones below them
+        let lcd = kind.lcd_strip_size().map(|(w, h)| {
+            if h > w {
+                ScreenSegment { x: keys_size.0, y: GAP, w, h }
+            } else {
+                ScreenSegment { x: GAP, y: keys_size.1, w, h }
+            }
+        });
+
+        let mut size = match lcd {
+            Some(lcd) => ((lcd.x + lcd.w + GAP).max(keys_size.0), (lcd.y + lcd.h + GAP).max(keys_size.1)),
+            None => keys_size,
+        };
+
+        let touch_point_count = kind.touchpoint_count() as usize;
+        let touch_point_w = (size.0 - GAP) / touch_point_count.max(1) - GAP;
+
+        let touch_points = (0..touch_point_count)
+            .map(|point| ScreenSegment {
+                x: GAP + point * (touch_point_w + GAP),
+                y: size.1,
+                w: touch_point_w,
+                h: TOUCH_POINT_HEIGHT,
+            })
+            .collect::<Vec<_>>();
+
+        if touch_point_count > 0 {
+            size.1 += TOUCH_POINT_HEIGHT + GAP;
+        }
+
+        DeckLayout { kind, size, keys, touch_points, lcd }
+    }
+
+    /// Finds what is at the point of the preview, None for space between parts
+    pub fn hit_test(&self, x: usize, y: usize) -> Option<PreviewHit> {
+        if let Some(key) = self.keys.iter().position(|rect| rect.contains(x, y)) {
+            return Some(PreviewHit::Key(key as u8));
+        }
+
+        if let Some(point) = self.touch_points.iter().position(|rect| rect.contains(x, y)) {
+            return Some(PreviewHit::TouchPoint(point as u8));
+        }
+
+        let lcd = self.lcd.filter(|lcd| lcd.contains(x, y))?;
+        let screen = self.kind.screen(Screen::LCD_STRIP)?;
+        let (x, y) = (x - lcd.x, y - lcd.y);
+
+        Some(PreviewHit::Screen {
+            id: screen.id,
+            x,
+            y,
+            segment: screen.segments.iter().position(|segment| segment.contains(x, y)).map(|segment| segment as u8),
+        })
+    }
+
+    /// Finds what is at the point of the preview drawn scaled to the size, for previews that don't match [size](DeckLayout::size)
+    pub fn hit_test_scaled(&self, x: f32, y: f32, drawn_size: (f32, f32)) -> Option<PreviewHit> {
+        if drawn_size.0 <= 0.0 || drawn_size.1 <= 0.0 || x < 0.0 || y < 0.0 {
+            return None;
+        }
+
+        self.hit_test((x * self.size.0 as f32 / drawn_size.0) as usize, (y * self.size.1 as f32 / drawn_size.1) as usize)
+    }
+}
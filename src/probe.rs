@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+use hidapi::HidApi;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+
+use crate::info::{is_vendor_familiar, Kind};
+use crate::protocol::{
+    StringReport, GEN1_FEATURE_REPORT_LENGTH, GEN1_FIRMWARE_VERSION_REPORT_ID, GEN1_SERIAL_NUMBER_REPORT_ID, GEN2_FEATURE_REPORT_LENGTH, GEN2_FIRMWARE_VERSION_REPORT_ID, GEN2_SERIAL_NUMBER_REPORT_ID,
+    MODULE_FIRMWARE_VERSION_REPORT_ID,
+};
+use crate::transport::Transport;
+use crate::util::{extract_str_lossy, get_feature_report};
+use crate::{ConnectOptions, StreamDeck, StreamDeckError};
+
+/// Feature reports read while fingerprinting as (report id, length), all of them only query information
+const HARMLESS_FEATURE_REPORTS: [(u8, usize); 6] = [
+    (GEN1_SERIAL_NUMBER_REPORT_ID, GEN1_FEATURE_REPORT_LENGTH),
+    (GEN1_SERIAL_NUMBER_REPORT_ID, GEN2_FEATURE_REPORT_LENGTH),
+    (GEN1_FIRMWARE_VERSION_REPORT_ID, GEN1_FEATURE_REPORT_LENGTH),
+    (GEN2_FIRMWARE_VERSION_REPORT_ID, GEN2_FEATURE_REPORT_LENGTH),
+    (GEN2_SERIAL_NUMBER_REPORT_ID, GEN2_FEATURE_REPORT_LENGTH),
+    (MODULE_FIRMWARE_VERSION_REPORT_ID, GEN1_FEATURE_REPORT_LENGTH),
+];
+
+/// Kinds a provisional kind is picked from, in order of preference when several match
+const CANDIDATES: [Kind; 8] = [Kind::Mk2, Kind::XlV2, Kind::Neo, Kind::Plus, Kind::PlusXl, Kind::Pedal, Kind::MiniMk2, Kind::Original];
+
+/// Family of the protocol a device speaks, telling which feature reports it understands
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum ProtocolGeneration {
+    /// Original v1 and Mini family, using 17 byte feature reports
+    Gen1,
+    /// Everything newer, using 32 byte feature reports
+    Gen2,
+}
+
+impl ProtocolGeneration {
+    /// Protocol generation of the kind
+    pub fn of(kind: Kind) -> ProtocolGeneration {
+        match kind {
+            Kind::Original | Kind::Mini | Kind::MiniMk2 | Kind::MiniDiscord | Kind::MiniMk2Module => ProtocolGeneration::Gen1,
+            _ => ProtocolGeneration::Gen2,
+        }
+    }
+}
+
+/// What probing found out about a device
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ProbeReport {
+    /// Protocol generation, if the device answered a firmware version request of one
+    pub generation: Option<ProtocolGeneration>,
+    /// Firmware version the device reported
+    pub firmware: Option<String>,
+    /// Length of input reports including report id, according to the report descriptor
+    pub input_report_length: Option<usize>,
+    /// Key count of the known kind whose input reports have the same layout
+    pub key_count: Option<u8>,
+    /// Known kind that the device most likely behaves like, only a guess that might need adjusting
+    pub provisional_kind: Option<Kind>,
+}
+
+/// Device of a familiar vendor with unknown product ID, found by [probe_unknown_devices]
+#[derive(Clone, Debug)]
+pub struct ProbedDevice {
+    /// Vendor ID of the device
+    pub vendor_id: u16,
+    /// Product ID of the device
+    pub product_id: u16,
+    /// Serial number of the device
+    pub serial: Option<String>,
+    /// Platform specific path of the device
+    pub path: String,
+    /// What probing found out
+    pub report: ProbeReport,
+}
+
+/// Everything that can be found out about a device without changing its state, to be attached to new hardware issues
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DeviceFingerprint {
+    /// Version of this library that made the fingerprint
+    pub library_version: String,
+    /// Vendor ID of the device
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_hex_id"))]
+    pub vendor_id: Option<u16>,
+    /// Product ID of the device
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_hex_id"))]
+    pub product_id: Option<u16>,
+    /// Platform specific path of the device
+    pub path: Option<String>,
+    /// Interface number of the device
+    pub interface_number: Option<i32>,
+    /// HID usage page of the device
+    pub usage_page: Option<u16>,
+    /// HID usage of the device
+    pub usage: Option<u16>,
+    /// Manufacturer string of the device
+    pub manufacturer: Option<String>,
+    /// Product string of the device
+    pub product: Option<String>,
+    /// Serial number the operating system reports
+    pub serial: Option<String>,
+    /// HID report descriptor
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_hex"))]
+    pub report_descriptor: Vec<u8>,
+    /// Responses to harmless feature report requests as (report id, response or error)
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_feature_reports"))]
+    pub feature_reports: Vec<(u8, Result<Vec<u8>, String>)>,
+    /// Guesses of the prober
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub report: ProbeReport,
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl DeviceFingerprint {
+    /// Serializes the fingerprint as JSON, binary data is hex encoded
+    pub fn to_json(&self) -> Result<String, StreamDeckError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Writes the fingerprint as JSON into the file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), StreamDeckError> {
+        Ok(std::fs::write(path, self.to_json()?)?)
+    }
+}
+
+/// Converts the path for hidapi, failing with [InvalidInput](io::ErrorKind::InvalidInput) if it contains a NUL byte
+fn device_path(path: &str) -> Result<CString, StreamDeckError> {
+    CString::new(path).map_err(|e| StreamDeckError::IoError(io::Error::new(io::ErrorKind::InvalidInput, e)))
+}
+
+/// Opens device at the path without writing anything to it and gathers its fingerprint
+pub fn probe_unknown_device(hidapi: &HidApi, path: &str) -> Result<DeviceFingerprint, StreamDeckError> {
+    let c_path = device_path(path)?;
+    let device = hidapi.open_path(&c_path)?;
+
+    let mut fingerprint = fingerprint_transport(&device);
+    fingerprint.path = Some(path.to_string());
+
+    if let Some(info) = hidapi.device_list().find(|info| info.path() == c_path.as_c_str()) {
+        fingerprint.vendor_id = Some(info.vendor_id());
+        fingerprint.product_id = Some(info.product_id());
+        fingerprint.interface_number = Some(info.interface_number());
+        fingerprint.usage_page = Some(info.usage_page());
+        fingerprint.usage = Some(info.usage());
+        fingerprint.serial = info.serial_number().map(|serial| serial.to_string());
+    }
+
+    Ok(fingerprint)
+}
+
+/// Gathers fingerprint of the device behind the transport, only reading from it
+pub fn fingerprint_transport(transport: &dyn Transport) -> DeviceFingerprint {
+    let mut descriptor = vec![0u8; 4096];
+    let descriptor_length = transport.report_descriptor(&mut descriptor).unwrap_or(0);
+    descriptor.truncate(descriptor_length);
+
+    DeviceFingerprint {
+        library_version: env!("CARGO_PKG_VERSION").to_string(),
+        manufacturer: transport.manufacturer().ok().flatten(),
+        product: transport.product().ok().flatten(),
+        report_descriptor: descriptor,
+        feature_reports: HARMLESS_FEATURE_REPORTS
+            .iter()
+            .map(|(report_id, length)| (*report_id, get_feature_report(transport, *report_id, *length).map_err(|e| e.to_string())))
+            .collect(),
+        report: probe_transport(transport),
+        ..Default::default()
+    }
+}
+
+/// Probes the device with requests that don't change its state, only reading firmware version and report descriptor
+pub fn probe_transport(transport: &dyn Transport) -> ProbeReport {
+    let mut report = ProbeReport::default();
+
+    // Kinds stand for their generation, which all read firmware version the same way
+    for (generation, kind) in [(ProtocolGeneration::Gen2, Kind::Mk2), (ProtocolGeneration::Gen1, Kind::Mini)] {
+        let firmware_report = StringReport::firmware_version(kind);
+
+        let Ok(bytes) = get_feature_report(transport, firmware_report.report_id, firmware_report.length) else {
+            continue;
+        };
+
+        if let Some(firmware) = bytes.get(firmware_report.offset..).map(extract_str_lossy)
+            && is_plausible_version(&firmware)
+        {
+            report.generation = Some(generation);
+            report.firmware = Some(firmware);
+            break;
+        }
+    }
+
+    let mut descriptor = vec![0u8; 4096];
+
+    if let Ok(length) = transport.report_descriptor(&mut descriptor)
+        && length > 0
+    {
+        report.input_report_length = input_report_length(&descriptor[..length]);
+    }
+
+    let matching = CANDIDATES
+        .into_iter()
+        .filter(|kind| report.generation == Some(ProtocolGeneration::of(*kind)))
+        .find(|kind| report.input_report_length == Some(kind.report_layout().length));
+
+    report.key_count = matching.map(|kind| kind.key_count());
+
+    // Without a matching layout, the most common kind of the generation is the best guess
+    report.provisional_kind = matching.or(match report.generation {
+        Some(ProtocolGeneration::Gen1) => Some(Kind::MiniMk2),
+        Some(ProtocolGeneration::Gen2) => Some(Kind::Mk2),
+        None => None,
+    });
+
+    report
+}
+
+/// Probes devices of familiar vendors that have product IDs this library doesn't know.
+/// Devices are only probed when this is called, and ones that can't be opened are skipped
+pub fn probe_unknown_devices(hidapi: &HidApi) -> Vec<ProbedDevice> {
+    hidapi
+        .device_list()
+        .filter(|info| is_vendor_familiar(&info.vendor_id()) && Kind::from_vid_pid(info.vendor_id(), info.product_id()).is_none())
+        .filter_map(|info| {
+            let device = info.open_device(hidapi).ok()?;
+
+            Some(ProbedDevice {
+                vendor_id: info.vendor_id(),
+                product_id: info.product_id(),
+                serial: info.serial_number().map(|serial| serial.to_string()),
+                path: info.path().to_string_lossy().into_owned(),
+                report: probe_transport(&device),
+            })
+        })
+        .collect()
+}
+
+/// Connects to the probed device, treating it as its provisional kind.
+/// Fails with [StreamDeckError::UnrecognizedPID] if probing didn't come up with a kind
+pub fn connect_provisional(hidapi: &HidApi, device: &ProbedDevice, options: ConnectOptions) -> Result<StreamDeck, StreamDeckError> {
+    let kind = device.report.provisional_kind.ok_or(StreamDeckError::UnrecognizedPID)?;
+
+    let path = device_path(&device.path)?;
+    let transport = hidapi.open_path(&path)?;
+
+    Ok(StreamDeck::from_transport(kind, transport, options))
+}
+
+/// Tells if the string looks like a firmware version, rather than garbage of a report the device doesn't understand
+fn is_plausible_version(version: &str) -> bool {
+    let version = version.trim();
+    !version.is_empty() && version.contains('.') && version.chars().all(|c| c.is_ascii_alphanumeric() || c == '.')
+}
+
+/// Computes length of input report with id 1 from the report descriptor, including the report id byte
+fn input_report_length(descriptor: &[u8]) -> Option<usize> {
+    let mut bits: HashMap<u8, usize> = HashMap::new();
+    // Report size, report count and report id
+    let mut globals = (0usize, 0usize, 0u8);
+    let mut stack = vec![];
+    let mut uses_ids = false;
+
+    let mut index = 0;
+
+    while index < descriptor.len() {
+        let prefix = descriptor[index];
+
+        // Long items don't carry anything of interest
+        if prefix == 0xfe {
+            index += 3 + *descriptor.get(index + 1)? as usize;
+            continue;
+        }
+
+        let size = match prefix & 0x3 {
+            3 => 4,
+            size => size as usize,
+        };
+
+        let data = descriptor.get(index + 1..index + 1 + size)?;
+        let value = data.iter().rev().fold(0usize, |value, byte| (value << 8) | *byte as usize);
+
+        match prefix & 0xfc {
+            0x74 => globals.0 = value,
+            0x94 => globals.1 = value,
+            0x84 => {
+                globals.2 = value as u8;
+                uses_ids = true;
+            }
+            0xa4 => stack.push(globals),
+            0xb4 => globals = stack.pop()?,
+            // Sizes that overflow can only come from a broken descriptor
+            0x80 => {
+                let field_bits = bits.entry(globals.2).or_default();
+                *field_bits = field_bits.checked_add(globals.0.checked_mul(globals.1)?)?;
+            }
+            _ => {}
+        }
+
+        index += 1 + size;
+    }
+
+    if uses_ids { Some(bits.get(&1)?.div_ceil(8) + 1) } else { Some(bits.get(&0)?.div_ceil(8)) }
+}
+
+#[cfg(feature = "serde")]
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(feature = "serde")]
+fn serialize_hex_id<S: Serializer>(id: &Option<u16>, serializer: S) -> Result<S::Ok, S::Error> {
+    id.map(|id| format!("{:04x}", id)).serialize(serializer)
+}
+
+#[cfg(feature = "serde")]
+fn serialize_hex<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex(data))
+}
+
+/// Serializes feature reports as objects with report id, and hex encoded data or error
+#[cfg(feature = "serde")]
+fn serialize_feature_reports<S: Serializer>(reports: &[(u8, Result<Vec<u8>, String>)], serializer: S) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct FeatureReport<'a> {
+        report_id: u8,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        data: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<&'a str>,
+    }
+
+    serializer.collect_seq(reports.iter().map(|(report_id, response)| FeatureReport {
+        report_id: *report_id,
+        data: response.as_ref().ok().map(|data| hex(data)),
+        error: response.as_ref().err().map(String::as_str),
+    }))
+}
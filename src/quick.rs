@@ -0,0 +1,113 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::util::normalize_serial;
+use crate::{list_devices, new_hidapi, ConnectOptions, StreamDeck, StreamDeckError};
+
+/// How many times opening the device is attempted by default
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+
+/// How long opening the device waits before the next attempt by default
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Opens a device, performs a single command on it and closes it, for scripts and one-shot command line tools.
+///
+/// No reader, manager or background flushing is set up, so the command runs as soon as the device is open.
+/// Opening is attempted again when it fails with errors that can go away by themselves, like the device being busy or reconnecting.
+/// The command runs once, since it isn't known whether it's safe to repeat. Images staged by the command are flushed before the device is closed
+/// ```no_run
+/// # use elgato_streamdeck::quick::QuickCommand;
+/// QuickCommand::new().serial("AL12H1A00000").run(|device| device.set_brightness(50)).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct QuickCommand {
+    serial: Option<String>,
+    options: ConnectOptions,
+    attempts: u32,
+    retry_delay: Duration,
+}
+
+impl QuickCommand {
+    /// Creates command that runs on the first device found, ordered by serial number
+    pub fn new() -> QuickCommand {
+        QuickCommand {
+            serial: None,
+            options: ConnectOptions::default(),
+            attempts: DEFAULT_ATTEMPTS,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+
+    /// Runs the command on the device with the serial number instead
+    pub fn serial(mut self, serial: &str) -> QuickCommand {
+        self.serial = Some(normalize_serial(serial));
+        self
+    }
+
+    /// Sets options the device is connected with
+    pub fn options(mut self, options: ConnectOptions) -> QuickCommand {
+        self.options = options;
+        self
+    }
+
+    /// Sets how many times opening the device is attempted, at least once
+    pub fn attempts(mut self, attempts: u32) -> QuickCommand {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Sets how long to wait before the next attempt
+    pub fn retry_delay(mut self, delay: Duration) -> QuickCommand {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Opens the device and performs the command, returning its result.
+    /// Fails with [StreamDeckError::DeviceNotFound] if no matching device was found in any attempt
+    pub fn run<T>(&self, command: impl FnOnce(&StreamDeck) -> Result<T, StreamDeckError>) -> Result<T, StreamDeckError> {
+        let device = self.open()?;
+        let result = command(&device)?;
+        device.flush()?;
+
+        Ok(result)
+    }
+
+    /// Opens the device, attempting again while the error can go away by itself
+    fn open(&self) -> Result<StreamDeck, StreamDeckError> {
+        let mut attempt = 1;
+
+        loop {
+            match self.try_open() {
+                Err(error) if attempt < self.attempts && is_transient(&error) => {
+                    attempt += 1;
+                    sleep(self.retry_delay);
+                }
+
+                result => return result,
+            }
+        }
+    }
+
+    fn try_open(&self) -> Result<StreamDeck, StreamDeckError> {
+        let hidapi = new_hidapi()?;
+
+        let (kind, serial) = list_devices(&hidapi)
+            .into_iter()
+            .filter(|(_, serial)| self.serial.as_ref().is_none_or(|wanted| wanted == serial))
+            .min_by(|(_, a), (_, b)| a.cmp(b))
+            .ok_or(StreamDeckError::DeviceNotFound)?;
+
+        StreamDeck::connect_with_options(&hidapi, kind, &serial, self.options.clone())
+    }
+}
+
+impl Default for QuickCommand {
+    fn default() -> Self {
+        QuickCommand::new()
+    }
+}
+
+/// Tells if the error can go away by trying again, like when the device is busy or still reconnecting
+fn is_transient(error: &StreamDeckError) -> bool {
+    matches!(error, StreamDeckError::HidError(_) | StreamDeckError::Timeout | StreamDeckError::DeviceNotFound)
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use crate::DeviceStateUpdate;
+
+/// Update of a [Macro], with time since the previous one
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct MacroStep {
+    /// Time since the previous step, zero for the first step
+    pub delay: Duration,
+    /// Press, release or twist that happened
+    pub update: DeviceStateUpdate,
+}
+
+/// Recorded sequence of presses, releases and twists with timings between them, that applications can store and replay
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub struct Macro {
+    /// Steps in the order they happened
+    pub steps: Vec<MacroStep>,
+}
+
+impl Macro {
+    /// Time from the first step to the last
+    pub fn duration(&self) -> Duration {
+        self.steps.iter().map(|step| step.delay).sum()
+    }
+
+    /// Shortens delays longer than the maximum to it, for macros recorded with pauses that shouldn't be replayed
+    pub fn with_max_delay(mut self, max: Duration) -> Macro {
+        for step in &mut self.steps {
+            step.delay = step.delay.min(max);
+        }
+
+        self
+    }
+
+    /// Updates of the macro without timings
+    pub fn updates(&self) -> impl Iterator<Item = DeviceStateUpdate> + '_ {
+        self.steps.iter().map(|step| step.update)
+    }
+}
+
+/// Records updates into a [Macro], keeping only presses, releases and twists.
+///
+/// Pass it updates along with the time they were read from [read_timed](crate::DeviceStateReader::read_timed),
+/// so timings aren't skewed by how long the application took to handle previous updates
+///
+/// Macros aren't derived from [recent_activity](crate::StreamDeck::recent_activity), since the activity log keeps a bounded number
+/// of truncated raw reports with wall clock times, while macros need every update after debouncing and held input handling
+#[derive(Clone, Debug, Default)]
+pub struct MacroRecorder {
+    steps: Vec<MacroStep>,
+    last: Option<Instant>,
+}
+
+impl MacroRecorder {
+    /// Creates empty recorder
+    pub fn new() -> MacroRecorder {
+        MacroRecorder::default()
+    }
+
+    /// Records updates that happened at the time, skipping touch screen updates
+    pub fn record(&mut self, updates: &[DeviceStateUpdate], at: Instant) {
+        for update in updates.iter().filter(|update| is_recorded(update)) {
+            let delay = self.last.map(|last| at.saturating_duration_since(last)).unwrap_or(Duration::ZERO);
+            self.last = Some(at);
+            self.steps.push(MacroStep { delay, update: *update });
+        }
+    }
+
+    /// Records updates that happened just now, see [record](MacroRecorder::record)
+    pub fn record_now(&mut self, updates: &[DeviceStateUpdate]) {
+        self.record(updates, Instant::now());
+    }
+
+    /// Amount of steps recorded so far
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Tells if nothing was recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Returns what was recorded and starts over
+    pub fn finish(&mut self) -> Macro {
+        self.last = None;
+
+        Macro {
+            steps: std::mem::take(&mut self.steps),
+        }
+    }
+}
+
+fn is_recorded(update: &DeviceStateUpdate) -> bool {
+    matches!(
+        update,
+        DeviceStateUpdate::ButtonDown(_)
+            | DeviceStateUpdate::ButtonUp(_)
+            | DeviceStateUpdate::EncoderDown(_)
+            | DeviceStateUpdate::EncoderUp(_)
+            | DeviceStateUpdate::EncoderTwist(_, _)
+            | DeviceStateUpdate::TouchPointDown(_)
+            | DeviceStateUpdate::TouchPointUp(_)
+            | DeviceStateUpdate::TouchKeyDown(_)
+            | DeviceStateUpdate::TouchKeyUp(_)
+    )
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_IN_DAY: u64 = 24 * 60 * 60;
+
+/// Changes brightness by time of day windows, smoothly transitioning between them
+///
+/// Standard library has no access to the local time zone, so offset from UTC has to be provided by the application
+#[derive(Clone, Debug, Default)]
+pub struct BrightnessSchedule {
+    /// Windows as (seconds since midnight, brightness), sorted by start time
+    windows: Vec<(u64, u8)>,
+    transition: Duration,
+    utc_offset: i32,
+}
+
+impl BrightnessSchedule {
+    /// Creates empty schedule
+    pub fn new() -> BrightnessSchedule {
+        Self::default()
+    }
+
+    /// Adds a window starting at specified time of day with provided brightness,
+    /// the window lasts until the next window starts
+    pub fn window(mut self, hour: u8, minute: u8, brightness: u8) -> BrightnessSchedule {
+        let start = (hour as u64 % 24) * 60 * 60 + (minute as u64 % 60) * 60;
+
+        self.windows.retain(|(s, _)| *s != start);
+        self.windows.push((start, brightness.min(100)));
+        self.windows.sort_by_key(|(s, _)| *s);
+
+        self
+    }
+
+    /// Sets how long brightness takes to change from previous window to the next one
+    pub fn transition(mut self, transition: Duration) -> BrightnessSchedule {
+        self.transition = transition;
+        self
+    }
+
+    /// Sets offset of the local time zone from UTC in seconds
+    pub fn utc_offset(mut self, seconds: i32) -> BrightnessSchedule {
+        self.utc_offset = seconds;
+        self
+    }
+
+    /// Brightness at provided time of day, None if schedule has no windows
+    pub fn brightness_at(&self, time_of_day: Duration) -> Option<u8> {
+        let now = time_of_day.as_secs() % SECONDS_IN_DAY;
+
+        // Window that started last, wrapping around to the previous day
+        let current = self.windows.iter().rposition(|(start, _)| *start <= now).unwrap_or(self.windows.len().checked_sub(1)?);
+        let previous = if current == 0 { self.windows.len() - 1 } else { current - 1 };
+
+        let (start, brightness) = self.windows[current];
+        let (_, previous_brightness) = self.windows[previous];
+
+        let elapsed = (now + SECONDS_IN_DAY - start) % SECONDS_IN_DAY;
+
+        if elapsed >= self.transition.as_secs() {
+            return Some(brightness);
+        }
+
+        let progress = elapsed as f32 / self.transition.as_secs_f32();
+        Some((previous_brightness as f32 + (brightness as f32 - previous_brightness as f32) * progress).round() as u8)
+    }
+
+    /// Brightness right now according to system clock, None if schedule has no windows
+    pub fn current_brightness(&self) -> Option<u8> {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        let local = (since_epoch + self.utc_offset as i64).rem_euclid(SECONDS_IN_DAY as i64);
+
+        self.brightness_at(Duration::from_secs(local as u64))
+    }
+}
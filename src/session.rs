@@ -0,0 +1,191 @@
+use std::cell::RefCell;
+use std::io;
+use std::iter::once;
+use std::ptr::{null, null_mut};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{spawn, JoinHandle};
+use std::time::Duration;
+
+use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::System::RemoteDesktop::{WTSRegisterSessionNotification, WTSUnRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION};
+use windows_sys::Win32::System::Threading::GetCurrentThreadId;
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW, PostThreadMessageW, RegisterClassW, HWND_MESSAGE, MSG, WM_QUIT, WM_WTSSESSION_CHANGE, WNDCLASSW,
+    WTS_CONSOLE_CONNECT, WTS_CONSOLE_DISCONNECT, WTS_REMOTE_CONNECT, WTS_REMOTE_DISCONNECT, WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
+};
+
+use crate::{StreamDeck, StreamDeckError};
+
+/// Change of the Windows session the application runs in
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum SessionEvent {
+    /// Session got locked
+    Locked,
+    /// Session got unlocked
+    Unlocked,
+    /// Session was switched away from, by fast user switching or a remote connection ending
+    Disconnected,
+    /// Session was switched back to
+    Connected,
+}
+
+impl SessionEvent {
+    /// Tells if devices should be suspended, HID handles often stop working until the session comes back
+    pub fn suspends(&self) -> bool {
+        matches!(self, SessionEvent::Locked | SessionEvent::Disconnected)
+    }
+
+    /// [Suspends](StreamDeck::suspend) or [resumes](StreamDeck::resume) the device depending on the event
+    pub fn apply(&self, device: &StreamDeck) -> Result<(), StreamDeckError> {
+        if self.suspends() {
+            device.suspend();
+            Ok(())
+        } else {
+            device.resume()
+        }
+    }
+
+    fn from_code(code: u32) -> Option<SessionEvent> {
+        match code {
+            WTS_SESSION_LOCK => Some(SessionEvent::Locked),
+            WTS_SESSION_UNLOCK => Some(SessionEvent::Unlocked),
+            WTS_CONSOLE_DISCONNECT | WTS_REMOTE_DISCONNECT => Some(SessionEvent::Disconnected),
+            WTS_CONSOLE_CONNECT | WTS_REMOTE_CONNECT => Some(SessionEvent::Connected),
+            _ => None,
+        }
+    }
+}
+
+thread_local! {
+    /// Where the window procedure of the watcher thread sends events
+    static EVENTS: RefCell<Option<Sender<SessionEvent>>> = const { RefCell::new(None) };
+}
+
+/// Receives lock, unlock and fast user switching notifications of the session on a background thread,
+/// so applications running as a service or in the tray can suspend devices while the session is away
+/// ```no_run
+/// # use elgato_streamdeck::session::SessionWatcher;
+/// # fn example(device: &elgato_streamdeck::StreamDeck) -> Result<(), elgato_streamdeck::StreamDeckError> {
+/// let watcher = SessionWatcher::new()?;
+///
+/// while let Some(event) = watcher.recv() {
+///     event.apply(device)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct SessionWatcher {
+    thread_id: u32,
+    events: Receiver<SessionEvent>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SessionWatcher {
+    /// Registers for notifications of the current session
+    pub fn new() -> Result<SessionWatcher, StreamDeckError> {
+        let (sender, events) = channel();
+        let (ready_sender, ready) = channel();
+
+        let thread = spawn(move || watch(sender, ready_sender));
+
+        let thread_id = match ready.recv() {
+            Ok(result) => result?,
+            Err(_) => return Err(io::Error::other("Session watcher thread exited").into()),
+        };
+
+        Ok(SessionWatcher {
+            thread_id,
+            events,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns the next event without waiting
+    pub fn try_recv(&self) -> Option<SessionEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Waits for the next event, None if the watcher stopped
+    pub fn recv(&self) -> Option<SessionEvent> {
+        self.events.recv().ok()
+    }
+
+    /// Waits for the next event up to the timeout
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<SessionEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for SessionWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            PostThreadMessageW(self.thread_id, WM_QUIT, 0, 0);
+        }
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// Creates a message-only window registered for session notifications and pumps its messages until WM_QUIT
+fn watch(events: Sender<SessionEvent>, ready: Sender<io::Result<u32>>) {
+    let class_name = "ElgatoStreamDeckSessionWatcher".encode_utf16().chain(once(0)).collect::<Vec<u16>>();
+
+    unsafe {
+        let instance = GetModuleHandleW(null());
+
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_procedure),
+            hInstance: instance,
+            lpszClassName: class_name.as_ptr(),
+            ..Default::default()
+        };
+
+        // Fails if another watcher registered the class already, which is fine
+        RegisterClassW(&class);
+
+        let window = CreateWindowExW(0, class_name.as_ptr(), null(), 0, 0, 0, 0, 0, HWND_MESSAGE, null_mut(), instance, null());
+
+        if window.is_null() {
+            ready.send(Err(io::Error::last_os_error())).ok();
+            return;
+        }
+
+        if WTSRegisterSessionNotification(window, NOTIFY_FOR_THIS_SESSION) == 0 {
+            let error = io::Error::last_os_error();
+            DestroyWindow(window);
+            ready.send(Err(error)).ok();
+            return;
+        }
+
+        EVENTS.with(|slot| *slot.borrow_mut() = Some(events));
+        ready.send(Ok(GetCurrentThreadId())).ok();
+
+        let mut message = MSG::default();
+
+        while GetMessageW(&mut message, null_mut(), 0, 0) > 0 {
+            DispatchMessageW(&message);
+        }
+
+        WTSUnRegisterSessionNotification(window);
+        DestroyWindow(window);
+    }
+}
+
+unsafe extern "system" fn window_procedure(window: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if message == WM_WTSSESSION_CHANGE
+        && let Some(event) = SessionEvent::from_code(wparam as u32)
+    {
+        EVENTS.with(|slot| {
+            if let Some(events) = &*slot.borrow() {
+                events.send(event).ok();
+            }
+        });
+
+        return 0;
+    }
+
+    unsafe { DefWindowProcW(window, message, wparam, lparam) }
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::info::ImageRotation;
+use crate::{DeviceId, StreamDeck, StreamDeckError};
+
+/// Settings remembered for a single device.
+///
+/// Devices have no setting for orientation or color profile, so [apply](DeviceSettings::apply) leaves those alone
+/// and they're only stored for the application to use when drawing images for the device
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceSettings {
+    /// Brightness of the device, value range is 0 - 100
+    pub brightness: Option<u8>,
+    /// Rotation of the device as it's mounted, not applied by the crate
+    pub orientation: Option<ImageRotation>,
+    /// Name of the color profile images for the device are converted with, not applied by the crate
+    pub color_profile: Option<String>,
+    /// Name of the profile the application last used on the device
+    pub profile: Option<String>,
+}
+
+impl DeviceSettings {
+    /// Applies settings that the device understands to the device, which is only brightness
+    pub fn apply(&self, device: &StreamDeck) -> Result<(), StreamDeckError> {
+        if let Some(brightness) = self.brightness {
+            device.set_brightness(brightness)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Per-device settings persisted to a file, keyed by [DeviceId].
+/// The file is TOML if it has `.toml` extension and JSON otherwise
+#[derive(Clone, Debug)]
+pub struct SettingsStore {
+    path: PathBuf,
+    devices: BTreeMap<String, DeviceSettings>,
+}
+
+impl SettingsStore {
+    /// Loads settings from the file, store is empty if the file doesn't exist yet
+    pub fn open(path: impl AsRef<Path>) -> Result<SettingsStore, StreamDeckError> {
+        let path = path.as_ref().to_path_buf();
+
+        let devices = match fs::read(&path) {
+            Ok(data) if is_toml(&path) => toml::from_str(std::str::from_utf8(&data)?).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?,
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(SettingsStore { path, devices })
+    }
+
+    /// Returns path of the file the store is saved to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns settings of the device, if there are any
+    pub fn get(&self, id: &DeviceId) -> Option<&DeviceSettings> {
+        self.devices.get(&id.to_string())
+    }
+
+    /// Replaces settings of the device, changes must be saved with `.save()`
+    pub fn set(&mut self, id: &DeviceId, settings: DeviceSettings) {
+        self.devices.insert(id.to_string(), settings);
+    }
+
+    /// Returns mutable settings of the device, creating default settings if there are none.
+    /// Changes must be saved with `.save()`
+    pub fn entry(&mut self, id: &DeviceId) -> &mut DeviceSettings {
+        self.devices.entry(id.to_string()).or_default()
+    }
+
+    /// Forgets settings of the device, changes must be saved with `.save()`
+    pub fn remove(&mut self, id: &DeviceId) -> Option<DeviceSettings> {
+        self.devices.remove(&id.to_string())
+    }
+
+    /// Writes settings to the file
+    pub fn save(&self) -> Result<(), StreamDeckError> {
+        let data = if is_toml(&self.path) {
+            toml::to_string_pretty(&self.devices).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?.into_bytes()
+        } else {
+            serde_json::to_vec_pretty(&self.devices)?
+        };
+
+        fs::write(&self.path, data)?;
+        Ok(())
+    }
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+}
@@ -0,0 +1,576 @@
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroU32;
+use std::rc::Rc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use hidapi::HidError;
+use image::{DynamicImage, RgbaImage};
+use winit::application::ApplicationHandler;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy};
+use winit::window::{Window, WindowId};
+
+use crate::info::{ImageFormat, ImageMirroring, ImageRotation, Kind, ScreenSegment};
+use crate::palette::{simulate_deficiency, Deficiency};
+use crate::preview::{DeckLayout, PreviewHit};
+use crate::protocol::{brightness_command, decode_image_report, input_report, reset_command, ImageTarget, GEN2_COMMAND_REPORT_ID, GEN2_TOUCHPOINT_COLOR_OPCODE};
+use crate::transport::Transport;
+use crate::{ConnectOptions, StreamDeck, StreamDeckError, StreamDeckInput};
+
+const BACKGROUND: u32 = 0x202020;
+const EMPTY_KEY: u32 = 0x000000;
+const PRESSED_OUTLINE: u32 = 0xc0c0c0;
+
+/// Software Stream Deck that renders keys in a window and turns mouse input into input reports, for developing without hardware.
+///
+/// Devices connected with [connect](Simulator::connect) or made from [transport](Simulator::transport) use the standard API.
+/// Left click presses keys, touch points and the touch screen, right click presses the encoder below a part of the LCD and scrolling over it twists the encoder.
+///
+/// Window has to be ran with [run](Simulator::run) on the main thread, so the application should use the device from another thread
+pub struct Simulator {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    kind: Kind,
+    state: Mutex<SimulatedState>,
+    input: Mutex<VecDeque<Vec<u8>>>,
+    input_ready: Condvar,
+    redraw: Mutex<Option<EventLoopProxy<()>>>,
+}
+
+struct SimulatedState {
+    brightness: u8,
+    keys: Vec<Option<RgbaImage>>,
+    lcd: Option<RgbaImage>,
+    touchpoint_colors: Vec<(u8, u8, u8)>,
+    pending_images: HashMap<ImageTarget, Vec<u8>>,
+    buttons: Vec<bool>,
+    encoders: Vec<bool>,
+    deficiency: Option<Deficiency>,
+}
+
+impl Simulator {
+    /// Creates simulator of the kind, with all keys blank
+    pub fn new(kind: Kind) -> Simulator {
+        let lcd = kind.lcd_strip_size().map(|(w, h)| RgbaImage::new(w as u32, h as u32));
+
+        Simulator {
+            shared: Arc::new(Shared {
+                kind,
+                state: Mutex::new(SimulatedState {
+                    brightness: 100,
+                    keys: vec![None; kind.key_count() as usize],
+                    lcd,
+                    touchpoint_colors: vec![(0, 0, 0); kind.touchpoint_count() as usize],
+                    pending_images: HashMap::new(),
+                    buttons: vec![false; kind.report_layout().buttons.count],
+                    encoders: vec![false; kind.encoder_count() as usize],
+                    deficiency: None,
+                }),
+                input: Mutex::new(VecDeque::new()),
+                input_ready: Condvar::new(),
+                redraw: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Kind the simulator pretends to be
+    pub fn kind(&self) -> Kind {
+        self.shared.kind
+    }
+
+    /// Layout of the window, for finding what is at a point of it
+    pub fn layout(&self) -> DeckLayout {
+        DeckLayout::of(self.shared.kind)
+    }
+
+    /// Returns transport connected to the simulator, clones share the same simulator
+    pub fn transport(&self) -> SimulatedTransport {
+        SimulatedTransport { shared: self.shared.clone() }
+    }
+
+    /// Connects to the simulator like to a real device
+    pub fn connect(&self, options: ConnectOptions) -> StreamDeck {
+        StreamDeck::from_transport(self.shared.kind, self.transport(), options)
+    }
+
+    /// Previews the deck as it's seen with the color vision deficiency, None shows true colors
+    pub fn preview_deficiency(&self, deficiency: Option<Deficiency>) -> Result<(), StreamDeckError> {
+        self.shared.state.lock()?.deficiency = deficiency;
+        self.shared.request_redraw();
+
+        Ok(())
+    }
+
+    /// Opens the window and blocks until it's closed, has to be called from the main thread on some platforms
+    pub fn run(self) -> Result<(), StreamDeckError> {
+        let event_loop = EventLoop::new().map_err(|e| StreamDeckError::SimulatorError(e.to_string()))?;
+
+        *self.shared.redraw.lock()? = Some(event_loop.create_proxy());
+
+        let mut app = SimulatorApp {
+            shared: self.shared.clone(),
+            layout: DeckLayout::of(self.shared.kind),
+            window: None,
+            surface: None,
+            cursor: PhysicalPosition::new(0.0, 0.0),
+            error: None,
+        };
+
+        let result = event_loop.run_app(&mut app).map_err(|e| StreamDeckError::SimulatorError(e.to_string()));
+
+        *self.shared.redraw.lock()? = None;
+
+        match app.error {
+            Some(error) => Err(error),
+            None => result,
+        }
+    }
+}
+
+impl Shared {
+    fn request_redraw(&self) {
+        if let Ok(redraw) = self.redraw.lock()
+            && let Some(proxy) = redraw.as_ref()
+        {
+            let _ = proxy.send_event(());
+        }
+    }
+
+    fn push_input(&self, input: StreamDeckInput) {
+        let Some(report) = input_report(self.kind, &input) else {
+            return;
+        };
+
+        if let Ok(mut queue) = self.input.lock() {
+            queue.push_back(report);
+            self.input_ready.notify_all();
+        }
+    }
+
+    /// Collects image pages, decoding the image once its last page arrives
+    fn receive_image_report(&self, state: &mut SimulatedState, report: &[u8]) -> bool {
+        let Some(decoded) = decode_image_report(self.kind, report) else {
+            return false;
+        };
+
+        let buffer = state.pending_images.entry(decoded.target).or_default();
+
+        if decoded.page_number == 0 {
+            buffer.clear();
+        }
+
+        buffer.extend_from_slice(decoded.payload);
+
+        if !decoded.last_package {
+            return false;
+        }
+
+        let Some(data) = state.pending_images.remove(&decoded.target) else {
+            return false;
+        };
+
+        match decoded.target {
+            ImageTarget::Key(key) => {
+                let image = image::load_from_memory(&data).ok().map(|image| upright(image, self.kind.key_image_format()));
+
+                if let Some(slot) = state.keys.get_mut(key as usize) {
+                    *slot = image.map(|image| image.into_rgba8());
+                }
+            }
+
+            ImageTarget::LcdRegion { x, y, .. } => {
+                if let (Some(lcd), Ok(image)) = (state.lcd.as_mut(), image::load_from_memory(&data)) {
+                    image::imageops::replace(lcd, &image.into_rgba8(), x as i64, y as i64);
+                }
+            }
+
+            ImageTarget::LcdFill => {
+                if let (Some(lcd), Some(format), Ok(image)) = (state.lcd.as_mut(), self.kind.lcd_image_format(), image::load_from_memory(&data)) {
+                    image::imageops::replace(lcd, &upright(image, format).into_rgba8(), 0, 0);
+                }
+            }
+        }
+
+        true
+    }
+
+    fn receive_feature_report(&self, state: &mut SimulatedState, report: &[u8]) -> bool {
+        if report == reset_command(self.kind).as_slice() {
+            state.keys.iter_mut().for_each(|key| *key = None);
+            state.lcd = self.kind.lcd_strip_size().map(|(w, h)| RgbaImage::new(w as u32, h as u32));
+            state.brightness = 100;
+            return true;
+        }
+
+        if let Some(percent) = (0..=100).find(|percent| brightness_command(self.kind, *percent).as_slice() == report) {
+            state.brightness = percent;
+            return true;
+        }
+
+        // Touch point color command
+        if let [GEN2_COMMAND_REPORT_ID, GEN2_TOUCHPOINT_COLOR_OPCODE, index, red, green, blue, ..] = *report
+            && let Some(color) = index.checked_sub(self.kind.key_count()).and_then(|point| state.touchpoint_colors.get_mut(point as usize))
+        {
+            *color = (red, green, blue);
+            return true;
+        }
+
+        false
+    }
+}
+
+/// Undoes rotation and mirroring the image format applies, so the image is shown the way it was set
+fn upright(image: DynamicImage, format: ImageFormat) -> DynamicImage {
+    let image = match format.mirror {
+        ImageMirroring::None => image,
+        ImageMirroring::X => image.fliph(),
+        ImageMirroring::Y => image.flipv(),
+        ImageMirroring::Both => image.fliph().flipv(),
+    };
+
+    match format.rotation {
+        ImageRotation::Rot0 => image,
+        ImageRotation::Rot90 => image.rotate270(),
+        ImageRotation::Rot180 => image.rotate180(),
+        ImageRotation::Rot270 => image.rotate90(),
+    }
+}
+
+/// Transport that talks to a [Simulator] instead of hardware
+#[derive(Clone)]
+pub struct SimulatedTransport {
+    shared: Arc<Shared>,
+}
+
+impl Transport for SimulatedTransport {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        let redraw = match self.shared.state.lock() {
+            Ok(mut state) => self.shared.receive_image_report(&mut state, data),
+            Err(_) => false,
+        };
+
+        if redraw {
+            self.shared.request_redraw();
+        }
+
+        Ok(data.len())
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, HidError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        let Ok(mut queue) = self.shared.input.lock() else {
+            return Ok(0);
+        };
+
+        loop {
+            if let Some(report) = queue.pop_front() {
+                let length = report.len().min(buf.len());
+                buf[..length].copy_from_slice(&report[..length]);
+                return Ok(length);
+            }
+
+            let Some(remaining) = deadline.and_then(|deadline| deadline.checked_duration_since(Instant::now())) else {
+                return Ok(0);
+            };
+
+            queue = match self.shared.input_ready.wait_timeout(queue, remaining) {
+                Ok((queue, _)) => queue,
+                Err(_) => return Ok(0),
+            };
+        }
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        // Strings like serial number and firmware version are left empty
+        buf.iter_mut().skip(1).for_each(|byte| *byte = 0);
+        Ok(buf.len())
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError> {
+        let redraw = match self.shared.state.lock() {
+            Ok(mut state) => self.shared.receive_feature_report(&mut state, data),
+            Err(_) => false,
+        };
+
+        if redraw {
+            self.shared.request_redraw();
+        }
+
+        Ok(())
+    }
+
+    fn manufacturer(&self) -> Result<Option<String>, HidError> {
+        Ok(Some("Simulator".to_string()))
+    }
+
+    fn product(&self) -> Result<Option<String>, HidError> {
+        Ok(Some(format!("Simulated {:?}", self.shared.kind)))
+    }
+}
+
+struct SimulatorApp {
+    shared: Arc<Shared>,
+    layout: DeckLayout,
+    window: Option<Rc<Window>>,
+    surface: Option<softbuffer::Surface<Rc<Window>, Rc<Window>>>,
+    cursor: PhysicalPosition<f64>,
+    error: Option<StreamDeckError>,
+}
+
+impl SimulatorApp {
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: impl ToString) {
+        self.error = Some(StreamDeckError::SimulatorError(error.to_string()));
+        event_loop.exit();
+    }
+
+    fn draw(&mut self) -> Result<(), softbuffer::SoftBufferError> {
+        let (Some(window), Some(surface)) = (&self.window, &mut self.surface) else {
+            return Ok(());
+        };
+
+        let size = window.inner_size();
+        let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) else {
+            return Ok(());
+        };
+
+        surface.resize(width, height)?;
+        let mut buffer = surface.buffer_mut()?;
+        buffer.fill(BACKGROUND);
+
+        let Ok(state) = self.shared.state.lock() else {
+            return buffer.present();
+        };
+
+        let (width, height) = (size.width as usize, size.height as usize);
+        let brightness = state.brightness.min(100) as u32;
+
+        let mut put = |x: usize, y: usize, color: u32| {
+            if x < width && y < height {
+                buffer[y * width + x] = color;
+            }
+        };
+
+        let deficiency = state.deficiency;
+
+        let rgb = |r: u8, g: u8, b: u8| {
+            let (r, g, b) = match deficiency {
+                Some(deficiency) => simulate_deficiency((r, g, b), deficiency),
+                None => (r, g, b),
+            };
+
+            ((r as u32 * brightness / 100) << 16) | ((g as u32 * brightness / 100) << 8) | (b as u32 * brightness / 100)
+        };
+
+        let draw_image = |rect: ScreenSegment, image: Option<&RgbaImage>, put: &mut dyn FnMut(usize, usize, u32)| {
+            for y in 0..rect.h {
+                for x in 0..rect.w {
+                    let color = match image.and_then(|image| image.get_pixel_checked(x as u32, y as u32)) {
+                        Some(pixel) => rgb(pixel[0], pixel[1], pixel[2]),
+                        None => EMPTY_KEY,
+                    };
+
+                    put(rect.x + x, rect.y + y, color);
+                }
+            }
+        };
+
+        for (key, rect) in self.layout.keys.iter().enumerate() {
+            draw_image(*rect, state.keys.get(key).and_then(|image| image.as_ref()), &mut put);
+
+            if state.buttons.get(key).copied().unwrap_or(false) {
+                outline(*rect, &mut put);
+            }
+        }
+
+        if let Some(rect) = self.layout.lcd {
+            draw_image(rect, state.lcd.as_ref(), &mut put);
+        }
+
+        for (point, rect) in self.layout.touch_points.iter().enumerate() {
+            let (red, green, blue) = state.touchpoint_colors.get(point).copied().unwrap_or_default();
+            let color = rgb(red, green, blue);
+
+            for y in 0..rect.h {
+                for x in 0..rect.w {
+                    put(rect.x + x, rect.y + y, color);
+                }
+            }
+
+            if state.buttons.get(self.shared.kind.key_count() as usize + point).copied().unwrap_or(false) {
+                outline(*rect, &mut put);
+            }
+        }
+
+        drop(state);
+        buffer.present()
+    }
+
+    fn mouse_input(&mut self, button: MouseButton, pressed: bool) {
+        let (x, y) = (self.cursor.x.max(0.0) as usize, self.cursor.y.max(0.0) as usize);
+        let kind = self.shared.kind;
+
+        let Ok(mut state) = self.shared.state.lock() else {
+            return;
+        };
+
+        let mut input = None;
+
+        let hit = self.layout.hit_test(x, y);
+
+        let hovered_button = match hit {
+            Some(PreviewHit::Key(key)) => Some(key as usize),
+            Some(PreviewHit::TouchPoint(point)) => Some(kind.key_count() as usize + point as usize),
+            _ => None,
+        };
+
+        match (button, hovered_button) {
+            // Releasing the button anywhere, so keys don't get stuck when the cursor leaves them
+            (MouseButton::Left, _) if !pressed && state.buttons.iter().any(|state| *state) => {
+                state.buttons.iter_mut().for_each(|state| *state = false);
+                input = Some(StreamDeckInput::ButtonStateChange(state.buttons.clone()));
+            }
+
+            (MouseButton::Left, Some(index)) if pressed => {
+                if let Some(button) = state.buttons.get_mut(index) {
+                    *button = true;
+                    input = Some(StreamDeckInput::ButtonStateChange(state.buttons.clone()));
+                }
+            }
+
+            (MouseButton::Left, None) if pressed => {
+                if let Some(PreviewHit::Screen { x, y, .. }) = hit {
+                    input = Some(StreamDeckInput::TouchScreenPress(x as u16, y as u16));
+                }
+            }
+
+            (MouseButton::Right, _) if !pressed && state.encoders.iter().any(|state| *state) => {
+                state.encoders.iter_mut().for_each(|state| *state = false);
+                input = Some(StreamDeckInput::EncoderStateChange(state.encoders.clone()));
+            }
+
+            (MouseButton::Right, _) if pressed => {
+                if let Some(encoder) = self.encoder_at(kind, hit)
+                    && let Some(state_of_encoder) = state.encoders.get_mut(encoder)
+                {
+                    *state_of_encoder = true;
+                    input = Some(StreamDeckInput::EncoderStateChange(state.encoders.clone()));
+                }
+            }
+
+            _ => {}
+        }
+
+        drop(state);
+
+        if let Some(input) = input {
+            self.shared.push_input(input);
+            self.shared.request_redraw();
+        }
+    }
+
+    fn mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let kind = self.shared.kind;
+        let (x, y) = (self.cursor.x.max(0.0) as usize, self.cursor.y.max(0.0) as usize);
+
+        let steps = match delta {
+            MouseScrollDelta::LineDelta(_, lines) => lines.round() as i32,
+            MouseScrollDelta::PixelDelta(position) => (position.y / 20.0).round() as i32,
+        };
+
+        if steps == 0 {
+            return;
+        }
+
+        let Some(encoder) = self.encoder_at(kind, self.layout.hit_test(x, y)) else {
+            return;
+        };
+
+        let mut values = vec![0i8; kind.encoder_count() as usize];
+        values[encoder] = steps.clamp(i8::MIN as i32, i8::MAX as i32) as i8;
+
+        self.shared.push_input(StreamDeckInput::EncoderTwist(values));
+    }
+
+    /// Encoder below the part of the LCD at the position
+    fn encoder_at(&self, kind: Kind, hit: Option<PreviewHit>) -> Option<usize> {
+        let Some(PreviewHit::Screen { segment: Some(segment), .. }) = hit else {
+            return None;
+        };
+
+        (segment < kind.encoder_count()).then_some(segment as usize)
+    }
+}
+
+fn outline(rect: ScreenSegment, put: &mut dyn FnMut(usize, usize, u32)) {
+    for x in rect.x.saturating_sub(2)..rect.x + rect.w + 2 {
+        for y in [rect.y.saturating_sub(2), rect.y.saturating_sub(1), rect.y + rect.h, rect.y + rect.h + 1] {
+            put(x, y, PRESSED_OUTLINE);
+        }
+    }
+
+    for y in rect.y..rect.y + rect.h {
+        for x in [rect.x.saturating_sub(2), rect.x.saturating_sub(1), rect.x + rect.w, rect.x + rect.w + 1] {
+            put(x, y, PRESSED_OUTLINE);
+        }
+    }
+}
+
+impl ApplicationHandler for SimulatorApp {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+
+        let attributes = Window::default_attributes()
+            .with_title(format!("{:?} simulator", self.shared.kind))
+            .with_inner_size(PhysicalSize::new(self.layout.size.0 as u32, self.layout.size.1 as u32))
+            .with_resizable(false);
+
+        let window = match event_loop.create_window(attributes) {
+            Ok(window) => Rc::new(window),
+            Err(e) => return self.fail(event_loop, e),
+        };
+
+        let surface = softbuffer::Context::new(window.clone()).and_then(|context| softbuffer::Surface::new(&context, window.clone()));
+
+        match surface {
+            Ok(surface) => {
+                self.surface = Some(surface);
+                self.window = Some(window);
+            }
+
+            Err(e) => self.fail(event_loop, e),
+        }
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, _event: ()) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+
+            WindowEvent::RedrawRequested => {
+                if let Err(e) = self.draw() {
+                    self.fail(event_loop, e);
+                }
+            }
+
+            WindowEvent::CursorMoved { position, .. } => self.cursor = position,
+
+            WindowEvent::MouseInput { state, button, .. } => self.mouse_input(button, state == ElementState::Pressed),
+
+            WindowEvent::MouseWheel { delta, .. } => self.mouse_wheel(delta),
+
+            _ => {}
+        }
+    }
+}
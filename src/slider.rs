@@ -0,0 +1,172 @@
+use image::imageops::crop_imm;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::images::convert_image_with_format;
+use crate::info::{ImageRotation, Kind, Screen, ScreenSegment};
+use crate::palette::StatusColor;
+use crate::{DeviceStateUpdate, StreamDeck, StreamDeckError};
+
+/// Change of a [TouchSlider]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct SliderChange {
+    /// Value before the change
+    pub previous: u8,
+    /// Value after the change
+    pub value: u8,
+}
+
+/// Slider on the touch screen of Stream Deck Plus, with value in 0 - 100 range set by pressing or swiping along it.
+///
+/// Track runs along the x axis of the region, in coordinates the device uses for touches and regions.
+/// Only parts of the slider that changed since the last write are sent to the device
+#[derive(Clone, Debug)]
+pub struct TouchSlider {
+    /// Part of the LCD strip the slider covers
+    pub region: ScreenSegment,
+    /// Color of the filled part of the track and the handle as (red, green, blue)
+    pub color: (u8, u8, u8),
+    value: u8,
+    /// Handle position that is on the device as (left, right) within the region, None if the slider wasn't written yet
+    written_handle: Option<(usize, usize)>,
+}
+
+impl TouchSlider {
+    /// Creates slider covering the region, starting at 0
+    pub fn new(region: ScreenSegment) -> TouchSlider {
+        TouchSlider {
+            region,
+            color: StatusColor::Info.rgb(),
+            value: 0,
+            written_handle: None,
+        }
+    }
+
+    /// Creates slider covering the whole LCD strip of the kind, None if the kind has no touch screen
+    pub fn for_kind(kind: Kind) -> Option<TouchSlider> {
+        if !kind.has_touch_screen() {
+            return None;
+        }
+
+        let (w, h) = kind.lcd_strip_size()?;
+        Some(TouchSlider::new(ScreenSegment { x: 0, y: 0, w, h }))
+    }
+
+    /// Current value
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+
+    /// Sets the value, clamped to 0 - 100 range
+    pub fn set_value(&mut self, value: u8) {
+        self.value = value.min(100);
+    }
+
+    /// Moves the slider if the update is a press or a swipe starting within the slider, returns the change if the value changed.
+    /// Swipes set the value where they end
+    pub fn handle(&mut self, update: &DeviceStateUpdate) -> Option<SliderChange> {
+        let x = match *update {
+            DeviceStateUpdate::TouchScreenPress(x, y) | DeviceStateUpdate::TouchScreenLongPress(x, y) if self.contains(x, y) => x,
+            DeviceStateUpdate::TouchScreenSwipe((x, y), (end_x, _)) if self.contains(x, y) => end_x,
+            _ => return None,
+        };
+
+        let (start, length) = self.track();
+        let offset = (x as f32 - (self.region.x + start) as f32).clamp(0.0, length as f32);
+        let value = (offset / length.max(1) as f32 * 100.0).round() as u8;
+
+        let previous = self.value;
+        self.set_value(value);
+
+        (self.value != previous).then_some(SliderChange { previous, value: self.value })
+    }
+
+    /// Renders the whole slider in size of the region
+    pub fn render(&self) -> RgbaImage {
+        let (w, h) = (self.region.w as u32, self.region.h as u32);
+        let (handle_left, handle_right) = self.handle_span();
+        let (track_start, track_length) = self.track();
+        let track_height = (h / 5).max(1);
+        let track_top = (h - track_height) / 2;
+        let margin = h / 10;
+        let (red, green, blue) = self.color;
+
+        RgbaImage::from_fn(w, h, |x, y| {
+            let xu = x as usize;
+
+            if xu >= handle_left && xu < handle_right && y >= margin && y < h - margin {
+                Rgba([red, green, blue, 255])
+            } else if y >= track_top && y < track_top + track_height && xu >= track_start && xu < track_start + track_length {
+                if xu < handle_left { Rgba([red / 2, green / 2, blue / 2, 255]) } else { Rgba([64, 64, 64, 255]) }
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        })
+    }
+
+    /// Writes parts of the slider that changed since the last write, the whole slider on first write.
+    /// Fails with [StreamDeckError::NoScreen] if the device has no LCD strip
+    pub fn write(&mut self, device: &StreamDeck) -> Result<(), StreamDeckError> {
+        let screen = device.kind().screen(Screen::LCD_STRIP).ok_or(StreamDeckError::NoScreen)?;
+        let handle = self.handle_span();
+
+        let (left, right) = match self.written_handle {
+            Some(written) if written == handle => return Ok(()),
+            Some((written_left, written_right)) => (written_left.min(handle.0), written_right.max(handle.1)),
+            None => (0, self.region.w),
+        };
+
+        let dirty = crop_imm(&self.render(), left as u32, 0, (right - left) as u32, self.region.h as u32).to_image();
+
+        // Slider is rendered in device coordinates, so rotation the conversion applies has to be undone first
+        let dirty = match screen.format.rotation {
+            ImageRotation::Rot0 => DynamicImage::ImageRgba8(dirty),
+            ImageRotation::Rot90 => DynamicImage::ImageRgba8(dirty).rotate270(),
+            ImageRotation::Rot180 => DynamicImage::ImageRgba8(dirty).rotate180(),
+            ImageRotation::Rot270 => DynamicImage::ImageRgba8(dirty).rotate90(),
+        };
+
+        let region = ScreenSegment {
+            x: self.region.x + left,
+            y: self.region.y,
+            w: right - left,
+            h: self.region.h,
+        };
+
+        let mut format = screen.format;
+        format.size = (region.w, region.h);
+
+        device.write_screen(screen.id, Some(region), &convert_image_with_format(format, dirty)?)?;
+        self.written_handle = Some(handle);
+
+        Ok(())
+    }
+
+    /// Forgets what was written, so the next write repaints the whole slider. Needed after changing region or color, or after the device was reset
+    pub fn invalidate(&mut self) {
+        self.written_handle = None;
+    }
+
+    fn contains(&self, x: u16, y: u16) -> bool {
+        let (x, y) = (x as usize, y as usize);
+        x >= self.region.x && x < self.region.x + self.region.w && y >= self.region.y && y < self.region.y + self.region.h
+    }
+
+    fn handle_width(&self) -> usize {
+        (self.region.h / 3).max(8).min(self.region.w)
+    }
+
+    /// Start and length of the track within the region, leaving room for the handle at both ends
+    fn track(&self) -> (usize, usize) {
+        let half = self.handle_width() / 2;
+        (half, self.region.w.saturating_sub(half * 2))
+    }
+
+    /// Left and right edge of the handle within the region
+    fn handle_span(&self) -> (usize, usize) {
+        let (start, length) = self.track();
+        let center = start + length * self.value as usize / 100;
+        let left = center.saturating_sub(self.handle_width() / 2);
+
+        (left, (left + self.handle_width()).min(self.region.w))
+    }
+}
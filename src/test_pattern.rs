@@ -0,0 +1,106 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::compositor::{draw_badge, Badge, BadgeCorner};
+use crate::info::{ImageRotation, Screen, ScreenSegment};
+
+const MARK_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Renders test pattern of a key: red grows to the right and green grows downwards, so rotation and mirroring are easy to tell apart,
+/// with a border showing cropping, a cross marking the center and the key index in a badge in the top left corner
+pub fn key_test_pattern(index: u8, size: (usize, usize)) -> DynamicImage {
+    let (w, h) = (size.0 as u32, size.1 as u32);
+    let mut canvas = gradient(w, h);
+
+    mark_region(&mut canvas, 0, 0, w, h);
+    numbered(canvas, index)
+}
+
+/// Renders test pattern of a screen in its own coordinates, gradient spans the whole screen
+/// and every segment gets a border, a cross and its index like [key_test_pattern] does
+pub fn screen_test_pattern(screen: &Screen) -> DynamicImage {
+    let (w, h) = (screen.resolution.0 as u32, screen.resolution.1 as u32);
+    let mut canvas = gradient(w, h);
+
+    let segments = if screen.segments.is_empty() {
+        vec![ScreenSegment {
+            x: 0,
+            y: 0,
+            w: screen.resolution.0,
+            h: screen.resolution.1,
+        }]
+    } else {
+        screen.segments.clone()
+    };
+
+    for (index, segment) in segments.iter().enumerate() {
+        let (x, y, sw, sh) = (segment.x as u32, segment.y as u32, segment.w as u32, segment.h as u32);
+
+        mark_region(&mut canvas, x, y, sw, sh);
+
+        let region = canvas.view(x, y, sw.min(w.saturating_sub(x)), sh.min(h.saturating_sub(y))).to_image();
+        let region = numbered(region, index as u8);
+
+        image::imageops::replace(&mut canvas, &region.to_rgba8(), x as i64, y as i64);
+    }
+
+    // Pattern is drawn in screen coordinates, so rotation the conversion applies has to be undone first
+    let image = DynamicImage::ImageRgba8(canvas);
+
+    match screen.format.rotation {
+        ImageRotation::Rot0 => image,
+        ImageRotation::Rot90 => image.rotate270(),
+        ImageRotation::Rot180 => image.rotate180(),
+        ImageRotation::Rot270 => image.rotate90(),
+    }
+}
+
+fn gradient(w: u32, h: u32) -> RgbaImage {
+    RgbaImage::from_fn(w, h, |x, y| {
+        let red = (x * 255 / w.saturating_sub(1).max(1)) as u8;
+        let green = (y * 255 / h.saturating_sub(1).max(1)) as u8;
+
+        Rgba([red, green, 96, 255])
+    })
+}
+
+/// Draws a border along edges of the region and a cross in its center
+fn mark_region(canvas: &mut RgbaImage, x: u32, y: u32, w: u32, h: u32) {
+    let (canvas_w, canvas_h) = canvas.dimensions();
+    let mut put = |px: u32, py: u32| {
+        if px < canvas_w && py < canvas_h {
+            canvas.put_pixel(px, py, MARK_COLOR);
+        }
+    };
+
+    if w == 0 || h == 0 {
+        return;
+    }
+
+    for px in x..x + w {
+        put(px, y);
+        put(px, y + h - 1);
+    }
+
+    for py in y..y + h {
+        put(x, py);
+        put(x + w - 1, py);
+    }
+
+    let (center_x, center_y) = (x + w / 2, y + h / 2);
+    let arm = w.min(h) / 6;
+
+    for offset in 0..=arm * 2 {
+        put(center_x - arm + offset, center_y);
+        put(center_x, center_y - arm + offset);
+    }
+}
+
+fn numbered(canvas: RgbaImage, index: u8) -> DynamicImage {
+    let badge = Badge {
+        corner: BadgeCorner::TopLeft,
+        color: (0, 0, 0),
+        ..Badge::number(index as u32)
+    };
+
+    draw_badge(&DynamicImage::ImageRgba8(canvas), &badge)
+}
@@ -0,0 +1,578 @@
+use std::fmt::{Debug, Formatter};
+use std::iter::zip;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ab_glyph::{point, Font as _, FontRef, Glyph, GlyphId, GlyphImageFormat, PxScale, ScaleFont};
+use image::imageops::{overlay, resize, FilterType};
+use image::{DynamicImage, Rgba, RgbaImage};
+use rustybuzz::{Direction, UnicodeBuffer};
+use unicode_bidi::BidiInfo;
+
+use crate::animation::KeyAnimation;
+use crate::compositor::{rounded_background, Badge, BadgeContent, BadgeCorner};
+use crate::info::Kind;
+use crate::StreamDeckError;
+
+/// Font used to render text, supports TrueType and OpenType fonts.
+///
+/// Characters missing from the font are taken from fallback fonts, in order they were added.
+/// Color glyphs stored as bitmaps (CBDT and sbix tables, used by Noto Color Emoji and Apple Color Emoji) are drawn in color,
+/// glyphs of COLR fonts are drawn with their outlines in text color
+#[derive(Clone)]
+pub struct Font {
+    faces: Vec<Face>,
+}
+
+/// Font file parsed once for drawing and once for shaping, both borrowing the same data
+#[derive(Clone)]
+struct Face {
+    /// Glyph outlines, metrics and bitmaps
+    glyphs: FontRef<'static>,
+    /// Tables used by the shaper
+    shaper: rustybuzz::Face<'static>,
+    /// Contents of the font file, declared last so it's dropped after the faces borrowing it
+    _data: Arc<[u8]>,
+}
+
+impl Face {
+    fn parse(data: Vec<u8>) -> Result<Face, StreamDeckError> {
+        let data: Arc<[u8]> = data.into();
+
+        // SAFETY: contents of the Arc never move and are only freed once its last clone is dropped.
+        // Every face holds a clone and drops the faces borrowing it first, so the data outlives them
+        let bytes: &'static [u8] = unsafe { &*(data.as_ref() as *const [u8]) };
+
+        Ok(Face {
+            glyphs: FontRef::try_from_slice(bytes).map_err(|_| StreamDeckError::InvalidFont)?,
+            shaper: rustybuzz::Face::from_slice(bytes, 0).ok_or(StreamDeckError::InvalidFont)?,
+            _data: data,
+        })
+    }
+}
+
+impl Debug for Font {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Font").field("faces", &self.faces.len()).finish()
+    }
+}
+
+impl Font {
+    /// Loads font from contents of a font file
+    pub fn from_bytes(data: Vec<u8>) -> Result<Font, StreamDeckError> {
+        Ok(Font { faces: vec![Face::parse(data)?] })
+    }
+
+    /// Adds font that is used for characters missing from this font, for example an emoji font
+    pub fn with_fallback(mut self, fallback: Font) -> Font {
+        self.faces.extend(fallback.faces);
+        self
+    }
+
+    /// Index of the first face that has a glyph for the character
+    fn face_for(&self, c: char) -> Option<usize> {
+        self.faces.iter().position(|face| face.glyphs.glyph_id(c).0 != 0)
+    }
+
+    /// Splits text into pieces that are drawn with the same face in the same direction, in order they are drawn from left to right
+    fn segments<'a>(&self, text: &'a str) -> Vec<(usize, &'a str, bool)> {
+        let bidi = BidiInfo::new(text, None);
+        let mut segments = vec![];
+
+        for paragraph in &bidi.paragraphs {
+            let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+
+            for run in runs {
+                let rtl = levels[run.start].is_rtl();
+                let mut pieces: Vec<(usize, Range<usize>)> = vec![];
+
+                for (offset, c) in text[run.clone()].char_indices() {
+                    let index = run.start + offset;
+
+                    // Characters no face has, like variation selectors, stay with the face of preceding characters
+                    let face = self.face_for(c).or(pieces.last().map(|(face, _)| *face)).unwrap_or(0);
+
+                    match pieces.last_mut() {
+                        Some((current, range)) if *current == face => range.end = index + c.len_utf8(),
+                        _ => pieces.push((face, index..index + c.len_utf8())),
+                    }
+                }
+
+                if rtl {
+                    pieces.reverse();
+                }
+
+                segments.extend(pieces.into_iter().map(|(face, range)| (face, &text[range], rtl)));
+            }
+        }
+
+        segments
+    }
+}
+
+/// Glyphs of a shaped line, positioned from the top left corner of the line
+struct ShapedLine {
+    /// Glyphs along with index of the face they come from
+    glyphs: Vec<(usize, Glyph)>,
+    /// Sum of advances of the glyphs
+    width: f32,
+}
+
+/// Shapes a single line of text with [rustybuzz](https://crates.io/crates/rustybuzz), laying glyphs of all segments out from left to right
+fn shape_line(font: &Font, text: &str, scale: PxScale) -> ShapedLine {
+    let primary = font.faces[0].glyphs.as_scaled(scale);
+
+    let mut glyphs = vec![];
+    let mut caret = 0.0;
+
+    for (face, segment, rtl) in font.segments(text) {
+        let scaled = font.faces[face].glyphs.as_scaled(scale);
+
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(segment);
+        buffer.set_direction(if rtl { Direction::RightToLeft } else { Direction::LeftToRight });
+
+        let shaped = rustybuzz::shape(&font.faces[face].shaper, &[], buffer);
+
+        // Shaped positions are in font units
+        let (h, v) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+
+        for (info, position) in zip(shaped.glyph_infos(), shaped.glyph_positions()) {
+            let origin = point(caret + position.x_offset as f32 * h, primary.ascent() - position.y_offset as f32 * v);
+            glyphs.push((face, GlyphId(info.glyph_id as u16).with_scale_and_position(scale, origin)));
+
+            caret += position.x_advance as f32 * h;
+        }
+    }
+
+    ShapedLine { glyphs, width: caret }
+}
+
+/// Renders a single line of text onto a transparent image that is exactly as wide as the text.
+///
+/// Text is shaped with [rustybuzz](https://crates.io/crates/rustybuzz), so ligatures, Arabic joining and Indic reordering work
+/// as long as the font supports them, and mixed left-to-right and right-to-left text is ordered by the Unicode bidirectional algorithm
+pub fn render_line(font: &Font, text: &str, size: f32, color: (u8, u8, u8)) -> RgbaImage {
+    let scale = PxScale::from(size);
+    let primary = font.faces[0].glyphs.as_scaled(scale);
+    let line = shape_line(font, text, scale);
+
+    let width = line.width.ceil().max(1.0) as u32;
+    let height = (primary.ascent() - primary.descent()).ceil().max(1.0) as u32;
+
+    let (red, green, blue) = color;
+    let mut canvas = RgbaImage::new(width, height);
+
+    for (face, glyph) in line.glyphs {
+        let face = &font.faces[face].glyphs;
+
+        if let Some(bitmap) = color_glyph(face, &glyph, size) {
+            overlay(&mut canvas, &bitmap.image, bitmap.x, bitmap.y);
+            continue;
+        }
+
+        let Some(outlined) = face.as_scaled(scale).outline_glyph(glyph) else {
+            continue;
+        };
+
+        let bounds = outlined.px_bounds();
+
+        outlined.draw(|x, y, coverage| {
+            let px = bounds.min.x as i32 + x as i32;
+            let py = bounds.min.y as i32 + y as i32;
+
+            if px < 0 || py < 0 || px >= width as i32 || py >= height as i32 {
+                return;
+            }
+
+            let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+            let alpha = ((coverage.clamp(0.0, 1.0) * 255.0) as u8).max(pixel.0[3]);
+            *pixel = Rgba([red, green, blue, alpha]);
+        });
+    }
+
+    canvas
+}
+
+/// Renders a single line of text like [render_line] on the blocking thread pool, so async applications don't block the runtime
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub async fn render_line_async(font: Font, text: String, size: f32, color: (u8, u8, u8)) -> Result<RgbaImage, StreamDeckError> {
+    Ok(tokio::task::spawn_blocking(move || render_line(&font, &text, size, color)).await?)
+}
+
+/// Horizontal alignment of lines of text
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub enum TextAlignment {
+    /// Lines start at the left edge
+    Left,
+    /// Lines are centered
+    #[default]
+    Center,
+    /// Lines end at the right edge
+    Right,
+}
+
+/// Vertical alignment of a block of text
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub enum VerticalAlignment {
+    /// Text starts at the top edge
+    Top,
+    /// Text is centered
+    #[default]
+    Middle,
+    /// Text ends at the bottom edge
+    Bottom,
+}
+
+/// How [render_text] lays text out on an image
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    font_size: f32,
+    color: (u8, u8, u8),
+    alignment: TextAlignment,
+    vertical_alignment: VerticalAlignment,
+    wrap: bool,
+    padding: u32,
+    line_spacing: f32,
+}
+
+impl TextStyle {
+    /// Creates style of centered text in the color, wrapped at word boundaries to fit the image
+    pub fn new(font_size: f32, color: (u8, u8, u8)) -> TextStyle {
+        TextStyle {
+            font_size,
+            color,
+            alignment: TextAlignment::Center,
+            vertical_alignment: VerticalAlignment::Middle,
+            wrap: true,
+            padding: 4,
+            line_spacing: 1.0,
+        }
+    }
+
+    /// Sets horizontal alignment of lines
+    pub fn alignment(mut self, alignment: TextAlignment) -> TextStyle {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Sets vertical alignment of the whole text
+    pub fn vertical_alignment(mut self, alignment: VerticalAlignment) -> TextStyle {
+        self.vertical_alignment = alignment;
+        self
+    }
+
+    /// Sets if lines too long to fit are wrapped at word boundaries, otherwise lines only break at line feeds and overflow the image
+    pub fn wrap(mut self, wrap: bool) -> TextStyle {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Sets space between edges of the image and the text in pixels
+    pub fn padding(mut self, pixels: u32) -> TextStyle {
+        self.padding = pixels;
+        self
+    }
+
+    /// Sets distance between lines as a multiple of the line height
+    pub fn line_spacing(mut self, factor: f32) -> TextStyle {
+        self.line_spacing = factor.max(0.0);
+        self
+    }
+}
+
+/// Draws text on top of the image, laid out according to the style. Use [key_text_image] for a key of a specific kind
+pub fn render_text(image: &DynamicImage, font: &Font, text: &str, style: &TextStyle) -> DynamicImage {
+    let mut canvas = image.to_rgba8();
+    let (w, h) = canvas.dimensions();
+    let available = w.saturating_sub(style.padding * 2).max(1);
+
+    let lines = text
+        .lines()
+        .flat_map(|paragraph| {
+            if style.wrap {
+                wrap_line(font, paragraph, style.font_size, available)
+            } else {
+                vec![paragraph.to_string()]
+            }
+        })
+        .map(|line| render_line(font, &line, style.font_size, style.color))
+        .collect::<Vec<_>>();
+
+    let Some(line_height) = lines.first().map(|line| line.height()) else {
+        return DynamicImage::ImageRgba8(canvas);
+    };
+
+    let advance = (line_height as f32 * style.line_spacing).round() as i64;
+    let block_height = advance * (lines.len() as i64 - 1) + line_height as i64;
+
+    let top = match style.vertical_alignment {
+        VerticalAlignment::Top => style.padding as i64,
+        VerticalAlignment::Middle => (h as i64 - block_height) / 2,
+        VerticalAlignment::Bottom => h as i64 - style.padding as i64 - block_height,
+    };
+
+    for (index, line) in lines.iter().enumerate() {
+        let x = match style.alignment {
+            TextAlignment::Left => style.padding as i64,
+            TextAlignment::Center => (w as i64 - line.width() as i64) / 2,
+            TextAlignment::Right => w as i64 - style.padding as i64 - line.width() as i64,
+        };
+
+        overlay(&mut canvas, line, x, top + advance * index as i64);
+    }
+
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// Draws text like [render_text] on the blocking thread pool, so async applications don't block the runtime
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub async fn render_text_async(image: DynamicImage, font: Font, text: String, style: TextStyle) -> Result<DynamicImage, StreamDeckError> {
+    Ok(tokio::task::spawn_blocking(move || render_text(&image, &font, &text, &style)).await?)
+}
+
+/// Renders text like [render_text] on a key image of the kind filled with the background color
+pub fn key_text_image(kind: Kind, font: &Font, text: &str, style: &TextStyle, background: (u8, u8, u8)) -> DynamicImage {
+    let background = DynamicImage::ImageRgba8(rounded_background(kind.key_image_resolution(), background, 0));
+    render_text(&background, font, text, style)
+}
+
+/// Splits a line into lines that fit the width at word boundaries, words wider than the width get lines of their own.
+/// Words are shaped once and their advances added up, instead of rendering every candidate line
+fn wrap_line(font: &Font, text: &str, size: f32, width: u32) -> Vec<String> {
+    let scale = PxScale::from(size);
+    let space = shape_line(font, " ", scale).width;
+
+    let mut lines: Vec<String> = vec![];
+    let mut current = String::new();
+    let mut current_width = 0.0;
+
+    for word in text.split_whitespace() {
+        let word_width = shape_line(font, word, scale).width;
+
+        if current.is_empty() {
+            current = word.to_string();
+            current_width = word_width;
+        } else if (current_width + space + word_width).ceil() <= width as f32 {
+            current.push(' ');
+            current.push_str(word);
+            current_width += space + word_width;
+        } else {
+            lines.push(std::mem::replace(&mut current, word.to_string()));
+            current_width = word_width;
+        }
+    }
+
+    lines.push(current);
+    lines
+}
+
+struct ColorGlyph {
+    image: RgbaImage,
+    x: i64,
+    y: i64,
+}
+
+/// Decodes bitmap of a color glyph scaled to the font size and positioned on the line
+fn color_glyph(face: &FontRef<'static>, glyph: &Glyph, size: f32) -> Option<ColorGlyph> {
+    let raster = face.glyph_raster_image2(glyph.id, size.round() as u16)?;
+
+    let image = match raster.format {
+        GlyphImageFormat::Png => image::load_from_memory_with_format(raster.data, image::ImageFormat::Png).ok()?.to_rgba8(),
+
+        GlyphImageFormat::BitmapPremulBgra32 => {
+            let mut image = RgbaImage::from_raw(raster.width as u32, raster.height as u32, raster.data.to_vec())?;
+
+            for pixel in image.pixels_mut() {
+                let [b, g, r, a] = pixel.0;
+                let unpremultiply = |c: u8| if a == 0 { 0 } else { (c as u32 * 255 / a as u32).min(255) as u8 };
+                *pixel = Rgba([unpremultiply(r), unpremultiply(g), unpremultiply(b), a]);
+            }
+
+            image
+        }
+
+        _ => return None,
+    };
+
+    // Bitmaps are stored for a specific size in pixels per em, which has to be matched to the requested size
+    let em = face.as_scaled(glyph.scale).scale_factor().horizontal * face.units_per_em()?;
+    let factor = em / raster.pixels_per_em.max(1) as f32;
+
+    let width = ((image.width() as f32 * factor).round() as u32).max(1);
+    let height = ((image.height() as f32 * factor).round() as u32).max(1);
+    let image = resize(&image, width, height, FilterType::Triangle);
+
+    // Offset is from the glyph origin on the baseline to the bottom left corner of the bitmap
+    let x = glyph.position.x + raster.origin.x * factor;
+    let y = glyph.position.y - raster.origin.y * factor - height as f32;
+
+    Some(ColorGlyph {
+        image,
+        x: x.round() as i64,
+        y: y.round() as i64,
+    })
+}
+
+/// Key animation that scrolls a line of text that is too long to fit on the key.
+/// Text that fits is shown centered without scrolling
+pub struct TextTicker {
+    line: RgbaImage,
+    size: (u32, u32),
+    background: (u8, u8, u8),
+    corner_radius: u32,
+    speed: f32,
+    gap: u32,
+    last_offset: Option<u32>,
+}
+
+impl TextTicker {
+    /// Creates ticker for a key of provided size, for example [key_image_resolution](crate::info::Kind::key_image_resolution)
+    pub fn new(font: &Font, text: &str, font_size: f32, color: (u8, u8, u8), size: (usize, usize)) -> TextTicker {
+        TextTicker {
+            line: render_line(font, text, font_size, color),
+            size: (size.0 as u32, size.1 as u32),
+            background: (0, 0, 0),
+            corner_radius: 0,
+            speed: 30.0,
+            gap: size.0 as u32 / 2,
+            last_offset: None,
+        }
+    }
+
+    /// Sets background color of the key
+    pub fn background(mut self, color: (u8, u8, u8)) -> TextTicker {
+        self.background = color;
+        self
+    }
+
+    /// Sets radius of rounded corners of the background, area outside of the corners stays black
+    pub fn corner_radius(mut self, radius: u32) -> TextTicker {
+        self.corner_radius = radius;
+        self
+    }
+
+    /// Sets scrolling speed in pixels per second
+    pub fn speed(mut self, pixels_per_second: f32) -> TextTicker {
+        self.speed = pixels_per_second.max(0.0);
+        self
+    }
+
+    /// Sets empty space between the end of the text and its next repetition in pixels
+    pub fn gap(mut self, pixels: u32) -> TextTicker {
+        self.gap = pixels;
+        self
+    }
+}
+
+impl KeyAnimation for TextTicker {
+    fn frame(&mut self, elapsed: Duration) -> Option<DynamicImage> {
+        let (w, h) = self.size;
+        let scrolls = self.line.width() > w;
+        let period = self.line.width() + self.gap;
+
+        let offset = if scrolls { (elapsed.as_secs_f32() * self.speed) as u32 % period } else { 0 };
+
+        if self.last_offset == Some(offset) {
+            return None;
+        }
+
+        self.last_offset = Some(offset);
+
+        let mut canvas = rounded_background((w as usize, h as usize), self.background, self.corner_radius);
+        let y = (h as i64 - self.line.height() as i64) / 2;
+
+        if scrolls {
+            overlay(&mut canvas, &self.line, -(offset as i64), y);
+            overlay(&mut canvas, &self.line, period as i64 - offset as i64, y);
+        } else {
+            overlay(&mut canvas, &self.line, (w as i64 - self.line.width() as i64) / 2, y);
+        }
+
+        Some(DynamicImage::ImageRgba8(canvas))
+    }
+}
+
+/// Shared look of keys, so every key of an application is styled the same way
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// Font used for all text
+    pub font: Font,
+    /// Largest font size in pixels, text that doesn't fit is shrunk
+    pub font_size: f32,
+    /// Color of text as (red, green, blue)
+    pub foreground: (u8, u8, u8),
+    /// Color of key background as (red, green, blue)
+    pub background: (u8, u8, u8),
+    /// Color of highlights like badges as (red, green, blue)
+    pub accent: (u8, u8, u8),
+    /// Space between edges of the key and its content in pixels
+    pub padding: u32,
+    /// Radius of rounded corners of key background in pixels
+    pub corner_radius: u32,
+}
+
+impl Theme {
+    /// Creates theme with white text on black background using provided font
+    pub fn new(font: Font) -> Theme {
+        Theme {
+            font,
+            font_size: 16.0,
+            foreground: (255, 255, 255),
+            background: (0, 0, 0),
+            accent: (230, 40, 40),
+            padding: 4,
+            corner_radius: 0,
+        }
+    }
+
+    /// Renders empty key background of provided size
+    pub fn background(&self, size: (usize, usize)) -> DynamicImage {
+        DynamicImage::ImageRgba8(rounded_background(size, self.background, self.corner_radius))
+    }
+
+    /// Renders a key of provided size with text centered on it
+    pub fn label(&self, text: &str, size: (usize, usize)) -> DynamicImage {
+        let (w, h) = (size.0 as u32, size.1 as u32);
+        let available = w.saturating_sub(self.padding * 2).max(1);
+
+        let mut line = render_line(&self.font, text, self.font_size, self.foreground);
+
+        if line.width() > available {
+            let font_size = self.font_size * available as f32 / line.width() as f32;
+            line = render_line(&self.font, text, font_size, self.foreground);
+        }
+
+        let mut canvas = rounded_background(size, self.background, self.corner_radius);
+        overlay(&mut canvas, &line, (w as i64 - line.width() as i64) / 2, (h as i64 - line.height() as i64) / 2);
+
+        DynamicImage::ImageRgba8(canvas)
+    }
+
+    /// Renders a key like [label](Theme::label) on the blocking thread pool, so async applications don't block the runtime
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn label_async(&self, text: String, size: (usize, usize)) -> Result<DynamicImage, StreamDeckError> {
+        let theme = self.clone();
+        Ok(tokio::task::spawn_blocking(move || theme.label(&text, size)).await?)
+    }
+
+    /// Creates ticker for a key of provided size that scrolls the text
+    pub fn ticker(&self, text: &str, size: (usize, usize)) -> TextTicker {
+        TextTicker::new(&self.font, text, self.font_size, self.foreground, size)
+            .background(self.background)
+            .corner_radius(self.corner_radius)
+    }
+
+    /// Creates badge in the top right corner with accent color
+    pub fn badge(&self, content: BadgeContent) -> Badge {
+        Badge {
+            content,
+            corner: BadgeCorner::TopRight,
+            color: self.accent,
+        }
+    }
+}
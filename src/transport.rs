@@ -0,0 +1,326 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+use hidapi::{HidDevice, HidError};
+
+/// Connection that reports are exchanged with, implemented for [HidDevice] and [MockTransport].
+///
+/// Other USB stacks can be used instead of hidapi by implementing this trait for them
+/// and connecting with [StreamDeck::from_transport](crate::StreamDeck::from_transport)
+pub trait Transport: Send {
+    /// Writes output report, returns number of bytes written
+    fn write(&self, data: &[u8]) -> Result<usize, HidError>;
+
+    /// Reads input report into the buffer, returns number of bytes read or 0 if there was no data.
+    /// Waits for data for up to the timeout if it's specified
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, HidError>;
+
+    /// Gets feature report with report id in the first byte of the buffer
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError>;
+
+    /// Sends feature report
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError>;
+
+    /// Returns manufacturer string of the device
+    fn manufacturer(&self) -> Result<Option<String>, HidError>;
+
+    /// Returns product string of the device
+    fn product(&self) -> Result<Option<String>, HidError>;
+
+    /// Reads HID report descriptor of the device into the buffer, returns its length or 0 if the transport can't provide it
+    fn report_descriptor(&self, _buf: &mut [u8]) -> Result<usize, HidError> {
+        Ok(0)
+    }
+}
+
+impl Transport for HidDevice {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        HidDevice::write(self, data)
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, HidError> {
+        self.set_blocking_mode(timeout.is_some())?;
+
+        match timeout {
+            Some(timeout) => self.read_timeout(buf, timeout.as_millis() as i32),
+            None => HidDevice::read(self, buf),
+        }
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        HidDevice::get_feature_report(self, buf)
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError> {
+        HidDevice::send_feature_report(self, data)
+    }
+
+    fn manufacturer(&self) -> Result<Option<String>, HidError> {
+        self.get_manufacturer_string()
+    }
+
+    fn product(&self) -> Result<Option<String>, HidError> {
+        self.get_product_string()
+    }
+
+    fn report_descriptor(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        self.get_report_descriptor(buf)
+    }
+}
+
+type TransportCall = Box<dyn FnOnce(&dyn Transport) -> Box<dyn Any + Send> + Send>;
+
+/// Transport that runs the inner transport on its own thread, so writes can be given up on after a timeout.
+///
+/// hidapi can't interrupt a write once it started, so a write that timed out keeps the thread busy until it returns.
+/// Until then, every call fails right away instead of waiting behind it.
+/// The thread exits once the transport is dropped and the call in progress returns
+pub(crate) struct WatchdogTransport {
+    calls: Sender<TransportCall>,
+    replies: Mutex<WatchdogReplies>,
+    write_timeout: Duration,
+}
+
+struct WatchdogReplies {
+    receiver: Receiver<Box<dyn Any + Send>>,
+    /// Call that was given up on is still running, its reply is the next one to arrive
+    stuck: bool,
+}
+
+impl WatchdogTransport {
+    pub(crate) fn new(inner: Box<dyn Transport>, write_timeout: Duration) -> WatchdogTransport {
+        let (calls, receiver) = channel::<TransportCall>();
+        let (reply_sender, replies) = channel();
+
+        spawn(move || {
+            for call in receiver {
+                if reply_sender.send(call(inner.as_ref())).is_err() {
+                    break;
+                }
+            }
+        });
+
+        WatchdogTransport {
+            calls,
+            replies: Mutex::new(WatchdogReplies { receiver: replies, stuck: false }),
+            write_timeout,
+        }
+    }
+
+    /// Runs the call on the transport thread, waiting for its result for up to the timeout if it's specified
+    fn call<R: Send + 'static>(&self, timeout: Option<Duration>, call: impl FnOnce(&dyn Transport) -> R + Send + 'static) -> Result<R, HidError> {
+        let mut replies = self.replies.lock().unwrap_or_else(|e| e.into_inner());
+
+        if replies.stuck {
+            match replies.receiver.try_recv() {
+                Ok(_) => replies.stuck = false,
+                Err(TryRecvError::Empty) => return Err(timeout_error("transport is still stuck on a write that timed out")),
+                Err(TryRecvError::Disconnected) => return Err(stopped_error()),
+            }
+        }
+
+        self.calls.send(Box::new(move |transport| Box::new(call(transport)))).map_err(|_| stopped_error())?;
+
+        let reply = match timeout {
+            Some(timeout) => replies.receiver.recv_timeout(timeout).map_err(|error| match error {
+                RecvTimeoutError::Timeout => {
+                    replies.stuck = true;
+                    timeout_error("write timed out")
+                }
+                RecvTimeoutError::Disconnected => stopped_error(),
+            })?,
+            None => replies.receiver.recv().map_err(|_| stopped_error())?,
+        };
+
+        Ok(*reply.downcast::<R>().expect("Reply of a different call"))
+    }
+}
+
+fn timeout_error(message: &str) -> HidError {
+    HidError::IoError {
+        error: io::Error::new(ErrorKind::TimedOut, message),
+    }
+}
+
+fn stopped_error() -> HidError {
+    HidError::HidApiError {
+        message: "transport thread has stopped".to_string(),
+    }
+}
+
+impl Transport for WatchdogTransport {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        let data = data.to_vec();
+        self.call(Some(self.write_timeout), move |transport| transport.write(&data))?
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, HidError> {
+        let mut data = vec![0; buf.len()];
+
+        let (result, data) = self.call(None, move |transport| (transport.read(&mut data, timeout), data))?;
+        buf.copy_from_slice(&data);
+
+        result
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        let mut data = buf.to_vec();
+
+        let (result, data) = self.call(None, move |transport| (transport.get_feature_report(&mut data), data))?;
+        buf.copy_from_slice(&data);
+
+        result
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError> {
+        let data = data.to_vec();
+        self.call(None, move |transport| transport.send_feature_report(&data))?
+    }
+
+    fn manufacturer(&self) -> Result<Option<String>, HidError> {
+        self.call(None, |transport| transport.manufacturer())?
+    }
+
+    fn product(&self) -> Result<Option<String>, HidError> {
+        self.call(None, |transport| transport.product())?
+    }
+
+    fn report_descriptor(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        let mut data = vec![0; buf.len()];
+
+        let (result, data) = self.call(None, move |transport| (transport.report_descriptor(&mut data), data))?;
+        buf.copy_from_slice(&data);
+
+        result
+    }
+}
+
+/// Transport that doesn't talk to any hardware, for testing and benchmarking without a device.
+///
+/// Clones share the same state, so a clone can be kept to inspect what the device was sent
+#[derive(Clone, Debug, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    written: Vec<Vec<u8>>,
+    feature_reports: Vec<Vec<u8>>,
+    inputs: VecDeque<Vec<u8>>,
+    write_delay: Duration,
+    feature_responses: Vec<Vec<u8>>,
+    report_descriptor: Vec<u8>,
+}
+
+impl MockTransport {
+    /// Creates mock transport with nothing to read
+    pub fn new() -> MockTransport {
+        Self::default()
+    }
+
+    /// Sets how long each written report takes, to simulate USB bandwidth
+    pub fn set_write_delay(&self, delay: Duration) {
+        self.lock().write_delay = delay;
+    }
+
+    /// Queues input report that will be returned by the next read
+    pub fn push_input(&self, report: Vec<u8>) {
+        self.lock().inputs.push_back(report);
+    }
+
+    /// Sets response to reads of the feature report with the same report id as the first byte of the response,
+    /// other feature reports read as zeroes
+    pub fn set_feature_response(&self, response: Vec<u8>) {
+        let mut state = self.lock();
+        state.feature_responses.retain(|existing| existing.first() != response.first());
+        state.feature_responses.push(response);
+    }
+
+    /// Sets report descriptor of the mock device
+    pub fn set_report_descriptor(&self, descriptor: Vec<u8>) {
+        self.lock().report_descriptor = descriptor;
+    }
+
+    /// Returns output reports written so far and forgets them
+    pub fn take_written(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.lock().written)
+    }
+
+    /// Returns feature reports sent so far and forgets them
+    pub fn take_feature_reports(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.lock().feature_reports)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Transport for MockTransport {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        let delay = {
+            let mut state = self.lock();
+            state.written.push(data.to_vec());
+            state.write_delay
+        };
+
+        if !delay.is_zero() {
+            sleep(delay);
+        }
+
+        Ok(data.len())
+    }
+
+    fn read(&self, buf: &mut [u8], _timeout: Option<Duration>) -> Result<usize, HidError> {
+        let Some(report) = self.lock().inputs.pop_front() else {
+            return Ok(0);
+        };
+
+        let length = report.len().min(buf.len());
+        buf[..length].copy_from_slice(&report[..length]);
+
+        Ok(length)
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        let state = self.lock();
+
+        if let Some(response) = state.feature_responses.iter().find(|response| response.first() == buf.first()) {
+            let length = response.len().min(buf.len());
+            buf[..length].copy_from_slice(&response[..length]);
+            buf[length..].fill(0);
+            return Ok(length);
+        }
+
+        // Everything but the report id reads as zeroes
+        buf[1..].fill(0);
+        Ok(buf.len())
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError> {
+        self.lock().feature_reports.push(data.to_vec());
+        Ok(())
+    }
+
+    fn manufacturer(&self) -> Result<Option<String>, HidError> {
+        Ok(Some("Mock".to_string()))
+    }
+
+    fn product(&self) -> Result<Option<String>, HidError> {
+        Ok(Some("Mock Stream Deck".to_string()))
+    }
+
+    fn report_descriptor(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        let state = self.lock();
+        let length = state.report_descriptor.len().min(buf.len());
+        buf[..length].copy_from_slice(&state.report_descriptor[..length]);
+
+        Ok(length)
+    }
+}
@@ -0,0 +1,238 @@
+//! [Transport] that talks to devices through [nusb], a pure Rust USB stack, instead of hidapi.
+//!
+//! Reports are exchanged over the interrupt endpoints of the HID interface, and feature reports through HID class requests.
+//! On Linux the kernel HID driver is detached from the interface while it's claimed. On Windows the device has to use
+//! the WinUSB driver, since nusb can't claim interfaces that the HID driver owns
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{current, park, park_timeout, Thread};
+use std::time::{Duration, Instant};
+
+use hidapi::HidError;
+use nusb::transfer::{Control, ControlType, Direction, EndpointType, Queue, Recipient, RequestBuffer};
+use nusb::{DeviceInfo, Interface};
+
+use crate::info::{is_vendor_familiar, Kind};
+use crate::transport::Transport;
+use crate::util::normalize_serial;
+use crate::{ConnectOptions, StreamDeck, StreamDeckError};
+
+/// Interface class of HID devices
+const HID_CLASS: u8 = 0x03;
+/// HID class request that reads a report
+const GET_REPORT: u8 = 0x01;
+/// HID class request that writes a report
+const SET_REPORT: u8 = 0x09;
+/// Report type of feature reports in HID class requests
+const FEATURE_REPORT_TYPE: u16 = 0x03;
+/// Standard request that reads a descriptor
+const GET_DESCRIPTOR: u8 = 0x06;
+/// Descriptor type of HID report descriptors
+const REPORT_DESCRIPTOR_TYPE: u16 = 0x22;
+/// How long control transfers are allowed to take
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Returns a list of devices as (Kind, Serial Number) that could be found using nusb, serial numbers are [normalized](normalize_serial)
+pub fn list_devices() -> Result<Vec<(Kind, String)>, StreamDeckError> {
+    Ok(nusb::list_devices()?
+        .filter(|info| is_vendor_familiar(&info.vendor_id()))
+        .filter_map(|info| Some((Kind::from_vid_pid(info.vendor_id(), info.product_id())?, normalize_serial(info.serial_number()?))))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect())
+}
+
+/// Attempts to connect to the device through nusb
+pub fn connect(kind: Kind, serial: &str, options: ConnectOptions) -> Result<StreamDeck, StreamDeckError> {
+    Ok(StreamDeck::from_transport(kind, NusbTransport::open(kind, serial)?, options))
+}
+
+/// Connection to a device through nusb
+pub struct NusbTransport {
+    interface: Interface,
+    input: Mutex<Queue<RequestBuffer>>,
+    output: Mutex<Queue<Vec<u8>>>,
+    input_packet_size: usize,
+    manufacturer: Option<String>,
+    product: Option<String>,
+}
+
+impl NusbTransport {
+    /// Opens the device of the kind with the serial number
+    pub fn open(kind: Kind, serial: &str) -> Result<NusbTransport, StreamDeckError> {
+        let serial = normalize_serial(serial);
+
+        let info = nusb::list_devices()?
+            .find(|info| info.vendor_id() == kind.vendor_id() && info.product_id() == kind.product_id() && info.serial_number().is_some_and(|other| normalize_serial(other) == serial))
+            .ok_or_else(|| HidError::HidApiError {
+                message: format!("{:?} with serial {} not found", kind, serial),
+            })?;
+
+        Ok(Self::open_info(&info)?)
+    }
+
+    /// Opens the device, claiming its HID interface
+    pub fn open_info(info: &DeviceInfo) -> Result<NusbTransport, HidError> {
+        let device = info.open().map_err(io_error)?;
+        let configuration = device.active_configuration().map_err(|error| hid_error(&error))?;
+
+        let setting = configuration
+            .interface_alt_settings()
+            .find(|setting| setting.class() == HID_CLASS)
+            .ok_or_else(|| HidError::HidApiError {
+                message: "device has no HID interface".to_string(),
+            })?;
+
+        let endpoint = |direction: Direction| {
+            setting
+                .endpoints()
+                .find(|endpoint| endpoint.direction() == direction && endpoint.transfer_type() == EndpointType::Interrupt)
+                .map(|endpoint| (endpoint.address(), endpoint.max_packet_size()))
+                .ok_or_else(|| HidError::HidApiError {
+                    message: format!("HID interface has no interrupt {:?} endpoint", direction),
+                })
+        };
+
+        let (input_endpoint, input_packet_size) = endpoint(Direction::In)?;
+        let (output_endpoint, _) = endpoint(Direction::Out)?;
+
+        #[cfg(target_os = "linux")]
+        let interface = device.detach_and_claim_interface(setting.interface_number()).map_err(io_error)?;
+        #[cfg(not(target_os = "linux"))]
+        let interface = device.claim_interface(setting.interface_number()).map_err(io_error)?;
+
+        Ok(NusbTransport {
+            input: Mutex::new(interface.interrupt_in_queue(input_endpoint)),
+            output: Mutex::new(interface.interrupt_out_queue(output_endpoint)),
+            interface,
+            input_packet_size: input_packet_size.max(1),
+            manufacturer: info.manufacturer_string().map(str::to_string),
+            product: info.product_string().map(str::to_string),
+        })
+    }
+
+    fn hid_control(&self, request: u8, value: u16) -> Control {
+        Control {
+            control_type: ControlType::Class,
+            recipient: Recipient::Interface,
+            request,
+            value,
+            index: self.interface.interface_number() as u16,
+        }
+    }
+}
+
+impl Transport for NusbTransport {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        let mut output = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        output.submit(data.to_vec());
+
+        let completion = wait_for(|context| output.poll_next(context), None).ok_or(HidError::HidApiErrorEmpty)?;
+        completion.status.map_err(|error| hid_error(&error))?;
+
+        Ok(data.len())
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, HidError> {
+        let mut input = self.input.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Whole packets are requested, and the transfer stays pending between reads so reports aren't lost while nobody is reading
+        let request_length = buf.len().div_ceil(self.input_packet_size).max(1) * self.input_packet_size;
+
+        if input.pending() == 0 {
+            input.submit(RequestBuffer::new(request_length));
+        }
+
+        let Some(completion) = wait_for(|context| input.poll_next(context), Some(Instant::now() + timeout.unwrap_or_default())) else {
+            return Ok(0);
+        };
+
+        input.submit(RequestBuffer::new(request_length));
+        completion.status.map_err(|error| hid_error(&error))?;
+
+        let length = completion.data.len().min(buf.len());
+        buf[..length].copy_from_slice(&completion.data[..length]);
+
+        Ok(length)
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        let report_id = *buf.first().ok_or(HidError::InvalidZeroSizeData)?;
+        let control = self.hid_control(GET_REPORT, (FEATURE_REPORT_TYPE << 8) | report_id as u16);
+
+        self.interface.control_in_blocking(control, buf, CONTROL_TIMEOUT).map_err(|error| hid_error(&error))
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError> {
+        let report_id = *data.first().ok_or(HidError::InvalidZeroSizeData)?;
+        let control = self.hid_control(SET_REPORT, (FEATURE_REPORT_TYPE << 8) | report_id as u16);
+
+        self.interface.control_out_blocking(control, data, CONTROL_TIMEOUT).map_err(|error| hid_error(&error))?;
+
+        Ok(())
+    }
+
+    fn manufacturer(&self) -> Result<Option<String>, HidError> {
+        Ok(self.manufacturer.clone())
+    }
+
+    fn product(&self) -> Result<Option<String>, HidError> {
+        Ok(self.product.clone())
+    }
+
+    fn report_descriptor(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        let control = Control {
+            control_type: ControlType::Standard,
+            recipient: Recipient::Interface,
+            request: GET_DESCRIPTOR,
+            value: REPORT_DESCRIPTOR_TYPE << 8,
+            index: self.interface.interface_number() as u16,
+        };
+
+        self.interface.control_in_blocking(control, buf, CONTROL_TIMEOUT).map_err(|error| hid_error(&error))
+    }
+}
+
+/// Wakes the thread that waits for a transfer
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Blocks until the poll is ready, returns None if the deadline passed first
+fn wait_for<T>(mut poll: impl FnMut(&mut Context) -> Poll<T>, deadline: Option<Instant>) -> Option<T> {
+    let waker = Waker::from(Arc::new(ThreadWaker(current())));
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(value) = poll(&mut context) {
+            return Some(value);
+        }
+
+        match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                if remaining.is_zero() {
+                    return None;
+                }
+
+                park_timeout(remaining);
+            }
+            None => park(),
+        }
+    }
+}
+
+fn io_error(error: std::io::Error) -> HidError {
+    HidError::IoError { error }
+}
+
+fn hid_error(error: &dyn std::fmt::Display) -> HidError {
+    HidError::HidApiError { message: error.to_string() }
+}
@@ -0,0 +1,177 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use image::imageops::crop_imm;
+use image::{DynamicImage, RgbaImage};
+
+use crate::images::{changed_columns, convert_image_with_format, ResizeFilter};
+use crate::info::{ImageRotation, Screen, ScreenSegment};
+use crate::{StreamDeck, StreamDeckError};
+
+type FrameSource = Box<dyn FnMut() -> Result<Option<DynamicImage>, StreamDeckError> + Send>;
+
+/// Plays frames on the LCD strip at a target frame rate, for now-playing visualizers and small video monitors.
+///
+/// Frames come from a decoder callback or an iterator, and are scaled to the strip. When the device can't keep up,
+/// late frames are skipped to stay in time. Frames that didn't change aren't written, and on devices that write regions
+/// only the columns that changed are
+pub struct StripPlayer {
+    source: FrameSource,
+    frame_interval: Duration,
+    filter: ResizeFilter,
+    started: Option<Instant>,
+    /// Index of the next frame the source will return
+    next_frame: u64,
+    /// Last frame written to the device, scaled and rotated like the device expects
+    written: Option<RgbaImage>,
+    dropped_frames: u64,
+    finished: bool,
+}
+
+impl StripPlayer {
+    /// Creates player that gets frames from the decoder callback, which returns None once there are no more frames
+    pub fn new(fps: f32, decode: impl FnMut() -> Result<Option<DynamicImage>, StreamDeckError> + Send + 'static) -> StripPlayer {
+        StripPlayer {
+            source: Box::new(decode),
+            frame_interval: Duration::from_secs_f32(1.0 / fps.max(0.001)),
+            filter: ResizeFilter::Triangle,
+            started: None,
+            next_frame: 0,
+            written: None,
+            dropped_frames: 0,
+            finished: false,
+        }
+    }
+
+    /// Creates player of already decoded frames
+    pub fn from_frames<I>(fps: f32, frames: I) -> StripPlayer
+    where
+        I: IntoIterator<Item = DynamicImage>,
+        I::IntoIter: Send + 'static,
+    {
+        let mut frames = frames.into_iter();
+        StripPlayer::new(fps, move || Ok(frames.next()))
+    }
+
+    /// Sets filter used for scaling frames to the strip, [Triangle](ResizeFilter::Triangle) by default
+    pub fn resize_filter(mut self, filter: ResizeFilter) -> StripPlayer {
+        self.filter = filter;
+        self
+    }
+
+    /// Tells if the source ran out of frames
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Number of frames skipped so far because they were late
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames
+    }
+
+    /// Time left until the next frame is due
+    pub fn next_frame_in(&self) -> Duration {
+        match self.started {
+            Some(started) => self.frame_interval.mul_f64(self.next_frame as f64).saturating_sub(started.elapsed()),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Writes the frame that is due, skipping frames that are late. Returns true if anything was written to the device.
+    /// Fails with [StreamDeckError::NoScreen] if the device has no LCD strip
+    pub fn tick(&mut self, device: &StreamDeck) -> Result<bool, StreamDeckError> {
+        if self.finished {
+            return Ok(false);
+        }
+
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let due = (started.elapsed().as_secs_f64() / self.frame_interval.as_secs_f64()) as u64;
+
+        if due < self.next_frame {
+            return Ok(false);
+        }
+
+        let mut frame = None;
+
+        while self.next_frame <= due {
+            match (self.source)()? {
+                Some(next) => {
+                    if frame.replace(next).is_some() {
+                        self.dropped_frames += 1;
+                    }
+
+                    self.next_frame += 1;
+                }
+
+                None => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+
+        match frame {
+            Some(frame) => self.write(device, frame),
+            None => Ok(false),
+        }
+    }
+
+    /// Plays frames until the source runs out of them, sleeping between frames
+    pub fn play(&mut self, device: &StreamDeck) -> Result<(), StreamDeckError> {
+        while !self.finished {
+            self.tick(device)?;
+            sleep(self.next_frame_in());
+        }
+
+        Ok(())
+    }
+
+    /// Forgets what was written, so the next frame is written whole. Needed after something else wrote to the strip, or after the device was reset
+    pub fn invalidate(&mut self) {
+        self.written = None;
+    }
+
+    fn write(&mut self, device: &StreamDeck, frame: DynamicImage) -> Result<bool, StreamDeckError> {
+        let screen = device.kind().screen(Screen::LCD_STRIP).ok_or(StreamDeckError::NoScreen)?;
+        let (w, h) = screen.resolution;
+
+        // Frames are compared in device orientation, which is what regions are in
+        let frame = match screen.format.rotation {
+            ImageRotation::Rot0 => frame,
+            ImageRotation::Rot90 => frame.rotate90(),
+            ImageRotation::Rot180 => frame.rotate180(),
+            ImageRotation::Rot270 => frame.rotate270(),
+        };
+
+        let frame = match frame {
+            DynamicImage::ImageRgba8(frame) if frame.dimensions() == (w as u32, h as u32) => frame,
+            frame => frame.resize_exact(w as u32, h as u32, self.filter.filter_type()).into_rgba8(),
+        };
+
+        let (left, right) = match &self.written {
+            Some(written) if *written == frame => return Ok(false),
+            Some(written) if screen.writes_regions => changed_columns(written, &frame).unwrap_or((0, w)),
+            _ => (0, w),
+        };
+
+        let dirty = DynamicImage::ImageRgba8(crop_imm(&frame, left as u32, 0, (right - left) as u32, h as u32).to_image());
+
+        // Conversion applies the rotation again
+        let dirty = match screen.format.rotation {
+            ImageRotation::Rot0 => dirty,
+            ImageRotation::Rot90 => dirty.rotate270(),
+            ImageRotation::Rot180 => dirty.rotate180(),
+            ImageRotation::Rot270 => dirty.rotate90(),
+        };
+
+        let region = ScreenSegment { x: left, y: 0, w: right - left, h };
+
+        let mut format = screen.format;
+        format.size = (region.w, region.h);
+
+        device.write_screen(screen.id, Some(region), &convert_image_with_format(format, dirty)?)?;
+        self.written = Some(frame);
+
+        Ok(true)
+    }
+}
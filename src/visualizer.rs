@@ -0,0 +1,199 @@
+use image::imageops::crop_imm;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::images::{changed_columns, convert_image_with_format};
+use crate::info::{ImageRotation, Kind, Screen, ScreenSegment};
+use crate::palette::StatusColor;
+use crate::{StreamDeck, StreamDeckError};
+
+/// Space between bars in pixels
+const BAR_GAP: usize = 2;
+
+/// How [AudioVisualizer] draws its samples
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum VisualizerStyle {
+    /// Bars growing from the bottom, for levels of frequency bands. Samples are grouped into the amount of bars
+    Bars(usize),
+    /// Line through amplitudes of the signal
+    Waveform,
+}
+
+/// Audio visualizer on the touch screen of Stream Deck Plus, drawing samples the application pushes.
+///
+/// Samples are levels in 0.0 - 1.0 range for [bars](VisualizerStyle::Bars), like magnitudes of FFT bins,
+/// and amplitudes in -1.0 - 1.0 range for [waveform](VisualizerStyle::Waveform).
+/// Only columns that changed since the last write are sent to the device
+#[derive(Clone, Debug)]
+pub struct AudioVisualizer {
+    /// Part of the LCD strip the visualizer covers
+    pub region: ScreenSegment,
+    /// Color of the bars or the line as (red, green, blue)
+    pub color: (u8, u8, u8),
+    /// How the samples are drawn
+    pub style: VisualizerStyle,
+    /// Fraction of the previous level bars keep when new levels are lower, so they fall smoothly. 0.0 makes them fall right away
+    pub decay: f32,
+    samples: Vec<f32>,
+    /// Image that is on the device, None if the visualizer wasn't written yet
+    written: Option<RgbaImage>,
+}
+
+impl AudioVisualizer {
+    /// Creates visualizer covering the region, without samples
+    pub fn new(region: ScreenSegment, style: VisualizerStyle) -> AudioVisualizer {
+        AudioVisualizer {
+            region,
+            color: StatusColor::Info.rgb(),
+            style,
+            decay: 0.0,
+            samples: vec![],
+            written: None,
+        }
+    }
+
+    /// Creates visualizer covering the whole LCD strip of the kind, None if the kind has no touch screen
+    pub fn for_kind(kind: Kind, style: VisualizerStyle) -> Option<AudioVisualizer> {
+        if !kind.has_touch_screen() {
+            return None;
+        }
+
+        let (w, h) = kind.lcd_strip_size()?;
+        Some(AudioVisualizer::new(ScreenSegment { x: 0, y: 0, w, h }, style))
+    }
+
+    /// Sets samples to draw, replacing previous ones. Values out of range are clamped
+    pub fn push(&mut self, samples: &[f32]) {
+        match self.style {
+            VisualizerStyle::Bars(count) => {
+                let levels = group_levels(samples, count);
+                self.samples.resize(levels.len(), 0.0);
+
+                for (current, level) in self.samples.iter_mut().zip(levels) {
+                    *current = level.max(*current * self.decay.clamp(0.0, 1.0));
+                }
+            }
+
+            VisualizerStyle::Waveform => {
+                self.samples = samples.iter().map(|sample| sample.clamp(-1.0, 1.0)).collect();
+            }
+        }
+    }
+
+    /// Samples that are drawn, levels of bars after decay or amplitudes of waveform
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /// Renders the whole visualizer in size of the region
+    pub fn render(&self) -> RgbaImage {
+        let (w, h) = (self.region.w, self.region.h);
+        let (red, green, blue) = self.color;
+        let mut image = RgbaImage::from_pixel(w as u32, h as u32, Rgba([0, 0, 0, 255]));
+
+        if self.samples.is_empty() || w == 0 || h == 0 {
+            return image;
+        }
+
+        match self.style {
+            VisualizerStyle::Bars(_) => {
+                let count = self.samples.len();
+                let bar_width = (w.saturating_sub(BAR_GAP * (count - 1)) / count).max(1);
+
+                for (index, level) in self.samples.iter().enumerate() {
+                    let left = index * (bar_width + BAR_GAP);
+                    let top = h - (level * h as f32).round() as usize;
+
+                    for x in left..(left + bar_width).min(w) {
+                        for y in top..h {
+                            image.put_pixel(x as u32, y as u32, Rgba([red, green, blue, 255]));
+                        }
+                    }
+                }
+            }
+
+            VisualizerStyle::Waveform => {
+                let height_of = |sample: f32| (((1.0 - sample) / 2.0 * (h - 1) as f32).round() as usize).min(h - 1);
+                let mut previous = None;
+
+                for x in 0..w {
+                    let y = height_of(self.samples[x * self.samples.len() / w]);
+
+                    // Connecting to the previous column, so steep parts don't break up into dots
+                    let (top, bottom) = match previous {
+                        Some(previous) => (y.min(previous), y.max(previous)),
+                        None => (y, y),
+                    };
+
+                    for y in top..=bottom {
+                        image.put_pixel(x as u32, y as u32, Rgba([red, green, blue, 255]));
+                    }
+
+                    previous = Some(y);
+                }
+            }
+        }
+
+        image
+    }
+
+    /// Writes columns of the visualizer that changed since the last write, the whole visualizer on first write.
+    /// Fails with [StreamDeckError::NoScreen] if the device has no LCD strip
+    pub fn write(&mut self, device: &StreamDeck) -> Result<(), StreamDeckError> {
+        let screen = device.kind().screen(Screen::LCD_STRIP).ok_or(StreamDeckError::NoScreen)?;
+        let image = self.render();
+
+        let (left, right) = match &self.written {
+            Some(written) if written.dimensions() == image.dimensions() => match changed_columns(written, &image) {
+                Some(columns) => columns,
+                None => return Ok(()),
+            },
+            _ => (0, self.region.w),
+        };
+
+        let dirty = crop_imm(&image, left as u32, 0, (right - left) as u32, self.region.h as u32).to_image();
+
+        // Visualizer is rendered in device coordinates, so rotation the conversion applies has to be undone first
+        let dirty = match screen.format.rotation {
+            ImageRotation::Rot0 => DynamicImage::ImageRgba8(dirty),
+            ImageRotation::Rot90 => DynamicImage::ImageRgba8(dirty).rotate270(),
+            ImageRotation::Rot180 => DynamicImage::ImageRgba8(dirty).rotate180(),
+            ImageRotation::Rot270 => DynamicImage::ImageRgba8(dirty).rotate90(),
+        };
+
+        let region = ScreenSegment {
+            x: self.region.x + left,
+            y: self.region.y,
+            w: right - left,
+            h: self.region.h,
+        };
+
+        let mut format = screen.format;
+        format.size = (region.w, region.h);
+
+        device.write_screen(screen.id, Some(region), &convert_image_with_format(format, dirty)?)?;
+        self.written = Some(image);
+
+        Ok(())
+    }
+
+    /// Forgets what was written, so the next write repaints the whole visualizer. Needed after changing region, color or style, or after the device was reset
+    pub fn invalidate(&mut self) {
+        self.written = None;
+    }
+}
+
+/// Groups samples into the amount of levels by taking the loudest sample of every group
+fn group_levels(samples: &[f32], count: usize) -> Vec<f32> {
+    if samples.is_empty() || count == 0 {
+        return vec![0.0; count];
+    }
+
+    (0..count)
+        .map(|index| {
+            let start = index * samples.len() / count;
+            let end = ((index + 1) * samples.len() / count).max(start + 1).min(samples.len());
+
+            samples[start..end].iter().fold(0.0f32, |loudest, sample| loudest.max(sample.clamp(0.0, 1.0)))
+        })
+        .collect()
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "assets")]
+
+use std::sync::Arc;
+
+use elgato_streamdeck::assets::AssetRegistry;
+use elgato_streamdeck::image::{DynamicImage, Rgb, RgbImage};
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, StreamDeck, StreamDeckError};
+
+/// Assets produce the same reports as setting the image directly, also when taken from the converted cache
+#[test]
+fn assets_match_direct_images() {
+    let image = DynamicImage::ImageRgb8(RgbImage::from_fn(40, 30, |x, y| Rgb([(x * 6) as u8, (y * 8) as u8, 128])));
+
+    let assets = Arc::new(AssetRegistry::new());
+    assets.register("mute_on", image.clone()).unwrap();
+
+    let options = ConnectOptions {
+        assets: Some(assets.clone()),
+        ..Default::default()
+    };
+
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), options);
+
+    for key in [0, 5] {
+        device.set_button_asset(key, "mute_on").expect("Failed to set asset");
+    }
+
+    device.flush().expect("Failed to flush");
+
+    let direct_transport = MockTransport::new();
+    let direct = StreamDeck::from_transport(Kind::Mk2, direct_transport.clone(), ConnectOptions::default());
+
+    for key in [0, 5] {
+        direct.set_button_image(key, image.clone()).expect("Failed to set image");
+    }
+
+    direct.flush().expect("Failed to flush");
+
+    assert_eq!(transport.take_written(), direct_transport.take_written());
+    assert!(matches!(device.set_button_asset(1, "mute_off"), Err(StreamDeckError::UnknownAsset)));
+    assert!(matches!(direct.set_button_asset(1, "mute_on"), Err(StreamDeckError::UnknownAsset)));
+}
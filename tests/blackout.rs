@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::protocol::input_report;
+use elgato_streamdeck::transport::{MockTransport, Transport};
+use elgato_streamdeck::{ConnectOptions, DeviceStateUpdate, InputDiffer, KeyIndex, StreamDeck, StreamDeckInput};
+use hidapi::HidError;
+
+/// Mock transport whose feature reports fail while the flag is set
+struct FailingTransport {
+    inner: MockTransport,
+    fail: Arc<AtomicBool>,
+}
+
+impl Transport for FailingTransport {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        self.inner.write(data)
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, HidError> {
+        self.inner.read(buf, timeout)
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        self.inner.get_feature_report(buf)
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError> {
+        if self.fail.load(Ordering::SeqCst) {
+            return Err(HidError::HidApiError {
+                message: "feature report failed".into(),
+            });
+        }
+
+        self.inner.send_feature_report(data)
+    }
+
+    fn manufacturer(&self) -> Result<Option<String>, HidError> {
+        self.inner.manufacturer()
+    }
+
+    fn product(&self) -> Result<Option<String>, HidError> {
+        self.inner.product()
+    }
+}
+
+fn buttons(pressed: &[usize]) -> StreamDeckInput {
+    let mut buttons = vec![false; Kind::Mk2.key_count() as usize];
+    pressed.iter().for_each(|key| buttons[*key] = true);
+    StreamDeckInput::ButtonStateChange(buttons)
+}
+
+/// Press that wakes the device is swallowed along with its release, later presses are delivered
+#[test]
+fn wake_press_and_release_are_swallowed() {
+    let mut differ = InputDiffer::new(Kind::Mk2);
+
+    let mut updates = differ.diff(buttons(&[2]));
+    assert!(differ.swallow_wake_input(&mut updates, true));
+    assert!(updates.is_empty());
+
+    let mut updates = differ.diff(buttons(&[]));
+    assert!(!differ.swallow_wake_input(&mut updates, false));
+    assert!(updates.is_empty());
+
+    let mut updates = differ.diff(buttons(&[2]));
+    assert!(!differ.swallow_wake_input(&mut updates, false));
+    assert_eq!(updates, [DeviceStateUpdate::ButtonDown(KeyIndex(2))]);
+}
+
+/// Release of a button held since before the blackout is delivered and doesn't wake the device
+#[test]
+fn release_of_held_button_does_not_wake() {
+    let mut differ = InputDiffer::new(Kind::Mk2);
+
+    let mut updates = differ.diff(buttons(&[1]));
+    assert!(!differ.swallow_wake_input(&mut updates, false));
+    assert_eq!(updates, [DeviceStateUpdate::ButtonDown(KeyIndex(1))]);
+
+    let mut updates = differ.diff(buttons(&[]));
+    assert!(!differ.swallow_wake_input(&mut updates, true));
+    assert_eq!(updates, [DeviceStateUpdate::ButtonUp(KeyIndex(1))]);
+}
+
+/// Reader wakes a blacked out device on the first press without delivering it
+#[test]
+fn reader_wakes_device() {
+    let transport = MockTransport::new();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let device = Arc::new(StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default()));
+    let reader = device.get_reader();
+
+    device.blackout().unwrap();
+    transport.take_feature_reports();
+
+    transport.push_input(input_report(Kind::Mk2, &buttons(&[0])).unwrap());
+    assert!(reader.read(None).unwrap().is_empty());
+
+    assert!(!device.is_blacked_out());
+    assert!(!transport.take_feature_reports().is_empty());
+}
+
+/// Device isn't considered blacked out if turning it dark failed, so the next press isn't swallowed
+#[test]
+fn failed_blackout_keeps_device_awake() {
+    let fail = Arc::new(AtomicBool::new(true));
+    let device = StreamDeck::from_transport(
+        Kind::Mk2,
+        FailingTransport {
+            inner: MockTransport::new(),
+            fail: fail.clone(),
+        },
+        ConnectOptions::default(),
+    );
+
+    assert!(device.blackout().is_err());
+    assert!(!device.is_blacked_out());
+
+    fail.store(false, Ordering::SeqCst);
+    device.blackout().unwrap();
+    assert!(device.is_blacked_out());
+}
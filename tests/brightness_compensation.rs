@@ -0,0 +1,64 @@
+use elgato_streamdeck::image::{DynamicImage, Rgb, RgbImage};
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::protocol::decode_image_report;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, StreamDeck};
+
+fn connect(transport: &MockTransport) -> StreamDeck {
+    let device = StreamDeck::from_transport(
+        Kind::Mk2,
+        transport.clone(),
+        ConnectOptions {
+            brightness_compensation: true,
+            ..Default::default()
+        },
+    );
+
+    device.set_button_image(0, DynamicImage::ImageRgb8(RgbImage::from_pixel(72, 72, Rgb([20, 20, 20])))).unwrap();
+    device.flush().unwrap();
+    transport.take_written();
+
+    device
+}
+
+/// Number of key images started in the reports written since the last call
+fn images_written(transport: &MockTransport) -> usize {
+    transport
+        .take_written()
+        .iter()
+        .filter_map(|report| decode_image_report(Kind::Mk2, report))
+        .filter(|report| report.page_number == 0)
+        .count()
+}
+
+/// Brightness changes during blackout don't render keys, they're rendered once on wake
+#[test]
+fn blackout_defers_compensation() {
+    let transport = MockTransport::new();
+    let device = connect(&transport);
+
+    device.blackout().unwrap();
+    device.set_brightness(30).unwrap();
+    device.set_brightness(20).unwrap();
+
+    device.flush().unwrap();
+    assert_eq!(images_written(&transport), 0);
+
+    device.wake().unwrap();
+    device.flush().unwrap();
+    assert_eq!(images_written(&transport), 1);
+}
+
+/// Brightness changes during suspend don't render keys, they're rendered once and sent on resume
+#[test]
+fn suspend_defers_compensation() {
+    let transport = MockTransport::new();
+    let device = connect(&transport);
+
+    device.suspend();
+    device.set_brightness(30).unwrap();
+    device.set_brightness(20).unwrap();
+
+    device.resume().unwrap();
+    assert_eq!(images_written(&transport), 1);
+}
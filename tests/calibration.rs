@@ -0,0 +1,72 @@
+#![cfg(feature = "tools")]
+
+use std::sync::Arc;
+
+use elgato_streamdeck::calibration::{Calibration, CalibrationEvent, KeyRemap};
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::protocol::{decode_image_report, input_report, ImageTarget};
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, DeviceStateUpdate, KeyIndex, StreamDeck, StreamDeckInput};
+
+/// Presses of keys that were already assigned are rejected, and the table maps reported indices to physical keys
+#[test]
+fn presses_build_remap() {
+    let mut calibration = Calibration::new(4, 2);
+    let press = |key: u8| DeviceStateUpdate::ButtonDown(KeyIndex(key));
+
+    assert_eq!(calibration.prompt(), Some((0, 0)));
+    assert_eq!(calibration.handle(&press(1)), Some(CalibrationEvent::Assigned { physical: 0, reported: 1 }));
+    assert_eq!(calibration.handle(&DeviceStateUpdate::ButtonUp(KeyIndex(1))), None);
+    assert_eq!(calibration.handle(&press(1)), Some(CalibrationEvent::AlreadyAssigned { physical: 0, reported: 1 }));
+    assert_eq!(calibration.handle(&press(7)), Some(CalibrationEvent::OutOfRange(7)));
+
+    calibration.handle(&press(0));
+    assert_eq!(calibration.prompt(), Some((1, 0)));
+    calibration.handle(&press(3));
+
+    let Some(CalibrationEvent::Finished(remap)) = calibration.handle(&press(2)) else {
+        panic!("Calibration didn't finish");
+    };
+
+    assert_eq!(remap.table(), [1, 0, 3, 2]);
+    assert_eq!(remap.reported(2), Some(3));
+    assert_eq!(remap.apply(press(0)), press(1));
+    assert_eq!(KeyRemap::from_table(remap.table().to_vec()), Some(remap));
+    assert_eq!(KeyRemap::from_table(vec![0, 0]), None);
+    assert_eq!(calibration.prompt(), None);
+}
+
+/// Device connected with a remap reads and writes keys by their physical index
+#[test]
+fn remap_applies_to_reader_and_writes() {
+    let mut table = (0..Kind::Mk2.key_count()).collect::<Vec<_>>();
+    table.swap(0, 1);
+
+    let transport = MockTransport::new();
+    let options = ConnectOptions {
+        key_remap: KeyRemap::from_table(table),
+        ..Default::default()
+    };
+
+    #[allow(clippy::arc_with_non_send_sync)]
+    let device = Arc::new(StreamDeck::from_transport(Kind::Mk2, transport.clone(), options));
+    let reader = device.get_reader();
+
+    let mut buttons = vec![false; Kind::Mk2.key_count() as usize];
+    buttons[0] = true;
+    transport.push_input(input_report(Kind::Mk2, &StreamDeckInput::ButtonStateChange(buttons)).unwrap());
+
+    assert_eq!(reader.read(None).unwrap(), [DeviceStateUpdate::ButtonDown(KeyIndex(1))]);
+
+    device.write_image(1, Kind::Mk2.blank_image()).unwrap();
+    device.flush().unwrap();
+
+    let targets = transport
+        .take_written()
+        .iter()
+        .filter_map(|report| decode_image_report(Kind::Mk2, report))
+        .map(|report| report.target)
+        .collect::<Vec<_>>();
+    assert!(!targets.is_empty());
+    assert!(targets.iter().all(|target| *target == ImageTarget::Key(0)));
+}
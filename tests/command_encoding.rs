@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::PathBuf;
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::protocol::command_snapshot;
+
+const KINDS: [Kind; 16] = [
+    Kind::Original,
+    Kind::OriginalV2,
+    Kind::Mini,
+    Kind::Xl,
+    Kind::XlV2,
+    Kind::Mk2,
+    Kind::Mk2Scissor,
+    Kind::MiniMk2,
+    Kind::MiniDiscord,
+    Kind::Neo,
+    Kind::Pedal,
+    Kind::Plus,
+    Kind::PlusXl,
+    Kind::MiniMk2Module,
+    Kind::Mk2Module,
+    Kind::XlV2Module,
+];
+
+/// Compares command encoding of every kind with snapshots in `tests/snapshots`, `UPDATE_SNAPSHOTS=1` rewrites them instead
+#[test]
+fn command_encoding_matches_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut mismatched = vec![];
+
+    for kind in KINDS {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(format!("{:?}.txt", kind));
+        let snapshot = command_snapshot(kind);
+
+        if update {
+            fs::write(&path, snapshot).expect("Failed to write snapshot");
+            continue;
+        }
+
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+
+        if expected != snapshot {
+            mismatched.push(kind);
+        }
+    }
+
+    assert!(
+        mismatched.is_empty(),
+        "Command encoding of {:?} doesn't match snapshots, run with UPDATE_SNAPSHOTS=1 and review the diff",
+        mismatched
+    );
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "fake")]
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use elgato_streamdeck::fake::Transcript;
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::{ConnectOptions, DeviceStateUpdate, EncoderIndex, KeyIndex, StreamDeck};
+
+/// Replays a recorded Plus session through the standard reader and checks that the application's output matches the recording
+#[test]
+fn plus_transcript_replays_end_to_end() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/transcripts/plus.txt");
+    let transcript = Transcript::load(path).expect("Failed to load transcript");
+
+    let transport = transcript.mock();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let device = Arc::new(StreamDeck::from_transport(Kind::Plus, transport.clone(), ConnectOptions::default()));
+    let reader = device.get_reader();
+
+    let mut updates = vec![];
+
+    while let Ok(read) = reader.read(None) {
+        if read.is_empty() {
+            break;
+        }
+
+        updates.extend(read);
+    }
+
+    assert_eq!(
+        format!("{:?}", updates),
+        format!(
+            "{:?}",
+            [
+                DeviceStateUpdate::ButtonDown(KeyIndex(2)),
+                DeviceStateUpdate::ButtonUp(KeyIndex(2)),
+                DeviceStateUpdate::EncoderTwist(EncoderIndex(1), -2),
+                DeviceStateUpdate::TouchScreenPress(420, 50),
+            ]
+        )
+    );
+
+    device.set_brightness(50).unwrap();
+
+    assert_eq!(transcript.verify(&transport), None);
+}
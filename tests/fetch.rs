@@ -0,0 +1,146 @@
+#![cfg(feature = "url")]
+
+use std::io::{Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+
+use elgato_streamdeck::fetch::ImageCache;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+
+/// How the test server answers requests
+#[derive(Copy, Clone)]
+enum Answer {
+    Image,
+    Error,
+    Slow(Duration),
+}
+
+/// HTTP server on localhost that counts requests and answers them as told
+struct Server {
+    url: String,
+    requests: Arc<AtomicUsize>,
+    answer: Arc<Mutex<Answer>>,
+}
+
+impl Server {
+    fn start() -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/icon.png", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let answer = Arc::new(Mutex::new(Answer::Image));
+
+        spawn({
+            let requests = requests.clone();
+            let answer = answer.clone();
+
+            move || {
+                for stream in listener.incoming().flatten() {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    let answer = *answer.lock().unwrap();
+                    spawn(move || respond(stream, answer));
+                }
+            }
+        });
+
+        Server { url, requests, answer }
+    }
+
+    fn answer(&self, answer: Answer) {
+        *self.answer.lock().unwrap() = answer;
+    }
+
+    fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+fn respond(mut stream: TcpStream, answer: Answer) {
+    let mut request = vec![];
+    let mut buf = [0; 1024];
+
+    while !request.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(length) => request.extend_from_slice(&buf[..length]),
+        }
+    }
+
+    let (status, body) = match answer {
+        Answer::Image => ("200 OK", png()),
+        Answer::Error => ("500 Internal Server Error", vec![]),
+        Answer::Slow(delay) => {
+            sleep(delay);
+            ("200 OK", png())
+        }
+    };
+
+    let _ = write!(stream, "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+    let _ = stream.write_all(&body);
+}
+
+fn png() -> Vec<u8> {
+    let image = DynamicImage::ImageRgb8(RgbImage::from_fn(16, 16, |x, y| Rgb([x as u8 * 16, y as u8 * 16, 128])));
+    let mut data = Cursor::new(vec![]);
+    image.write_to(&mut data, ImageFormat::Png).unwrap();
+    data.into_inner()
+}
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("streamdeck-fetch-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Fresh cached image is returned without fetching it again
+#[test]
+fn cached_image_is_reused() {
+    let server = Server::start();
+    let cache = ImageCache::new(cache_dir("reused"));
+
+    let first = cache.from_url(&server.url).unwrap();
+    let second = cache.from_url(&server.url).unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(server.requests(), 1);
+
+    cache.invalidate(&server.url).unwrap();
+    cache.from_url(&server.url).unwrap();
+    assert_eq!(server.requests(), 2);
+}
+
+/// Stale cached image is returned if fetching it again fails, and nothing is returned without one
+#[test]
+fn stale_image_is_used_when_fetching_fails() {
+    let server = Server::start();
+    let cache = ImageCache::new(cache_dir("stale")).max_age(Duration::ZERO);
+
+    let fetched = cache.from_url(&server.url).unwrap();
+
+    server.answer(Answer::Error);
+    assert_eq!(cache.from_url(&server.url).unwrap(), fetched);
+    assert_eq!(server.requests(), 2);
+
+    cache.invalidate(&server.url).unwrap();
+    assert!(cache.from_url(&server.url).is_err());
+}
+
+/// Fetching gives up on slow servers and files that are too large
+#[test]
+fn fetching_is_limited() {
+    let server = Server::start();
+
+    let cache = ImageCache::new(cache_dir("too-large")).max_size(64);
+    assert!(cache.from_url(&server.url).is_err());
+
+    server.answer(Answer::Slow(Duration::from_secs(5)));
+
+    let cache = ImageCache::new(cache_dir("slow")).timeout(Duration::from_millis(200));
+    let start = Instant::now();
+
+    assert!(cache.from_url(&server.url).is_err());
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
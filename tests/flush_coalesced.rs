@@ -0,0 +1,76 @@
+#![cfg(feature = "async")]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hidapi::HidError;
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::transport::{MockTransport, Transport};
+use elgato_streamdeck::{AsyncStreamDeck, ConnectOptions, StreamDeck, StreamDeckError};
+use tokio::time::timeout;
+
+/// Mock transport whose writes panic while the flag is set
+struct PanickingTransport {
+    inner: MockTransport,
+    panic: Arc<AtomicBool>,
+}
+
+impl Transport for PanickingTransport {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        if self.panic.load(Ordering::SeqCst) {
+            panic!("write panicked");
+        }
+
+        self.inner.write(data)
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, HidError> {
+        self.inner.read(buf, timeout)
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        self.inner.get_feature_report(buf)
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError> {
+        self.inner.send_feature_report(data)
+    }
+
+    fn manufacturer(&self) -> Result<Option<String>, HidError> {
+        self.inner.manufacturer()
+    }
+
+    fn product(&self) -> Result<Option<String>, HidError> {
+        self.inner.product()
+    }
+}
+
+/// Waiters of a flush whose worker panicked get an error, and later flushes start a new worker
+#[tokio::test(flavor = "multi_thread")]
+async fn panicking_flush_releases_waiters() {
+    let transport = MockTransport::new();
+    let panic = Arc::new(AtomicBool::new(true));
+    let device = AsyncStreamDeck::from(StreamDeck::from_transport(
+        Kind::Mk2,
+        PanickingTransport {
+            inner: transport.clone(),
+            panic: panic.clone(),
+        },
+        ConnectOptions::default(),
+    ));
+
+    device.write_image(0, Kind::Mk2.blank_image()).await.unwrap();
+
+    let (first, second) = timeout(Duration::from_secs(5), async { tokio::join!(device.flush_coalesced(), device.flush_coalesced()) })
+        .await
+        .expect("Waiters of the panicked flush weren't released");
+
+    assert!(matches!(first, Err(StreamDeckError::SharedFlushError(_))));
+    assert!(matches!(second, Err(StreamDeckError::SharedFlushError(_))));
+
+    panic.store(false, Ordering::SeqCst);
+    timeout(Duration::from_secs(5), device.flush_coalesced()).await.expect("Flush worker wasn't restarted").unwrap();
+
+    assert!(!transport.take_written().is_empty());
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "async")]
+
+use std::time::Duration;
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{AsyncStreamDeck, ConnectOptions, FlushStrategy, StreamDeck};
+use image::DynamicImage;
+use tokio::time::{sleep, timeout};
+
+fn device(flush_strategy: FlushStrategy) -> (AsyncStreamDeck, MockTransport) {
+    let transport = MockTransport::new();
+    let options = ConnectOptions { flush_strategy, ..Default::default() };
+
+    (AsyncStreamDeck::from(StreamDeck::from_transport(Kind::Mk2, transport.clone(), options)), transport)
+}
+
+/// Immediate flush unlocks the device between images, so other tasks don't wait for all of them
+#[tokio::test(flavor = "multi_thread")]
+async fn immediate_flush_does_not_hold_device() {
+    let (device, transport) = device(FlushStrategy::Immediate);
+
+    for key in 0..Kind::Mk2.key_count() {
+        device.set_button_image(key, DynamicImage::new_rgb8(72, 72)).await.unwrap();
+    }
+
+    transport.take_written();
+    transport.set_write_delay(Duration::from_millis(50));
+
+    // Compensation stages every key at once
+    let writer = tokio::spawn({
+        let device = device.clone();
+        async move { device.set_brightness_compensation(true).await }
+    });
+
+    sleep(Duration::from_millis(100)).await;
+
+    timeout(Duration::from_millis(300), device.firmware_version())
+        .await
+        .expect("Device was locked for the whole flush")
+        .unwrap();
+
+    assert!(!writer.is_finished());
+
+    writer.await.unwrap().unwrap();
+    assert!(transport.take_written().len() >= Kind::Mk2.key_count() as usize);
+}
+
+/// Ticker of a device wrapped outside a runtime starts with the first write
+#[test]
+fn ticker_starts_inside_runtime() {
+    let (device, transport) = device(FlushStrategy::Ticker(Duration::from_millis(10)));
+
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+
+    runtime.block_on(async {
+        device.write_image(0, Kind::Mk2.blank_image()).await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+    });
+
+    assert!(!transport.take_written().is_empty());
+}
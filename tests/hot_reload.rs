@@ -0,0 +1,62 @@
+#![cfg(feature = "hot-reload")]
+
+use std::fs;
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use elgato_streamdeck::assets::AssetRegistry;
+use elgato_streamdeck::hot_reload::AssetWatcher;
+use elgato_streamdeck::image::{ImageFormat, Rgb, RgbImage};
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, StreamDeck};
+
+/// Changing a file registers it again and writes keys showing it, removing it unregisters it
+#[test]
+fn changed_files_are_written() {
+    let directory = std::env::temp_dir().join(format!("streamdeck-hot-reload-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let path = directory.join("mute_on.bmp");
+    RgbImage::from_pixel(72, 72, Rgb([255, 0, 0])).save_with_format(&path, ImageFormat::Bmp).unwrap();
+
+    let assets = Arc::new(AssetRegistry::new());
+    let watcher = AssetWatcher::new(assets.clone(), &directory).expect("Failed to watch");
+    assert_eq!(assets.names().unwrap(), ["mute_on"]);
+
+    let options = ConnectOptions {
+        assets: Some(assets.clone()),
+        ..Default::default()
+    };
+
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), options);
+    device.set_button_asset(3, "mute_on").unwrap();
+    device.flush().unwrap();
+    let red = transport.take_written();
+
+    RgbImage::from_pixel(72, 72, Rgb([0, 0, 255])).save_with_format(&path, ImageFormat::Bmp).unwrap();
+
+    let started = Instant::now();
+    while transport.take_written().is_empty() {
+        assert!(started.elapsed() < Duration::from_secs(5), "Change wasn't picked up");
+        watcher.update(&[&device]).unwrap();
+        sleep(Duration::from_millis(20));
+    }
+
+    device.set_button_asset(3, "mute_on").unwrap();
+    device.flush().unwrap();
+    assert_ne!(transport.take_written(), red);
+
+    fs::remove_file(&path).unwrap();
+
+    let started = Instant::now();
+    while assets.contains("mute_on").unwrap() {
+        assert!(started.elapsed() < Duration::from_secs(5), "Removal wasn't picked up");
+        watcher.update(&[&device]).unwrap();
+        sleep(Duration::from_millis(20));
+    }
+
+    fs::remove_dir_all(&directory).ok();
+}
@@ -0,0 +1,40 @@
+#![cfg(all(feature = "fake", feature = "manager"))]
+
+use elgato_streamdeck::fake::FAKE_ENV;
+use elgato_streamdeck::hotplug::DeviceWatcher;
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::manager::DeviceEvent;
+use elgato_streamdeck::DeviceId;
+
+fn ids(events: Vec<DeviceEvent>) -> Vec<(bool, DeviceId)> {
+    let mut ids = events
+        .into_iter()
+        .filter_map(|event| match event {
+            DeviceEvent::Connected(id) => Some((true, id)),
+            DeviceEvent::Disconnected(id) => Some((false, id)),
+            DeviceEvent::Error(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    ids.sort_by_key(|(connected, id)| (*connected, id.serial.clone()));
+    ids
+}
+
+/// Devices present at the first scan are reported as connected, later scans only report what changed
+#[test]
+fn scans_report_changes() {
+    let id = |kind: Kind, serial: &str| DeviceId { kind, serial: serial.to_string() };
+
+    // Only test in this binary, so nothing else sees the variable change
+    unsafe { std::env::set_var(FAKE_ENV, "Mk2:A1,Plus:B1") };
+
+    let mut watcher = DeviceWatcher::new().expect("Failed to create watcher");
+
+    assert_eq!(ids(watcher.scan()), [(true, id(Kind::Mk2, "A1")), (true, id(Kind::Plus, "B1"))]);
+    assert!(watcher.scan().is_empty());
+
+    unsafe { std::env::set_var(FAKE_ENV, "Plus:B1,Neo:C1") };
+
+    assert_eq!(ids(watcher.scan()), [(false, id(Kind::Mk2, "A1")), (true, id(Kind::Neo, "C1"))]);
+    assert_eq!(watcher.devices().len(), 2);
+}
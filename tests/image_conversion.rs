@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::PathBuf;
+
+use elgato_streamdeck::images::{content_hash, convert_image, RawImage};
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::StreamDeckError;
+use image::{DynamicImage, Rgb, RgbImage};
+
+const KINDS: [Kind; 16] = [
+    Kind::Original,
+    Kind::OriginalV2,
+    Kind::Mini,
+    Kind::Xl,
+    Kind::XlV2,
+    Kind::Mk2,
+    Kind::Mk2Scissor,
+    Kind::MiniMk2,
+    Kind::MiniDiscord,
+    Kind::Neo,
+    Kind::Pedal,
+    Kind::Plus,
+    Kind::PlusXl,
+    Kind::MiniMk2Module,
+    Kind::Mk2Module,
+    Kind::XlV2Module,
+];
+
+/// Image with gradients and a diagonal, so rotation, mirroring and scaling all show up in the output
+fn test_image() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(97, 83, |x, y| {
+        if x == y {
+            Rgb([255, 255, 255])
+        } else {
+            Rgb([(x * 255 / 96) as u8, (y * 255 / 82) as u8, ((x + y) % 256) as u8])
+        }
+    }))
+}
+
+/// Compares hashes of images converted for every kind with `tests/snapshots/image_conversion.txt`, `UPDATE_SNAPSHOTS=1` rewrites it instead
+#[test]
+fn image_conversion_matches_snapshot() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots/image_conversion.txt");
+
+    let snapshot = KINDS
+        .iter()
+        .map(|kind| {
+            let data = convert_image(*kind, test_image()).expect("Failed to convert image");
+            format!("{:?}: {} bytes, {:016x}\n", kind, data.len(), content_hash(&data))
+        })
+        .collect::<String>();
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, snapshot).expect("Failed to write snapshot");
+        return;
+    }
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap_or_default(),
+        snapshot,
+        "Image conversion doesn't match snapshot, run with UPDATE_SNAPSHOTS=1 and review the diff"
+    );
+}
+
+/// Converting the same image twice gives the same bytes
+#[test]
+fn image_conversion_is_repeatable() {
+    for kind in KINDS {
+        assert_eq!(convert_image(kind, test_image()).unwrap(), convert_image(kind, test_image()).unwrap(), "{:?}", kind);
+    }
+}
+
+/// Raw pixel data of the wrong length is rejected as a caller error
+#[test]
+fn raw_image_rejects_wrong_length() {
+    assert!(RawImage::new(2, 2, vec![0; 16]).is_ok());
+    assert!(matches!(RawImage::new(2, 2, vec![0; 15]), Err(StreamDeckError::InvalidImageSize)));
+}
@@ -0,0 +1,14 @@
+use std::collections::HashSet;
+
+use elgato_streamdeck::info::Kind;
+
+/// Every kind has its own product id, which maps back to the kind if it's supported
+#[test]
+fn all_kinds_round_trip() {
+    let ids = Kind::ALL.iter().map(|kind| (kind.vendor_id(), kind.product_id())).collect::<HashSet<_>>();
+    assert_eq!(ids.len(), Kind::ALL.len());
+
+    for kind in Kind::all() {
+        assert_eq!(Kind::from_vid_pid(kind.vendor_id(), kind.product_id()), Some(kind));
+    }
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "input-tools")]
+
+use std::sync::Arc;
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::layers::{LayerUpdate, Layers, BASE_LAYER};
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, DeviceStateUpdate, KeyIndex, StreamDeck};
+use image::DynamicImage;
+
+/// Keys pressed while a modifier is held belong to its layer, and their releases stay on that layer after the modifier is released
+#[test]
+fn modifier_tags_keys_until_their_release() {
+    let mut layers = Layers::new();
+    layers.add_modifier(0, 1);
+
+    let updates = layers.handle_all([
+        DeviceStateUpdate::ButtonDown(KeyIndex(0)),
+        DeviceStateUpdate::ButtonDown(KeyIndex(3)),
+        DeviceStateUpdate::ButtonUp(KeyIndex(0)),
+        DeviceStateUpdate::ButtonUp(KeyIndex(3)),
+        DeviceStateUpdate::ButtonDown(KeyIndex(3)),
+    ]);
+
+    assert_eq!(
+        updates,
+        [
+            LayerUpdate::LayerChanged { previous: BASE_LAYER, layer: 1 },
+            LayerUpdate::Input {
+                layer: 1,
+                update: DeviceStateUpdate::ButtonDown(KeyIndex(3))
+            },
+            LayerUpdate::LayerChanged { previous: 1, layer: BASE_LAYER },
+            LayerUpdate::Input {
+                layer: 1,
+                update: DeviceStateUpdate::ButtonUp(KeyIndex(3))
+            },
+            LayerUpdate::Input {
+                layer: BASE_LAYER,
+                update: DeviceStateUpdate::ButtonDown(KeyIndex(3))
+            },
+        ]
+    );
+}
+
+/// Last pressed modifier wins, and releasing it goes back to the modifier still held
+#[test]
+fn last_pressed_modifier_wins() {
+    let mut layers = Layers::new();
+    layers.add_modifier(0, 1);
+    layers.add_modifier(1, 2);
+
+    layers.handle(DeviceStateUpdate::ButtonDown(KeyIndex(0)));
+    layers.handle(DeviceStateUpdate::ButtonDown(KeyIndex(1)));
+    assert_eq!(layers.active_layer(), 2);
+
+    assert_eq!(layers.handle(DeviceStateUpdate::ButtonUp(KeyIndex(1))), Some(LayerUpdate::LayerChanged { previous: 2, layer: 1 }));
+    assert_eq!(
+        layers.handle(DeviceStateUpdate::ButtonUp(KeyIndex(0))),
+        Some(LayerUpdate::LayerChanged { previous: 1, layer: BASE_LAYER })
+    );
+}
+
+/// Reading through the layers stages images of the new layer when a modifier is pressed
+#[test]
+fn layer_change_swaps_images() {
+    let transport = MockTransport::new();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let device = Arc::new(StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default()));
+    let reader = device.get_reader();
+
+    let mut layers = Layers::new();
+    layers.add_modifier(0, 1);
+    layers.set_image(1, 4, DynamicImage::new_rgb8(72, 72));
+
+    let mut report = vec![0u8; 4 + Kind::Mk2.key_count() as usize];
+    report[0] = 0x01;
+    report[4] = 1;
+    transport.push_input(report);
+
+    let updates = reader.read_layered(&mut layers, None).expect("Failed to read");
+    assert_eq!(updates, [LayerUpdate::LayerChanged { previous: BASE_LAYER, layer: 1 }]);
+
+    transport.take_written();
+    device.flush().expect("Failed to flush");
+    assert!(!transport.take_written().is_empty());
+}
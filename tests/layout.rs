@@ -0,0 +1,129 @@
+#![cfg(feature = "layout")]
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::layout::{Layout, LayoutEvent, LayoutRuntime};
+use elgato_streamdeck::protocol::{decode_image_report, ImageTarget};
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, DeviceStateUpdate, EncoderIndex, KeyIndex, StreamDeck, StreamDeckError};
+
+const LAYOUT: &str = r#"
+[[pages]]
+name = "main"
+
+[[pages.keys]]
+key = 0
+action = "mute"
+color = [200, 0, 0]
+
+[[pages.keys]]
+key = 1
+page = "lights"
+
+[[pages.encoders]]
+encoder = 2
+action = "volume"
+label = "Volume"
+
+[[pages]]
+name = "lights"
+
+[[pages.keys]]
+key = 0
+action = "lamp"
+"#;
+
+/// Updates are reported with action ids, and releases keep the action of their press across page changes
+#[test]
+fn updates_become_actions() {
+    let layout = Layout::from_toml(LAYOUT).expect("Failed to parse layout");
+    assert_eq!(Layout::from_json(&serde_json::to_string(&layout).unwrap()).unwrap(), layout);
+
+    let mut runtime = LayoutRuntime::new(layout).unwrap();
+
+    let events = runtime.handle_all([
+        DeviceStateUpdate::ButtonDown(KeyIndex(0)),
+        DeviceStateUpdate::EncoderTwist(EncoderIndex(2), -3),
+        DeviceStateUpdate::EncoderTwist(EncoderIndex(0), 1),
+        DeviceStateUpdate::ButtonDown(KeyIndex(1)),
+        DeviceStateUpdate::ButtonUp(KeyIndex(0)),
+        DeviceStateUpdate::ButtonUp(KeyIndex(1)),
+        DeviceStateUpdate::ButtonDown(KeyIndex(0)),
+    ]);
+
+    assert_eq!(
+        events,
+        [
+            LayoutEvent::Pressed("mute".to_string()),
+            LayoutEvent::Turned("volume".to_string(), -3),
+            LayoutEvent::PageChanged {
+                previous: "main".to_string(),
+                page: "lights".to_string(),
+            },
+            LayoutEvent::Released("mute".to_string()),
+            LayoutEvent::Pressed("lamp".to_string()),
+        ]
+    );
+    assert_eq!(runtime.page().name, "lights");
+}
+
+/// Keys switching to pages that don't exist are rejected
+#[test]
+fn unknown_pages_are_rejected() {
+    let layout = r#"{ "pages": [{ "name": "main", "keys": [{ "key": 0, "page": "missing" }] }] }"#;
+
+    assert!(matches!(Layout::from_json(layout), Err(StreamDeckError::InvalidLayout(_))));
+    assert!(matches!(Layout::from_toml(""), Err(StreamDeckError::InvalidLayout(_))));
+}
+
+/// Variables are replaced with values of providers, and refreshing only draws keys whose text changed
+#[test]
+fn variables_redraw_changed_keys() {
+    let layout = r#"{ "pages": [{ "name": "main", "keys": [
+        { "key": 0, "text": "{cpu}%", "color": [0, 0, 80] },
+        { "key": 1, "text": "{{cpu}} {missing}", "color": [0, 0, 80] }
+    ] }] }"#;
+
+    let mut runtime = LayoutRuntime::new(Layout::from_json(layout).unwrap()).unwrap();
+    let mut load = 0;
+    runtime.add_provider("cpu", Duration::ZERO, move || {
+        load += 5;
+        load.to_string()
+    });
+
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default());
+    runtime.apply(&device).unwrap();
+    device.flush().unwrap();
+    transport.take_written();
+
+    assert!(runtime.refresh(&device).unwrap());
+    assert_eq!(runtime.expand("{cpu}% {{cpu}} {missing}"), "5% {cpu} {missing}");
+
+    device.flush().unwrap();
+    let keys = transport
+        .take_written()
+        .iter()
+        .filter_map(|report| decode_image_report(Kind::Mk2, report))
+        .filter(|report| report.page_number == 0)
+        .map(|report| report.target)
+        .collect::<Vec<_>>();
+
+    assert_eq!(keys, [ImageTarget::Key(0)]);
+}
+
+/// Bound closures get events of their action, other events are returned
+#[test]
+fn bound_actions_are_dispatched() {
+    let mut runtime = LayoutRuntime::new(Layout::from_toml(LAYOUT).unwrap()).unwrap();
+    let received = Arc::new(Mutex::new(vec![]));
+
+    let sink = received.clone();
+    runtime.bind("volume", move |event| sink.lock().unwrap().push(event.clone()));
+
+    assert_eq!(runtime.dispatch(DeviceStateUpdate::EncoderTwist(EncoderIndex(2), 4)), None);
+    assert_eq!(runtime.dispatch(DeviceStateUpdate::ButtonDown(KeyIndex(0))), Some(LayoutEvent::Pressed("mute".to_string())));
+    assert_eq!(*received.lock().unwrap(), [LayoutEvent::Turned("volume".to_string(), 4)]);
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "tools")]
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::lease::{request_lease_in, try_lease_in};
+use elgato_streamdeck::DeviceId;
+
+/// Device can only be leased once at a time, and the holder sees requests for it
+#[test]
+fn lease_is_exclusive() {
+    let directory = std::env::temp_dir().join(format!("streamdeck-lease-{}", std::process::id()));
+    let id = DeviceId {
+        kind: Kind::Mk2,
+        serial: "AL12H1A00000".to_string(),
+    };
+
+    let lease = try_lease_in(&directory, &id).unwrap().expect("Device wasn't leased");
+    assert!(try_lease_in(&directory, &id).unwrap().is_none());
+    assert!(lease.take_request().unwrap().is_none());
+
+    request_lease_in(&directory, &id).unwrap();
+    assert_eq!(lease.take_request().unwrap().and_then(|request| request.pid), Some(std::process::id()));
+    assert!(lease.take_request().unwrap().is_none());
+
+    drop(lease);
+    assert!(try_lease_in(&directory, &id).unwrap().is_some());
+
+    std::fs::remove_dir_all(&directory).ok();
+}
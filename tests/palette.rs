@@ -0,0 +1,56 @@
+#![cfg(feature = "widgets")]
+
+use elgato_streamdeck::palette::{contrast_ratio, is_distinguishable, readable_text_color, simulate_deficiency, Deficiency, StatusColor};
+
+const BLACK: (u8, u8, u8) = (0, 0, 0);
+const WHITE: (u8, u8, u8) = (255, 255, 255);
+
+/// Contrast ratios match the WCAG definition
+#[test]
+fn contrast_ratios_match_wcag() {
+    let cases = [
+        (BLACK, WHITE, 21.0),
+        (WHITE, BLACK, 21.0),
+        (WHITE, WHITE, 1.0),
+        (WHITE, (255, 0, 0), 4.0),
+        (WHITE, (119, 119, 119), 4.48),
+        (BLACK, (0, 0, 255), 2.44),
+    ];
+
+    for (a, b, expected) in cases {
+        let ratio = contrast_ratio(a, b);
+        assert!((ratio - expected).abs() < 0.01, "Contrast of {a:?} and {b:?} is {ratio}, expected {expected}");
+    }
+
+    assert_eq!(readable_text_color((255, 255, 0)), BLACK);
+    assert_eq!(readable_text_color((0, 0, 128)), WHITE);
+}
+
+/// Pure red is simulated with the full severity matrices of Machado et al.
+#[test]
+fn red_is_simulated_for_every_deficiency() {
+    let cases = [
+        (Deficiency::Protanopia, (109, 95, 0)),
+        (Deficiency::Deuteranopia, (163, 144, 0)),
+        (Deficiency::Tritanopia, (255, 0, 15)),
+        (Deficiency::Achromatopsia, (127, 127, 127)),
+    ];
+
+    for (deficiency, expected) in cases {
+        assert_eq!(simulate_deficiency((255, 0, 0), deficiency), expected, "{deficiency:?}");
+    }
+}
+
+/// Status colors stay distinguishable from each other, while red and green don't
+#[test]
+fn status_colors_are_distinguishable() {
+    let statuses = [StatusColor::Ok, StatusColor::Warning, StatusColor::Error, StatusColor::Info, StatusColor::Neutral];
+
+    for (i, a) in statuses.iter().enumerate() {
+        for b in &statuses[i + 1..] {
+            assert!(is_distinguishable(a.rgb(), b.rgb()), "{a:?} and {b:?}");
+        }
+    }
+
+    assert!(!is_distinguishable((200, 60, 40), (90, 140, 40)));
+}
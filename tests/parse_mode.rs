@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, DeviceStateUpdate, KeyIndex, ParseMode, StreamDeck, StreamDeckError, StreamDeckInput};
+
+/// Button state report of Stream Deck Plus with the keys pressed
+fn button_report(pressed: &[u8]) -> Vec<u8> {
+    let mut report = vec![0x01, 0x00, Kind::Plus.key_count(), 0x00];
+    report.extend((0..Kind::Plus.key_count()).map(|key| pressed.contains(&key) as u8));
+    report
+}
+
+/// Resynchronizing after a garbled report discards only the malformed reports queued after it, well-formed ones are still read
+#[test]
+fn resync_keeps_valid_reports() {
+    let transport = MockTransport::new();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let device = Arc::new(StreamDeck::from_transport(Kind::Plus, transport.clone(), ConnectOptions::default()));
+    let reader = device.get_reader();
+
+    transport.push_input(button_report(&[0]));
+    assert!(matches!(reader.read(None).unwrap()[..], [DeviceStateUpdate::ButtonDown(KeyIndex(0))]));
+
+    let garbled = button_report(&[])[..3].to_vec();
+    transport.push_input(garbled.clone());
+    transport.push_input(garbled);
+    transport.push_input(button_report(&[]));
+
+    assert!(reader.read(None).unwrap().is_empty());
+    assert_eq!(device.recovered_desyncs(), 1);
+
+    assert!(matches!(reader.read(None).unwrap()[..], [DeviceStateUpdate::ButtonUp(KeyIndex(0))]));
+    assert!(reader.read(None).unwrap().is_empty());
+}
+
+/// Strict mode fails on malformed and unknown reports instead of skipping them, and doesn't resynchronize
+#[test]
+fn strict_mode_rejects_bad_reports() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(
+        Kind::Plus,
+        transport.clone(),
+        ConnectOptions {
+            parse_mode: ParseMode::Strict,
+            ..Default::default()
+        },
+    );
+
+    let mut unknown = button_report(&[]);
+    unknown[1] = 0x7f;
+
+    transport.push_input(button_report(&[])[..3].to_vec());
+    transport.push_input(unknown);
+    transport.push_input(button_report(&[1]));
+
+    assert!(matches!(device.read_input(None), Err(StreamDeckError::BadData)));
+    assert!(matches!(device.read_input(None), Err(StreamDeckError::BadData)));
+    assert!(matches!(device.read_input(None), Ok(StreamDeckInput::ButtonStateChange(buttons)) if buttons[1]));
+
+    assert_eq!(device.recovered_desyncs(), 0);
+}
+
+/// Passthrough mode returns reports of unknown type along with their type byte, instead of the HID report id
+#[test]
+fn passthrough_returns_unknown_report_type() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(
+        Kind::Plus,
+        transport.clone(),
+        ConnectOptions {
+            parse_mode: ParseMode::Passthrough,
+            ..Default::default()
+        },
+    );
+
+    let mut unknown = button_report(&[]);
+    unknown[1] = 0x7f;
+
+    transport.push_input(unknown.clone());
+
+    assert!(matches!(device.read_input(None), Ok(StreamDeckInput::Unknown(0x7f, report)) if report == unknown));
+    assert_eq!(device.recovered_desyncs(), 0);
+}
+
+/// Kinds without a report type byte tell reports apart by the HID report id, so reports with another id are passed through
+/// instead of being read as button states
+#[test]
+fn passthrough_returns_unknown_report_id() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(
+        Kind::Mk2,
+        transport.clone(),
+        ConnectOptions {
+            parse_mode: ParseMode::Passthrough,
+            ..Default::default()
+        },
+    );
+
+    let mut buttons = vec![0x01, 0x00, Kind::Mk2.key_count(), 0x00];
+    buttons.extend((0..Kind::Mk2.key_count()).map(|key| (key == 2) as u8));
+
+    let mut unknown = buttons.clone();
+    unknown[0] = 0x02;
+
+    transport.push_input(unknown.clone());
+    transport.push_input(buttons);
+
+    assert!(matches!(device.read_input(None), Ok(StreamDeckInput::Unknown(0x02, report)) if report == unknown));
+    assert!(matches!(device.read_input(None), Ok(StreamDeckInput::ButtonStateChange(buttons)) if buttons[2]));
+    assert_eq!(device.recovered_desyncs(), 0);
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "pipeline")]
+
+use std::sync::{Arc, Mutex};
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::pipeline::Pipeline;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, StreamDeck, StreamDeckError};
+use image::{DynamicImage, Rgb, RgbImage};
+
+fn pipeline(workers: usize) -> (Pipeline, MockTransport) {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default());
+
+    (Pipeline::new(device, workers), transport)
+}
+
+fn solid(size: u32, color: [u8; 3]) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(size, size, Rgb(color)))
+}
+
+/// Large noisy image that takes a while to encode
+fn slow_image() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(2048, 2048, |x, y| Rgb([(x * 7 + y * 13) as u8, (x ^ y) as u8, (x * y) as u8])))
+}
+
+/// Keys of the images written, taken from the last report of every image
+fn written_keys(transport: &MockTransport) -> Vec<u8> {
+    transport
+        .take_written()
+        .iter()
+        .filter(|report| report[0] == 0x02 && report[1] == 0x07 && report[3] == 1)
+        .map(|report| report[2])
+        .collect()
+}
+
+/// Frames are written in submission order even if a later frame finishes encoding first
+#[test]
+fn frames_are_written_in_order() {
+    let (mut pipeline, transport) = pipeline(4);
+    let completed = Arc::new(Mutex::new(vec![]));
+
+    let first = pipeline.submit(vec![(0, slow_image())]);
+    let second = pipeline.submit(vec![(1, solid(72, [255, 0, 0]))]);
+
+    for (frame, handle) in [(0, &first), (1, &second)] {
+        let completed = completed.clone();
+        handle.on_complete(move |_| completed.lock().unwrap().push(frame));
+    }
+
+    second.wait().unwrap();
+    assert!(first.is_done());
+
+    assert_eq!(*completed.lock().unwrap(), vec![0, 1]);
+    assert_eq!(written_keys(&transport), vec![0, 1]);
+}
+
+/// Keys of a frame are written in key order, the last image of a repeated key wins
+#[test]
+fn keys_are_written_in_order() {
+    let (mut pipeline, transport) = pipeline(2);
+
+    pipeline
+        .submit(vec![(3, solid(72, [255, 0, 0])), (1, solid(72, [0, 255, 0])), (3, solid(72, [0, 0, 255])), (2, solid(72, [0, 0, 255]))])
+        .wait()
+        .unwrap();
+
+    assert_eq!(written_keys(&transport), vec![1, 2, 3]);
+
+    // Key 3 already shows blue, so only keeping the last image makes this a no-op
+    pipeline.submit(vec![(3, solid(72, [0, 0, 255]))]).wait().unwrap();
+    assert!(written_keys(&transport).is_empty());
+}
+
+/// Keys that would get the image they already show aren't written again
+#[test]
+fn unchanged_keys_are_skipped() {
+    let (mut pipeline, transport) = pipeline(2);
+
+    pipeline.submit(vec![(0, solid(72, [255, 0, 0])), (1, solid(72, [0, 255, 0]))]).wait().unwrap();
+    assert_eq!(written_keys(&transport), vec![0, 1]);
+
+    pipeline.submit(vec![(0, solid(72, [255, 0, 0])), (1, solid(72, [0, 255, 0]))]).wait().unwrap();
+    assert!(written_keys(&transport).is_empty());
+
+    pipeline.submit(vec![(0, solid(72, [255, 0, 0])), (1, solid(72, [0, 0, 255]))]).wait().unwrap();
+    assert_eq!(written_keys(&transport), vec![1]);
+}
+
+/// Failed frames report the error without holding back the frames after them
+#[test]
+fn failed_frame_reports_error() {
+    let (mut pipeline, transport) = pipeline(2);
+
+    let failed = pipeline.submit(vec![(200, solid(72, [255, 0, 0]))]);
+    let next = pipeline.submit(vec![(0, solid(72, [255, 0, 0]))]);
+
+    assert!(matches!(failed.wait(), Err(e) if matches!(*e, StreamDeckError::InvalidKeyIndex)));
+    next.wait().unwrap();
+
+    assert_eq!(written_keys(&transport), vec![0]);
+}
+
+/// Finishing writes every submitted frame before giving the device back
+#[test]
+fn finish_writes_pending_frames() {
+    let (mut pipeline, transport) = pipeline(3);
+
+    let handles = (0..3).map(|key| pipeline.submit(vec![(key, solid(72, [key * 80, 0, 0]))])).collect::<Vec<_>>();
+
+    let device = pipeline.finish().expect("Writer thread panicked");
+
+    assert_eq!(device.kind(), Kind::Mk2);
+    assert!(handles.iter().all(|handle| handle.is_done()));
+    assert!(handles.iter().all(|handle| handle.wait().is_ok()));
+    assert_eq!(written_keys(&transport), vec![0, 1, 2]);
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "tools")]
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::probe::probe_transport;
+use elgato_streamdeck::transport::MockTransport;
+
+/// Mock device answering Gen2 firmware version requests
+fn gen2_transport() -> MockTransport {
+    let transport = MockTransport::new();
+
+    // Firmware version feature report of Gen2 devices, version string starts at byte 6
+    let mut firmware = vec![0x05, 0, 0, 0, 0, 0];
+    firmware.extend_from_slice(b"1.01.000");
+    transport.set_feature_response(firmware);
+
+    transport
+}
+
+/// Input report length is taken from the report descriptor and matched to a kind with the same layout
+#[test]
+fn probe_reads_descriptor() {
+    let transport = gen2_transport();
+
+    // Report id 1, 8 bit fields, 511 of them, input
+    transport.set_report_descriptor(vec![0x85, 0x01, 0x75, 0x08, 0x96, 0xff, 0x01, 0x81, 0x02]);
+
+    let report = probe_transport(&transport);
+    assert_eq!(report.input_report_length, Some(512));
+    assert_eq!(report.provisional_kind, Some(Kind::Mk2));
+}
+
+/// Field sizes that overflow make the descriptor unparseable instead of panicking or wrapping around
+#[test]
+fn probe_rejects_overflowing_descriptor() {
+    let transport = gen2_transport();
+
+    // Report id 1, largest report size and count, two inputs of that size
+    transport.set_report_descriptor(vec![0x85, 0x01, 0x77, 0xff, 0xff, 0xff, 0xff, 0x97, 0xff, 0xff, 0xff, 0xff, 0x81, 0x02, 0x81, 0x02]);
+
+    let report = probe_transport(&transport);
+    assert_eq!(report.input_report_length, None);
+    assert!(report.firmware.is_some());
+}
+
+/// Fingerprints serialize with hex encoded binary data and the probe report flattened in
+#[cfg(feature = "serde")]
+#[test]
+fn fingerprint_json() {
+    use elgato_streamdeck::probe::{DeviceFingerprint, ProbeReport};
+
+    let fingerprint = DeviceFingerprint {
+        library_version: "1.0.0".to_string(),
+        vendor_id: Some(0x0fd9),
+        product: Some("Stream \"Deck\"".to_string()),
+        report_descriptor: vec![0x05, 0x0c],
+        feature_reports: vec![(5, Ok(vec![0xab])), (6, Err("broken".to_string()))],
+        report: ProbeReport {
+            key_count: Some(15),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let json: serde_json::Value = serde_json::from_str(&fingerprint.to_json().unwrap()).unwrap();
+
+    assert_eq!(json["vendor_id"], "0fd9");
+    assert_eq!(json["product_id"], serde_json::Value::Null);
+    assert_eq!(json["product"], "Stream \"Deck\"");
+    assert_eq!(json["report_descriptor"], "05 0c");
+    assert_eq!(json["feature_reports"], serde_json::json!([{ "report_id": 5, "data": "ab" }, { "report_id": 6, "error": "broken" }]));
+    assert_eq!(json["key_count"], 15);
+}
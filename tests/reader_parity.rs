@@ -0,0 +1,143 @@
+#![cfg(feature = "async")]
+
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::Duration;
+
+use elgato_streamdeck::info::{Kind, TouchKey};
+use elgato_streamdeck::protocol::input_report;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{AsyncStreamDeck, ConnectOptions, DeviceStateUpdate, EncoderIndex, HeldInput, InputDiffer, KeyIndex, StreamDeck, StreamDeckInput};
+
+/// Feeds the inputs to a fresh reader of each kind, one read per input
+async fn read_both(kind: Kind, inputs: &[StreamDeckInput]) -> (Vec<DeviceStateUpdate>, Vec<DeviceStateUpdate>) {
+    let reports = inputs.iter().map(|input| input_report(kind, input).expect("Kind can't send the input")).collect::<Vec<_>>();
+
+    let transport = MockTransport::new();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let device = Arc::new(StreamDeck::from_transport(kind, transport.clone(), ConnectOptions::default()));
+    let reader = device.get_reader();
+    let mut sync_updates = vec![];
+
+    for report in &reports {
+        transport.push_input(report.clone());
+        sync_updates.extend(reader.read(None).expect("Failed to read"));
+    }
+
+    let transport = MockTransport::new();
+    let device = AsyncStreamDeck::from(StreamDeck::from_transport(kind, transport.clone(), ConnectOptions::default()));
+    let reader = device.get_reader();
+    let mut async_updates = vec![];
+
+    for report in &reports {
+        transport.push_input(report.clone());
+        async_updates.extend(reader.read(100.0).await.expect("Failed to read"));
+    }
+
+    (sync_updates, async_updates)
+}
+
+/// Encoders and touch screen of the Plus produce the same updates through both readers
+#[tokio::test(flavor = "multi_thread")]
+async fn encoders_and_touch_screen_match() {
+    let (sync_updates, async_updates) = read_both(
+        Kind::Plus,
+        &[
+            StreamDeckInput::EncoderStateChange(vec![true, false, false, true]),
+            StreamDeckInput::EncoderTwist(vec![0, -2, 0, 5]),
+            StreamDeckInput::TouchScreenPress(120, 40),
+            StreamDeckInput::TouchScreenLongPress(300, 50),
+            StreamDeckInput::TouchScreenSwipe((10, 20), (600, 30)),
+            StreamDeckInput::EncoderStateChange(vec![false, false, false, true]),
+            StreamDeckInput::ButtonStateChange(vec![false, true, false, false, false, false, false, false]),
+        ],
+    )
+    .await;
+
+    assert_eq!(
+        sync_updates,
+        [
+            DeviceStateUpdate::EncoderDown(EncoderIndex(0)),
+            DeviceStateUpdate::EncoderDown(EncoderIndex(3)),
+            DeviceStateUpdate::EncoderTwist(EncoderIndex(1), -2),
+            DeviceStateUpdate::EncoderTwist(EncoderIndex(3), 5),
+            DeviceStateUpdate::TouchScreenPress(120, 40),
+            DeviceStateUpdate::TouchScreenLongPress(300, 50),
+            DeviceStateUpdate::TouchScreenSwipe((10, 20), (600, 30)),
+            DeviceStateUpdate::EncoderUp(EncoderIndex(0)),
+            DeviceStateUpdate::ButtonDown(KeyIndex(1)),
+        ]
+    );
+    assert_eq!(sync_updates, async_updates);
+}
+
+/// Touch points of the Neo produce the same updates through both readers
+#[tokio::test(flavor = "multi_thread")]
+async fn touch_points_match() {
+    let mut pressed = vec![false; 10];
+    pressed[8] = true;
+    let mut released = pressed.clone();
+    released[8] = false;
+    released[9] = true;
+
+    let (sync_updates, async_updates) = read_both(
+        Kind::Neo,
+        &[
+            StreamDeckInput::ButtonStateChange(pressed),
+            StreamDeckInput::ButtonStateChange(released),
+            StreamDeckInput::ButtonStateChange(vec![false; 10]),
+        ],
+    )
+    .await;
+
+    assert_eq!(
+        sync_updates,
+        [
+            DeviceStateUpdate::TouchKeyDown(TouchKey::PageLeft),
+            DeviceStateUpdate::TouchKeyUp(TouchKey::PageLeft),
+            DeviceStateUpdate::TouchKeyDown(TouchKey::PageRight),
+            DeviceStateUpdate::TouchKeyUp(TouchKey::PageRight),
+        ]
+    );
+    assert_eq!(sync_updates, async_updates);
+}
+
+/// Differ used directly delivers transitions held back by debounce once it's due, even without more input
+#[test]
+fn differ_delivers_debounced_transitions() {
+    let mut differ = InputDiffer::new(Kind::Mk2);
+    differ.set_debounce(Duration::from_millis(20));
+
+    let mut pressed = vec![false; Kind::Mk2.key_count() as usize];
+    pressed[2] = true;
+
+    assert_eq!(differ.diff(StreamDeckInput::ButtonStateChange(pressed.clone())), [DeviceStateUpdate::ButtonDown(KeyIndex(2))]);
+    assert!(differ.diff(StreamDeckInput::ButtonStateChange(vec![false; pressed.len()])).is_empty());
+    assert!(differ.buttons()[2]);
+
+    sleep(Duration::from_millis(30));
+    assert_eq!(differ.diff(StreamDeckInput::NoData), [DeviceStateUpdate::ButtonUp(KeyIndex(2))]);
+    assert!(!differ.buttons()[2]);
+}
+
+/// Controls reported as held right after the differ was created don't produce updates, and neither do their releases
+#[test]
+fn differ_ignores_held_input() {
+    let mut differ = InputDiffer::new(Kind::Plus);
+    differ.set_held_input(HeldInput::Ignore(Duration::from_secs(60)));
+
+    let mut held = vec![false; Kind::Plus.key_count() as usize];
+    held[3] = true;
+
+    assert!(differ.diff(StreamDeckInput::ButtonStateChange(held)).is_empty());
+    assert!(differ.diff(StreamDeckInput::EncoderStateChange(vec![false, true, false, false])).is_empty());
+
+    differ.set_held_input(HeldInput::Report);
+
+    assert!(differ.diff(StreamDeckInput::ButtonStateChange(vec![false; Kind::Plus.key_count() as usize])).is_empty());
+    assert!(differ.diff(StreamDeckInput::EncoderStateChange(vec![false; 4])).is_empty());
+    assert_eq!(
+        differ.diff(StreamDeckInput::EncoderStateChange(vec![false, true, false, false])),
+        [DeviceStateUpdate::EncoderDown(EncoderIndex(1))]
+    );
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::protocol::input_report;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, ReaderStats, StreamDeck, StreamDeckInput};
+
+/// Reports, updates and skipped unknown reports are all counted
+#[test]
+fn stats_count_reports_updates_and_failures() {
+    let transport = MockTransport::new();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let device = Arc::new(StreamDeck::from_transport(Kind::Plus, transport.clone(), ConnectOptions::default()));
+    let reader = device.get_reader();
+
+    let mut buttons = vec![false; Kind::Plus.key_count() as usize];
+    buttons[0] = true;
+    buttons[1] = true;
+    let pressed = input_report(Kind::Plus, &StreamDeckInput::ButtonStateChange(buttons)).expect("Plus can't send button states");
+
+    let mut unknown = pressed.clone();
+    unknown[1] = 0x7f;
+
+    transport.push_input(pressed);
+    transport.push_input(unknown);
+
+    assert_eq!(reader.read(None).expect("Failed to read").len(), 2);
+    assert!(reader.read(None).expect("Failed to read").is_empty());
+
+    assert_eq!(
+        reader.stats(),
+        ReaderStats {
+            reports_read: 1,
+            updates_emitted: 2,
+            parse_failures: 1,
+            recovered_desyncs: 0,
+        }
+    );
+}
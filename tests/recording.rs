@@ -0,0 +1,46 @@
+#![cfg(feature = "input-tools")]
+
+use std::time::{Duration, Instant};
+
+use elgato_streamdeck::recording::MacroRecorder;
+use elgato_streamdeck::{DeviceStateUpdate, EncoderIndex, KeyIndex};
+
+/// Delays are measured between the times updates happened, not when they were recorded
+#[test]
+fn delays_follow_update_times() {
+    let start = Instant::now();
+    let mut recorder = MacroRecorder::new();
+
+    recorder.record(&[DeviceStateUpdate::ButtonDown(KeyIndex(1))], start + Duration::from_millis(500));
+    recorder.record(
+        &[
+            DeviceStateUpdate::ButtonUp(KeyIndex(1)),
+            DeviceStateUpdate::TouchScreenPress(10, 10),
+            DeviceStateUpdate::EncoderTwist(EncoderIndex(0), 2),
+        ],
+        start + Duration::from_millis(620),
+    );
+    recorder.record(&[DeviceStateUpdate::ButtonDown(KeyIndex(2))], start + Duration::from_millis(3620));
+
+    // Updates from before the previous one don't produce negative delays
+    recorder.record(&[DeviceStateUpdate::ButtonUp(KeyIndex(2))], start);
+
+    let recorded = recorder.finish();
+    let delays = recorded.steps.iter().map(|step| step.delay.as_millis()).collect::<Vec<_>>();
+
+    assert_eq!(delays, [0, 120, 0, 3000, 0]);
+    assert_eq!(
+        recorded.updates().collect::<Vec<_>>(),
+        [
+            DeviceStateUpdate::ButtonDown(KeyIndex(1)),
+            DeviceStateUpdate::ButtonUp(KeyIndex(1)),
+            DeviceStateUpdate::EncoderTwist(EncoderIndex(0), 2),
+            DeviceStateUpdate::ButtonDown(KeyIndex(2)),
+            DeviceStateUpdate::ButtonUp(KeyIndex(2)),
+        ]
+    );
+
+    assert_eq!(recorded.duration(), Duration::from_millis(3120));
+    assert_eq!(recorded.with_max_delay(Duration::from_secs(1)).duration(), Duration::from_millis(1120));
+    assert!(recorder.is_empty());
+}
@@ -0,0 +1,24 @@
+#![cfg(feature = "serde")]
+
+use elgato_streamdeck::info::{ImageFormat, Kind, Vendor};
+use elgato_streamdeck::DeviceId;
+
+/// Kinds, their image formats and vendors, and device ids survive a round trip through JSON
+#[test]
+fn info_types_round_trip() {
+    let id = DeviceId {
+        kind: Kind::Plus,
+        serial: "A1".to_string(),
+    };
+
+    let json = serde_json::to_string(&id).unwrap();
+    assert_eq!(json, r#"{"kind":"Plus","serial":"A1"}"#);
+    assert_eq!(serde_json::from_str::<DeviceId>(&json).unwrap(), id);
+
+    let format = Kind::Plus.key_image_format();
+    assert_eq!(serde_json::from_str::<ImageFormat>(&serde_json::to_string(&format).unwrap()).unwrap(), format);
+
+    let vendor = Kind::Plus.vendor();
+    assert_eq!(serde_json::to_string(&vendor).unwrap(), r#""Elgato""#);
+    assert_eq!(serde_json::from_str::<Vendor>(r#""Elgato""#).unwrap(), vendor);
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "settings")]
+
+use std::path::PathBuf;
+
+use elgato_streamdeck::info::{ImageRotation, Kind};
+use elgato_streamdeck::settings::{DeviceSettings, SettingsStore};
+use elgato_streamdeck::DeviceId;
+
+fn settings_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("streamdeck-settings-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn id(kind: Kind, serial: &str) -> DeviceId {
+    DeviceId { kind, serial: serial.to_string() }
+}
+
+fn settings() -> DeviceSettings {
+    DeviceSettings {
+        brightness: Some(40),
+        orientation: Some(ImageRotation::Rot90),
+        color_profile: Some("Display P3".to_string()),
+        profile: Some("Streaming".to_string()),
+    }
+}
+
+/// Saved settings load back the same from both JSON and TOML files, and a missing file is an empty store
+#[test]
+fn settings_round_trip() {
+    for name in ["settings.json", "settings.toml"] {
+        let path = settings_path(name);
+
+        let mut store = SettingsStore::open(&path).unwrap();
+        assert_eq!(store.get(&id(Kind::Mk2, "A1")), None);
+
+        store.set(&id(Kind::Mk2, "A1"), settings());
+        store.entry(&id(Kind::Plus, "B1")).brightness = Some(80);
+        store.save().unwrap();
+
+        if name.ends_with(".toml") {
+            assert!(std::fs::read_to_string(&path).unwrap().contains("brightness = 40"));
+        }
+
+        let store = SettingsStore::open(&path).unwrap();
+        assert_eq!(store.get(&id(Kind::Mk2, "A1")), Some(&settings()));
+        assert_eq!(store.get(&id(Kind::Plus, "B1")).and_then(|settings| settings.brightness), Some(80));
+        assert_eq!(store.get(&id(Kind::Plus, "B1")).and_then(|settings| settings.orientation), None);
+    }
+}
+
+/// Manager applies stored settings to devices it connects to
+#[cfg(all(feature = "manager", feature = "fake"))]
+#[test]
+fn manager_applies_settings_on_connect() {
+    use elgato_streamdeck::activity::ActivityKind;
+    use elgato_streamdeck::fake::FAKE_ENV;
+    use elgato_streamdeck::manager::ConnectDecision;
+    use elgato_streamdeck::protocol::brightness_command;
+    use elgato_streamdeck::{ConnectOptions, DeckManager};
+
+    // Only test in this binary that touches the variable
+    unsafe { std::env::set_var(FAKE_ENV, "Mk2:A1") };
+
+    let mut store = SettingsStore::open(settings_path("manager.json")).unwrap();
+    store.set(&id(Kind::Mk2, "A1"), settings());
+
+    let mut manager = DeckManager::new().expect("Failed to create manager");
+    manager.set_settings_store(store);
+    manager.set_policy(|_| ConnectDecision::Connect {
+        options: ConnectOptions {
+            activity_log_size: 16,
+            ..Default::default()
+        },
+        brightness: None,
+    });
+    manager.rescan();
+
+    let device = manager.device(&id(Kind::Mk2, "A1")).expect("Device wasn't connected");
+    let command = brightness_command(Kind::Mk2, 40);
+
+    assert!(
+        device
+            .recent_activity()
+            .iter()
+            .any(|activity| activity.kind == ActivityKind::SendFeatureReport && activity.data.starts_with(&command))
+    );
+}
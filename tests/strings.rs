@@ -0,0 +1,11 @@
+use elgato_streamdeck::util::extract_str_lossy;
+
+/// Lossy strings end at the terminator, so garbage after it isn't joined onto the string
+#[test]
+fn lossy_string_ends_at_terminator() {
+    assert_eq!(extract_str_lossy(b"ABC\0\xff12"), "ABC");
+    assert_eq!(extract_str_lossy(b"\0\0ABC\0\0\0"), "ABC");
+    assert_eq!(extract_str_lossy(b"1.0.5\xff\xfe"), "1.0.5");
+    assert_eq!(extract_str_lossy(b" A1\x01 \0garbage"), "A1");
+    assert_eq!(extract_str_lossy(b"\0\0\0"), "");
+}
@@ -0,0 +1,152 @@
+use elgato_streamdeck::image::{DynamicImage, Rgb, RgbImage};
+use elgato_streamdeck::info::{Kind, Screen, ScreenSegment};
+use elgato_streamdeck::protocol::{brightness_command, decode_image_report, reset_command, ImageTarget};
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, StreamDeck};
+
+/// Nothing is written while suspended, and resuming applies the state that was set along with images staged in the meantime
+#[test]
+fn resume_restores_state() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default());
+    let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(72, 72, Rgb([255, 0, 0])));
+
+    device.set_button_image(0, image.clone()).unwrap();
+    device.flush().unwrap();
+    transport.take_written();
+
+    device.suspend();
+    device.set_brightness(40).unwrap();
+    device.set_button_image(1, image).unwrap();
+    device.flush().unwrap();
+
+    assert!(transport.take_written().is_empty());
+    assert!(transport.take_feature_reports().is_empty());
+
+    device.resume().unwrap();
+    assert!(!device.is_suspended());
+
+    let keys = transport
+        .take_written()
+        .iter()
+        .filter_map(|report| decode_image_report(Kind::Mk2, report))
+        .filter(|report| report.page_number == 0)
+        .map(|report| report.target)
+        .collect::<Vec<_>>();
+
+    assert_eq!(keys, [ImageTarget::Key(0), ImageTarget::Key(1)]);
+    assert_eq!(transport.take_feature_reports(), [brightness_command(Kind::Mk2, 40)]);
+}
+
+/// Resuming with reset resets the device before restoring its state
+#[test]
+fn resume_with_reset_reinitializes() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default());
+
+    device.set_brightness(70).unwrap();
+    transport.take_feature_reports();
+
+    device.suspend();
+    device.resume_with_reset().unwrap();
+
+    assert!(!device.is_suspended());
+    assert_eq!(transport.take_feature_reports(), [reset_command(Kind::Mk2), brightness_command(Kind::Mk2, 70)]);
+}
+
+/// Clearing keys, blackout and wake don't write while suspended, resuming blacks the device out and sends the cleared key
+#[test]
+fn suspended_clear_and_blackout_are_held_back() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default());
+
+    device.suspend();
+    device.clear_button_image(0).unwrap();
+    device.blackout().unwrap();
+    device.wake().unwrap();
+    device.blackout().unwrap();
+
+    assert!(transport.take_written().is_empty());
+    assert!(transport.take_feature_reports().is_empty());
+
+    device.resume().unwrap();
+
+    let keys = transport
+        .take_written()
+        .iter()
+        .filter_map(|report| decode_image_report(Kind::Mk2, report))
+        .filter(|report| report.page_number == 0)
+        .map(|report| report.target)
+        .collect::<Vec<_>>();
+
+    assert_eq!(keys, [ImageTarget::Key(0)]);
+    assert_eq!(transport.take_feature_reports(), [brightness_command(Kind::Mk2, 0)]);
+}
+
+/// Screen writes are queued while suspended, a fill drops the writes queued before it, and the rest are written in order on resume
+#[test]
+fn suspended_screen_writes_are_queued() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Plus, transport.clone(), ConnectOptions::default());
+    let (w, h) = Kind::Plus.lcd_strip_size().unwrap();
+    let region = ScreenSegment { x: 0, y: 0, w: 100, h: 100 };
+
+    device.suspend();
+    device.write_screen(Screen::LCD_STRIP, Some(region), &[1]).unwrap();
+    device.write_lcd_fill(&vec![2; w * h]).unwrap();
+    device.write_screen(Screen::LCD_STRIP, Some(region), &[3]).unwrap();
+
+    assert!(transport.take_written().is_empty());
+
+    device.resume().unwrap();
+
+    let targets = transport
+        .take_written()
+        .iter()
+        .filter_map(|report| decode_image_report(Kind::Plus, report))
+        .filter(|report| report.page_number == 0)
+        .map(|report| (report.target, report.payload[0]))
+        .collect::<Vec<_>>();
+
+    // Plus fills the strip by writing a region covering all of it
+    let fill = ImageTarget::LcdRegion { x: 0, y: 0, w: w as u16, h: h as u16 };
+    assert_eq!(targets, [(fill, 2), (ImageTarget::LcdRegion { x: 0, y: 0, w: 100, h: 100 }, 3)]);
+}
+
+/// Clearing a key writes it right away and drops its staged image
+#[test]
+fn clear_writes_right_away() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default());
+
+    device.write_image(0, Kind::Mk2.blank_image()).unwrap();
+    device.clear_button_image(0).unwrap();
+
+    assert!(!transport.take_written().is_empty());
+
+    device.flush().unwrap();
+    assert!(transport.take_written().is_empty());
+}
+
+/// Reset while suspended is held back and happens on resume, before the state is restored
+#[test]
+fn suspended_reset_is_held_back() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default());
+
+    device.set_brightness(70).unwrap();
+    transport.take_feature_reports();
+
+    device.suspend();
+    device.reset().unwrap();
+    device.reset_preserving_state().unwrap();
+
+    assert!(transport.take_feature_reports().is_empty());
+
+    device.resume().unwrap();
+    assert_eq!(transport.take_feature_reports(), [reset_command(Kind::Mk2), brightness_command(Kind::Mk2, 70)]);
+
+    device.suspend();
+    device.resume().unwrap();
+    assert!(!transport.take_feature_reports().contains(&reset_command(Kind::Mk2)));
+}
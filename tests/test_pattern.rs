@@ -0,0 +1,45 @@
+#![cfg(feature = "widgets")]
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::protocol::{decode_image_report, ImageTarget};
+use elgato_streamdeck::test_pattern::key_test_pattern;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, StreamDeck};
+use image::GenericImageView;
+
+/// Gradient grows to the right and downwards, so the corners tell orientation apart
+#[test]
+fn key_pattern_shows_orientation() {
+    let image = key_test_pattern(3, (72, 72));
+    let [red, green, ..] = image.get_pixel(70, 36).0;
+    assert!(red > 200 && green < 150);
+
+    let [red, green, ..] = image.get_pixel(36, 70).0;
+    assert!(red < 150 && green > 200);
+}
+
+/// Every key and the LCD strip get the pattern
+#[test]
+fn pattern_covers_keys_and_strip() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Plus, transport.clone(), ConnectOptions::default());
+
+    device.show_test_pattern().unwrap();
+    device.flush().unwrap();
+
+    let mut targets = transport
+        .take_written()
+        .iter()
+        .filter_map(|report| decode_image_report(Kind::Plus, report))
+        .filter(|report| report.page_number == 0)
+        .map(|report| report.target)
+        .collect::<Vec<_>>();
+
+    targets.sort_by_key(|target| format!("{:?}", target));
+
+    let mut expected = (0..8).map(ImageTarget::Key).collect::<Vec<_>>();
+    expected.push(ImageTarget::LcdRegion { x: 0, y: 0, w: 800, h: 100 });
+    expected.sort_by_key(|target| format!("{:?}", target));
+
+    assert_eq!(targets, expected);
+}
@@ -0,0 +1,188 @@
+#![cfg(feature = "text")]
+
+use std::time::Duration;
+
+use elgato_streamdeck::animation::KeyAnimation;
+use elgato_streamdeck::image::{DynamicImage, Rgba, RgbaImage};
+use elgato_streamdeck::text::{render_line, render_text, Font, TextAlignment, TextStyle, TextTicker, Theme, VerticalAlignment};
+
+const WHITE: (u8, u8, u8) = (255, 255, 255);
+
+/// Cantarell, licensed under the SIL Open Font License, see fonts/OFL.txt
+fn cantarell() -> Font {
+    Font::from_bytes(include_bytes!("fonts/Cantarell-Regular.ttf").to_vec()).unwrap()
+}
+
+/// Font with color bitmaps for Hebrew alef (red square) and bet (blue square) in an sbix table, and nothing else
+fn color_squares() -> Font {
+    Font::from_bytes(include_bytes!("fonts/ColorSquares.ttf").to_vec()).unwrap()
+}
+
+/// Leftmost and rightmost columns that have a pixel matching the predicate
+fn columns(image: &RgbaImage, matches: impl Fn(&Rgba<u8>) -> bool) -> Option<(u32, u32)> {
+    let columns = (0..image.width()).filter(|&x| (0..image.height()).any(|y| matches(image.get_pixel(x, y)))).collect::<Vec<_>>();
+    Some((*columns.first()?, *columns.last()?))
+}
+
+/// Top rows of bands of consecutive rows that have drawn pixels, one band per line of text
+fn line_tops(image: &RgbaImage) -> Vec<u32> {
+    let drawn = (0..image.height()).map(|y| (0..image.width()).any(|x| is_drawn(image.get_pixel(x, y)))).collect::<Vec<_>>();
+    (0..drawn.len()).filter(|&y| drawn[y] && (y == 0 || !drawn[y - 1])).map(|y| y as u32).collect()
+}
+
+/// Text drawn with the style on a transparent image of the size
+fn text_image(text: &str, style: TextStyle, size: (u32, u32)) -> RgbaImage {
+    render_text(&DynamicImage::new_rgba8(size.0, size.1), &cantarell(), text, &style).to_rgba8()
+}
+
+fn is_red(pixel: &Rgba<u8>) -> bool {
+    pixel.0 == [255, 0, 0, 255]
+}
+
+fn is_blue(pixel: &Rgba<u8>) -> bool {
+    pixel.0 == [0, 0, 255, 255]
+}
+
+fn is_drawn(pixel: &Rgba<u8>) -> bool {
+    pixel.0[3] > 0
+}
+
+/// Kerning from the font's GPOS table pulls "AV" pairs together, so shaped text is narrower than its glyphs side by side
+#[test]
+fn shaping_applies_kerning() {
+    let font = cantarell();
+    let width = |text| render_line(&font, text, 40.0, WHITE).width();
+
+    assert!(width("AVAVAVAV") < 4 * (width("A") + width("V")));
+}
+
+/// Characters missing from the font are drawn with the fallback, instead of the missing glyph box of the primary font
+#[test]
+fn missing_characters_use_fallback() {
+    let without = render_line(&cantarell(), "\u{5D0}", 20.0, WHITE);
+    assert!(columns(&without, is_red).is_none());
+    assert!(columns(&without, is_drawn).is_some());
+
+    let with = render_line(&cantarell().with_fallback(color_squares()), "\u{5D0}", 20.0, WHITE);
+    assert_eq!(columns(&with, is_red), Some((0, 19)));
+}
+
+/// Bitmap glyphs keep their own colors instead of taking the text color, scaled from their strike to the font size
+#[test]
+fn color_glyphs_keep_their_colors() {
+    let line = render_line(&color_squares(), "\u{5D1}", 30.0, WHITE);
+
+    assert_eq!(columns(&line, is_blue), Some((0, 29)));
+    assert!(line.pixels().all(|pixel| !is_drawn(pixel) || is_blue(pixel)));
+}
+
+/// Right-to-left runs are drawn in reverse of their logical order, and stay after the left-to-right text preceding them
+#[test]
+fn right_to_left_text_is_reordered() {
+    let font = cantarell().with_fallback(color_squares());
+
+    // Alef then bet, so bet is drawn on the left
+    let line = render_line(&font, "\u{5D0}\u{5D1}", 20.0, WHITE);
+    assert_eq!(columns(&line, is_blue), Some((0, 19)));
+    assert_eq!(columns(&line, is_red), Some((20, 39)));
+
+    let mixed = render_line(&font, "a \u{5D0}\u{5D1}", 20.0, WHITE);
+    let (_, latin_end) = columns(&mixed, |pixel| is_drawn(pixel) && !is_red(pixel) && !is_blue(pixel)).unwrap();
+    let (blue_start, blue_end) = columns(&mixed, is_blue).unwrap();
+    let (red_start, _) = columns(&mixed, is_red).unwrap();
+
+    assert!(latin_end < blue_start);
+    assert!(blue_end < red_start);
+}
+
+/// Labels are centered on the key, and text too wide for the key without padding is shrunk to fit
+#[test]
+fn theme_labels_are_centered_and_fit() {
+    let theme = Theme::new(cantarell());
+
+    let label = theme.label("Hi", (72, 72)).to_rgba8();
+    let (left, right) = columns(&label, |pixel| pixel.0[0] > 0).unwrap();
+    assert!((left as i32 - (71 - right) as i32).abs() <= 1);
+
+    let label = theme.label("Much too long for a key", (72, 72)).to_rgba8();
+    let (left, right) = columns(&label, |pixel| pixel.0[0] > 0).unwrap();
+    assert!(left + 1 >= theme.padding);
+    assert!(right <= 71 + 1 - theme.padding);
+}
+
+/// Text that fits stays still, longer text scrolls and comes back to the start after scrolling by its width and the gap
+#[test]
+fn ticker_scrolls_long_text() {
+    let font = cantarell();
+
+    let mut still = TextTicker::new(&font, "Hi", 16.0, WHITE, (72, 72));
+    assert!(still.frame(Duration::ZERO).is_some());
+    assert!(still.frame(Duration::from_secs(1)).is_none());
+
+    let text = "Scrolling along the key";
+    let period = render_line(&font, text, 16.0, WHITE).width() + 10;
+    let mut ticker = TextTicker::new(&font, text, 16.0, WHITE, (72, 72)).gap(10).speed(period as f32);
+
+    let start = ticker.frame(Duration::ZERO).unwrap();
+    let halfway = ticker.frame(Duration::from_millis(500)).unwrap();
+    assert_ne!(start, halfway);
+
+    // Nothing moved, so there's no new frame
+    assert!(ticker.frame(Duration::from_millis(500)).is_none());
+
+    assert_eq!(ticker.frame(Duration::from_secs(1)).unwrap(), start);
+}
+
+/// Lines too long for the image are wrapped at spaces, unless wrapping is turned off
+#[test]
+fn text_wraps_at_word_boundaries() {
+    let style = TextStyle::new(20.0, WHITE);
+
+    // "Hello Hello" is about 77 pixels wide at this size, too wide for 72 pixels with padding but not for 90
+    assert_eq!(line_tops(&text_image("Hello Hello", style, (72, 72))).len(), 2);
+    assert_eq!(line_tops(&text_image("Hello Hello", style, (90, 72))).len(), 1);
+    assert_eq!(line_tops(&text_image("Hello Hello", style.wrap(false), (72, 72))).len(), 1);
+    assert_eq!(line_tops(&text_image("Hello\nHello", style.wrap(false), (200, 72))).len(), 2);
+}
+
+/// Lines are aligned to the padded edges or centered
+#[test]
+fn text_is_aligned() {
+    let style = TextStyle::new(20.0, WHITE).padding(10);
+    let columns = |alignment| columns(&text_image("Hello", style.alignment(alignment), (200, 72)), is_drawn).unwrap();
+
+    let (left, _) = columns(TextAlignment::Left);
+    assert!((10..14).contains(&left), "{left}");
+
+    let (_, right) = columns(TextAlignment::Right);
+    assert!((186..190).contains(&right), "{right}");
+
+    let (start, end) = columns(TextAlignment::Center);
+    assert!(((start + end) as i32 - 199).abs() <= 2, "{start} {end}");
+}
+
+/// Padding moves text away from the edges it's aligned to
+#[test]
+fn padding_moves_text_from_edges() {
+    let style = TextStyle::new(20.0, WHITE).alignment(TextAlignment::Left).vertical_alignment(VerticalAlignment::Top);
+
+    let near = text_image("Hello", style.padding(0), (100, 100));
+    let far = text_image("Hello", style.padding(20), (100, 100));
+
+    assert_eq!(columns(&far, is_drawn).unwrap().0, columns(&near, is_drawn).unwrap().0 + 20);
+    assert_eq!(line_tops(&far)[0], line_tops(&near)[0] + 20);
+}
+
+/// Line spacing multiplies the distance between lines
+#[test]
+fn line_spacing_scales_distance_between_lines() {
+    let style = TextStyle::new(20.0, WHITE).vertical_alignment(VerticalAlignment::Top);
+    let distance = |spacing| {
+        let tops = line_tops(&text_image("H\nH", style.line_spacing(spacing), (72, 144)));
+        tops[1] - tops[0]
+    };
+
+    let single = distance(1.0);
+    assert!((20..30).contains(&single), "{single}");
+    assert!((distance(2.0) as i32 - 2 * single as i32).abs() <= 1);
+}
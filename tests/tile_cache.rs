@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use elgato_streamdeck::images::TileCache;
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::transport::{MockTransport, Transport};
+use elgato_streamdeck::{ConnectOptions, StreamDeck};
+use hidapi::HidError;
+use image::{DynamicImage, Rgb, RgbImage};
+
+/// Mock transport whose writes fail while the flag is set
+struct FailingTransport {
+    inner: MockTransport,
+    fail: Arc<AtomicBool>,
+}
+
+impl Transport for FailingTransport {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        if self.fail.load(Ordering::SeqCst) {
+            return Err(HidError::HidApiError { message: "write failed".into() });
+        }
+
+        self.inner.write(data)
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, HidError> {
+        self.inner.read(buf, timeout)
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        self.inner.get_feature_report(buf)
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), HidError> {
+        self.inner.send_feature_report(data)
+    }
+
+    fn manufacturer(&self) -> Result<Option<String>, HidError> {
+        self.inner.manufacturer()
+    }
+
+    fn product(&self) -> Result<Option<String>, HidError> {
+        self.inner.product()
+    }
+}
+
+fn device() -> (StreamDeck, MockTransport, Arc<AtomicBool>) {
+    let transport = MockTransport::new();
+    let fail = Arc::new(AtomicBool::new(false));
+
+    let device = StreamDeck::from_transport(
+        Kind::Mk2,
+        FailingTransport {
+            inner: transport.clone(),
+            fail: fail.clone(),
+        },
+        ConnectOptions::default(),
+    );
+
+    (device, transport, fail)
+}
+
+/// Frame with a different color on every key
+fn frame(shift: u8) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(360, 216, |x, y| Rgb([(x / 72 * 50) as u8, (y / 72 * 80) as u8, shift])))
+}
+
+/// Writing the same frame again sends nothing
+#[test]
+fn unchanged_frame_sends_nothing() {
+    let (device, transport, _) = device();
+    let mut cache = TileCache::new();
+
+    assert_eq!(cache.write_frame(&device, &frame(0)).unwrap(), 15);
+    assert!(!transport.take_written().is_empty());
+
+    assert_eq!(cache.write_frame(&device, &frame(0)).unwrap(), 0);
+    assert!(transport.take_written().is_empty());
+}
+
+/// Failed write forgets all tiles, so the next frame is written in full
+#[test]
+fn failed_write_invalidates_cache() {
+    let (device, transport, fail) = device();
+    let mut cache = TileCache::new();
+
+    cache.write_frame(&device, &frame(0)).unwrap();
+    transport.take_written();
+
+    fail.store(true, Ordering::SeqCst);
+    assert!(cache.write_frame(&device, &frame(100)).is_err());
+
+    fail.store(false, Ordering::SeqCst);
+    assert_eq!(cache.write_frame(&device, &frame(0)).unwrap(), 15);
+    assert!(!transport.take_written().is_empty());
+}
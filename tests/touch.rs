@@ -0,0 +1,13 @@
+use elgato_streamdeck::info::Kind;
+
+/// Points are normalized in the orientation images for the strip are drawn in
+#[test]
+fn touch_points_follow_strip_orientation() {
+    assert_eq!(Kind::Plus.normalize_touch_point(200, 25), Some((0.25, 0.25)));
+    assert_eq!(Kind::Plus.normalize_touch_point(1000, 200), Some((1.0, 1.0)));
+
+    // Plus XL panel is mounted rotated, so points along its long side run along the width of the strip images
+    assert_eq!(Kind::PlusXl.normalize_touch_point(25, 300), Some((0.75, 0.25)));
+
+    assert_eq!(Kind::Mk2.normalize_touch_point(0, 0), None);
+}
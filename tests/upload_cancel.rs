@@ -0,0 +1,73 @@
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, StreamDeck};
+
+/// Payload of a single image report of Mk2, 1024 bytes report with 8 bytes header
+const PAGE: usize = 1016;
+
+/// Returns key, page number and first payload byte of every image report written since the last call
+fn pages(transport: &MockTransport) -> Vec<(u8, usize, u8)> {
+    transport
+        .take_written()
+        .iter()
+        .filter(|report| report[..2] == [0x02, 0x07])
+        .map(|report| (report[2], u16::from_le_bytes([report[6], report[7]]) as usize, report[8]))
+        .collect()
+}
+
+/// Canceling a key partway through its upload stops it before the next page, drops it from the staged images,
+/// and leaves the other staged images to be sent. A newer image staged afterwards is sent in full
+#[test]
+fn cancel_stops_upload_partway() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default());
+
+    device.write_image(0, vec![1; PAGE * 4]).unwrap();
+    device.write_image(1, vec![2; PAGE * 2]).unwrap();
+
+    transport.set_write_delay(Duration::from_millis(100));
+
+    // Lands while the second page of key 0 is being written
+    let canceller = device.upload_canceller();
+    let cancel = spawn(move || {
+        sleep(Duration::from_millis(150));
+        canceller.cancel(0);
+    });
+
+    device.flush().unwrap();
+    cancel.join().unwrap();
+
+    assert_eq!(pages(&transport), [(0, 0, 1), (0, 1, 1), (1, 0, 2), (1, 1, 2)]);
+
+    // Nothing is left staged
+    transport.set_write_delay(Duration::ZERO);
+    device.flush().unwrap();
+    assert!(pages(&transport).is_empty());
+
+    device.write_image(0, vec![3; PAGE * 2]).unwrap();
+    device.flush().unwrap();
+    assert_eq!(pages(&transport), [(0, 0, 3), (0, 1, 3)]);
+}
+
+/// Image staged for a key supersedes the one that is still waiting to be sent, only the newer one is written
+#[test]
+fn staged_image_is_superseded() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Mk2, transport.clone(), ConnectOptions::default());
+
+    device.write_image(0, vec![1; PAGE * 2]).unwrap();
+    device.write_image(1, vec![2; PAGE]).unwrap();
+    device.write_image(0, vec![3; PAGE]).unwrap();
+
+    assert!(device.flush_next().unwrap());
+    assert_eq!(pages(&transport), [(1, 0, 2)]);
+
+    assert!(device.flush_next().unwrap());
+    assert_eq!(pages(&transport), [(0, 0, 3)]);
+
+    assert!(!device.flush_next().unwrap());
+    assert!(pages(&transport).is_empty());
+}
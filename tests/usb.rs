@@ -0,0 +1,18 @@
+#![cfg(feature = "nusb")]
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::usb::{list_devices, NusbTransport};
+
+/// Listing doesn't depend on any device being connected, it either lists the connected ones or fails to enumerate
+#[test]
+fn list_devices_smoke() {
+    if let Ok(devices) = list_devices() {
+        assert!(devices.iter().all(|(_, serial)| !serial.is_empty()));
+    }
+}
+
+/// Opening a device that isn't connected fails instead of panicking
+#[test]
+fn open_missing_device() {
+    assert!(NusbTransport::open(Kind::Mk2, "NOT-A-REAL-SERIAL").is_err());
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "video")]
+
+use elgato_streamdeck::image::{DynamicImage, Rgba, RgbaImage};
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::protocol::{decode_image_report, ImageTarget};
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::video::StripPlayer;
+use elgato_streamdeck::{ConnectOptions, StreamDeck};
+
+/// Identical frames aren't written again, and only columns that changed are written on the Plus
+#[test]
+fn writes_only_changes() {
+    let transport = MockTransport::new();
+    let device = StreamDeck::from_transport(Kind::Plus, transport.clone(), ConnectOptions::default());
+
+    let red = RgbaImage::from_pixel(800, 100, Rgba([255, 0, 0, 255]));
+    let mut changed = red.clone();
+
+    for x in 100..150 {
+        for y in 0..100 {
+            changed.put_pixel(x, y, Rgba([0, 0, 255, 255]));
+        }
+    }
+
+    let frames = [red.clone(), red, changed].map(DynamicImage::ImageRgba8);
+    let mut player = StripPlayer::from_frames(20.0, frames);
+    player.play(&device).expect("Failed to play");
+
+    let regions = transport
+        .take_written()
+        .iter()
+        .filter_map(|report| decode_image_report(Kind::Plus, report))
+        .filter(|report| report.page_number == 0)
+        .map(|report| report.target)
+        .collect::<Vec<_>>();
+
+    assert_eq!(player.dropped_frames(), 0);
+    assert_eq!(regions, [ImageTarget::LcdRegion { x: 0, y: 0, w: 800, h: 100 }, ImageTarget::LcdRegion { x: 100, y: 0, w: 50, h: 100 }]);
+}
@@ -0,0 +1,77 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::transport::MockTransport;
+use elgato_streamdeck::{ConnectOptions, StreamDeck, StreamDeckError};
+
+/// Payload of a single image report of Mk2, 1024 bytes report with 8 bytes header
+const PAGE: usize = 1016;
+
+fn connect(transport: &MockTransport, write_timeout: Duration) -> StreamDeck {
+    StreamDeck::from_transport(
+        Kind::Mk2,
+        transport.clone(),
+        ConnectOptions {
+            write_timeout: Some(write_timeout),
+            ..Default::default()
+        },
+    )
+}
+
+/// Returns page numbers of image reports written since the last call
+fn pages(transport: &MockTransport) -> Vec<usize> {
+    transport
+        .take_written()
+        .iter()
+        .filter(|report| report[..2] == [0x02, 0x07])
+        .map(|report| u16::from_le_bytes([report[6], report[7]]) as usize)
+        .collect()
+}
+
+/// A write that hangs is given up on, everything else fails right away until it returns,
+/// and the image stays staged to be sent again
+#[test]
+fn stuck_write_times_out() {
+    let transport = MockTransport::new();
+    let device = connect(&transport, Duration::from_millis(50));
+
+    transport.set_write_delay(Duration::from_secs(2));
+    device.write_image(0, vec![0; PAGE * 3]).unwrap();
+
+    assert!(matches!(device.flush(), Err(StreamDeckError::Timeout)));
+    assert!(matches!(device.set_brightness(50), Err(StreamDeckError::Timeout)));
+    assert!(transport.take_feature_reports().is_empty());
+
+    transport.set_write_delay(Duration::ZERO);
+
+    while matches!(device.set_brightness(50), Err(StreamDeckError::Timeout)) {
+        sleep(Duration::from_millis(10));
+    }
+
+    let feature_reports = transport.take_feature_reports();
+    assert_eq!(feature_reports.len(), 1);
+    assert_eq!(feature_reports[0][..3], [0x03, 0x08, 50]);
+
+    device.flush().unwrap();
+
+    // The stuck first page, then the whole image again
+    assert_eq!(pages(&transport), [0, 0, 1, 2]);
+}
+
+/// A transfer whose last report started before the deadline succeeds even though it finished after it
+#[test]
+fn transfer_finishing_after_deadline_succeeds() {
+    let transport = MockTransport::new();
+    let device = connect(&transport, Duration::from_secs(1));
+
+    transport.set_write_delay(Duration::from_millis(600));
+    device.write_image(0, vec![0; PAGE * 2]).unwrap();
+
+    device.flush().unwrap();
+    assert_eq!(pages(&transport), [0, 1]);
+
+    // Nothing is left staged to be sent again
+    device.flush().unwrap();
+    assert!(transport.take_written().is_empty());
+}